@@ -11,6 +11,7 @@ use rand::distributions::Uniform;
 use rand::prelude::*;
 
 use audio_adaptive::audiograph::*;
+use audio_adaptive::audiograph_parser;
 use audio_adaptive::faust_effect::*;
 use audio_adaptive::samplerate;
 
@@ -288,6 +289,131 @@ fn cubicnl_bench(c: &mut Criterion) {
     );
 }
 
+/// Effects with smoothed parameters, for `smoothing_bench`
+fn smoothed_effect(kind: &str) -> Box<AudioEffect> {
+    match kind {
+        "zita_reverb" => Box::new(ZitaReverb::new(10., 300, 10000, 6., 10., 96_200)),
+        "freeverb" => Box::new(MonoFreeverb::new(0.2, 0.6, 0.8, 0.4)),
+        "compressor" => Box::new(Compressor::new(2., 20., 0.5, 1.)),
+        _ => unreachable!(),
+    }
+}
+
+/// Cost of a cycle of 64 samples while the first parameter follows a ramp, which is processed sample by sample,
+/// compared to the same cycle with settled parameters
+fn smoothing_bench(c: &mut Criterion) {
+    let bench_effect = |b: &mut Bencher, kind: &&str, ramp: bool| {
+        let mut rng = SmallRng::seed_from_u64(345987);
+        let unity_interval = Uniform::new_inclusive(-1., 1.);
+        let mut effect = smoothed_effect(kind);
+        let mut inputs = vec![DspEdge::new(1, 1, 64, 44100); effect.nb_inputs()];
+        for input in inputs.iter_mut() {
+            input.buffer_mut().copy_from_slice(
+                &rng.sample_iter(&unity_interval)
+                    .take(64)
+                    .collect::<Vec<f32>>(),
+            );
+        }
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44100); effect.nb_outputs()];
+        let (min, max) = (effect.parameters()[0].min, effect.parameters()[0].max);
+        let mut high = false;
+        b.iter(|| {
+            if ramp {
+                // A new target every cycle, so that the ramp never ends
+                high = !high;
+                effect.set_parameter(0, if high { max } else { min });
+            }
+            effect.process(&inputs, &mut outputs)
+        })
+    };
+    c.bench(
+        "smoothing",
+        ParameterizedBenchmark::new(
+            "settled",
+            move |b, kind| bench_effect(b, kind, false),
+            vec!["zita_reverb", "freeverb", "compressor"],
+        )
+        .with_function("ramp", move |b, kind| bench_effect(b, kind, true)),
+    );
+}
+
+/// Random audiograph with `nb_nodes` oscillators, modulators and mixers.
+/// Every node is connected to random previous nodes and non connected outlets go to the sink.
+fn random_audiograph(rng: &mut SmallRng, nb_nodes: usize) -> String {
+    let mut description = String::new();
+    let mut edges = String::new();
+    for i in 0..nb_nodes {
+        let kind = if i == 0 { 0 } else { rng.gen_range(0, 10) };
+        match kind {
+            0 | 1 => description.push_str(&format!(
+                "node{} = {{kind : \"osc\", out : 1, freq : {},}};\n",
+                i,
+                rng.gen_range(100, 1000)
+            )),
+            2..=6 => {
+                description.push_str(&format!(
+                    "node{} = {{kind : \"mod\", in : 1, out : 1, freq : {},}};\n",
+                    i,
+                    rng.gen_range(100, 1000)
+                ));
+                edges.push_str(&format!("node{}.1 -> node{}.1;\n", rng.gen_range(0, i), i));
+            }
+            _ => {
+                description.push_str(&format!(
+                    "node{} = {{kind : \"mix\", in : 2, out : 1,}};\n",
+                    i
+                ));
+                edges.push_str(&format!("node{}.1 -> node{}.1;\n", rng.gen_range(0, i), i));
+                edges.push_str(&format!("node{}.1 -> node{}.2;\n", rng.gen_range(0, i), i));
+            }
+        }
+    }
+    description.push_str(&edges);
+    description
+}
+
+fn executor_bench(c: &mut Criterion) {
+    let mut rng = SmallRng::seed_from_u64(345987);
+    let descriptions = [10, 50, 100, 500]
+        .iter()
+        .map(|n| (*n, random_audiograph(&mut rng, *n)))
+        .collect::<Vec<_>>();
+    let pooled_descriptions = descriptions.clone();
+    let parallel_descriptions = descriptions.clone();
+    let bench_graph = |descriptions: &[(usize, String)], n: usize, nb_threads: usize| {
+        let description = &descriptions.iter().find(|(size, _)| *size == n).unwrap().1;
+        let mut audio_graph =
+            audiograph_parser::parse_audiograph(description, 256, 2, 44_100).unwrap();
+        audio_graph.set_nb_threads(nb_threads);
+        audio_graph.update_schedule().unwrap();
+        audio_graph
+    };
+    c.bench(
+        "executors",
+        ParameterizedBenchmark::new(
+            "pooled",
+            move |b: &mut Bencher, n: &usize| {
+                let mut audio_graph = bench_graph(&pooled_descriptions, *n, 1);
+                let inputs = vec![DspEdge::new(1, 1, 512, 44_100)];
+                let mut outputs = vec![DspEdge::new(1, 1, 512, 44_100)];
+                b.iter(|| audio_graph.process(&inputs, &mut outputs))
+            },
+            vec![10, 50, 100, 500],
+        )
+        .with_function("parallel", move |b: &mut Bencher, n: &usize| {
+            let mut audio_graph = bench_graph(&parallel_descriptions, *n, 4);
+            let inputs = vec![DspEdge::new(1, 1, 512, 44_100)];
+            let mut outputs = vec![DspEdge::new(1, 1, 512, 44_100)];
+            b.iter(|| audio_graph.process(&inputs, &mut outputs))
+        })
+        .with_function("copy", move |b: &mut Bencher, n: &usize| {
+            let mut audio_graph = bench_graph(&descriptions, *n, 1);
+            let inputs = vec![DspEdge::new(1, 1, 512, 44_100)];
+            let mut outputs = vec![DspEdge::new(1, 1, 512, 44_100)];
+            b.iter(|| audio_graph.process_copy(&inputs, &mut outputs))
+        }),
+    );
+}
 
 criterion_group!(
     benches,
@@ -301,6 +427,8 @@ criterion_group!(
     freeverb_bench,
     compressor_bench,
     autowah_bench,
-    cubicnl_bench
+    cubicnl_bench,
+    smoothing_bench,
+    executor_bench
 );
 criterion_main!(benches);
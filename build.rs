@@ -0,0 +1,454 @@
+//! Generates the Faust effects of faust-effects/ as `AudioEffect`s.
+//!
+//! Each `name.dsp` comes with the output of `faust -lang rust -cn Name name.dsp`, committed next to it
+//! as `name.rs`, so that the build does not depend on the version of faust installed, if any.
+//! With the `faust` feature, the .dsp files are compiled again by faust (which can be set with
+//! the `FAUST` environment variable) instead. The effects which declare `hand_ported` in their
+//! .dsp file are written by hand in `faust_effect` and skipped, as well as the effects without
+//! a committed .rs file, with a warning. For now, only autowah, compressor, cubicnl and transpose
+//! have one, written by hand in the format of the output of faust, which should replace it.
+//!
+//! The ports, parameters and delay lines are read in the lines of the generated code, and
+//! `$OUT_DIR/faust_generated.rs` gets, for each effect:
+//!
+//! - a module `name` with the code generated by Faust, and its implementation of `GeneratedDsp`
+//! - a static `NAME: GeneratedEffect` with its ports and parameters
+//! - a type `Name = FaustEffect<name::Name>`
+//! - its registration as the node kind `name` in `register_generated`
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Same as `faust_generated::MAX_PORTS`
+const MAX_PORTS: usize = 32;
+
+/// Attributes of the nodes which are not given to the effect, see `audiograph_parser`
+const RESERVED_ATTRIBUTES: [&str; 7] =
+    ["in", "out", "text", "kind", "wcet", "volume", "rate_switch"];
+
+/// Names of the attributes whose label in the .dsp file does not give the name used in the graphs,
+/// as `(kind, label, name)`
+const PARAMETER_NAMES: [(&str, &str, &str); 3] = [
+    ("transpose", "Window length", "window"),
+    ("transpose", "Crossfade duration", "xfade"),
+    ("transpose", "Shift", "semitones"),
+];
+
+struct Parameter {
+    name: String,
+    kind: &'static str,
+    index: i32,
+    min: f64,
+    max: f64,
+    default: f64,
+}
+
+struct Effect {
+    /// Stem of the .dsp file, used as module name and node kind
+    name: String,
+    struct_name: String,
+    description: String,
+    nb_inputs: usize,
+    nb_outputs: usize,
+    parameters: Vec<Parameter>,
+    delay_lines: Vec<String>,
+    iota: Option<String>,
+    code: String,
+}
+
+/// `zita_reverb` -> `ZitaReverb`
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| part[..1].to_uppercase() + &part[1..])
+        .collect()
+}
+
+/// Name of an attribute from the label of a Faust widget: `"Window length [unit:ms]"` -> `window_length`.
+/// It is an identifier of the .ag grammar: an ASCII letter followed by letters, digits and `_`.
+fn parameter_name(label: &str) -> String {
+    let label = match label.find('[') {
+        Some(metadata) => &label[..metadata],
+        None => label,
+    };
+    let name = label
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("p{}", name)
+    }
+}
+
+/// The text between the quotes of `"text"`, and what follows the closing quote
+fn quoted(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if !text.starts_with('"') {
+        return None;
+    }
+    let end = text[1..].find('"')? + 1;
+    Some((&text[1..end], &text[end + 1..]))
+}
+
+/// `1.0`, `-2e+01`, `0.5f32`
+fn number(text: &str) -> Option<f64> {
+    text.trim()
+        .trim_end_matches("f32")
+        .trim_end_matches("f64")
+        .parse()
+        .ok()
+}
+
+/// The integer returned by `fn function`, as in `return 2;`
+fn returned_integer(code: &str, function: &str) -> Option<usize> {
+    let body = &code[code.find(&format!("fn {}(", function))?..];
+    let body = &body[body.find('{')? + 1..body.find('}')?];
+    body.trim()
+        .trim_start_matches("return")
+        .trim_end_matches(';')
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The widget added by a line of the UI of the effect `effect`, as in
+/// `ui_interface.add_horizontal_slider("label", ParamIndex(0), 1.0, 0.0, 2.0, 0.1);`.
+/// None for bargraphs, which are outputs, and for the other lines.
+fn parameter(effect: &str, line: &str) -> Result<Option<Parameter>, String> {
+    let line = line.trim();
+    if !line.starts_with("ui_interface.add_") {
+        return Ok(None);
+    }
+    let open = line.find('(').ok_or("Invalid widget")?;
+    let close = line.rfind(')').ok_or("Invalid widget")?;
+    let widget = &line["ui_interface.add_".len()..open];
+    let (label, arguments) = quoted(&line[open + 1..close]).ok_or("Widget without label")?;
+    // , ParamIndex(i), init, min, max, step
+    let arguments = arguments.split(',').skip(1).collect::<Vec<_>>();
+    let index = arguments
+        .first()
+        .map(|index| index.trim())
+        .filter(|index| index.starts_with("ParamIndex(") && index.ends_with(')'))
+        .and_then(|index| index["ParamIndex(".len()..index.len() - 1].parse().ok())
+        .ok_or_else(|| format!("No index for {}", label))?;
+    let (kind, numbers) = match widget {
+        "button" | "check_button" if arguments.len() == 1 => ("Boolean", vec![0., 0., 1.]),
+        "horizontal_slider" | "vertical_slider" | "num_entry" if arguments.len() == 5 => {
+            let numbers = arguments[1..]
+                .iter()
+                .map(|argument| number(argument))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("Invalid declaration of {}", label))?;
+            let integer = numbers[3] == 1. && numbers[..3].iter().all(|x| x.fract() == 0.);
+            (if integer { "Integer" } else { "Float" }, numbers)
+        }
+        "horizontal_bargraph" | "vertical_bargraph" => return Ok(None),
+        _ => return Err(format!("Unknown widget {} for {}", widget, label)),
+    };
+    let name = match PARAMETER_NAMES
+        .iter()
+        .find(|&&(kind, widget, _)| kind == effect && widget == label)
+    {
+        Some(&(_, _, name)) => name.to_string(),
+        None => parameter_name(label),
+    };
+    Ok(Some(Parameter {
+        name,
+        kind,
+        index,
+        default: numbers[0],
+        min: numbers[1],
+        max: numbers[2],
+    }))
+}
+
+/// The arrays of floats of `struct struct_name` indexed with an `IOTA` in the generated code, and this `IOTA`.
+/// Integer delay lines are not rescaled, as they cannot be interpolated.
+fn delay_lines(code: &str, struct_name: &str) -> Result<(Vec<String>, Option<String>), String> {
+    let start = code
+        .find(&format!("struct {} {{", struct_name))
+        .ok_or("Cannot find the generated struct")?;
+    let mut lines = Vec::new();
+    let mut iotas: Vec<String> = Vec::new();
+    for field in code[start..]
+        .lines()
+        .skip(1)
+        .take_while(|line| line.trim() != "}")
+    {
+        let mut parts = field.trim().trim_start_matches("pub ").splitn(2, ':');
+        let name = parts.next().unwrap().trim();
+        let kind = parts.next().unwrap_or("").replace(' ', "");
+        if !(kind.starts_with("[F32;") || kind.starts_with("[f32;") || kind.starts_with("[f64;")) {
+            continue;
+        }
+        let access = format!("self.{}[", name);
+        for (position, _) in code.match_indices(&access) {
+            let index = &code[position + access.len()..];
+            let index = &index[..index.find(']').unwrap_or(index.len())];
+            let iota = match index.find("self.IOTA") {
+                Some(iota) => index[iota + "self.".len()..]
+                    .chars()
+                    .take_while(|&c| c.is_ascii_alphanumeric() || c == '_')
+                    .collect::<String>(),
+                None => continue,
+            };
+            if !iotas.contains(&iota) {
+                iotas.push(iota);
+            }
+            if !lines.iter().any(|line| line == name) {
+                lines.push(name.to_string());
+            }
+        }
+    }
+    if iotas.len() > 1 {
+        return Err(format!(
+            "Delay lines indexed with several IOTAs: {}",
+            iotas.join(", ")
+        ));
+    }
+    Ok((lines, iotas.pop()))
+}
+
+/// Compile the .dsp file with faust
+fn compile(faust: &str, dsp_path: &Path, struct_name: &str) -> Result<String, String> {
+    let output = Command::new(faust)
+        .arg("-lang")
+        .arg("rust")
+        .arg("-cn")
+        .arg(struct_name)
+        .arg(dsp_path)
+        .output()
+        .map_err(|e| format!("Cannot run {}: {}", faust, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// `faust` is the compiler, if the .dsp files are compiled again.
+/// `None` for the effects ported by hand.
+fn effect(faust: Option<&str>, dsp_path: &Path) -> Result<Option<Effect>, String> {
+    let name = dsp_path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .filter(|name| {
+            name.starts_with(|c: char| c.is_ascii_lowercase())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        })
+        .ok_or("The name of the file must be in snake case")?
+        .to_string();
+    let dsp = fs::read_to_string(dsp_path).map_err(|e| e.to_string())?;
+    if dsp
+        .lines()
+        .any(|line| line.trim_start().starts_with("declare hand_ported "))
+    {
+        return Ok(None);
+    }
+    let code = match faust {
+        Some(faust) => compile(faust, dsp_path, &camel_case(&name))?,
+        None => fs::read_to_string(dsp_path.with_extension("rs")).map_err(|_| {
+            format!(
+                "No {}.rs: commit the output of faust or build with the faust feature",
+                name
+            )
+        })?,
+    };
+
+    let struct_name = code
+        .lines()
+        .filter_map(|line| line.trim().split("impl FaustDsp for ").nth(1))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .next()
+        .ok_or("No implementation of FaustDsp in the generated code")?
+        .to_string();
+    let (delay_lines, iota) = delay_lines(&code, &struct_name)?;
+
+    let nb_inputs = returned_integer(&code, "get_num_inputs").ok_or("Unknown number of inputs")?;
+    let nb_outputs =
+        returned_integer(&code, "get_num_outputs").ok_or("Unknown number of outputs")?;
+    if nb_outputs == 0 || nb_inputs > MAX_PORTS || nb_outputs > MAX_PORTS {
+        return Err(format!(
+            "{} inputs and {} outputs are not supported",
+            nb_inputs, nb_outputs
+        ));
+    }
+
+    let mut parameters: Vec<Parameter> = Vec::new();
+    for line in code.lines() {
+        if let Some(mut parameter) = parameter(&name, line)? {
+            if RESERVED_ATTRIBUTES.contains(&parameter.name.as_str())
+                || parameters.iter().any(|other| other.name == parameter.name)
+            {
+                parameter.name = format!("{}_{}", parameter.name, parameters.len());
+            }
+            parameters.push(parameter);
+        }
+    }
+
+    // m.declare("description", r"...");
+    let description = code
+        .lines()
+        .filter_map(|line| line.trim().split("m.declare(\"description\",").nth(1))
+        .filter_map(|value| quoted(value.trim_start().trim_start_matches('r')))
+        .map(|(description, _)| description.to_string())
+        .next()
+        .unwrap_or_else(|| format!("Faust effect {}.dsp", name));
+
+    Ok(Some(Effect {
+        description,
+        struct_name,
+        nb_inputs,
+        nb_outputs,
+        parameters,
+        delay_lines,
+        iota,
+        name,
+        code,
+    }))
+}
+
+fn generate(effects: &[Effect]) -> String {
+    let mut out = String::new();
+    for effect in effects.iter() {
+        let upper = effect.name.to_uppercase();
+        let delay_lines = effect
+            .delay_lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| format!("            {} => Some(&mut self.{}),\n", index, line))
+            .collect::<String>();
+        let iota = effect
+            .iota
+            .as_ref()
+            .map_or("0".to_string(), |iota| format!("self.{}", iota));
+        out.push_str(&format!(
+            "#[allow(non_camel_case_types, non_snake_case, non_upper_case_globals, unused_parens, unused_mut, unused_variables, dead_code, clippy::all)]\n\
+             pub mod {kind} {{\n    use super::*;\n{code}\n\n\
+             impl GeneratedDsp for {struct_name} {{\n    \
+             fn effect() -> &'static GeneratedEffect {{\n        &{upper}\n    }}\n\n    \
+             fn delay_line(&mut self, index: usize) -> Option<&mut [F32]> {{\n        \
+             match index {{\n{delay_lines}            _ => None,\n        }}\n    }}\n\n    \
+             fn iota(&self) -> i32 {{\n        {iota}\n    }}\n}}\n}}\n\n",
+            kind = effect.name,
+            code = effect.code,
+            struct_name = effect.struct_name,
+            upper = upper,
+            delay_lines = delay_lines,
+            iota = iota,
+        ));
+        let parameters = effect
+            .parameters
+            .iter()
+            .map(|parameter| {
+                format!(
+                    "        Parameter::new({:?}, ParameterType::{}, {:?}, {:?}, {:?}),\n",
+                    parameter.name, parameter.kind, parameter.min, parameter.max, parameter.default
+                )
+            })
+            .collect::<String>();
+        let indices = effect
+            .parameters
+            .iter()
+            .map(|parameter| parameter.index.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!(
+            "pub static {upper}: GeneratedEffect = GeneratedEffect {{\n    \
+             kind: {kind:?},\n    \
+             description: {description:?},\n    \
+             nb_inputs: {nb_inputs},\n    \
+             nb_outputs: {nb_outputs},\n    \
+             parameters: &[\n{parameters}    ],\n    \
+             param_indices: &[{indices}],\n\
+             }};\n\n\
+             pub type {camel} = FaustEffect<{kind}::{struct_name}>;\n\n",
+            upper = upper,
+            kind = effect.name,
+            description = effect.description,
+            nb_inputs = effect.nb_inputs,
+            nb_outputs = effect.nb_outputs,
+            parameters = parameters,
+            indices = indices,
+            camel = camel_case(&effect.name),
+            struct_name = effect.struct_name,
+        ));
+    }
+
+    out.push_str(&format!(
+        "/// The effects of faust-effects/\n\
+         pub static GENERATED: [&GeneratedEffect; {}] = [{}];\n\n",
+        effects.len(),
+        effects
+            .iter()
+            .map(|effect| format!("&{}", effect.name.to_uppercase()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    out.push_str(
+        "/// Register the effects of faust-effects/ in `registry`\n\
+         #[allow(unused_variables)]\n\
+         pub fn register_generated(registry: &mut NodeRegistry) {\n",
+    );
+    for effect in effects.iter() {
+        out.push_str(&format!(
+            "    registry.register({kind:?}, {upper}.metadata(), |node, _| {{\n        \
+             Ok(Box::new({camel}::from_node_infos(node)?))\n    }});\n",
+            kind = effect.name,
+            upper = effect.name.to_uppercase(),
+            camel = camel_case(&effect.name)
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn main() {
+    let effects_dir = Path::new("faust-effects");
+    let faust = if env::var_os("CARGO_FEATURE_FAUST").is_some() {
+        Some(env::var("FAUST").unwrap_or_else(|_| "faust".to_string()))
+    } else {
+        None
+    };
+    println!("cargo:rerun-if-env-changed=FAUST");
+    println!("cargo:rerun-if-changed={}", effects_dir.display());
+
+    let mut dsp_paths = fs::read_dir(effects_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension() == Some(OsStr::new("dsp")))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    dsp_paths.sort();
+
+    let mut effects = Vec::new();
+    for dsp_path in dsp_paths.iter() {
+        println!("cargo:rerun-if-changed={}", dsp_path.display());
+        println!(
+            "cargo:rerun-if-changed={}",
+            dsp_path.with_extension("rs").display()
+        );
+        match effect(faust.as_ref().map(|faust| &faust[..]), dsp_path) {
+            Ok(Some(effect)) => effects.push(effect),
+            Ok(None) => {}
+            // Compiling the effects is asked for explicitly, so it must succeed
+            Err(ref error) if faust.is_some() => panic!("{}: {}", dsp_path.display(), error),
+            Err(error) => println!("cargo:warning=Skipping {}: {}", dsp_path.display(), error),
+        }
+    }
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("faust_generated.rs");
+    let mut file = File::create(&out_path).unwrap();
+    file.write_all(generate(&effects).as_bytes()).unwrap();
+}
@@ -1,6 +1,6 @@
 import("stdfaust.lib");
 
-// We don't care about the values here. Just need it for the generation
+declare description "Wah-wah driven by the level of its input";
 
 level = hslider("level", 0.9, 0, 1, 0.01);
 
@@ -0,0 +1,167 @@
+/* ------------------------------------------------------------
+name: "autowah"
+Written by hand in the format of the rust backend of Faust, from the port of autowah.dsp
+in faust_effect: it is not the output of faust. Used by build.rs without the faust feature.
+Replace it with the output of: faust -lang rust -cn Autowah autowah.dsp -o autowah.rs
+------------------------------------------------------------ */
+fn Autowah_faustpower2_f(value: F32) -> F32 {
+	return value * value;
+}
+pub struct Autowah {
+	fHslider0: F32,
+	fSampleRate: i32,
+	fConst0: F32,
+	fConst1: F32,
+	fConst2: F32,
+	fRec2: [F32;2],
+	fRec1: [F32;2],
+	fConst3: F32,
+	fConst4: F32,
+	fRec3: [F32;2],
+	fRec4: [F32;2],
+	fRec0: [F32;3],
+}
+
+impl FaustDsp for Autowah {
+	type T = F32;
+		
+	fn new() -> Autowah { 
+		Autowah {
+			fHslider0: 0.0,
+			fSampleRate: 0,
+			fConst0: 0.0,
+			fConst1: 0.0,
+			fConst2: 0.0,
+			fRec2: [0.0;2],
+			fRec1: [0.0;2],
+			fConst3: 0.0,
+			fConst4: 0.0,
+			fRec3: [0.0;2],
+			fRec4: [0.0;2],
+			fRec0: [0.0;3],
+		}
+	}
+	fn metadata(&self, m: &mut dyn Meta) { 
+		m.declare("analyzers.lib/name", r"Faust Analyzer Library");
+		m.declare("basics.lib/name", r"Faust Basic Element Library");
+		m.declare("description", r"Wah-wah driven by the level of its input");
+		m.declare("filename", r"autowah.dsp");
+		m.declare("maths.lib/name", r"Faust Math Library");
+		m.declare("name", r"autowah");
+		m.declare("signals.lib/name", r"Faust Signal Routing Library");
+		m.declare("vaeffects.lib/autowah:author", r"Julius O. Smith III");
+		m.declare("vaeffects.lib/name", r"Faust Virtual Analog Filter Effect Library");
+	}
+
+	fn get_sample_rate(&self) -> i32 {
+		return self.fSampleRate;
+	}
+	fn get_num_inputs(&self) -> i32 {
+		return 1;
+	}
+	fn get_num_outputs(&self) -> i32 {
+		return 1;
+	}
+	
+	fn class_init(sample_rate: i32) {
+	}
+	fn instance_reset_params(&mut self) {
+		self.fHslider0 = 0.9;
+	}
+	fn instance_clear(&mut self) {
+		for l0 in 0..2 {
+			self.fRec2[l0 as usize] = 0.0;
+		}
+		for l1 in 0..2 {
+			self.fRec1[l1 as usize] = 0.0;
+		}
+		for l2 in 0..2 {
+			self.fRec3[l2 as usize] = 0.0;
+		}
+		for l3 in 0..2 {
+			self.fRec4[l3 as usize] = 0.0;
+		}
+		for l4 in 0..3 {
+			self.fRec0[l4 as usize] = 0.0;
+		}
+	}
+	fn instance_constants(&mut self, sample_rate: i32) {
+		self.fSampleRate = sample_rate;
+		self.fConst0 = F32::min(1.92e+05, F32::max(1.0, (self.fSampleRate) as F32));
+		self.fConst1 = F32::exp(0.0 - 1e+01 / self.fConst0);
+		self.fConst2 = 1.0 - self.fConst1;
+		self.fConst3 = 1413.7167 / self.fConst0;
+		self.fConst4 = 2827.4333 / self.fConst0;
+	}
+	fn instance_init(&mut self, sample_rate: i32) {
+		self.instance_constants(sample_rate);
+		self.instance_reset_params();
+		self.instance_clear();
+	}
+	fn init(&mut self, sample_rate: i32) {
+		Autowah::class_init(sample_rate);
+		self.instance_init(sample_rate);
+	}
+	
+	fn build_user_interface(&self, ui_interface: &mut dyn UI<Self::T>) {
+		Self::build_user_interface_static(ui_interface);
+	}
+	
+	fn build_user_interface_static(ui_interface: &mut dyn UI<Self::T>) {
+		ui_interface.open_vertical_box("autowah");
+		ui_interface.add_horizontal_slider("level", ParamIndex(0), 0.9, 0.0, 1.0, 0.01);
+		ui_interface.close_box();
+	}
+	
+	fn get_param(&self, param: ParamIndex) -> Option<Self::T> {
+		match param.0 {
+			0 => Some(self.fHslider0),
+			_ => None,
+		}
+	}
+	
+	fn set_param(&mut self, param: ParamIndex, value: Self::T) {
+		match param.0 {
+			0 => { self.fHslider0 = value }
+			_ => {}
+		}
+	}
+	
+	fn compute(&mut self, count: i32, inputs: &[&[Self::T]], outputs: &mut[&mut[Self::T]]) {
+		let (inputs0) = if let [inputs0, ..] = inputs {
+			let inputs0 = inputs0[..count as usize].iter();
+			(inputs0)
+		} else {
+			panic!("wrong number of inputs");
+		};
+		let (outputs0) = if let [outputs0, ..] = outputs {
+			let outputs0 = outputs0[..count as usize].iter_mut();
+			(outputs0)
+		} else {
+			panic!("wrong number of outputs");
+		};
+		let mut fSlow0: F32 = self.fHslider0;
+		let mut fSlow1: F32 = 1.0 - fSlow0;
+		let zipped_iterators = inputs0.zip(outputs0);
+		for (input0, output0) in zipped_iterators {
+			let mut fTemp0: F32 = *input0;
+			let mut fTemp1: F32 = F32::abs(fTemp0);
+			self.fRec2[0] = F32::max(fTemp1, self.fConst1 * self.fRec2[1] + self.fConst2 * fTemp1);
+			self.fRec1[0] = 0.999 * self.fRec1[1] + 0.0001 * F32::powf(4.0, self.fRec2[0]);
+			let mut fTemp2: F32 = F32::powf(2.0, 2.3 * self.fRec2[0]);
+			let mut fTemp3: F32 = 1.0 - self.fConst3 * (fTemp2 / F32::powf(2.0, 2.0 * (1.0 - self.fRec2[0]) + 1.0));
+			self.fRec3[0] = 0.999 * self.fRec3[1] - 0.002 * fTemp3 * F32::cos(self.fConst4 * fTemp2);
+			self.fRec4[0] = 0.999 * self.fRec4[1] + 0.001 * Autowah_faustpower2_f(fTemp3);
+			self.fRec0[0] = fTemp0 * self.fRec1[0] - (self.fRec3[0] * self.fRec0[1] + self.fRec4[0] * self.fRec0[2]);
+			*output0 = fSlow1 * fTemp0 + fSlow0 * (self.fRec0[0] - self.fRec0[1]);
+			self.fRec2[1] = self.fRec2[0];
+			self.fRec1[1] = self.fRec1[0];
+			self.fRec3[1] = self.fRec3[0];
+			self.fRec4[1] = self.fRec4[0];
+			self.fRec0[2] = self.fRec0[1];
+			self.fRec0[1] = self.fRec0[0];
+		}
+	}
+
+}
+
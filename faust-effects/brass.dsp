@@ -1,3 +1,10 @@
 import("stdfaust.lib");
 
-process = pm.brassModel(3, 0.6, 0.9, 0.8);
+declare description "Brass physical model";
+
+tube_length = hslider("tube_length", 3, 0.2, 3, 0.01);
+mute = hslider("mute", 0.6, 0, 1, 0.01);
+lips_tension = hslider("lips_tension", 0.9, 0, 1, 0.01);
+pressure = hslider("pressure", 0.8, 0, 1, 0.01);
+
+process = pm.brassModel(tube_length, mute, lips_tension, pressure);
@@ -1,6 +1,6 @@
 import("stdfaust.lib");
 
-// We don't care about the values here. Just need it for the generation
+declare description "Dynamic range compressor";
 
 ratio = hslider("ratio", 2, 1, 1000, 0.1);
 thresh = hslider("thresh", 20, 0, 100, 1);
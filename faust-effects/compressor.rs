@@ -0,0 +1,164 @@
+/* ------------------------------------------------------------
+name: "compressor"
+Written by hand in the format of the rust backend of Faust, from the port of compressor.dsp
+in faust_effect: it is not the output of faust. Used by build.rs without the faust feature.
+Replace it with the output of: faust -lang rust -cn Compressor compressor.dsp -o compressor.rs
+------------------------------------------------------------ */
+pub struct Compressor {
+	fSampleRate: i32,
+	fConst0: F32,
+	fConst1: F32,
+	fHslider0: F32,
+	fHslider1: F32,
+	fConst2: F32,
+	fHslider2: F32,
+	fRec2: [F32;2],
+	fRec1: [F32;2],
+	fHslider3: F32,
+	fRec0: [F32;2],
+}
+
+impl FaustDsp for Compressor {
+	type T = F32;
+		
+	fn new() -> Compressor { 
+		Compressor {
+			fSampleRate: 0,
+			fConst0: 0.0,
+			fConst1: 0.0,
+			fHslider0: 0.0,
+			fHslider1: 0.0,
+			fConst2: 0.0,
+			fHslider2: 0.0,
+			fRec2: [0.0;2],
+			fRec1: [0.0;2],
+			fHslider3: 0.0,
+			fRec0: [0.0;2],
+		}
+	}
+	fn metadata(&self, m: &mut dyn Meta) { 
+		m.declare("analyzers.lib/name", r"Faust Analyzer Library");
+		m.declare("basics.lib/name", r"Faust Basic Element Library");
+		m.declare("compressors.lib/compressor_mono:author", r"Julius O. Smith III");
+		m.declare("compressors.lib/name", r"Faust Compressor Effect Library");
+		m.declare("description", r"Dynamic range compressor");
+		m.declare("filename", r"compressor.dsp");
+		m.declare("maths.lib/name", r"Faust Math Library");
+		m.declare("name", r"compressor");
+		m.declare("signals.lib/name", r"Faust Signal Routing Library");
+	}
+
+	fn get_sample_rate(&self) -> i32 {
+		return self.fSampleRate;
+	}
+	fn get_num_inputs(&self) -> i32 {
+		return 1;
+	}
+	fn get_num_outputs(&self) -> i32 {
+		return 1;
+	}
+	
+	fn class_init(sample_rate: i32) {
+	}
+	fn instance_reset_params(&mut self) {
+		self.fHslider0 = 0.5;
+		self.fHslider1 = 2.0;
+		self.fHslider2 = 1.0;
+		self.fHslider3 = 2e+01;
+	}
+	fn instance_clear(&mut self) {
+		for l0 in 0..2 {
+			self.fRec2[l0 as usize] = 0.0;
+		}
+		for l1 in 0..2 {
+			self.fRec1[l1 as usize] = 0.0;
+		}
+		for l2 in 0..2 {
+			self.fRec0[l2 as usize] = 0.0;
+		}
+	}
+	fn instance_constants(&mut self, sample_rate: i32) {
+		self.fSampleRate = sample_rate;
+		self.fConst0 = F32::min(1.92e+05, F32::max(1.0, (self.fSampleRate) as F32));
+		self.fConst1 = 2.0 / self.fConst0;
+		self.fConst2 = 1.0 / self.fConst0;
+	}
+	fn instance_init(&mut self, sample_rate: i32) {
+		self.instance_constants(sample_rate);
+		self.instance_reset_params();
+		self.instance_clear();
+	}
+	fn init(&mut self, sample_rate: i32) {
+		Compressor::class_init(sample_rate);
+		self.instance_init(sample_rate);
+	}
+	
+	fn build_user_interface(&self, ui_interface: &mut dyn UI<Self::T>) {
+		Self::build_user_interface_static(ui_interface);
+	}
+	
+	fn build_user_interface_static(ui_interface: &mut dyn UI<Self::T>) {
+		ui_interface.open_vertical_box("compressor");
+		ui_interface.add_horizontal_slider("ratio", ParamIndex(1), 2.0, 1.0, 1e+03, 0.1);
+		ui_interface.add_horizontal_slider("thresh", ParamIndex(3), 2e+01, 0.0, 1e+02, 1.0);
+		ui_interface.add_horizontal_slider("att", ParamIndex(0), 0.5, 0.0, 1e+02, 0.1);
+		ui_interface.add_horizontal_slider("rel", ParamIndex(2), 1.0, 0.0, 1e+02, 0.1);
+		ui_interface.close_box();
+	}
+	
+	fn get_param(&self, param: ParamIndex) -> Option<Self::T> {
+		match param.0 {
+			0 => Some(self.fHslider0),
+			1 => Some(self.fHslider1),
+			2 => Some(self.fHslider2),
+			3 => Some(self.fHslider3),
+			_ => None,
+		}
+	}
+	
+	fn set_param(&mut self, param: ParamIndex, value: Self::T) {
+		match param.0 {
+			0 => { self.fHslider0 = value }
+			1 => { self.fHslider1 = value }
+			2 => { self.fHslider2 = value }
+			3 => { self.fHslider3 = value }
+			_ => {}
+		}
+	}
+	
+	fn compute(&mut self, count: i32, inputs: &[&[Self::T]], outputs: &mut[&mut[Self::T]]) {
+		let (inputs0) = if let [inputs0, ..] = inputs {
+			let inputs0 = inputs0[..count as usize].iter();
+			(inputs0)
+		} else {
+			panic!("wrong number of inputs");
+		};
+		let (outputs0) = if let [outputs0, ..] = outputs {
+			let outputs0 = outputs0[..count as usize].iter_mut();
+			(outputs0)
+		} else {
+			panic!("wrong number of outputs");
+		};
+		let mut fSlow0: F32 = self.fHslider0;
+		let mut fSlow1: F32 = F32::exp(0.0 - self.fConst1 / fSlow0);
+		let mut fSlow2: F32 = (1.0 / self.fHslider1 + -1.0) * (1.0 - fSlow1);
+		let mut fSlow3: F32 = F32::exp(0.0 - self.fConst2 / fSlow0);
+		let mut fSlow4: F32 = F32::exp(0.0 - self.fConst2 / self.fHslider2);
+		let mut fSlow5: F32 = self.fHslider3;
+		let zipped_iterators = inputs0.zip(outputs0);
+		for (input0, output0) in zipped_iterators {
+			let mut fTemp0: F32 = *input0;
+			let mut fTemp1: F32 = F32::abs(fTemp0);
+			let mut fTemp2: F32 = if (self.fRec1[1] > fTemp1) as i32 != 0 { fSlow4 } else { fSlow3 };
+			self.fRec2[0] = self.fRec2[1] * fTemp2 + fTemp1 * (1.0 - fTemp2);
+			self.fRec1[0] = self.fRec2[0];
+			self.fRec0[0] = fSlow1 * self.fRec0[1] + fSlow2 * F32::max(2e+01 * F32::log10(self.fRec1[0]) - fSlow5, 0.0);
+			*output0 = fTemp0 * F32::powf(1e+01, 0.05 * self.fRec0[0]);
+			self.fRec2[1] = self.fRec2[0];
+			self.fRec1[1] = self.fRec1[0];
+			self.fRec0[1] = self.fRec0[0];
+		}
+	}
+
+}
+
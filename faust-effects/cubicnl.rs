@@ -0,0 +1,116 @@
+/* ------------------------------------------------------------
+name: "cubicnl"
+Written by hand in the format of the rust backend of Faust, from the port of cubicnl.dsp
+in faust_effect: it is not the output of faust. Used by build.rs without the faust feature.
+Replace it with the output of: faust -lang rust -cn Cubicnl cubicnl.dsp -o cubicnl.rs
+------------------------------------------------------------ */
+fn Cubicnl_faustpower2_f(value: F32) -> F32 {
+	return value * value;
+}
+pub struct Cubicnl {
+	fHslider0: F32,
+	fHslider1: F32,
+	fSampleRate: i32,
+}
+
+impl FaustDsp for Cubicnl {
+	type T = F32;
+		
+	fn new() -> Cubicnl { 
+		Cubicnl {
+			fHslider0: 0.0,
+			fHslider1: 0.0,
+			fSampleRate: 0,
+		}
+	}
+	fn metadata(&self, m: &mut dyn Meta) { 
+		m.declare("basics.lib/name", r"Faust Basic Element Library");
+		m.declare("description", r"Cubic distortion");
+		m.declare("filename", r"cubicnl.dsp");
+		m.declare("maths.lib/name", r"Faust Math Library");
+		m.declare("misceffects.lib/name", r"Misc Effects Library");
+		m.declare("name", r"cubicnl");
+	}
+
+	fn get_sample_rate(&self) -> i32 {
+		return self.fSampleRate;
+	}
+	fn get_num_inputs(&self) -> i32 {
+		return 1;
+	}
+	fn get_num_outputs(&self) -> i32 {
+		return 1;
+	}
+	
+	fn class_init(sample_rate: i32) {
+	}
+	fn instance_reset_params(&mut self) {
+		self.fHslider0 = 0.9;
+		self.fHslider1 = 0.9;
+	}
+	fn instance_clear(&mut self) {
+	}
+	fn instance_constants(&mut self, sample_rate: i32) {
+		self.fSampleRate = sample_rate;
+	}
+	fn instance_init(&mut self, sample_rate: i32) {
+		self.instance_constants(sample_rate);
+		self.instance_reset_params();
+		self.instance_clear();
+	}
+	fn init(&mut self, sample_rate: i32) {
+		Cubicnl::class_init(sample_rate);
+		self.instance_init(sample_rate);
+	}
+	
+	fn build_user_interface(&self, ui_interface: &mut dyn UI<Self::T>) {
+		Self::build_user_interface_static(ui_interface);
+	}
+	
+	fn build_user_interface_static(ui_interface: &mut dyn UI<Self::T>) {
+		ui_interface.open_vertical_box("cubicnl");
+		ui_interface.add_horizontal_slider("drive", ParamIndex(1), 0.9, 0.0, 1.0, 0.01);
+		ui_interface.add_horizontal_slider("offset", ParamIndex(0), 0.9, 0.0, 1e+02, 0.01);
+		ui_interface.close_box();
+	}
+	
+	fn get_param(&self, param: ParamIndex) -> Option<Self::T> {
+		match param.0 {
+			0 => Some(self.fHslider0),
+			1 => Some(self.fHslider1),
+			_ => None,
+		}
+	}
+	
+	fn set_param(&mut self, param: ParamIndex, value: Self::T) {
+		match param.0 {
+			0 => { self.fHslider0 = value }
+			1 => { self.fHslider1 = value }
+			_ => {}
+		}
+	}
+	
+	fn compute(&mut self, count: i32, inputs: &[&[Self::T]], outputs: &mut[&mut[Self::T]]) {
+		let (inputs0) = if let [inputs0, ..] = inputs {
+			let inputs0 = inputs0[..count as usize].iter();
+			(inputs0)
+		} else {
+			panic!("wrong number of inputs");
+		};
+		let (outputs0) = if let [outputs0, ..] = outputs {
+			let outputs0 = outputs0[..count as usize].iter_mut();
+			(outputs0)
+		} else {
+			panic!("wrong number of outputs");
+		};
+		let mut fSlow0: F32 = self.fHslider0;
+		let mut fSlow1: F32 = F32::powf(1e+01, 2.0 * self.fHslider1);
+		let zipped_iterators = inputs0.zip(outputs0);
+		for (input0, output0) in zipped_iterators {
+			let mut fTemp0: F32 = F32::max(-1.0, F32::min(1.0, fSlow0 + fSlow1 * *input0));
+			*output0 = fTemp0 * (1.0 - 0.33333334 * Cubicnl_faustpower2_f(fTemp0));
+		}
+	}
+
+}
+
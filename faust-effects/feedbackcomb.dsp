@@ -1,6 +1,8 @@
 import("stdfaust.lib");
 
+declare description "Allpass feedback comb filter";
+
 maxdel=16;
-del=5.5;
-aN=0.8;
+del=hslider("delay", 5.5, 1, 16, 0.01);
+aN=hslider("gain", 0.8, -0.99, 0.99, 0.01);
 process =  fi.allpass_fcomb(maxdel,del,aN) ;
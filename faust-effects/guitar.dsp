@@ -1,5 +1,7 @@
 import("stdfaust.lib");
 
+declare hand_ported "Its variants are picked by the scheduler";
+
 length = hslider("length",2,0.2,10,0.1);
 pluckPosition = hslider("pluckPosition", 0.3, 0, 1, 0.01);
 gain = hslider("gain", 0.8, 0,1, 0.01);
@@ -1,5 +1,7 @@
 import("stdfaust.lib");
 
+declare hand_ported "Its variants are picked by the scheduler";
+
 // We don't care about the values here. Just need it for the generation
 
 fb1 = hslider("fb1", 0.5, 0, 1, 0.01);
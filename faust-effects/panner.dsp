@@ -1,4 +1,7 @@
 import("stdfaust.lib");
 
+declare description "Mono to stereo panner";
 
-process = sp.panner(0.8);
+pan = hslider("pan", 0.8, 0, 1, 0.01);
+
+process = sp.panner(pan);
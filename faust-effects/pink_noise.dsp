@@ -1,3 +1,5 @@
 import("stdfaust.lib");
 
+declare description "Pink noise generator";
+
 process = no.pink_noise_vm(10);
@@ -1,3 +1,7 @@
 import("stdfaust.lib");
 
-process = os.sawtooth(440);
+declare description "Band-limited sawtooth oscillator";
+
+freq = hslider("freq", 440, 20, 10000, 1);
+
+process = os.sawtooth(freq);
@@ -1,5 +1,7 @@
 import("stdfaust.lib");
 
+declare hand_ported "Its number of outputs is set by the node";
+
 //num_outputs = hslider("Number of outputs", 2, 1, 10, 1);
 num_outputs = 3;
 rotation = hslider("distance", 0.7, 0, 1, 0.01);
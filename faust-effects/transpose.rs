@@ -0,0 +1,145 @@
+/* ------------------------------------------------------------
+name: "transpose"
+Written by hand in the format of the rust backend of Faust, from the port of transpose.dsp
+in faust_effect: it is not the output of faust. Used by build.rs without the faust feature.
+Replace it with the output of: faust -lang rust -cn Transpose transpose.dsp -o transpose.rs
+------------------------------------------------------------ */
+pub struct Transpose {
+	IOTA0: i32,
+	fVec0: [F32;131072],
+	fHslider0: F32,
+	fHslider1: F32,
+	fRec0: [F32;2],
+	fHslider2: F32,
+	fSampleRate: i32,
+}
+
+impl FaustDsp for Transpose {
+	type T = F32;
+		
+	fn new() -> Transpose { 
+		Transpose {
+			IOTA0: 0,
+			fVec0: [0.0;131072],
+			fHslider0: 0.0,
+			fHslider1: 0.0,
+			fRec0: [0.0;2],
+			fHslider2: 0.0,
+			fSampleRate: 0,
+		}
+	}
+	fn metadata(&self, m: &mut dyn Meta) { 
+		m.declare("basics.lib/name", r"Faust Basic Element Library");
+		m.declare("delays.lib/name", r"Faust Delay Library");
+		m.declare("description", r"Pitch shifter");
+		m.declare("filename", r"transpose.dsp");
+		m.declare("maths.lib/name", r"Faust Math Library");
+		m.declare("misceffects.lib/name", r"Misc Effects Library");
+		m.declare("name", r"transpose");
+	}
+
+	fn get_sample_rate(&self) -> i32 {
+		return self.fSampleRate;
+	}
+	fn get_num_inputs(&self) -> i32 {
+		return 1;
+	}
+	fn get_num_outputs(&self) -> i32 {
+		return 1;
+	}
+	
+	fn class_init(sample_rate: i32) {
+	}
+	fn instance_reset_params(&mut self) {
+		self.fHslider0 = 128.0;
+		self.fHslider1 = 5.0;
+		self.fHslider2 = 32.0;
+	}
+	fn instance_clear(&mut self) {
+		self.IOTA0 = 0;
+		for l0 in 0..131072 {
+			self.fVec0[l0 as usize] = 0.0;
+		}
+		for l1 in 0..2 {
+			self.fRec0[l1 as usize] = 0.0;
+		}
+	}
+	fn instance_constants(&mut self, sample_rate: i32) {
+		self.fSampleRate = sample_rate;
+	}
+	fn instance_init(&mut self, sample_rate: i32) {
+		self.instance_constants(sample_rate);
+		self.instance_reset_params();
+		self.instance_clear();
+	}
+	fn init(&mut self, sample_rate: i32) {
+		Transpose::class_init(sample_rate);
+		self.instance_init(sample_rate);
+	}
+	
+	fn build_user_interface(&self, ui_interface: &mut dyn UI<Self::T>) {
+		Self::build_user_interface_static(ui_interface);
+	}
+	
+	fn build_user_interface_static(ui_interface: &mut dyn UI<Self::T>) {
+		ui_interface.open_vertical_box("transpose");
+		ui_interface.add_horizontal_slider("Window length", ParamIndex(0), 128.0, 1.0, 4096.0, 1.0);
+		ui_interface.add_horizontal_slider("Crossfade duration", ParamIndex(2), 32.0, 1.0, 4096.0, 1.0);
+		ui_interface.add_horizontal_slider("Shift", ParamIndex(1), 5.0, -48.0, 48.0, 1.0);
+		ui_interface.close_box();
+	}
+	
+	fn get_param(&self, param: ParamIndex) -> Option<Self::T> {
+		match param.0 {
+			0 => Some(self.fHslider0),
+			1 => Some(self.fHslider1),
+			2 => Some(self.fHslider2),
+			_ => None,
+		}
+	}
+	
+	fn set_param(&mut self, param: ParamIndex, value: Self::T) {
+		match param.0 {
+			0 => { self.fHslider0 = value }
+			1 => { self.fHslider1 = value }
+			2 => { self.fHslider2 = value }
+			_ => {}
+		}
+	}
+	
+	fn compute(&mut self, count: i32, inputs: &[&[Self::T]], outputs: &mut[&mut[Self::T]]) {
+		let (inputs0) = if let [inputs0, ..] = inputs {
+			let inputs0 = inputs0[..count as usize].iter();
+			(inputs0)
+		} else {
+			panic!("wrong number of inputs");
+		};
+		let (outputs0) = if let [outputs0, ..] = outputs {
+			let outputs0 = outputs0[..count as usize].iter_mut();
+			(outputs0)
+		} else {
+			panic!("wrong number of outputs");
+		};
+		let mut fSlow0: F32 = self.fHslider0;
+		let mut fSlow1: F32 = F32::powf(2.0, 0.083333336 * self.fHslider1);
+		let mut fSlow2: F32 = 1.0 / self.fHslider2;
+		let zipped_iterators = inputs0.zip(outputs0);
+		for (input0, output0) in zipped_iterators {
+			let mut fTemp0: F32 = *input0;
+			self.fVec0[(self.IOTA0 & 131071) as usize] = fTemp0;
+			self.fRec0[0] = (fSlow0 + (self.fRec0[1] + 1.0 - fSlow1)) % fSlow0;
+			let mut iTemp1: i32 = (self.fRec0[0]) as i32;
+			let mut fTemp2: F32 = F32::floor(self.fRec0[0]);
+			let mut fTemp3: F32 = 1.0 - self.fRec0[0];
+			let mut fTemp4: F32 = F32::min(fSlow2 * self.fRec0[0], 1.0);
+			let mut fTemp5: F32 = fSlow0 + self.fRec0[0];
+			let mut iTemp6: i32 = (fTemp5) as i32;
+			let mut fTemp7: F32 = F32::floor(fTemp5);
+			*output0 = (self.fVec0[((i32::wrapping_sub(self.IOTA0, std::cmp::min(65537, std::cmp::max(0, iTemp1)))) & 131071) as usize] * (fTemp2 + fTemp3) + (self.fRec0[0] - fTemp2) * self.fVec0[((i32::wrapping_sub(self.IOTA0, std::cmp::min(65537, std::cmp::max(0, i32::wrapping_add(iTemp1, 1))))) & 131071) as usize]) * fTemp4 + (self.fVec0[((i32::wrapping_sub(self.IOTA0, std::cmp::min(65537, std::cmp::max(0, iTemp6)))) & 131071) as usize] * (fTemp7 + fTemp3 - fSlow0) + (fSlow0 + (self.fRec0[0] - fTemp7)) * self.fVec0[((i32::wrapping_sub(self.IOTA0, std::cmp::min(65537, std::cmp::max(0, i32::wrapping_add(iTemp6, 1))))) & 131071) as usize]) * (1.0 - fTemp4);
+			self.IOTA0 = i32::wrapping_add(self.IOTA0, 1);
+			self.fRec0[1] = self.fRec0[0];
+		}
+	}
+
+}
+
@@ -1,3 +1,10 @@
 import("stdfaust.lib");
 
-process = pm.violinModel(2, 0.4, 0.5, 0.6);
+declare description "Bowed string physical model";
+
+string_length = hslider("string_length", 2, 0.2, 3, 0.01);
+bow_pressure = hslider("bow_pressure", 0.4, 0, 1, 0.01);
+bow_velocity = hslider("bow_velocity", 0.5, 0, 1, 0.01);
+bow_position = hslider("bow_position", 0.6, 0, 1, 0.01);
+
+process = pm.violinModel(string_length, bow_pressure, bow_velocity, bow_position);
@@ -1,3 +1,7 @@
 import("stdfaust.lib");
 
-process = ve.wah4(893);
+declare description "Resonant lowpass filter";
+
+freq = hslider("freq", 893, 20, 10000, 1);
+
+process = ve.wah4(freq);
@@ -1,5 +1,7 @@
 import("stdfaust.lib");
 
+declare hand_ported "Its variants are picked by the scheduler";
+
 rdel = hslider("rdel", 6, 0, 1000, 0.1);
 f1 = hslider("f1", 100, 15, 30000, 1);
 f2 = hslider("f2", 600, 15, 30000, 1);
@@ -4,19 +4,33 @@
 //! They are also meant to be used statically: no specific resamplers
 //! on connections but a resampler is a node akin to the other ones
 use petgraph;
-use petgraph::algo::toposort;
+use petgraph::algo::{has_path_connecting, toposort};
 use petgraph::dot::Dot;
-use petgraph::graph::{EdgeIndex, Edges, NodeIndex, WalkNeighbors};
-use petgraph::visit::{Dfs, Reversed, VisitMap};
-use petgraph::{Directed, Direction, EdgeDirection, Graph};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::stable_graph::{Edges, StableGraph, WalkNeighbors};
+use petgraph::visit::{Dfs, EdgeRef, NodeIndexable, Reversed, VisitMap, Visitable};
+use petgraph::{Directed, Direction, EdgeDirection};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
 
 use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use time::PreciseTime;
 
-use audiograph_parser;
-use faust_effect;
+use audiograph_parallel::ParallelExecutor;
+use audiograph_parser::{self, AudiographError};
+use cost_model::{CostModel, PredictedCost};
+use effect::{CallbackFlags, Quality, TimeMonitor};
+use node_registry::DEFAULT_REGISTRY;
 use samplerate;
+use stats::Stats;
+
+/// Resampling ratio used when degrading the remaining part of a schedule in adaptive mode
+pub const DEGRADATION_RATIO: f64 = 0.5;
 
 #[derive(Debug)]
 pub enum AudioGraphError {
@@ -78,6 +92,7 @@ impl fmt::Display for DspEdge {
 pub struct DspNode {
     node_infos: audiograph_parser::Node,
     node_processor: Box<dyn AudioEffect>,
+    exec_time: Stats, //Mean execution time at nominal samplerate, including copies from and to the edges
 }
 
 impl DspNode {
@@ -88,59 +103,30 @@ impl DspNode {
         DspNode {
             node_infos,
             node_processor,
+            exec_time: Stats::new(),
         }
     }
 
-    pub fn new(node_infos: audiograph_parser::Node, nb_channels: usize) -> DspNode {
-        let node_processor: Box<dyn AudioEffect> = match node_infos.class_name.as_str() {
-            "osc" => Box::new(Oscillator::from_node_infos(&node_infos)),
-            "mod" => Box::new(Modulator::from_node_infos(&node_infos)),
-            "mix" => Box::new(InputsOutputsAdaptor::from_node_infos(&node_infos)),
-            "resampler" => Box::new(Resampler::from_node_infos(&node_infos)),
-            "source" => Box::new(InputsOutputsAdaptor::new(
-                nb_channels,
-                node_infos.nb_outlets as usize,
-            )),
-            //TODO: Stack overflow if there
-            // Need to use ulimit -s 32768 to increase the size of the stack
-            "guitar" => Box::new(faust_effect::Guitar::from_node_infos(&node_infos)),
-            "transpose" => Box::new(faust_effect::Transposer::from_node_infos(&node_infos)),
-            "zita_reverb" => Box::new(faust_effect::ZitaReverb::from_node_infos(&node_infos)),
-            "freeverb" => Box::new(faust_effect::MonoFreeverb::from_node_infos(&node_infos)),
-            "compressor" => Box::new(faust_effect::Compressor::from_node_infos(&node_infos)),
-            "autowah" => Box::new(faust_effect::Autowah::from_node_infos(&node_infos)),
-            "cubicnl" => Box::new(faust_effect::Cubicnl::from_node_infos(&node_infos)),
-            "sink" => Box::new(InputsOutputsAdaptor::new(
-                node_infos.nb_inlets as usize,
-                nb_channels,
-            )),
-            _ => {
-                //We replace it by a default effect
-                println!(
-                    "Unkwown node {:?}. Replacing it by a known one.",
-                    node_infos
-                );
-                if node_infos.nb_inlets == 0 && node_infos.nb_outlets == 1 {
-                    Box::new(Oscillator::new(0., 440, 1.))
-                } else if node_infos.nb_inlets == 1 && node_infos.nb_outlets == 1 {
-                    Box::new(Modulator::new(0., 445, 1.))
-                } else if node_infos.nb_outlets == 0 {
-                    panic!("Not handled yet!")
-                } else {
-                    Box::new(InputsOutputsAdaptor::from_node_infos(&node_infos))
-                }
-            }
-        };
-
-        DspNode {
-            node_infos,
-            node_processor,
-        }
+    /// Node with a processor from the built-in kinds of nodes
+    pub fn new(
+        node_infos: audiograph_parser::Node,
+        nb_channels: usize,
+    ) -> Result<DspNode, AudiographError> {
+        DEFAULT_REGISTRY.create_node(node_infos, nb_channels)
     }
 
     pub fn node_infos(&self) -> &audiograph_parser::Node {
         &self.node_infos
     }
+
+    pub fn into_parts(self) -> (audiograph_parser::Node, Box<dyn AudioEffect>) {
+        (self.node_infos, self.node_processor)
+    }
+
+    /// Mean execution time of the node (in microseconds) measured when it is executed at the nominal samplerate
+    pub fn mean_execution_time(&self) -> f64 {
+        self.exec_time.mean
+    }
 }
 
 impl fmt::Display for DspNode {
@@ -149,15 +135,138 @@ impl fmt::Display for DspNode {
     }
 }
 
-pub trait AudioEffect: fmt::Display {
+/// Type of the values of a parameter. Values are always passed as `f32`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterType {
+    Float,
+    /// Values are rounded to the nearest integer
+    Integer,
+    /// 0 or 1
+    Boolean,
+}
+
+/// Description of a parameter of an audio effect
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub kind: ParameterType,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+impl Parameter {
+    pub const fn new(
+        name: &'static str,
+        kind: ParameterType,
+        min: f32,
+        max: f32,
+        default: f32,
+    ) -> Parameter {
+        Parameter {
+            name,
+            kind,
+            min,
+            max,
+            default,
+        }
+    }
+
+    /// Clamp `value` to the range of the parameter and round it according to its type
+    pub fn normalize(&self, value: f32) -> f32 {
+        let value = match self.kind {
+            ParameterType::Float => value,
+            ParameterType::Integer => value.round(),
+            ParameterType::Boolean => {
+                if value >= 0.5 {
+                    1.
+                } else {
+                    0.
+                }
+            }
+        };
+        value.max(self.min).min(self.max)
+    }
+}
+
+/// Nodes are `Send`: the parallel executor runs them on its worker threads.
+pub trait AudioEffect: fmt::Display + Send {
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]);
 
     fn nb_inputs(&self) -> usize;
     fn nb_outputs(&self) -> usize;
 
-    fn check_io_node_infos(&self, node_infos: &audiograph_parser::Node) {
-        assert_eq!(node_infos.nb_inlets as usize, self.nb_inputs());
-        assert_eq!(node_infos.nb_outlets as usize, self.nb_outputs());
+    /// Parameters which can be changed between two cycles. The index of a parameter is its position in the slice.
+    fn parameters(&self) -> &[Parameter] {
+        &[]
+    }
+
+    /// Current value of the parameter at `index`
+    fn get_parameter(&self, _index: usize) -> Option<f32> {
+        None
+    }
+
+    /// Store the value of the parameter at `index`. The value has already been normalized
+    /// by `set_parameter`, which should be used instead.
+    fn store_parameter(&mut self, _index: usize, _value: f32) {}
+
+    /// Set the parameter at `index` to `value`, clamped to the range of the parameter.
+    /// Returns false if there is no such parameter.
+    fn set_parameter(&mut self, index: usize, value: f32) -> bool {
+        let value = match self.parameters().get(index) {
+            Some(parameter) => parameter.normalize(value),
+            None => return false,
+        };
+        self.store_parameter(index, value);
+        true
+    }
+
+    fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameters()
+            .iter()
+            .position(|parameter| parameter.name == name)
+    }
+
+    fn get_parameter_by_name(&self, name: &str) -> Option<f32> {
+        self.parameter_index(name)
+            .and_then(|index| self.get_parameter(index))
+    }
+
+    fn set_parameter_by_name(&mut self, name: &str, value: f32) -> bool {
+        match self.parameter_index(name) {
+            Some(index) => self.set_parameter(index, value),
+            None => false,
+        }
+    }
+
+    /// Number of versions of the effect. Variant 0 is the nominal one, the other ones are cheaper
+    /// and of lower quality, and can be substituted to it when a deadline is about to be missed.
+    fn nb_variants(&self) -> usize {
+        1
+    }
+
+    /// Variant executed by `process`
+    fn variant(&self) -> usize {
+        0
+    }
+
+    /// Execute `variant` from the next cycle on. Returns false if there is no such variant.
+    fn set_variant(&mut self, variant: usize) -> bool {
+        variant == 0
+    }
+
+    /// Execution time of `variant` relative to the nominal variant, as measured by
+    /// `cost_model::measure_variant_costs`
+    fn variant_cost(&self, _variant: usize) -> f64 {
+        1.
+    }
+
+    /// Check that `node_infos` declares the inlets and outlets of the effect
+    fn check_io_node_infos(
+        &self,
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<(), AudiographError> {
+        node_infos.check_ports(self.nb_inputs() as u32, self.nb_outputs() as u32)
     }
 }
 
@@ -176,500 +285,2957 @@ impl AudioEffect for Box<AudioEffect> {
     fn nb_outputs(&self) -> usize {
         (**self).nb_outputs()
     }
-}
 
-/// Represents an audiograph of nodes with ports.
-/// Beware that NodeIndex are invalidated after removal of nodes (so just don't remove anything).
-/// If removing is necessary, we could consider using stable_graph instead of graph.
-pub struct AudioGraph {
-    pub graph: Graph<DspNode, DspEdge>,
-    schedule: Vec<NodeIndex<u32>>,
-    size: usize,   //Default size of a buffer
-    channels: u32, //Number of channels,
-    frames_per_buffer: u32,
-    input_node_index: NodeIndex,
-    input_edges: Vec<DspEdge>,
-    has_source: bool, //Whether there is a source from the audio soundcard or only synth
-    output_node_index: NodeIndex,
-    output_edges: Vec<DspEdge>,
-    nominal_samplerate: u32,
-}
+    #[inline]
+    fn parameters(&self) -> &[Parameter] {
+        (**self).parameters()
+    }
 
-impl AudioGraph {
-    pub fn new(frames_per_buffer: u32, channels: u32, samplerate: u32) -> AudioGraph {
-        let input_node_infos = audiograph_parser::Node {
-            class_name: "real_source".to_string(),
-            nb_outlets: 1,
-            ..Default::default()
-        };
-        let output_node_infos = audiograph_parser::Node {
-            class_name: "real_sink".to_string(),
-            nb_inlets: 1,
-            ..Default::default()
-        };
-        let mut graph = Graph::new();
-        let input_node = DspNode::from_parts(
-            input_node_infos,
-            Box::new(Source {
-                nb_channels: channels as usize,
-                frames_per_buffer,
-            }),
-        );
-        let output_node = DspNode::from_parts(
-            output_node_infos,
-            Box::new(Sink {
-                nb_channels: channels as usize,
-                frames_per_buffer,
-            }),
-        );
-        let input_node_index = graph.add_node(input_node);
-        let output_node_index = graph.add_node(output_node);
-        let size = frames_per_buffer as usize;
+    #[inline]
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        (**self).get_parameter(index)
+    }
 
-        AudioGraph {
-            graph,
-            schedule: Vec::new(),
-            size,
-            frames_per_buffer,
-            channels,
-            input_node_index,
-            input_edges: vec![DspEdge::new(1, 1, size, samplerate); channels as usize],
-            has_source: false,
-            output_node_index,
-            output_edges: vec![DspEdge::new(1, 1, size, samplerate); channels as usize],
-            nominal_samplerate: samplerate,
-        }
+    #[inline]
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        (**self).store_parameter(index, value)
     }
 
-    pub fn set_nominal_samplerate(&mut self, samplerate: u32) {
-        self.nominal_samplerate = samplerate;
-        for input_edge in self.input_edges.iter_mut() {
-            input_edge.samplerate = samplerate;
-        }
-        for output_edge in self.output_edges.iter_mut() {
-            output_edge.samplerate = samplerate;
-        }
+    #[inline]
+    fn nb_variants(&self) -> usize {
+        (**self).nb_variants()
     }
 
-    pub fn source_node(&self) -> NodeIndex {
-        self.input_node_index
+    #[inline]
+    fn variant(&self) -> usize {
+        (**self).variant()
     }
 
-    pub fn sink_node(&self) -> NodeIndex {
-        self.output_node_index
+    #[inline]
+    fn set_variant(&mut self, variant: usize) -> bool {
+        (**self).set_variant(variant)
     }
 
-    pub fn nb_nodes(&self) -> usize {
-        //Nb of active nodes
-        self.graph.node_count()
+    #[inline]
+    fn variant_cost(&self, variant: usize) -> f64 {
+        (**self).variant_cost(variant)
     }
+}
 
-    pub fn nb_edges(&self) -> usize {
-        self.graph.edge_count()
+/// Changes made to the execution of the pooled schedule when degrading its end in adaptive mode.
+/// They are undone at the end of the cycle. The vector is allocated at scheduling time
+/// with enough capacity for all the nodes, so that degrading never allocates.
+#[derive(Default)]
+struct Degradation {
+    /// Position in the pooled schedule of the first node executed at a lower samplerate. All the nodes after
+    /// it but the sink are degraded too.
+    from: Option<usize>,
+    /// Nodes switched to a cheaper variant, with the variant they were executing before
+    switched_variants: Vec<(NodeIndex, usize)>,
+    /// Number of edges whose resampler failed during the cycle
+    nb_resampling_errors: usize,
+}
+
+impl Degradation {
+    fn with_capacity(nb_nodes: usize) -> Degradation {
+        Degradation {
+            from: None,
+            switched_variants: Vec::with_capacity(nb_nodes),
+            nb_resampling_errors: 0,
+        }
     }
 
-    pub fn nb_channels(&self) -> u32 {
-        self.channels
+    /// Whether the node at `position` in the pooled schedule, or the real source if it is `None`,
+    /// is executed at a lower samplerate when the nodes from `from` are degraded
+    fn is_degraded(from: usize, position: Option<usize>) -> bool {
+        position.map_or(false, |position| position >= from)
     }
+}
 
-    pub fn default_buffer_size(&self) -> usize {
-        self.size
+/// Cycle in which the nodes of the pooled schedule from `from` but the sink are degraded
+#[derive(Clone, Copy)]
+struct DegradedCycle {
+    from: usize,
+    cycle: u64,
+}
+
+/// Size and samplerate of a buffer of `size` samples at `samplerate` in the degraded part of the graph
+fn degraded_format(size: usize, samplerate: u32) -> (usize, u32) {
+    (
+        (size as f64 * DEGRADATION_RATIO) as usize,
+        (samplerate as f64 * DEGRADATION_RATIO) as u32,
+    )
+}
+
+/// Resampler preallocated at scheduling time for an edge which can cross the boundary of the degraded part
+/// of the graph, with a buffer for the resampled samples as large as the nominal buffer of the edge.
+/// It is switched in at every degraded cycle and keeps its state from one cycle to the next,
+/// but is reset when it is switched in again after cycles without it, so that the samples left over from
+/// the previous degradation do not leak into the next one.
+/// The edge is identified by its endpoints and ports, which a control handle can translate to the indexes of the
+/// running graph.
+struct EdgeResampler {
+    src: NodeIndex,
+    src_port: u32,
+    dst: NodeIndex,
+    dst_port: u32,
+    resampler: samplerate::Resampler,
+    buffer: DspEdge,
+    last_cycle: u64, //Last cycle in which it was switched in
+}
+
+impl EdgeResampler {
+    fn new(
+        (src, src_port, dst, dst_port): (NodeIndex, u32, NodeIndex, u32),
+        buffer_size: usize,
+        samplerate: u32,
+    ) -> EdgeResampler {
+        EdgeResampler {
+            src,
+            src_port,
+            dst,
+            dst_port,
+            resampler: samplerate::Resampler::new(
+                samplerate::ConverterType::Linear,
+                1,
+                DEGRADATION_RATIO,
+            ),
+            buffer: DspEdge::new(1, 1, buffer_size, samplerate),
+            last_cycle: 0,
+        }
     }
 
-    pub fn frames_per_buffer(&self) -> u32 {
-        self.frames_per_buffer
+    /// Switch the resampler in for cycle `cycle`, resetting it if it was not used in the previous cycle
+    fn switch_in(&mut self, cycle: u64) {
+        if self.last_cycle + 1 != cycle {
+            self.resampler.reset();
+        }
+        self.last_cycle = cycle;
     }
 
-    pub fn add_node(&mut self, node: DspNode) -> NodeIndex {
-        let nb_inputs = node.node_processor.nb_inputs();
-        let nb_outputs = node.node_processor.nb_outputs();
-        let max_nb = std::cmp::max(nb_inputs, nb_outputs);
+    fn key(&self) -> (NodeIndex, u32, NodeIndex, u32) {
+        (self.src, self.src_port, self.dst, self.dst_port)
+    }
 
-        if max_nb > self.input_edges.len() {
-            //println!("Input: old={} new={}", self.input_edges.len(), max_nb);
-            self.input_edges.resize(
-                max_nb,
-                DspEdge::new(1, 1, self.size, self.nominal_samplerate),
-            );
+    /// Position among `resamplers`, the resamplers of the edges going into a node, of the one of `edge` coming from `src`
+    fn find(resamplers: &[EdgeResampler], src: NodeIndex, edge: &DspEdge) -> Option<usize> {
+        resamplers.iter().position(|resampler| {
+            resampler.src == src
+                && resampler.src_port == edge.src_port()
+                && resampler.dst_port == edge.dst_port()
+        })
+    }
+
+    /// Make room in the buffer for `size` samples, out of the audio thread
+    fn reserve(&mut self, size: usize) {
+        if self.buffer.buffer.capacity() < size {
+            self.buffer = DspEdge::new(1, 1, size, self.buffer.samplerate);
         }
-        // Actually not enough because there can be one port but several edges coming from it.
-        // So we need to call update_temp_buffers in update_schedule
-        if max_nb > self.output_edges.len() {
-            //println!("Output: old={} new={}", self.output_edges.len(), max_nb);
-            self.output_edges.resize(
-                max_nb,
-                DspEdge::new(1, 1, self.size, self.nominal_samplerate),
-            );
+    }
+
+    /// Resample `input` into the buffer of the resampler, with `size` samples at `samplerate`.
+    /// `size` is at most the nominal size of the edge, so the buffer is never reallocated.
+    /// Samples the converter has not generated yet are silent.
+    ///
+    /// If the converter fails, it is reset and the edge is passed through without filtering,
+    /// each output sample being the nearest input sample, so that the cycle still completes.
+    /// Returns false in that case.
+    fn resample(&mut self, input: &DspEdge, size: usize, samplerate: u32) -> bool {
+        debug_assert!(size <= self.buffer.buffer.capacity());
+        self.buffer.resize(size);
+        self.buffer.samplerate = samplerate;
+        self.resampler
+            .set_src_ratio(samplerate as f64 / input.samplerate as f64);
+        let generated = self
+            .resampler
+            .resample(input.buffer(), self.buffer.buffer_mut())
+            .map(|(_, generated)| generated as usize)
+            .ok();
+        match generated {
+            Some(generated) => {
+                for sample in self.buffer.buffer_mut()[generated.min(size)..].iter_mut() {
+                    *sample = 0.;
+                }
+                true
+            }
+            None => {
+                self.resampler.reset();
+                let input = input.buffer();
+                for (i, sample) in self.buffer.buffer_mut().iter_mut().enumerate() {
+                    *sample = input.get(i * input.len() / size).cloned().unwrap_or(0.);
+                }
+                false
+            }
         }
-        self.graph.add_node(node)
     }
+}
 
-    pub fn add_input(
-        &mut self,
-        src: DspNode,
-        src_port: u32,
-        dst: NodeIndex,
-        dst_port: u32,
-    ) -> NodeIndex {
+/// Preallocated buffers used by the pooled executor instead of the buffers of the edges.
+/// A buffer is identified by its index in the pool.
+#[derive(Default)]
+struct BufferPool {
+    buffers: Vec<DspEdge>,
+    /// Buffers which can be reused, by size and samplerate
+    free: HashMap<(usize, u32), Vec<usize>>,
+    /// Buffers are never reused by the parallel executor, as nodes executed in parallel could share them
+    no_reuse: bool,
+}
+
+impl BufferPool {
+    /// Add a new buffer filled with zeros
+    fn add(&mut self, size: usize, samplerate: u32) -> usize {
+        self.buffers.push(DspEdge::new(1, 1, size, samplerate));
+        self.buffers.len() - 1
+    }
+
+    /// Get a free buffer, or add a new one if there is none of the right size and samplerate
+    fn allocate(&mut self, size: usize, samplerate: u32) -> usize {
+        match self
+            .free
+            .get_mut(&(size, samplerate))
+            .and_then(|free| free.pop())
         {
-            assert!(src_port <= src.node_processor.nb_outputs() as u32 && src_port >= 1);
-            let dst_node = self.graph.node_weight(dst).unwrap();
-            assert!(dst_port <= dst_node.node_processor.nb_inputs() as u32 && dst_port >= 1);
+            Some(buffer) => buffer,
+            None => self.add(size, samplerate),
         }
-        let parent = self.graph.add_node(src);
-        self.graph.add_edge(
-            parent,
-            dst,
-            DspEdge::new(src_port, dst_port, self.size, self.nominal_samplerate),
-        );
-        parent
     }
 
-    pub fn add_output(
-        &mut self,
-        src: NodeIndex,
-        src_port: u32,
-        dst: DspNode,
-        dst_port: u32,
-    ) -> NodeIndex {
-        {
-            let src_node = self.graph.node_weight(src).unwrap();
-            assert!(src_port <= src_node.node_processor.nb_outputs() as u32 && src_port >= 1);
-            assert!(dst_port <= dst.node_processor.nb_inputs() as u32 && dst_port >= 1);
+    fn release(&mut self, buffer: usize) {
+        if self.no_reuse {
+            return;
         }
-        let child = self.graph.add_node(dst);
-        self.graph.add_edge(
-            src,
-            child,
-            DspEdge::new(src_port, dst_port, self.size, self.nominal_samplerate),
+        let key = (
+            self.buffers[buffer].buffer().len(),
+            self.buffers[buffer].samplerate,
         );
-        child
+        self.free.entry(key).or_insert_with(Vec::new).push(buffer);
     }
 
-    pub fn remove_connection(&mut self, src: NodeIndex, dest: NodeIndex) {
-        if let Some(edge_index) = self.graph.find_edge(src, dest) {
-            self.graph
-                .remove_edge(edge_index)
-                .expect("Edge should exist");
+    /// Sum buffer `src` into buffer `dst`
+    fn mix(&mut self, dst: usize, src: usize) {
+        debug_assert_ne!(dst, src);
+        if dst < src {
+            let (left, right) = self.buffers.split_at_mut(src);
+            mixer(left[dst].buffer_mut(), right[0].buffer());
+        } else {
+            let (left, right) = self.buffers.split_at_mut(dst);
+            mixer(right[0].buffer_mut(), left[src].buffer());
         }
     }
+}
 
-    pub fn remove_edge(&mut self, edge: EdgeIndex) {
-        self.graph.remove_edge(edge);
-    }
+/// Buffers of the pool used by a node of the schedule in the pooled executor
+struct PooledNode {
+    node: NodeIndex,
+    /// Buffer read by every inlet
+    inputs: Vec<usize>,
+    /// Buffers of the edges arriving at every inlet
+    sources: Vec<Vec<usize>>,
+    /// For every edge of `sources`, position of its source node in the pooled schedule (`None` for the
+    /// real source) and position of its resampler among the resamplers of the edges going into the node
+    source_edges: Vec<Vec<(Option<usize>, usize)>>,
+    /// Size and samplerate of the inputs
+    input_size: usize,
+    input_samplerate: u32,
+    /// Buffers summing the edges arriving at an inlet with several edges, with the buffers to sum
+    mixes: Vec<(usize, Vec<usize>)>,
+    /// Buffer written by every outlet. Empty for the sink, which writes to the soundcard.
+    outputs: Vec<usize>,
+    /// Size and samplerate of the outputs
+    output_size: usize,
+    output_samplerate: u32,
+}
 
-    pub fn add_connection(
-        &mut self,
+/// Interchange buffers of a thread of the parallel executor
+struct WorkerEdges {
+    inputs: Vec<DspEdge>,
+    outputs: Vec<DspEdge>,
+}
+
+/// Nodes of the graph, in pooled schedule order, and buffers of the pool, shared by the threads
+/// of the parallel executor during a cycle.
+/// A node is executed by only one thread, writes only its own output buffers and reads buffers only after
+/// the nodes writing them have been executed. Every thread uses its own `WorkerEdges`.
+struct ParallelCycle {
+    nodes: *const *mut DspNode,
+    buffers: *mut DspEdge,
+    workers: *mut WorkerEdges,
+    /// Resamplers of the edges going into every node, only used by the thread executing the node
+    resamplers: *mut Vec<EdgeResampler>,
+}
+
+// The nodes are `Send`, as required by `AudioEffect`, and each of them is used by one thread at a time
+unsafe impl Sync for ParallelCycle {}
+
+/// Pointers to the nodes of the pooled schedule, refreshed at every cycle of the parallel executor
+#[derive(Default)]
+struct NodePointers(Vec<*mut DspNode>);
+
+// Only dereferenced during a cycle of the parallel executor, as the nodes of `ParallelCycle`
+unsafe impl Send for NodePointers {}
+
+/// Everything `update_schedule` computes. A control handle computes it on its copy of the graph,
+/// out of the audio thread, and the audiograph swaps it in at the start of a cycle.
+#[derive(Default)]
+struct Schedule {
+    topological_order: Vec<NodeIndex>,
+    schedule: Vec<NodeIndex>,
+    schedule_expected_time: Vec<f64>,
+    has_source: bool,
+    pool: BufferPool,
+    pooled_schedule: Vec<PooledNode>,
+    source_outputs: Vec<usize>,
+    parallel: Option<ParallelExecutor>,
+    worker_edges: Vec<WorkerEdges>,
+    node_pointers: NodePointers,
+    input_edges: Vec<DspEdge>,
+    output_edges: Vec<DspEdge>,
+    degradation: Degradation,
+    edge_resamplers: Vec<Vec<EdgeResampler>>,
+    /// Size and samplerate of the edges going out of every node of the schedule. They are computed
+    /// by a control handle, and the audiograph only has to apply them to its edges.
+    output_formats: Vec<(usize, u32)>,
+}
+
+/// Edit of a running audiograph, sent by a `ControlHandle`.
+/// Nodes are identified by their index in the copy of the graph kept by the handle, which the audiograph
+/// maps to the index of the node in its own graph.
+pub enum GraphCommand {
+    /// The node gets the given index in the copy of the graph of the handle
+    AddNode(NodeIndex, DspNode),
+    RemoveNode(NodeIndex),
+    Connect {
         src: NodeIndex,
-        src_port: u32,
+        dst: NodeIndex,
+        edge: DspEdge,
+    },
+    Disconnect {
+        src: NodeIndex,
+        src_port: u32,
         dst: NodeIndex,
         dst_port: u32,
-    ) -> EdgeIndex {
-        self.graph.add_edge(
+    },
+    SetParameter {
+        node: NodeIndex,
+        index: usize,
+        value: f32,
+    },
+    /// Swap the processor of a node. The ports which do not exist any more have already been disconnected.
+    ReplaceNode(NodeIndex, DspNode),
+}
+
+/// Commands committed together, with the schedule of the graph once they are applied
+/// if they change the structure of the graph
+struct Transaction {
+    commands: Vec<GraphCommand>,
+    schedule: Option<Box<Schedule>>,
+    /// Empty map from the indexes of the handle to the indexes of the audiograph, with enough capacity for all
+    /// the nodes of the handle, if the current one is too small
+    handle_nodes: Option<Vec<NodeIndex>>,
+    /// Empty graph with enough capacity for the nodes and edges of the audiograph while the commands are applied,
+    /// if the current one is too small
+    graph: Option<StableGraph<DspNode, DspEdge>>,
+    /// What the commands replace in the audiograph, with room for all of it. The transaction
+    /// is sent back with it to the control thread, so that it is deallocated there.
+    garbage: Vec<Garbage>,
+}
+
+/// What the audio thread replaces in the audiograph and sends back to the control thread
+enum Garbage {
+    Node(DspNode),
+    Edge(DspEdge),
+    Schedule(Box<Schedule>),
+    HandleNodes(Vec<NodeIndex>),
+    Graph(StableGraph<DspNode, DspEdge>),
+}
+
+/// Receiving end of a control handle, owned by the audiograph
+struct ControlQueue {
+    transactions: Receiver<Transaction>,
+    garbage: Sender<Transaction>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ControlError {
+    UnknownNode(NodeIndex),
+    /// The real source and sink cannot be removed or replaced
+    ExternalNode(NodeIndex),
+    UnknownPort(NodeIndex, u32),
+    UnknownConnection,
+    UnknownParameter(NodeIndex, String),
+    /// The connection would create a cycle
+    Cycle,
+    /// The audio thread has not consumed the previous transactions yet
+    QueueFull,
+    /// The audiograph has been dropped
+    Disconnected,
+}
+
+/// Node with only the ports of a node, used in the copy of the graph kept by a control handle
+struct NodeShape {
+    nb_inputs: usize,
+    nb_outputs: usize,
+    parameters: Vec<Parameter>,
+}
+
+impl fmt::Display for NodeShape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "shape({}, {})", self.nb_inputs, self.nb_outputs)
+    }
+}
+
+impl AudioEffect for NodeShape {
+    fn process(&mut self, _inputs: &[DspEdge], _outputs: &mut [DspEdge]) {
+        unreachable!("A node shape is never executed")
+    }
+
+    fn nb_inputs(&self) -> usize {
+        self.nb_inputs
+    }
+    fn nb_outputs(&self) -> usize {
+        self.nb_outputs
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+}
+
+/// Processor left in the place of a node moved to another graph
+struct MovedNode;
+
+impl fmt::Display for MovedNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "moved")
+    }
+}
+
+impl AudioEffect for MovedNode {
+    fn process(&mut self, _inputs: &[DspEdge], _outputs: &mut [DspEdge]) {
+        unreachable!("A moved node is never executed")
+    }
+
+    fn nb_inputs(&self) -> usize {
+        0
+    }
+    fn nb_outputs(&self) -> usize {
+        0
+    }
+}
+
+impl DspNode {
+    /// Node without processor nor ports, which does not allocate
+    fn moved() -> DspNode {
+        DspNode::from_parts(audiograph_parser::Node::new(), Box::new(MovedNode))
+    }
+
+    fn shape(&self) -> DspNode {
+        DspNode::from_parts(
+            self.node_infos.clone(),
+            Box::new(NodeShape {
+                nb_inputs: self.node_processor.nb_inputs(),
+                nb_outputs: self.node_processor.nb_outputs(),
+                parameters: self.node_processor.parameters().to_vec(),
+            }),
+        )
+    }
+}
+
+/// Edits a running audiograph from a control thread.
+/// Edits are recorded by the handle and applied on a copy of the structure of the graph, which gives the
+/// indexes of the new nodes and is used to compute the new schedule out of the audio thread.
+/// The indexes of the handle are those of the copy: the audiograph maps them to the indexes of its own nodes,
+/// which can differ if nodes have been added or removed directly on the audiograph.
+/// `commit` sends them through a bounded lock-free queue, and the audiograph applies them all at
+/// the start of its next cycle.
+pub struct ControlHandle {
+    shadow: AudioGraph,
+    pending: Vec<GraphCommand>,
+    structure_changed: bool,
+    handle_nodes_capacity: usize, //Capacity of the map of the audiograph from the indexes of the handle to its own
+    graph_capacity: (usize, usize), //Capacity of the nodes and edges of the graph of the audiograph
+    peak_size: (usize, usize), //Largest numbers of nodes and edges of the copy of the graph since the last commit
+    transactions: Sender<Transaction>,
+    garbage: Receiver<Transaction>,
+}
+
+impl ControlHandle {
+    fn check_node(&self, node: NodeIndex) -> Result<(), ControlError> {
+        if !self.shadow.graph.contains_node(node) {
+            Err(ControlError::UnknownNode(node))
+        } else if node == self.shadow.input_node_index || node == self.shadow.output_node_index {
+            Err(ControlError::ExternalNode(node))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Record the size of the copy of the graph, which grows at the same time as the graph of the audiograph
+    fn update_peak_size(&mut self) {
+        self.peak_size.0 = self.peak_size.0.max(self.shadow.graph.node_count());
+        self.peak_size.1 = self.peak_size.1.max(self.shadow.graph.edge_count());
+    }
+
+    pub fn add_node(&mut self, node: DspNode) -> NodeIndex {
+        let index = self.shadow.graph.add_node(node.shape());
+        self.update_peak_size();
+        self.pending.push(GraphCommand::AddNode(index, node));
+        self.structure_changed = true;
+        index
+    }
+
+    pub fn remove_node(&mut self, node: NodeIndex) -> Result<(), ControlError> {
+        self.check_node(node)?;
+        //The edges are disconnected first, so that the audiograph sends them back to be deallocated
+        let edges = self
+            .shadow
+            .inputs(node)
+            .chain(self.shadow.outputs(node))
+            .map(|edge| {
+                let (src, dst) = (edge.source(), edge.target());
+                (src, edge.weight().src_port(), dst, edge.weight().dst_port())
+            })
+            .collect::<Vec<_>>();
+        for (src, src_port, dst, dst_port) in edges.into_iter() {
+            self.disconnect(src, src_port, dst, dst_port)?;
+        }
+        self.shadow.graph.remove_node(node);
+        self.pending.push(GraphCommand::RemoveNode(node));
+        self.structure_changed = true;
+        Ok(())
+    }
+
+    /// Connect port `src_port` of `src` to port `dst_port` of `dst`. The real source and sink can be connected.
+    pub fn connect(
+        &mut self,
+        src: NodeIndex,
+        src_port: u32,
+        dst: NodeIndex,
+        dst_port: u32,
+    ) -> Result<(), ControlError> {
+        for &node in [src, dst].iter() {
+            if !self.shadow.graph.contains_node(node) {
+                return Err(ControlError::UnknownNode(node));
+            }
+        }
+        if src_port < 1 || src_port as usize > self.shadow.graph[src].node_processor.nb_outputs() {
+            return Err(ControlError::UnknownPort(src, src_port));
+        }
+        if dst_port < 1 || dst_port as usize > self.shadow.graph[dst].node_processor.nb_inputs() {
+            return Err(ControlError::UnknownPort(dst, dst_port));
+        }
+        if has_path_connecting(&self.shadow.graph, dst, src, None) {
+            return Err(ControlError::Cycle);
+        }
+        let edge = DspEdge::new(
+            src_port,
+            dst_port,
+            self.shadow.size,
+            self.shadow.nominal_samplerate,
+        );
+        self.shadow.graph.add_edge(src, dst, edge.clone());
+        self.update_peak_size();
+        self.pending.push(GraphCommand::Connect { src, dst, edge });
+        self.structure_changed = true;
+        Ok(())
+    }
+
+    pub fn disconnect(
+        &mut self,
+        src: NodeIndex,
+        src_port: u32,
+        dst: NodeIndex,
+        dst_port: u32,
+    ) -> Result<(), ControlError> {
+        let edge = self
+            .shadow
+            .outputs(src)
+            .find(|edge| {
+                edge.target() == dst
+                    && edge.weight().src_port() == src_port
+                    && edge.weight().dst_port() == dst_port
+            })
+            .map(|edge| edge.id())
+            .ok_or(ControlError::UnknownConnection)?;
+        self.shadow.graph.remove_edge(edge);
+        self.pending.push(GraphCommand::Disconnect {
             src,
+            src_port,
             dst,
-            DspEdge::new(src_port, dst_port, self.size, self.nominal_samplerate),
-        )
+            dst_port,
+        });
+        self.structure_changed = true;
+        Ok(())
+    }
+
+    pub fn set_parameter(
+        &mut self,
+        node: NodeIndex,
+        parameter: &str,
+        value: f32,
+    ) -> Result<(), ControlError> {
+        if !self.shadow.graph.contains_node(node) {
+            return Err(ControlError::UnknownNode(node));
+        }
+        let index = self.shadow.graph[node]
+            .node_processor
+            .parameter_index(parameter)
+            .ok_or_else(|| ControlError::UnknownParameter(node, parameter.to_string()))?;
+        self.pending
+            .push(GraphCommand::SetParameter { node, index, value });
+        Ok(())
+    }
+
+    /// Swap the processor of `node` for the one of `new_node`, keeping its connections
+    /// to the ports which still exist.
+    pub fn replace_node(&mut self, node: NodeIndex, new_node: DspNode) -> Result<(), ControlError> {
+        self.check_node(node)?;
+        let nb_inputs = new_node.node_processor.nb_inputs() as u32;
+        let nb_outputs = new_node.node_processor.nb_outputs() as u32;
+        let dangling_edges = self
+            .shadow
+            .inputs(node)
+            .filter(|edge| edge.weight().dst_port() > nb_inputs)
+            .chain(
+                self.shadow
+                    .outputs(node)
+                    .filter(|edge| edge.weight().src_port() > nb_outputs),
+            )
+            .map(|edge| {
+                let (src, dst) = (edge.source(), edge.target());
+                (src, edge.weight().src_port(), dst, edge.weight().dst_port())
+            })
+            .collect::<Vec<_>>();
+        for (src, src_port, dst, dst_port) in dangling_edges.into_iter() {
+            self.disconnect(src, src_port, dst, dst_port)?;
+        }
+        self.shadow.replace_node(node, new_node.shape());
+        self.structure_changed |= self.shadow.schedule_dirty;
+        self.pending.push(GraphCommand::ReplaceNode(node, new_node));
+        Ok(())
+    }
+
+    /// Send the edits made since the last commit to the audiograph, which applies them
+    /// at the start of its next cycle. If the queue is full, the edits are kept and can be committed again later.
+    pub fn commit(&mut self) -> Result<(), ControlError> {
+        self.collect_garbage();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let schedule = if self.structure_changed {
+            self.shadow
+                .reschedule()
+                .expect("Cycles are rejected when connecting");
+            self.resize_new_edges();
+            let output_formats = self.shadow.output_formats();
+            let mut schedule = Box::new(Schedule::default());
+            self.shadow.swap_schedule(&mut schedule);
+            schedule.output_formats = output_formats;
+            //The copy of the graph needs the order to update it at the next commit
+            self.shadow.topological_order = schedule.topological_order.clone();
+            Some(schedule)
+        } else {
+            None
+        };
+        let node_bound = self.shadow.graph.node_bound();
+        let handle_nodes = if node_bound > self.handle_nodes_capacity {
+            Some(Vec::with_capacity(2 * node_bound))
+        } else {
+            None
+        };
+        let new_capacity = handle_nodes.as_ref().map(|nodes| nodes.capacity());
+        //The graph of the audiograph reaches the same sizes as the copy when the commands are applied
+        let (nb_nodes, nb_edges) = self.peak_size;
+        let graph = if nb_nodes > self.graph_capacity.0 || nb_edges > self.graph_capacity.1 {
+            Some(StableGraph::with_capacity(2 * nb_nodes, 2 * nb_edges))
+        } else {
+            None
+        };
+        let new_graph_capacity = graph.as_ref().map(|graph| graph.capacity());
+        //Everything a command replaces, and the old schedule, map of the indexes and graph
+        let nb_garbage = self
+            .pending
+            .iter()
+            .filter(|command| match **command {
+                GraphCommand::RemoveNode(_)
+                | GraphCommand::Disconnect { .. }
+                | GraphCommand::ReplaceNode(..) => true,
+                _ => false,
+            })
+            .count()
+            + 3;
+        let transaction = Transaction {
+            commands: mem::replace(&mut self.pending, Vec::new()),
+            schedule,
+            handle_nodes,
+            graph,
+            garbage: Vec::with_capacity(nb_garbage),
+        };
+        match self.transactions.try_send(transaction) {
+            Ok(()) => {
+                self.structure_changed = false;
+                if let Some(capacity) = new_capacity {
+                    self.handle_nodes_capacity = capacity;
+                }
+                if let Some(capacity) = new_graph_capacity {
+                    self.graph_capacity = capacity;
+                }
+                self.peak_size = (
+                    self.shadow.graph.node_count(),
+                    self.shadow.graph.edge_count(),
+                );
+                Ok(())
+            }
+            Err(TrySendError::Full(transaction)) => {
+                self.pending = transaction.commands;
+                Err(ControlError::QueueFull)
+            }
+            Err(TrySendError::Disconnected(transaction)) => {
+                self.pending = transaction.commands;
+                Err(ControlError::Disconnected)
+            }
+        }
+    }
+
+    /// Give the edges which will be added to the audiograph the size and samplerate computed by the new schedule,
+    /// so that the audiograph does not reallocate them
+    fn resize_new_edges(&mut self) {
+        let shadow = &self.shadow;
+        for command in self.pending.iter_mut() {
+            if let GraphCommand::Connect {
+                src,
+                dst,
+                ref mut edge,
+            } = *command
+            {
+                let format = shadow
+                    .outputs(src)
+                    .find(|shadow_edge| {
+                        shadow_edge.target() == dst
+                            && shadow_edge.weight().src_port() == edge.src_port()
+                            && shadow_edge.weight().dst_port() == edge.dst_port()
+                    })
+                    .map(|shadow_edge| {
+                        (
+                            shadow_edge.weight().buffer().len(),
+                            shadow_edge.weight().samplerate,
+                        )
+                    });
+                //The edge may have been disconnected before the commit
+                if let Some((size, samplerate)) = format {
+                    edge.resize(size);
+                    edge.samplerate = samplerate;
+                }
+            }
+        }
+    }
+
+    /// Deallocate the transactions applied by the audiograph, and what they replaced.
+    /// Returns the number of deallocated items.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.garbage
+            .try_iter()
+            .map(|transaction| transaction.garbage.len() + 1)
+            .sum()
+    }
+}
+
+/// Contribution of a node of the schedule to the worst-case execution time of a cycle
+#[derive(Debug, Clone)]
+pub struct WcetContribution {
+    pub node: NodeIndex,
+    pub id: String,
+    pub class_name: String,
+    /// WCET given in the audiograph file, at the nominal samplerate
+    pub wcet: Option<f64>,
+    /// Samplerate at which the node is executed divided by the nominal samplerate
+    pub rate_ratio: f64,
+    /// WCET scaled by the rate ratio
+    pub contribution: f64,
+}
+
+/// Result of the static schedulability analysis of an audiograph.
+/// All times are in microseconds.
+#[derive(Debug, Clone)]
+pub struct WcetReport {
+    /// Sum of the contributions of all the nodes in the schedule
+    pub total: f64,
+    /// Longest path in the graph, weighted by the contributions of the nodes
+    pub critical_path: Vec<NodeIndex>,
+    pub critical_path_wcet: f64,
+    /// Contributions in schedule order
+    pub contributions: Vec<WcetContribution>,
+    pub deadline: Option<f64>,
+    /// Deadline minus total WCET, if there is a deadline
+    pub slack: Option<f64>,
+}
+
+impl WcetReport {
+    /// A graph without deadline is considered schedulable
+    pub fn is_schedulable(&self) -> bool {
+        self.slack.map_or(true, |slack| slack >= 0.)
+    }
+
+    /// Number of nodes for which the audiograph does not give any WCET. They are counted as 0.
+    pub fn nb_missing_wcets(&self) -> usize {
+        self.contributions
+            .iter()
+            .filter(|contribution| contribution.wcet.is_none())
+            .count()
+    }
+}
+
+impl fmt::Display for WcetReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Node\tKind\tWCET\tRate ratio\tContribution\n")?;
+        for contribution in self.contributions.iter() {
+            write!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\n",
+                contribution.id,
+                contribution.class_name,
+                contribution
+                    .wcet
+                    .map_or("?".to_string(), |wcet| wcet.to_string()),
+                contribution.rate_ratio,
+                contribution.contribution
+            )?;
+        }
+        write!(f, "Total WCET: {}µs\n", self.total)?;
+        write!(f, "Critical path WCET: {}µs\n", self.critical_path_wcet)?;
+        write!(f, "Nodes without WCET: {}\n", self.nb_missing_wcets())?;
+        match (self.deadline, self.slack) {
+            (Some(deadline), Some(slack)) => write!(
+                f,
+                "Deadline: {}µs; slack: {}µs; {}",
+                deadline,
+                slack,
+                if self.is_schedulable() {
+                    "schedulable"
+                } else {
+                    "NOT schedulable"
+                }
+            ),
+            _ => write!(f, "No deadline"),
+        }
+    }
+}
+
+/// Represents an audiograph of nodes with ports.
+/// Nodes are stored in a stable graph: removing a node does not invalidate the indexes of the other nodes.
+/// Edits made with the methods of the audiograph between two cycles take effect once `update_schedule` has been
+/// called, out of the audio thread. `ControlHandle` edits a graph which is already running.
+pub struct AudioGraph {
+    pub graph: StableGraph<DspNode, DspEdge>,
+    schedule: Vec<NodeIndex<u32>>,
+    topological_order: Vec<NodeIndex>, //All the nodes of the graph, including inactive ones, in topological order
+    schedule_dirty: bool, //Whether the graph has been edited since the last update of the schedule
+    schedule_expected_time: Vec<f64>, //Cumulated expected execution time for every node starting from the end
+    degradation: Degradation,
+    edge_resamplers: Vec<Vec<EdgeResampler>>, //Resamplers of the edges going into every node of the schedule
+    cycle: u64,                                //Number of cycles processed in adaptive mode
+    time_resampler: Stats, //Time to upsample/downsample in adaptive mode
+    size: usize,           //Default size of a buffer
+    channels: u32,         //Number of channels,
+    frames_per_buffer: u32,
+    input_node_index: NodeIndex,
+    input_edges: Vec<DspEdge>,
+    has_source: bool, //Whether there is a source from the audio soundcard or only synth
+    output_node_index: NodeIndex,
+    output_edges: Vec<DspEdge>,
+    nominal_samplerate: u32,
+    deadline: Option<f64>, //Deadline for one cycle in microseconds, as given in the .ag file
+    pool: BufferPool,
+    pooled_schedule: Vec<PooledNode>, //Buffers used by every node of the schedule, in schedule order
+    source_outputs: Vec<usize>,       //Buffers written by the source, one per channel
+    nb_threads: usize, //Number of threads executing the graph, including the one calling process
+    parallel: Option<ParallelExecutor>,
+    worker_edges: Vec<WorkerEdges>,
+    node_pointers: NodePointers,
+    control: Option<ControlQueue>,
+    handle_nodes: Vec<NodeIndex>, //Index of the node of every index of the control handle
+    cost_model: Option<CostModel>, //Predicts the cost of the nodes when they are resampled
+}
+
+impl AudioGraph {
+    pub fn new(frames_per_buffer: u32, channels: u32, samplerate: u32) -> AudioGraph {
+        let input_node_infos = audiograph_parser::Node {
+            class_name: "real_source".to_string(),
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        let output_node_infos = audiograph_parser::Node {
+            class_name: "real_sink".to_string(),
+            nb_inlets: 1,
+            ..Default::default()
+        };
+        let mut graph = StableGraph::new();
+        let input_node = DspNode::from_parts(
+            input_node_infos,
+            Box::new(Source {
+                nb_channels: channels as usize,
+                frames_per_buffer,
+            }),
+        );
+        let output_node = DspNode::from_parts(
+            output_node_infos,
+            Box::new(Sink {
+                nb_channels: channels as usize,
+                frames_per_buffer,
+            }),
+        );
+        let input_node_index = graph.add_node(input_node);
+        let output_node_index = graph.add_node(output_node);
+        let size = frames_per_buffer as usize;
+
+        AudioGraph {
+            graph,
+            schedule: Vec::new(),
+            topological_order: Vec::new(),
+            schedule_dirty: true,
+            schedule_expected_time: Vec::new(),
+            degradation: Degradation::default(),
+            edge_resamplers: Vec::new(),
+            cycle: 0,
+            time_resampler: Stats::init(15.),
+            size,
+            frames_per_buffer,
+            channels,
+            input_node_index,
+            input_edges: vec![DspEdge::new(1, 1, size, samplerate); channels as usize],
+            has_source: false,
+            output_node_index,
+            output_edges: vec![DspEdge::new(1, 1, size, samplerate); channels as usize],
+            nominal_samplerate: samplerate,
+            deadline: None,
+            pool: BufferPool::default(),
+            pooled_schedule: Vec::new(),
+            source_outputs: Vec::new(),
+            nb_threads: 1,
+            parallel: None,
+            worker_edges: Vec::new(),
+            node_pointers: NodePointers(Vec::new()),
+            control: None,
+            handle_nodes: Vec::new(),
+            cost_model: None,
+        }
+    }
+
+    pub fn set_nominal_samplerate(&mut self, samplerate: u32) {
+        self.nominal_samplerate = samplerate;
+        for input_edge in self.input_edges.iter_mut() {
+            input_edge.samplerate = samplerate;
+        }
+        for output_edge in self.output_edges.iter_mut() {
+            output_edge.samplerate = samplerate;
+        }
+    }
+
+    /// Deadline for one cycle of the graph, in microseconds, if the audiograph file specifies one
+    pub fn deadline(&self) -> Option<f64> {
+        self.deadline
+    }
+
+    pub fn set_deadline(&mut self, deadline: f64) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Time budget for one cycle, in microseconds.
+    /// It is the deadline of the graph if there is one, otherwise the duration of a buffer at the nominal samplerate.
+    pub fn budget(&self) -> f64 {
+        self.deadline
+            .unwrap_or(self.frames_per_buffer as f64 / self.nominal_samplerate as f64 * 1_000_000.)
+    }
+
+    pub fn source_node(&self) -> NodeIndex {
+        self.input_node_index
+    }
+
+    pub fn sink_node(&self) -> NodeIndex {
+        self.output_node_index
+    }
+
+    pub fn nb_nodes(&self) -> usize {
+        //Nb of active nodes
+        self.graph.node_count()
+    }
+
+    pub fn nb_edges(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    pub fn nb_channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn default_buffer_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn frames_per_buffer(&self) -> u32 {
+        self.frames_per_buffer
+    }
+
+    /// Use `cost_model` to predict how the cost of the nodes changes when they are resampled in adaptive mode,
+    /// instead of assuming it is proportional to the number of samples.
+    /// The execution times of the nodes already in the graph and of the resamplers start from the predicted ones,
+    /// so it should be called before executing the graph.
+    pub fn set_cost_model(&mut self, cost_model: CostModel) {
+        self.cost_model = Some(cost_model);
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for node in nodes {
+            if let Some(predicted) = self.predicted_cost(node) {
+                self.graph[node].exec_time = Stats::init(predicted.nominal);
+            }
+        }
+        let frames = self.frames_per_buffer as usize;
+        if let Some(predicted) = self
+            .cost_model
+            .as_ref()
+            .and_then(|cost_model| cost_model.predict("resampler", frames, self.nominal_samplerate))
+        {
+            self.time_resampler = Stats::init(predicted.nominal);
+        }
+    }
+
+    pub fn cost_model(&self) -> Option<&CostModel> {
+        self.cost_model.as_ref()
+    }
+
+    /// Costs of `node` predicted by the cost model at the samplerate at which it is executed,
+    /// if there is a model for its kind
+    pub fn predicted_cost(&self, node: NodeIndex) -> Option<PredictedCost> {
+        let cost_model = self.cost_model.as_ref()?;
+        let samplerate = self.node_samplerate(node);
+        let frames =
+            self.frames_per_buffer as u64 * samplerate as u64 / self.nominal_samplerate as u64;
+        cost_model.predict(
+            &self.graph[node].node_infos.class_name,
+            frames as usize,
+            samplerate,
+        )
+    }
+
+    pub fn add_node(&mut self, node: DspNode) -> NodeIndex {
+        let nb_inputs = node.node_processor.nb_inputs();
+        let nb_outputs = node.node_processor.nb_outputs();
+        let max_nb = std::cmp::max(nb_inputs, nb_outputs);
+
+        if max_nb > self.input_edges.len() {
+            //println!("Input: old={} new={}", self.input_edges.len(), max_nb);
+            self.input_edges.resize(
+                max_nb,
+                DspEdge::new(1, 1, self.size, self.nominal_samplerate),
+            );
+        }
+        if max_nb > self.output_edges.len() {
+            //println!("Output: old={} new={}", self.output_edges.len(), max_nb);
+            self.output_edges.resize(
+                max_nb,
+                DspEdge::new(1, 1, self.size, self.nominal_samplerate),
+            );
+        }
+        self.schedule_dirty = true;
+        self.graph.add_node(node)
+    }
+
+    pub fn add_input(
+        &mut self,
+        src: DspNode,
+        src_port: u32,
+        dst: NodeIndex,
+        dst_port: u32,
+    ) -> NodeIndex {
+        {
+            assert!(src_port <= src.node_processor.nb_outputs() as u32 && src_port >= 1);
+            let dst_node = self.graph.node_weight(dst).unwrap();
+            assert!(dst_port <= dst_node.node_processor.nb_inputs() as u32 && dst_port >= 1);
+        }
+        self.schedule_dirty = true;
+        let parent = self.graph.add_node(src);
+        self.graph.add_edge(
+            parent,
+            dst,
+            DspEdge::new(src_port, dst_port, self.size, self.nominal_samplerate),
+        );
+        parent
+    }
+
+    pub fn add_output(
+        &mut self,
+        src: NodeIndex,
+        src_port: u32,
+        dst: DspNode,
+        dst_port: u32,
+    ) -> NodeIndex {
+        {
+            let src_node = self.graph.node_weight(src).unwrap();
+            assert!(src_port <= src_node.node_processor.nb_outputs() as u32 && src_port >= 1);
+            assert!(dst_port <= dst.node_processor.nb_inputs() as u32 && dst_port >= 1);
+        }
+        self.schedule_dirty = true;
+        let child = self.graph.add_node(dst);
+        self.graph.add_edge(
+            src,
+            child,
+            DspEdge::new(src_port, dst_port, self.size, self.nominal_samplerate),
+        );
+        child
+    }
+
+    pub fn remove_connection(&mut self, src: NodeIndex, dest: NodeIndex) {
+        if let Some(edge_index) = self.graph.find_edge(src, dest) {
+            self.graph
+                .remove_edge(edge_index)
+                .expect("Edge should exist");
+            self.schedule_dirty = true;
+        }
+    }
+
+    pub fn remove_edge(&mut self, edge: EdgeIndex) {
+        if self.graph.remove_edge(edge).is_some() {
+            self.schedule_dirty = true;
+        }
+    }
+
+    /// Remove `node` and all its connections. The indexes of the other nodes remain valid.
+    /// The real source and sink cannot be removed.
+    pub fn remove_node(&mut self, node: NodeIndex) -> Option<DspNode> {
+        assert!(
+            node != self.input_node_index && node != self.output_node_index,
+            "The real source and sink cannot be removed"
+        );
+        let removed = self.graph.remove_node(node);
+        if removed.is_some() {
+            self.schedule_dirty = true;
+        }
+        removed
+    }
+
+    /// Replace `node` by `new_node`, which keeps its index and its connections, and return the replaced node.
+    /// Connections to ports that `new_node` does not have are removed.
+    /// If both nodes have the same number of ports and are not resamplers, the schedule does not need
+    /// to be updated.
+    pub fn replace_node(&mut self, node: NodeIndex, new_node: DspNode) -> DspNode {
+        assert!(
+            node != self.input_node_index && node != self.output_node_index,
+            "The real source and sink cannot be replaced"
+        );
+        let nb_inputs = new_node.node_processor.nb_inputs();
+        let nb_outputs = new_node.node_processor.nb_outputs();
+        let old_node = mem::replace(&mut self.graph[node], new_node);
+
+        let dangling_edges = self
+            .inputs(node)
+            .filter(|edge| edge.weight().dst_port() as usize > nb_inputs)
+            .chain(
+                self.outputs(node)
+                    .filter(|edge| edge.weight().src_port() as usize > nb_outputs),
+            )
+            .map(|edge| edge.id())
+            .collect::<Vec<_>>();
+        for edge in dangling_edges.into_iter() {
+            self.graph.remove_edge(edge);
+        }
+
+        let same_structure = old_node.node_processor.nb_inputs() == nb_inputs
+            && old_node.node_processor.nb_outputs() == nb_outputs
+            && old_node.node_infos.class_name != "resampler"
+            && self.graph[node].node_infos.class_name != "resampler";
+        if !same_structure {
+            self.schedule_dirty = true;
+        }
+        old_node
+    }
+
+    /// Connect port `src_port` of `src` to port `dst_port` of `dst`.
+    /// A connection which would create a cycle is rejected, so that an edited graph can always be scheduled.
+    pub fn add_connection(
+        &mut self,
+        src: NodeIndex,
+        src_port: u32,
+        dst: NodeIndex,
+        dst_port: u32,
+    ) -> Result<EdgeIndex, AudioGraphError> {
+        if has_path_connecting(&self.graph, dst, src, None) {
+            return Err(AudioGraphError::Cycle);
+        }
+        self.schedule_dirty = true;
+        Ok(self.graph.add_edge(
+            src,
+            dst,
+            DspEdge::new(src_port, dst_port, self.size, self.nominal_samplerate),
+        ))
+    }
+
+    pub fn outputs(&self, src: NodeIndex) -> Edges<DspEdge, Directed> {
+        self.graph.edges_directed(src, EdgeDirection::Outgoing)
+    }
+
+    pub fn nb_outputs(&self, src: NodeIndex) -> u32 {
+        self.outputs(src).count() as u32
+    }
+
+    pub fn outputs_mut(&self, src: NodeIndex) -> WalkNeighbors<u32> {
+        self.graph
+            .neighbors_directed(src, EdgeDirection::Outgoing)
+            .detach()
+    }
+
+    pub fn inputs(&self, dest: NodeIndex) -> Edges<DspEdge, Directed> {
+        self.graph.edges_directed(dest, EdgeDirection::Incoming)
+    }
+
+    pub fn inputs_mut(&self, src: NodeIndex) -> WalkNeighbors<u32> {
+        self.graph
+            .neighbors_directed(src, EdgeDirection::Incoming)
+            .detach()
+    }
+
+    pub fn nb_inputs(&self, dest: NodeIndex) -> u32 {
+        self.inputs(dest).count() as u32
+    }
+
+    /// Remove nodes not connected to the sink from the schedule
+    fn active_component(&mut self) {
+        let rev_graph = Reversed(&self.graph);
+        let mut dfs = Dfs::new(&rev_graph, self.output_node_index);
+        while let Some(_) = dfs.next(&rev_graph) {} //Just traverse the graph to populate dfs.discovered
+
+        //println!("Source is connected? {:?}", dfs.discovered.is_visited(&self.input_node_index));
+
+        let mut filtered_schedule = Vec::with_capacity(self.schedule.len());
+
+        for node in self.schedule.iter() {
+            if dfs.discovered.is_visited(node) {
+                filtered_schedule.push(*node);
+            }
+        }
+        //println!("Schedule nodes before active components filtering:");
+        //self.print_schedule(&self.schedule);
+        self.schedule = filtered_schedule;
+        //self.schedule = self.schedule.iter().filter(|v| {dfs.discovered.is_visited(v)}).collect::<Vec<_>>();
+        self.has_source = false;
+        let mut source_index = 0;
+        for (i, e) in self.schedule.iter().enumerate() {
+            if *e == self.input_node_index {
+                self.has_source = true;
+                source_index = i;
+                break;
+            }
+        }
+        if self.has_source {
+            self.schedule.remove(source_index);
+        }
+    }
+
+    /// Adjust buffer sizes and samplerates for nodes between two resamplers
+    fn buffer_size_resamplers(&mut self) {
+        assert_eq!(self.schedule.len(), self.graph.node_count());
+        let schedule = mem::replace(&mut self.schedule, Vec::new());
+        self.propagate_rates(&schedule, 1.0);
+        self.schedule = schedule;
+
+        // Check sink
+        self.inputs(self.output_node_index)
+            .for_each(|ref edge| assert_eq!(edge.weight().samplerate, self.nominal_samplerate));
+    }
+
+    /// Propagate buffer sizes and samplerates along `nodes`, which must be in topological order.
+    /// Nodes without incoming edges produce buffers at the nominal samplerate multiplied by `source_ratio`.
+    fn propagate_rates(&mut self, nodes: &[NodeIndex], source_ratio: f64) {
+        for &node in nodes.iter() {
+            let is_resampler = self.graph[node].node_infos.class_name == "resampler";
+
+            //Get min incoming buffer size
+            let buf_size = self
+                .inputs(node)
+                .map(|edge| edge.weight().buffer().len())
+                .min()
+                .unwrap_or((self.default_buffer_size() as f64 * source_ratio) as usize);
+            //Get min incoming samplerate
+            let samplerate = self
+                .inputs(node)
+                .map(|edge| edge.weight().samplerate)
+                .min()
+                .unwrap_or((self.nominal_samplerate as f64 * source_ratio) as u32);
+
+            let (new_buf_size, new_samplerate) = if is_resampler {
+                /*
+                Some incoming edges may have not their buffer size downsampled yet (but at least one has it thanks to the dfs ordering)
+                Some incoming edges could have not had their buffer size with the normal sample size yet if an incoming branch was downsampled (but at least one has it thanks to the dfs ordering).
+                But actually this case does not happen, as if the path is downsampled, then thanks to dfs ordering, all the subsequent edges including the incoming one of the current node, must have been explored.
+                If it had happened, we would have taken max in the case of ratio < 1.0
+                */
+                let ratio: f64 = self.graph[node]
+                    .node_infos
+                    .attribute("ratio", RESAMPLER_RATIO)
+                    .expect("The ratio of a resampler is checked when it is created");
+                (
+                    (buf_size as f64 * ratio) as usize,
+                    (samplerate as f64 * ratio) as u32,
+                )
+            } else {
+                //We are not after a resampler so we propagate the min buffer size of the previous edges.
+                (buf_size, samplerate)
+            };
+
+            //Modify all outcoming buffer sizes
+            let mut output_edges = self.outputs_mut(node);
+            while let Some(edge) = output_edges.next_edge(&self.graph) {
+                self.graph
+                    .edge_weight_mut(edge)
+                    .unwrap()
+                    .resize(new_buf_size);
+                self.graph.edge_weight_mut(edge).unwrap().samplerate = new_samplerate;
+            }
+        }
+    }
+
+    /// Reset all buffer sizes to the default
+    fn reset_buffer_sizes(&mut self) {
+        let default_size = self.default_buffer_size();
+        let edges = self.graph.edge_indices().collect::<Vec<_>>();
+        for edge in edges.into_iter() {
+            self.graph[edge].resize(default_size);
+        }
+    }
+
+    /// Check that all nodes are isochronous
+    fn validate_buffer_sizes(&self) -> bool {
+        self.graph.node_indices().all(|node| {
+            let mut input_edges = self.graph.edges_directed(node, Direction::Incoming);
+            let first = input_edges
+                .next()
+                .map(|e| (e.weight().buffer().len(), e.weight().samplerate));
+            let res = if let Some((size_edge, samplerate)) = first {
+                input_edges.all(|e| {
+                    e.weight().buffer().len() == size_edge && e.weight().samplerate == samplerate
+                })
+            } else {
+                true
+            };
+            res && {
+                let mut output_edges = self.graph.edges_directed(node, Direction::Outgoing);
+                let first = output_edges
+                    .next()
+                    .map(|e| (e.weight().buffer().len(), e.weight().samplerate));
+                if let Some((size_edge, samplerate)) = first {
+                    output_edges.all(|e| {
+                        e.weight().buffer().len() == size_edge
+                            && e.weight().samplerate == samplerate
+                    })
+                } else {
+                    true
+                }
+            }
+        })
+    }
+
+    /// Autoconnect ports without edges to sources and sinks. If only externals, only autoconect virtual sources and sinks(externals)
+    pub fn autoconnect(&mut self, only_externals: bool) {
+        //automatically connect to adc and dac nodes which have inlets and outlets without node on the other side.
+        let mut io_edges = Vec::new();
+        for node_index in self.graph.node_indices() {
+            let node = self.graph.node_weight(node_index).unwrap();
+            let permitted_node = if only_externals {
+                node.node_infos.class_name == "source" || node.node_infos.class_name == "sink"
+            } else {
+                true
+            };
+            if permitted_node
+                && node_index != self.input_node_index
+                && node_index != self.output_node_index
+            {
+                // Theoretical I/O
+                let nb_in_t = node.node_processor.nb_inputs() as u32;
+                let nb_out_t = node.node_processor.nb_outputs() as u32;
+                //Collect connected input ports. Ports are checked one by one rather than counting the edges,
+                //as several edges can arrive at the same port while another one has none
+                let input_edges = self.inputs(node_index);
+                let input_ports = input_edges
+                    .map(|e| e.weight().dst_port())
+                    .collect::<HashSet<_>>();
+                //Connect them to audio source
+                for port in 1..(nb_in_t + 1) {
+                    if !input_ports.contains(&port) {
+                        //It's a non-connected port
+                        println!(
+                            "Autoconnect edge from source to {}:{} on port {}",
+                            node.node_infos.id, node, port
+                        );
+                        //Spread the ports over the channels of the source
+                        io_edges.push((
+                            self.input_node_index,
+                            (port - 1) % self.channels + 1,
+                            node_index,
+                            port,
+                        ));
+                    }
+                }
+                //Collect connected output ports
+                let output_edges = self.outputs(node_index);
+                let output_ports = output_edges
+                    .map(|e| e.weight().src_port())
+                    .collect::<HashSet<_>>();
+                //Connect them to audio sink
+                for port in 1..(nb_out_t + 1) {
+                    if !output_ports.contains(&port) {
+                        //It's a non-connected port
+                        println!(
+                            "Autoconnect edge to sink from {}:{} on port {}",
+                            node.node_infos.id, node, port
+                        );
+                        //Spread the ports over the channels of the sink
+                        io_edges.push((
+                            node_index,
+                            port,
+                            self.output_node_index,
+                            (port - 1) % self.channels + 1,
+                        ));
+                    }
+                }
+            }
+        }
+        //Finally add the edges
+        for edge in io_edges.into_iter() {
+            let (src_id, src_port, dst_id, dst_port) = edge;
+            self.add_connection(src_id, src_port, dst_id, dst_port)
+                .expect("Connections to the real source and sink cannot create cycles");
+        }
+    }
+
+    pub fn print_schedule(&self, schedule: &[NodeIndex]) {
+        print!("The schedule is: ",);
+        for node_index in schedule.iter() {
+            let node = self.graph.node_weight(*node_index).unwrap();
+            print!(
+                "{} {} ",
+                node,
+                if node.node_infos.class_name == "real_sink" {
+                    ""
+                } else {
+                    "->"
+                }
+            );
+        }
+        println!("");
+    }
+
+    /// Adjust interchange buffers: temporary buffers used to copy audio between edges
+    fn update_temp_buffers(&mut self) {
+        for node in self.graph.node_indices() {
+            //Edges are routed according to their ports, so we need one buffer per port
+            let (nb_inputs, nb_outputs) = {
+                let n = &self.graph[node].node_processor;
+                (n.nb_inputs(), n.nb_outputs())
+            };
+            if nb_inputs > self.input_edges.len() {
+                self.input_edges.resize(
+                    nb_inputs,
+                    DspEdge::new(1, 1, self.size, self.nominal_samplerate),
+                );
+            }
+            if nb_outputs > self.output_edges.len() {
+                self.output_edges.resize(
+                    nb_outputs,
+                    DspEdge::new(1, 1, self.size, self.nominal_samplerate),
+                );
+            }
+        }
+    }
+
+    pub fn update_schedule(&mut self) -> Result<(), AudioGraphError> {
+        self.reschedule()?;
+
+        if self.schedule.len() <= 100 {
+            self.print_schedule(&self.schedule);
+        }
+
+        Ok(())
+    }
+
+    /// Update the schedule after the graph has been edited. The previous topological order is reused
+    /// if the edits have kept it valid.
+    fn reschedule(&mut self) -> Result<(), AudioGraphError> {
+        self.update_temp_buffers();
+        self.reset_buffer_sizes();
+        if !self.repair_topological_order() {
+            self.topological_order = toposort(&self.graph, None)?; //If Cycle, returns an AudioGraphError::Cycle
+        }
+        self.schedule.clear();
+        self.schedule.extend_from_slice(&self.topological_order);
+        self.buffer_size_resamplers(); //Requires the topological sort
+        assert!(self.validate_buffer_sizes());
+
+        self.active_component();
+        self.schedule_expected_time.resize(self.schedule.len(), 0.);
+        self.update_buffer_pool();
+        self.update_parallel_executor();
+        self.update_edge_resamplers();
+        self.schedule_dirty = false;
+
+        Ok(())
+    }
+
+    /// Whether the graph has been edited since the last call to `update_schedule`.
+    /// The schedule is never updated in the audio thread: until `update_schedule` has been called
+    /// after an edit, the cycles output silence.
+    pub fn needs_schedule_update(&self) -> bool {
+        self.schedule_dirty
+    }
+
+    /// Silence for the cycles executed while the schedule is out of date
+    fn output_silence(outputs: &mut [DspEdge]) {
+        for sample in outputs[0].buffer_mut().iter_mut() {
+            *sample = 0.;
+        }
+    }
+
+    /// Create a handle to edit the graph once it is running. Transactions are sent through
+    /// a queue of `capacity` transactions. A graph has only one control handle: creating
+    /// a new one disconnects the previous one.
+    pub fn control_handle(&mut self, capacity: usize) -> ControlHandle {
+        let mut shadow = AudioGraph::new(
+            self.frames_per_buffer,
+            self.channels,
+            self.nominal_samplerate,
+        );
+        shadow.graph = self
+            .graph
+            .map(|_, node| node.shape(), |_, edge| edge.clone());
+        shadow.input_node_index = self.input_node_index;
+        shadow.output_node_index = self.output_node_index;
+        shadow.topological_order = self.topological_order.clone();
+        shadow.nb_threads = self.nb_threads;
+        shadow.schedule_dirty = false;
+
+        //The copy starts with the same indexes
+        let node_bound = self.graph.node_bound();
+        self.handle_nodes = Vec::with_capacity(2 * node_bound);
+        self.handle_nodes
+            .extend((0..node_bound).map(NodeIndex::new));
+
+        let (transactions_sender, transactions) = bounded(capacity);
+        //Each transaction comes back once. The handle collects them before sending a new one,
+        //so that there is never more than one more of them than transactions in the queue.
+        let (garbage, garbage_receiver) = bounded(capacity + 1);
+        self.control = Some(ControlQueue {
+            transactions,
+            garbage,
+        });
+        ControlHandle {
+            shadow,
+            pending: Vec::new(),
+            structure_changed: false,
+            handle_nodes_capacity: self.handle_nodes.capacity(),
+            graph_capacity: self.graph.capacity(),
+            peak_size: (self.graph.node_count(), self.graph.edge_count()),
+            transactions: transactions_sender,
+            garbage: garbage_receiver,
+        }
+    }
+
+    fn swap_schedule(&mut self, schedule: &mut Schedule) {
+        mem::swap(&mut self.topological_order, &mut schedule.topological_order);
+        mem::swap(&mut self.schedule, &mut schedule.schedule);
+        mem::swap(
+            &mut self.schedule_expected_time,
+            &mut schedule.schedule_expected_time,
+        );
+        mem::swap(&mut self.has_source, &mut schedule.has_source);
+        mem::swap(&mut self.pool, &mut schedule.pool);
+        mem::swap(&mut self.pooled_schedule, &mut schedule.pooled_schedule);
+        mem::swap(&mut self.source_outputs, &mut schedule.source_outputs);
+        mem::swap(&mut self.parallel, &mut schedule.parallel);
+        mem::swap(&mut self.worker_edges, &mut schedule.worker_edges);
+        mem::swap(&mut self.node_pointers, &mut schedule.node_pointers);
+        mem::swap(&mut self.input_edges, &mut schedule.input_edges);
+        mem::swap(&mut self.output_edges, &mut schedule.output_edges);
+        mem::swap(&mut self.degradation, &mut schedule.degradation);
+        mem::swap(&mut self.edge_resamplers, &mut schedule.edge_resamplers);
+    }
+
+    /// Keep something in the garbage of a transaction, to be deallocated in the control thread.
+    /// The control handle has reserved room for it.
+    fn throw_away(garbage: &mut Vec<Garbage>, item: Garbage) {
+        debug_assert!(garbage.len() < garbage.capacity());
+        garbage.push(item);
+    }
+
+    /// Apply the transactions committed by the control handle
+    fn apply_transactions(&mut self) {
+        loop {
+            let transaction = match self.control {
+                Some(ref control) => match control.transactions.try_recv() {
+                    Ok(transaction) => transaction,
+                    Err(_) => break,
+                },
+                None => break,
+            };
+            self.apply_transaction(transaction);
+        }
+    }
+
+    /// Move the nodes and edges to `graph`, an empty graph with more capacity, keeping their indexes,
+    /// so that the graph grows without allocating. Returns the previous graph, to be deallocated.
+    fn move_graph(
+        &mut self,
+        mut graph: StableGraph<DspNode, DspEdge>,
+    ) -> StableGraph<DspNode, DspEdge> {
+        debug_assert_eq!(graph.node_count(), 0);
+        let node_bound = self.graph.node_bound();
+        for index in 0..node_bound {
+            //Vacant indexes are filled, and freed again once the edges are moved
+            let node = self
+                .graph
+                .node_weight_mut(NodeIndex::new(index))
+                .map_or_else(DspNode::moved, |node| mem::replace(node, DspNode::moved()));
+            graph.add_node(node);
+        }
+        let edge_bound = self
+            .graph
+            .edge_indices()
+            .map(|edge| edge.index() + 1)
+            .max()
+            .unwrap_or(0);
+        for index in 0..edge_bound {
+            let edge = EdgeIndex::new(index);
+            let moved = DspEdge::new(1, 1, 0, self.nominal_samplerate);
+            match self.graph.edge_endpoints(edge) {
+                Some((src, dst)) => {
+                    let weight = mem::replace(&mut self.graph[edge], moved);
+                    graph.add_edge(src, dst, weight);
+                }
+                None => {
+                    graph.add_edge(self.input_node_index, self.input_node_index, moved);
+                }
+            }
+        }
+        for index in 0..edge_bound {
+            let edge = EdgeIndex::new(index);
+            if self.graph.edge_weight(edge).is_none() {
+                graph.remove_edge(edge);
+            }
+        }
+        for index in 0..node_bound {
+            let node = NodeIndex::new(index);
+            if !self.graph.contains_node(node) {
+                graph.remove_node(node);
+            }
+        }
+        mem::swap(&mut self.graph, &mut graph);
+        graph
+    }
+
+    fn apply_transaction(&mut self, mut transaction: Transaction) {
+        if let Some(mut handle_nodes) = transaction.handle_nodes.take() {
+            handle_nodes.extend_from_slice(&self.handle_nodes);
+            mem::swap(&mut self.handle_nodes, &mut handle_nodes);
+            AudioGraph::throw_away(&mut transaction.garbage, Garbage::HandleNodes(handle_nodes));
+        }
+        //The control handle sends a larger graph before the nodes and edges it adds exceed its capacity
+        if let Some(graph) = transaction.graph.take() {
+            let graph = self.move_graph(graph);
+            AudioGraph::throw_away(&mut transaction.garbage, Garbage::Graph(graph));
+        }
+        for command in transaction.commands.drain(..) {
+            match command {
+                GraphCommand::AddNode(index, node) => {
+                    let new_index = self.graph.add_node(node);
+                    //The control handle has sent a map with enough capacity for its indexes
+                    debug_assert!(index.index() < self.handle_nodes.capacity());
+                    if index.index() >= self.handle_nodes.len() {
+                        self.handle_nodes
+                            .resize(index.index() + 1, NodeIndex::end());
+                    }
+                    self.handle_nodes[index.index()] = new_index;
+                }
+                GraphCommand::RemoveNode(node) => {
+                    let node = self.handle_nodes[node.index()];
+                    if let Some(node) = self.graph.remove_node(node) {
+                        AudioGraph::throw_away(&mut transaction.garbage, Garbage::Node(node));
+                    }
+                }
+                GraphCommand::Connect { src, dst, edge } => {
+                    let (src, dst) = (
+                        self.handle_nodes[src.index()],
+                        self.handle_nodes[dst.index()],
+                    );
+                    self.graph.add_edge(src, dst, edge);
+                }
+                GraphCommand::Disconnect {
+                    src,
+                    src_port,
+                    dst,
+                    dst_port,
+                } => {
+                    let (src, dst) = (
+                        self.handle_nodes[src.index()],
+                        self.handle_nodes[dst.index()],
+                    );
+                    let edge = self
+                        .outputs(src)
+                        .find(|edge| {
+                            edge.target() == dst
+                                && edge.weight().src_port() == src_port
+                                && edge.weight().dst_port() == dst_port
+                        })
+                        .map(|edge| edge.id());
+                    if let Some(edge) = edge.and_then(|edge| self.graph.remove_edge(edge)) {
+                        AudioGraph::throw_away(&mut transaction.garbage, Garbage::Edge(edge));
+                    }
+                }
+                GraphCommand::SetParameter { node, index, value } => {
+                    let node = self.handle_nodes[node.index()];
+                    self.graph[node].node_processor.set_parameter(index, value);
+                }
+                GraphCommand::ReplaceNode(node, new_node) => {
+                    let node = self.handle_nodes[node.index()];
+                    let old_node = mem::replace(&mut self.graph[node], new_node);
+                    AudioGraph::throw_away(&mut transaction.garbage, Garbage::Node(old_node));
+                    continue;
+                }
+            }
+        }
+        if let Some(mut schedule) = transaction.schedule.take() {
+            self.translate_schedule(&mut schedule);
+            self.swap_schedule(&mut schedule);
+            self.apply_output_formats(&schedule.output_formats);
+            AudioGraph::throw_away(&mut transaction.garbage, Garbage::Schedule(schedule));
+        }
+        if let Some(ref control) = self.control {
+            let sent = control.garbage.try_send(transaction).is_ok();
+            debug_assert!(sent, "The queue of garbage is full");
+        }
+    }
+
+    /// Replace in place the indexes of the control handle in a schedule it has computed by the indexes of the nodes
+    fn translate_schedule(&self, schedule: &mut Schedule) {
+        let handle_nodes = &self.handle_nodes;
+        let nodes = schedule
+            .topological_order
+            .iter_mut()
+            .chain(schedule.schedule.iter_mut())
+            .chain(
+                schedule
+                    .pooled_schedule
+                    .iter_mut()
+                    .map(|node| &mut node.node),
+            );
+        for node in nodes {
+            *node = handle_nodes[node.index()];
+        }
+        for resampler in schedule
+            .edge_resamplers
+            .iter_mut()
+            .flat_map(|resamplers| resamplers.iter_mut())
+        {
+            resampler.src = handle_nodes[resampler.src.index()];
+            resampler.dst = handle_nodes[resampler.dst.index()];
+        }
+    }
+
+    /// Size and samplerate of the edges going out of every node, in topological order
+    fn output_formats(&self) -> Vec<(usize, u32)> {
+        self.topological_order
+            .iter()
+            .map(|&node| {
+                self.outputs(node)
+                    .next()
+                    .map(|edge| (edge.weight().buffer().len(), edge.weight().samplerate))
+                    .unwrap_or((self.default_buffer_size(), self.nominal_samplerate))
+            })
+            .collect()
     }
 
-    pub fn outputs(&self, src: NodeIndex) -> Edges<DspEdge, Directed> {
-        self.graph.edges_directed(src, EdgeDirection::Outgoing)
+    /// Resize the edges going out of every node of the topological order with the formats computed by
+    /// a control handle. The edges are never longer than when they were created by the handle, so it does not allocate.
+    fn apply_output_formats(&mut self, formats: &[(usize, u32)]) {
+        for (i, &(size, samplerate)) in formats.iter().enumerate() {
+            let mut edges = self.outputs_mut(self.topological_order[i]);
+            while let Some(edge) = edges.next_edge(&self.graph) {
+                let dsp_edge = &mut self.graph[edge];
+                dsp_edge.resize(size);
+                dsp_edge.samplerate = samplerate;
+            }
+        }
     }
 
-    pub fn nb_outputs(&self, src: NodeIndex) -> u32 {
-        self.outputs(src).count() as u32
+    /// Drop removed nodes from the previous topological order and append the new ones.
+    /// Returns false if an edge goes backward in the resulting order, which then needs to be sorted again.
+    fn repair_topological_order(&mut self) -> bool {
+        let graph = &self.graph;
+        self.topological_order
+            .retain(|&node| graph.contains_node(node));
+        let mut ordered = graph.visit_map();
+        for &node in self.topological_order.iter() {
+            ordered.visit(node);
+        }
+        for node in graph.node_indices() {
+            if !ordered.is_visited(&node) {
+                self.topological_order.push(node);
+            }
+        }
+
+        let mut positions = vec![0; graph.node_bound()];
+        for (i, node) in self.topological_order.iter().enumerate() {
+            positions[node.index()] = i;
+        }
+        graph.edge_indices().all(|edge| {
+            let (src, dst) = graph.edge_endpoints(edge).unwrap();
+            positions[src.index()] < positions[dst.index()]
+        })
     }
 
-    pub fn outputs_mut(&self, src: NodeIndex) -> WalkNeighbors<u32> {
+    pub fn nb_active_nodes(&self) -> usize {
+        self.schedule.len()
+    }
+
+    /// Number of threads executing the graph in `process`, including the calling thread.
+    /// It takes effect at the next `update_schedule`. With more than one thread, independent branches
+    /// of the graph are executed in parallel.
+    pub fn set_nb_threads(&mut self, nb_threads: usize) {
+        assert!(nb_threads >= 1);
+        self.nb_threads = nb_threads;
+    }
+
+    pub fn nb_threads(&self) -> usize {
+        self.nb_threads
+    }
+
+    /// Start the worker threads of the parallel executor for the current pooled schedule.
+    /// The sink is not part of the nodes executed in parallel: it is executed after all the other ones.
+    fn update_parallel_executor(&mut self) {
+        if self.nb_threads <= 1 {
+            self.parallel = None;
+            self.worker_edges.clear();
+            return;
+        }
+
+        let nb_nodes = self.pooled_schedule.len() - 1;
+        let positions = self.pooled_schedule[0..nb_nodes]
+            .iter()
+            .enumerate()
+            .map(|(i, pooled_node)| (pooled_node.node, i))
+            .collect::<HashMap<_, _>>();
+        let successors = self.pooled_schedule[0..nb_nodes]
+            .iter()
+            .map(|pooled_node| {
+                let mut successors = self
+                    .graph
+                    .neighbors_directed(pooled_node.node, Direction::Outgoing)
+                    .filter_map(|node| positions.get(&node).cloned())
+                    .collect::<Vec<_>>();
+                successors.sort();
+                successors.dedup();
+                successors
+            })
+            .collect::<Vec<_>>();
+        //The worker threads are only restarted if the dependencies between nodes have changed
+        let same_executor = self.parallel.as_ref().map_or(false, |parallel| {
+            parallel.nb_threads() == self.nb_threads && parallel.successors() == &successors[..]
+        });
+        if !same_executor {
+            //Stop the previous workers first
+            self.parallel = None;
+            self.parallel = Some(ParallelExecutor::new(self.nb_threads, successors));
+        }
+
+        //Interchange buffers of every thread, with enough capacity to never reallocate during a cycle
+        let max_size = self
+            .pool
+            .buffers
+            .iter()
+            .map(|buffer| buffer.buffer().len())
+            .max()
+            .unwrap_or(self.size);
+        let max_ports = std::cmp::max(self.input_edges.len(), self.output_edges.len());
+        let (size, samplerate) = (self.size, self.nominal_samplerate);
+        let new_edge = || {
+            let mut edge = DspEdge::new(1, 1, max_size, samplerate);
+            edge.resize(size);
+            edge
+        };
+        self.worker_edges = (0..self.nb_threads)
+            .map(|_| WorkerEdges {
+                inputs: (0..max_ports).map(|_| new_edge()).collect(),
+                outputs: (0..max_ports).map(|_| new_edge()).collect(),
+            })
+            .collect();
+        self.node_pointers = NodePointers(Vec::with_capacity(nb_nodes));
+    }
+
+    /// Number of buffers allocated for the pooled executor
+    pub fn nb_pooled_buffers(&self) -> usize {
+        self.pool.buffers.len()
+    }
+
+    /// Assign a buffer of the pool to every output port of the nodes of the schedule.
+    /// A buffer is reused as soon as the last node reading it in the schedule has been executed.
+    fn update_buffer_pool(&mut self) {
+        let mut pool = BufferPool {
+            no_reuse: self.nb_threads > 1,
+            ..Default::default()
+        };
+        let mut port_buffers: HashMap<(NodeIndex, u32), usize> = HashMap::new();
+        //Silent buffers for inlets without edges. They are never written. A node gets a different one
+        //for each of its inlets without edges, as the buffers of a node are swapped in one by one.
+        let mut silent_buffers: HashMap<(usize, u32), Vec<usize>> = HashMap::new();
+        //Number of edges going out of an output port which have not been read yet
+        let mut nb_readers: HashMap<(NodeIndex, u32), usize> = HashMap::new();
+        for &node in self.schedule.iter() {
+            for edge in self.inputs(node) {
+                *nb_readers
+                    .entry((edge.source(), edge.weight().src_port()))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        let mut source_outputs = Vec::new();
+        if self.has_source {
+            for port in 1..(self.channels + 1) {
+                let buffer =
+                    pool.allocate(self.frames_per_buffer as usize, self.nominal_samplerate);
+                source_outputs.push(buffer);
+                if nb_readers.contains_key(&(self.input_node_index, port)) {
+                    port_buffers.insert((self.input_node_index, port), buffer);
+                } else {
+                    pool.release(buffer);
+                }
+            }
+        }
+
+        let positions = self
+            .schedule
+            .iter()
+            .enumerate()
+            .map(|(i, &node)| (node, i))
+            .collect::<HashMap<_, _>>();
+        let mut pooled_schedule = Vec::with_capacity(self.schedule.len());
+        for &node in self.schedule.iter() {
+            let (nb_inputs, nb_outputs) = {
+                let n = &self.graph[node].node_processor;
+                (n.nb_inputs() as u32, n.nb_outputs() as u32)
+            };
+            //Same sizes and samplerates as in gather_inputs and process_node
+            let (in_size, in_samplerate) = self
+                .inputs(node)
+                .next()
+                .map(|edge| (edge.weight().buffer().len(), edge.weight().samplerate))
+                .unwrap_or((self.frames_per_buffer as usize, self.nominal_samplerate));
+            let (out_size, out_samplerate) = self
+                .outputs(node)
+                .next()
+                .map(|edge| (edge.weight().buffer().len(), edge.weight().samplerate))
+                .unwrap_or((self.frames_per_buffer as usize, self.nominal_samplerate));
+
+            let mut inputs = Vec::with_capacity(nb_inputs as usize);
+            let mut sources_by_inlet = Vec::with_capacity(nb_inputs as usize);
+            let mut source_edges = Vec::with_capacity(nb_inputs as usize);
+            let mut mixes = Vec::new();
+            let mut nb_silent = 0;
+            for port in 1..(nb_inputs + 1) {
+                //Edges are enumerated in the same order as their resamplers in update_edge_resamplers
+                let edges = self
+                    .inputs(node)
+                    .enumerate()
+                    .filter(|(_, edge)| edge.weight().dst_port() == port)
+                    .collect::<Vec<_>>();
+                let sources = edges
+                    .iter()
+                    .map(|(_, edge)| port_buffers[&(edge.source(), edge.weight().src_port())])
+                    .collect::<Vec<_>>();
+                source_edges.push(
+                    edges
+                        .iter()
+                        .map(|(resampler, edge)| {
+                            (positions.get(&edge.source()).cloned(), *resampler)
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                let buffer = if sources.is_empty() {
+                    let silent = silent_buffers
+                        .entry((in_size, in_samplerate))
+                        .or_insert_with(Vec::new);
+                    if nb_silent == silent.len() {
+                        silent.push(pool.add(in_size, in_samplerate));
+                    }
+                    nb_silent += 1;
+                    silent[nb_silent - 1]
+                } else if sources.len() == 1 && !inputs.contains(&sources[0]) {
+                    sources[0]
+                } else {
+                    //Several edges on the inlet, or a buffer already read by another inlet
+                    let mix = pool.allocate(in_size, in_samplerate);
+                    mixes.push((mix, sources.clone()));
+                    mix
+                };
+                inputs.push(buffer);
+                sources_by_inlet.push(sources);
+            }
+
+            let outputs = if node == self.output_node_index {
+                Vec::new()
+            } else {
+                (1..(nb_outputs + 1))
+                    .map(|port| {
+                        let buffer = pool.allocate(out_size, out_samplerate);
+                        port_buffers.insert((node, port), buffer);
+                        buffer
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            //Outputs are allocated before releasing the inputs, so that a node never writes into a buffer it reads
+            for &(mix, _) in mixes.iter() {
+                pool.release(mix);
+            }
+            for edge in self.inputs(node) {
+                let port = (edge.source(), edge.weight().src_port());
+                let nb = nb_readers.get_mut(&port).unwrap();
+                *nb -= 1;
+                if *nb == 0 {
+                    pool.release(port_buffers[&port]);
+                }
+            }
+            for (port, &buffer) in outputs.iter().enumerate() {
+                if !nb_readers.contains_key(&(node, port as u32 + 1)) {
+                    pool.release(buffer);
+                }
+            }
+
+            pooled_schedule.push(PooledNode {
+                node,
+                inputs,
+                sources: sources_by_inlet,
+                source_edges,
+                input_size: in_size,
+                input_samplerate: in_samplerate,
+                mixes,
+                outputs,
+                output_size: out_size,
+                output_samplerate: out_samplerate,
+            });
+        }
+
+        self.pool = pool;
+        self.pooled_schedule = pooled_schedule;
+        self.source_outputs = source_outputs;
+    }
+
+    /// Preallocate the resamplers switched in by `degrade_from` on the edges crossing the boundary of the
+    /// degraded part of the graph: every edge going into a node of the schedule can be one of them.
+    /// They are stored by position of the destination node in the schedule.
+    /// The resamplers of the edges still in the graph are kept, with their state.
+    ///
+    /// The interchange buffers, into which the inputs of the degraded nodes are gathered, get room for the
+    /// largest input, so that degrading never allocates.
+    fn update_edge_resamplers(&mut self) {
+        let mut previous = mem::replace(&mut self.edge_resamplers, Vec::new())
+            .into_iter()
+            .flat_map(|resamplers| resamplers.into_iter())
+            .map(|resampler| (resampler.key(), resampler))
+            .collect::<HashMap<_, _>>();
+        let edge_resamplers = self
+            .schedule
+            .iter()
+            .map(|&node| {
+                self.inputs(node)
+                    .map(|edge| {
+                        let key = (
+                            edge.source(),
+                            edge.weight().src_port(),
+                            node,
+                            edge.weight().dst_port(),
+                        );
+                        let (size, samplerate) =
+                            (edge.weight().buffer().len(), edge.weight().samplerate);
+                        let mut resampler = previous
+                            .remove(&key)
+                            .unwrap_or_else(|| EdgeResampler::new(key, size, samplerate));
+                        resampler.reserve(size);
+                        resampler
+                    })
+                    .collect()
+            })
+            .collect();
+        self.edge_resamplers = edge_resamplers;
+        self.degradation = Degradation::with_capacity(self.schedule.len());
+
+        let max_size = self
+            .pooled_schedule
+            .iter()
+            .map(|pooled_node| pooled_node.input_size)
+            .max()
+            .unwrap_or(self.size);
+        for input in self.input_edges.iter_mut() {
+            let len = input.buffer.len();
+            input.buffer.reserve(max_size.saturating_sub(len));
+        }
+    }
+
+    /// Fill `input_edges` with the edges going into `node`.
+    /// Edges are routed to the inlet given by their destination port, edges arriving at the same inlet
+    /// are summed, and inlets without any edge are silent.
+    fn gather_inputs(&mut self, node: NodeIndex, nb_inputs: usize) {
+        //All the incoming edges of a node have the same size and samplerate
+        let (buf_size, samplerate) = self
+            .inputs(node)
+            .next()
+            .map(|edge| (edge.weight().buffer().len(), edge.weight().samplerate))
+            .unwrap_or((self.frames_per_buffer as usize, self.nominal_samplerate));
+        for input in self.input_edges[0..nb_inputs].iter_mut() {
+            input.resize(buf_size);
+            input.samplerate = samplerate;
+            for sample in input.buffer_mut().iter_mut() {
+                *sample = 0.;
+            }
+        }
+
+        let mut edges = self.inputs_mut(node);
+        while let Some(edge) = edges.next_edge(&self.graph) {
+            let edge = &self.graph[edge];
+            let port = edge.dst_port() as usize - 1;
+            debug_assert!(port < nb_inputs);
+            debug_assert_eq!(edge.buffer().len(), buf_size);
+            mixer(self.input_edges[port].buffer_mut(), edge.buffer());
+        }
+    }
+
+    /// Copy `output_edges` into the edges going out of `node`, according to their source port
+    fn scatter_outputs(&mut self, node: NodeIndex) {
+        let mut edges = self.outputs_mut(node);
+        while let Some(edge) = edges.next_edge(&self.graph) {
+            let port = self.graph[edge].src_port() as usize - 1;
+            //The size of a DspEdge is the right one (computed at scheduling)
+            debug_assert_eq!(
+                self.graph[edge].buffer().len(),
+                self.output_edges[port].buffer().len()
+            );
+            self.graph[edge]
+                .buffer_mut()
+                .copy_from_slice(self.output_edges[port].buffer());
+        }
+    }
+
+    /// Copy the interlaced input buffer from the soundcard into the graph
+    fn process_source(&mut self, input_buffer: &[f32]) {
+        //Prepare input
+        self.input_edges[0].resize(input_buffer.len());
+        self.input_edges[0]
+            .buffer_mut()
+            .copy_from_slice(input_buffer);
+        //One output per channel
+        let frames = input_buffer.len() / self.channels as usize;
+        for output in self.output_edges[0..self.channels as usize].iter_mut() {
+            output.resize(frames);
+            output.samplerate = self.nominal_samplerate;
+        }
+        //Process
         self.graph
-            .neighbors_directed(src, EdgeDirection::Outgoing)
-            .detach()
+            .node_weight_mut(self.input_node_index)
+            .unwrap()
+            .node_processor
+            .process(
+                &self.input_edges[0..1],
+                &mut self.output_edges[0..self.channels as usize],
+            );
+        //Prepare Outputs
+        let input_node_index = self.input_node_index;
+        self.scatter_outputs(input_node_index);
     }
 
-    pub fn inputs(&self, dest: NodeIndex) -> Edges<DspEdge, Directed> {
-        self.graph.edges_directed(dest, EdgeDirection::Incoming)
+    /// Execute one node of the schedule, reading its input edges and writing to its output edges
+    fn process_node(&mut self, node: NodeIndex) {
+        //println!("Executing {}:{}", self.graph.node_weight(node).unwrap().node_infos.id, self.graph.node_weight(node).unwrap().node_processor);
+
+        let (nb_inputs, nb_outputs) = {
+            let n = &self.graph.node_weight(node).unwrap().node_processor;
+            (n.nb_inputs(), n.nb_outputs())
+        };
+
+        //Prepare inputs
+        self.gather_inputs(node, nb_inputs);
+
+        //Fix output_edges buffer sizes and samplerates.
+        //All the outgoing edges of a node have the same size and samplerate, including non connected ports.
+        let (buf_size, samplerate) = self
+            .outputs(node)
+            .next()
+            .map(|edge| (edge.weight().buffer().len(), edge.weight().samplerate))
+            .unwrap_or((self.frames_per_buffer as usize, self.nominal_samplerate));
+        for output in self.output_edges[0..nb_outputs].iter_mut() {
+            output.resize(buf_size);
+            output.samplerate = samplerate;
+        }
+
+        //Process
+        self.graph
+            .node_weight_mut(node)
+            .unwrap()
+            .node_processor
+            .process(
+                &self.input_edges[0..nb_inputs],
+                &mut self.output_edges[0..nb_outputs],
+            );
+
+        //Prepare Outputs
+        self.scatter_outputs(node);
     }
 
-    pub fn inputs_mut(&self, src: NodeIndex) -> WalkNeighbors<u32> {
+    /// Execute the sink and write the interlaced output to `output_buffer`
+    fn process_sink(&mut self, output_buffer: &mut [f32]) {
+        debug_assert_eq!(
+            self.graph
+                .node_weight(self.output_node_index)
+                .unwrap()
+                .node_infos
+                .class_name
+                .as_str(),
+            "real_sink"
+        );
+        //Prepare inputs: one per channel
+        let (output_node_index, channels) = (self.output_node_index, self.channels as usize);
+        self.gather_inputs(output_node_index, channels);
+        debug_assert!(self.input_edges[0..channels]
+            .iter()
+            .all(|input| input.samplerate == self.nominal_samplerate));
+        //Output edge needs to be the interlaced_size
+        self.output_edges[0].resize(output_buffer.len());
+        //Process
         self.graph
-            .neighbors_directed(src, EdgeDirection::Incoming)
-            .detach()
+            .node_weight_mut(self.output_node_index)
+            .unwrap()
+            .node_processor
+            .process(
+                &self.input_edges[0..self.channels as usize],
+                &mut self.output_edges[0..1],
+            );
+        //Prepare Output to soundcard
+        output_buffer.copy_from_slice(self.output_edges[0].buffer());
+        self.output_edges[0].resize(self.size);
     }
 
-    pub fn nb_inputs(&self, dest: NodeIndex) -> u32 {
-        self.inputs(dest).count() as u32
+    /// Sum the buffers of the inlets with several edges of node `i` of the pooled schedule
+    fn mix_pooled_inputs(&mut self, i: usize) {
+        for &(mix, ref sources) in self.pooled_schedule[i].mixes.iter() {
+            for sample in self.pool.buffers[mix].buffer_mut().iter_mut() {
+                *sample = 0.;
+            }
+            for &source in sources.iter() {
+                self.pool.mix(mix, source);
+            }
+        }
     }
 
-    /// Remove nodes not connected to the sink from the schedule
-    fn active_component(&mut self) {
-        let rev_graph = Reversed(&self.graph);
-        let mut dfs = Dfs::new(&rev_graph, self.output_node_index);
-        while let Some(_) = dfs.next(&rev_graph) {} //Just traverse the graph to populate dfs.discovered
+    /// Exchange the buffers of the pool used by node `i` of the pooled schedule with the interchange buffers.
+    /// Calling it a second time puts them back into the pool.
+    fn swap_pooled_buffers(&mut self, i: usize) {
+        let pooled_node = &self.pooled_schedule[i];
+        for (input, &buffer) in self.input_edges.iter_mut().zip(pooled_node.inputs.iter()) {
+            mem::swap(input, &mut self.pool.buffers[buffer]);
+        }
+        for (output, &buffer) in self.output_edges.iter_mut().zip(pooled_node.outputs.iter()) {
+            mem::swap(output, &mut self.pool.buffers[buffer]);
+        }
+    }
 
-        //println!("Source is connected? {:?}", dfs.discovered.is_visited(&self.input_node_index));
+    /// Copy the interlaced input buffer from the soundcard into the buffers of the pool read by the graph
+    fn process_pooled_source(&mut self, input_buffer: &[f32]) {
+        self.input_edges[0].resize(input_buffer.len());
+        self.input_edges[0]
+            .buffer_mut()
+            .copy_from_slice(input_buffer);
+        for (output, &buffer) in self.output_edges.iter_mut().zip(self.source_outputs.iter()) {
+            mem::swap(output, &mut self.pool.buffers[buffer]);
+        }
+        let channels = self.channels as usize;
+        self.graph[self.input_node_index]
+            .node_processor
+            .process(&self.input_edges[0..1], &mut self.output_edges[0..channels]);
+        for (output, &buffer) in self.output_edges.iter_mut().zip(self.source_outputs.iter()) {
+            mem::swap(output, &mut self.pool.buffers[buffer]);
+        }
+        self.input_edges[0].resize(self.size);
+    }
 
-        let mut filtered_schedule = Vec::with_capacity(self.schedule.len());
+    /// Execute node `i` of the pooled schedule, directly in the buffers of the pool
+    fn process_pooled_node(&mut self, i: usize) {
+        self.mix_pooled_inputs(i);
+        self.swap_pooled_buffers(i);
+        {
+            let pooled_node = &self.pooled_schedule[i];
+            self.graph[pooled_node.node].node_processor.process(
+                &self.input_edges[0..pooled_node.inputs.len()],
+                &mut self.output_edges[0..pooled_node.outputs.len()],
+            );
+        }
+        self.swap_pooled_buffers(i);
+    }
 
-        for node in self.schedule.iter() {
-            if dfs.discovered.is_visited(node) {
-                filtered_schedule.push(*node);
+    /// Execute the sink, the last node of the pooled schedule, and write the interlaced output to `output_buffer`
+    fn process_pooled_sink(&mut self, output_buffer: &mut [f32]) {
+        let i = self.pooled_schedule.len() - 1;
+        debug_assert_eq!(self.pooled_schedule[i].node, self.output_node_index);
+        self.mix_pooled_inputs(i);
+        self.swap_pooled_buffers(i);
+        self.output_edges[0].resize(output_buffer.len());
+        let channels = self.channels as usize;
+        self.graph[self.output_node_index]
+            .node_processor
+            .process(&self.input_edges[0..channels], &mut self.output_edges[0..1]);
+        output_buffer.copy_from_slice(self.output_edges[0].buffer());
+        self.output_edges[0].resize(self.size);
+        self.swap_pooled_buffers(i);
+    }
+
+    /// Fill `inputs` with the inputs of `pooled_node` during a degraded cycle. `degraded` tells whether the node
+    /// itself is degraded. Edges crossing the boundary of the degraded part are resampled by their preallocated resampler,
+    /// among `resamplers`, and the other ones are read from the pool as they are.
+    /// Returns the number of edges whose resampler failed.
+    ///
+    /// `buffers` points to the buffers of the pool. The buffers read by the node must not be written during the call.
+    unsafe fn gather_degraded_inputs(
+        pooled_node: &PooledNode,
+        degraded: bool,
+        degraded_cycle: DegradedCycle,
+        buffers: *const DspEdge,
+        resamplers: &mut [EdgeResampler],
+        inputs: &mut [DspEdge],
+        mut time_resampler: Option<&mut Stats>,
+    ) -> usize {
+        let (size, samplerate) = if degraded {
+            degraded_format(pooled_node.input_size, pooled_node.input_samplerate)
+        } else {
+            (pooled_node.input_size, pooled_node.input_samplerate)
+        };
+        let mut nb_errors = 0;
+        let inlets = pooled_node
+            .sources
+            .iter()
+            .zip(pooled_node.source_edges.iter());
+        for (input, (sources, edges)) in inputs.iter_mut().zip(inlets) {
+            input.resize(size);
+            input.samplerate = samplerate;
+            for sample in input.buffer_mut().iter_mut() {
+                *sample = 0.;
+            }
+            for (&source, &(position, resampler)) in sources.iter().zip(edges.iter()) {
+                let source = &*buffers.add(source);
+                if Degradation::is_degraded(degraded_cycle.from, position) == degraded {
+                    mixer(input.buffer_mut(), source.buffer());
+                } else {
+                    let start = PreciseTime::now();
+                    let resampler = &mut resamplers[resampler];
+                    resampler.switch_in(degraded_cycle.cycle);
+                    if !resampler.resample(source, size, samplerate) {
+                        nb_errors += 1;
+                    }
+                    mixer(input.buffer_mut(), resampler.buffer.buffer());
+                    if let Some(ref mut time_resampler) = time_resampler {
+                        time_resampler.update_time(start);
+                    }
+                }
             }
         }
-        //println!("Schedule nodes before active components filtering:");
-        //self.print_schedule(&self.schedule);
-        self.schedule = filtered_schedule;
-        //self.schedule = self.schedule.iter().filter(|v| {dfs.discovered.is_visited(v)}).collect::<Vec<_>>();
-        let mut source_index = 0;
-        for (i, e) in self.schedule.iter().enumerate() {
-            if *e == self.input_node_index {
-                self.has_source = true;
-                source_index = i;
-                break;
+        nb_errors
+    }
+
+    /// Execute node `i` of the pooled schedule at a lower samplerate, in the buffers of the pool,
+    /// its inputs being gathered into the interchange buffers
+    fn process_degraded_pooled_node(&mut self, i: usize) {
+        let from = self.degradation.from.expect("The cycle is degraded");
+        let nb_inputs = self.pooled_schedule[i].inputs.len();
+        self.degradation.nb_resampling_errors += unsafe {
+            AudioGraph::gather_degraded_inputs(
+                &self.pooled_schedule[i],
+                true,
+                DegradedCycle {
+                    from,
+                    cycle: self.cycle,
+                },
+                self.pool.buffers.as_ptr(),
+                &mut self.edge_resamplers[i],
+                &mut self.input_edges[0..nb_inputs],
+                Some(&mut self.time_resampler),
+            )
+        };
+        let pooled_node = &self.pooled_schedule[i];
+        let (size, samplerate) =
+            degraded_format(pooled_node.output_size, pooled_node.output_samplerate);
+        for (output, &buffer) in self.output_edges.iter_mut().zip(pooled_node.outputs.iter()) {
+            mem::swap(output, &mut self.pool.buffers[buffer]);
+            output.resize(size);
+            output.samplerate = samplerate;
+        }
+        self.graph[pooled_node.node].node_processor.process(
+            &self.input_edges[0..nb_inputs],
+            &mut self.output_edges[0..pooled_node.outputs.len()],
+        );
+        for (output, &buffer) in self.output_edges.iter_mut().zip(pooled_node.outputs.iter()) {
+            mem::swap(output, &mut self.pool.buffers[buffer]);
+        }
+    }
+
+    /// Execute the sink at the end of a degraded cycle, the edges coming from the degraded part of the graph
+    /// being upsampled back to the nominal samplerate, and write the interlaced output to `output_buffer`
+    fn process_degraded_pooled_sink(&mut self, output_buffer: &mut [f32]) {
+        let i = self.pooled_schedule.len() - 1;
+        debug_assert_eq!(self.pooled_schedule[i].node, self.output_node_index);
+        let from = self.degradation.from.expect("The cycle is degraded");
+        let channels = self.channels as usize;
+        self.degradation.nb_resampling_errors += unsafe {
+            AudioGraph::gather_degraded_inputs(
+                &self.pooled_schedule[i],
+                false,
+                DegradedCycle {
+                    from,
+                    cycle: self.cycle,
+                },
+                self.pool.buffers.as_ptr(),
+                &mut self.edge_resamplers[i],
+                &mut self.input_edges[0..channels],
+                Some(&mut self.time_resampler),
+            )
+        };
+        self.output_edges[0].resize(output_buffer.len());
+        self.graph[self.output_node_index]
+            .node_processor
+            .process(&self.input_edges[0..channels], &mut self.output_edges[0..1]);
+        output_buffer.copy_from_slice(self.output_edges[0].buffer());
+        self.output_edges[0].resize(self.size);
+    }
+
+    /// Execute all the nodes of the pooled schedule but the sink with the threads of the parallel executor.
+    /// Inputs are copied from the pool into the interchange buffers of the thread, so that several nodes
+    /// can read the same buffer at the same time.
+    ///
+    /// In adaptive mode, the nodes from position `degraded_from` are executed at a lower samplerate, and the
+    /// execution times of the nodes are measured if `measure` is set.
+    /// Returns the number of edges whose resampler failed.
+    fn process_parallel(&mut self, degraded_from: Option<usize>, measure: bool) -> usize {
+        let nb_nodes = self.pooled_schedule.len() - 1;
+        self.node_pointers.0.clear();
+        for pooled_node in self.pooled_schedule[0..nb_nodes].iter() {
+            let node: *mut DspNode = &mut self.graph[pooled_node.node];
+            self.node_pointers.0.push(node);
+        }
+        let cycle = ParallelCycle {
+            nodes: self.node_pointers.0.as_ptr(),
+            buffers: self.pool.buffers.as_mut_ptr(),
+            workers: self.worker_edges.as_mut_ptr(),
+            resamplers: self.edge_resamplers.as_mut_ptr(),
+        };
+        let nb_errors = AtomicUsize::new(0);
+        let pooled_schedule = &self.pooled_schedule;
+        let degraded_cycle = degraded_from.map(|from| DegradedCycle {
+            from,
+            cycle: self.cycle,
+        });
+        let task = |worker: usize, i: usize| unsafe {
+            let pooled_node = &pooled_schedule[i];
+            let worker = &mut *cycle.workers.add(worker);
+            let degraded = degraded_cycle.filter(|degraded_cycle| i >= degraded_cycle.from);
+            if let Some(degraded_cycle) = degraded {
+                let errors = AudioGraph::gather_degraded_inputs(
+                    pooled_node,
+                    true,
+                    degraded_cycle,
+                    cycle.buffers,
+                    &mut *cycle.resamplers.add(i),
+                    &mut worker.inputs[0..pooled_node.inputs.len()],
+                    None,
+                );
+                if errors > 0 {
+                    nb_errors.fetch_add(errors, Ordering::Relaxed);
+                }
+            } else {
+                for (input, sources) in worker.inputs.iter_mut().zip(pooled_node.sources.iter()) {
+                    input.resize(pooled_node.input_size);
+                    input.samplerate = pooled_node.input_samplerate;
+                    if sources.len() == 1 {
+                        input
+                            .buffer_mut()
+                            .copy_from_slice((*cycle.buffers.add(sources[0])).buffer());
+                    } else {
+                        for sample in input.buffer_mut().iter_mut() {
+                            *sample = 0.;
+                        }
+                        for &source in sources.iter() {
+                            mixer(input.buffer_mut(), (*cycle.buffers.add(source)).buffer());
+                        }
+                    }
+                }
+            }
+            let (size, samplerate) = if degraded.is_some() {
+                degraded_format(pooled_node.output_size, pooled_node.output_samplerate)
+            } else {
+                (pooled_node.output_size, pooled_node.output_samplerate)
+            };
+            for (output, &buffer) in worker.outputs.iter_mut().zip(pooled_node.outputs.iter()) {
+                mem::swap(output, &mut *cycle.buffers.add(buffer));
+                output.resize(size);
+                output.samplerate = samplerate;
+            }
+            let node = &mut **cycle.nodes.add(i);
+            let start = PreciseTime::now();
+            node.node_processor.process(
+                &worker.inputs[0..pooled_node.inputs.len()],
+                &mut worker.outputs[0..pooled_node.outputs.len()],
+            );
+            if measure {
+                node.exec_time.update_time(start);
+            }
+            for (output, &buffer) in worker.outputs.iter_mut().zip(pooled_node.outputs.iter()) {
+                mem::swap(output, &mut *cycle.buffers.add(buffer));
             }
+        };
+        self.parallel
+            .as_ref()
+            .expect("Parallel executor not started")
+            .run(&task);
+        nb_errors.into_inner()
+    }
+
+    /// Execute one cycle of the graph by copying audio between the buffers of the edges.
+    /// `process` rather uses buffers of a pool shared by the edges, which avoids the copies.
+    pub fn process_copy(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        self.apply_transactions();
+        if self.schedule_dirty {
+            AudioGraph::output_silence(outputs);
+            return;
         }
+        let interlaced_size = (self.channels * self.frames_per_buffer) as usize;
+        assert_eq!(inputs[0].buffer().len(), interlaced_size);
+        assert_eq!(outputs[0].buffer().len(), interlaced_size);
+
         if self.has_source {
-            self.schedule.remove(source_index);
+            self.process_source(inputs[0].buffer());
         }
+        self.input_edges[0].resize(self.size);
+
+        //We assume that sink is the last node in the schedule and execute it separately
+        for i in 0..self.schedule.len() - 1 {
+            let node = self.schedule[i];
+            self.process_node(node);
+        }
+
+        self.process_sink(outputs[0].buffer_mut());
     }
 
-    /// Adjust buffer sizes and samplerates for nodes between two resamplers
-    fn buffer_size_resamplers(&mut self) {
-        /*let sources = self.graph.externals(Direction::Incoming).collect::<Vec<_>>();
-        let mut dfs = Dfs::empty(&self.graph);
+    /// Populate the vec `schedule_expected_time`
+    /// `schedule_expected_time[i]` is the remaining time in the schedule `self.schedule` from node i included
+    /// to the last node.
+    ///
+    /// This should be invoked once at the beginning of every dsp cycle, or if the schedule changes
+    fn update_remaining_times(&mut self) {
+        let mut expected_acc = 0.;
 
-        for source in sources.iter() {
-        dfs.move_to(*source);//Change root for dfs but don't erase the visited map!
-        while let Some(node) = dfs.next(&self.graph) {*/
-        assert_eq!(self.schedule.len(), self.graph.node_count());
-        for &node in self.schedule.iter() {
-            let class_name = self.graph[node].node_infos.class_name.clone();
+        //Iterating backward from the end
+        for i in (0..self.schedule.len()).rev() {
+            expected_acc += self.graph[self.schedule[i]].exec_time.mean;
+            self.schedule_expected_time[i] = expected_acc;
+        }
+    }
 
-            let ratio: f64 = self.graph[node]
-                .node_infos
-                .more
-                .get("ratio")
-                .map_or(1.0, |v| v.parse().unwrap());
-            //Get min incoming buffer size
-            let buf_size = self
-                .inputs(node)
-                .map(|edge| edge.weight().buffer().len())
-                .min()
-                .unwrap_or(self.default_buffer_size());
-            //Get min incoming samplerate
-            let samplerate = self
-                .inputs(node)
-                .map(|edge| edge.weight().samplerate)
-                .min()
-                .unwrap_or(self.nominal_samplerate);
+    /// Degrade all the nodes of the pooled schedule from `position` (except the sink): they are executed at a
+    /// lower samplerate, and the preallocated resamplers of the edges coming into this part of the graph and going
+    /// out of it to the sink are switched in when the nodes are executed.
+    /// Returns the number of resampled edges.
+    fn degrade_from(&mut self, position: usize) -> usize {
+        let sink = self.pooled_schedule.len() - 1;
+        if position >= sink {
+            //Only the sink remains
+            return 0;
+        }
+        let nb_resamplers = self.pooled_schedule[position..]
+            .iter()
+            .enumerate()
+            .map(|(i, pooled_node)| {
+                let degraded = position + i < sink;
+                pooled_node
+                    .source_edges
+                    .iter()
+                    .flat_map(|edges| edges.iter())
+                    .filter(|&&(source, _)| Degradation::is_degraded(position, source) != degraded)
+                    .count()
+            })
+            .sum();
+        self.degradation.from = Some(position);
+        nb_resamplers
+    }
 
-            let (new_buf_size, new_samplerate) = if class_name == "resampler" {
-                /*
-                Some incoming edges may have not their buffer size downsampled yet (but at least one has it thanks to the dfs ordering)
-                Some incoming edges could have not had their buffer size with the normal sample size yet if an incoming branch was downsampled (but at least one has it thanks to the dfs ordering).
-                But actually this case does not happen, as if the path is downsampled, then thanks to dfs ordering, all the subsequent edges including the incoming one of the current node, must have been explored.
-                If it had happened, we would have taken max in the case of ratio < 1.0
-                */
-                (
-                    (buf_size as f64 * ratio) as usize,
-                    (samplerate as f64 * ratio) as u32,
-                )
-            } else {
-                //We are not after a resampler so we propagate the min buffer size of the previous edges.
-                (buf_size, samplerate)
-            };
+    /// Expected execution time of the nodes of `schedule` from `position` (except the sink) if they are
+    /// executed at a lower samplerate by `degrade_from`, including the resampled edges
+    fn expected_resampled_time(&self, schedule: &[NodeIndex], position: usize) -> f64 {
+        let degraded = &schedule[position..schedule.len() - 1];
+        let mut remaining = self.graph.visit_map();
+        for node in degraded.iter() {
+            remaining.visit(*node);
+        }
+        let nb_downsamplers = degraded
+            .iter()
+            .flat_map(|node| self.inputs(*node))
+            .filter(|edge| !remaining.is_visited(&edge.source()))
+            .count();
+        let nb_upsamplers = self
+            .inputs(self.output_node_index)
+            .filter(|edge| remaining.is_visited(&edge.source()))
+            .count();
+        let nodes_time = degraded
+            .iter()
+            .map(|node| self.expected_resampled_node_time(*node))
+            .sum::<f64>();
+        nodes_time + (nb_downsamplers + nb_upsamplers) as f64 * self.time_resampler.mean
+    }
 
-            //println!("Buffer size={}; samplerate={}", new_buf_size, new_samplerate);
-            //Modify all outcoming buffer sizes
-            let mut output_edges = self.outputs_mut(node);
-            while let Some(edge) = output_edges.next_edge(&self.graph) {
-                /*let (_,dst) = self.graph.edge_endpoints(edge).unwrap();
-                if self.graph[dst].node_infos.class_name == "real_sink" {
-                    println!("{}:{} -> {}:real_sink: ratio={}; samplerate={}; new_samplerate={}; buf_size={}; new_buf_size={}", self.graph[node].node_infos.id, class_name,
-                    self.graph[dst].node_infos.class_name,
-                    ratio, samplerate, new_samplerate, buf_size, new_buf_size);
-                }*/
-                self.graph
-                    .edge_weight_mut(edge)
-                    .unwrap()
-                    .resize(new_buf_size);
-                self.graph.edge_weight_mut(edge).unwrap().samplerate = new_samplerate;
+    /// Expected execution time of `node` once downsampled: its measured time scaled by the ratio of the
+    /// costs predicted by the cost model, or by `DEGRADATION_RATIO` if there is no model for its kind
+    fn expected_resampled_node_time(&self, node: NodeIndex) -> f64 {
+        let exec_time = self.graph[node].exec_time.mean;
+        match self.predicted_cost(node) {
+            Some(predicted) if predicted.nominal > 0. => {
+                exec_time * predicted.resampled / predicted.nominal
             }
+            _ => exec_time * DEGRADATION_RATIO,
         }
+    }
 
-        // Check sink
-        self.inputs(self.output_node_index)
-            .for_each(|ref edge| assert_eq!(edge.weight().samplerate, self.nominal_samplerate));
+    /// Execution time of `variant` of `node` relative to its nominal variant: the one profiled by the
+    /// cost model if there is one for its kind, or the one given by the node otherwise
+    fn variant_cost(&self, node: NodeIndex, variant: usize) -> f64 {
+        let dsp_node = &self.graph[node];
+        self.cost_model
+            .as_ref()
+            .and_then(|cost_model| {
+                cost_model.variant_cost(&dsp_node.node_infos.class_name, variant)
+            })
+            .unwrap_or_else(|| dsp_node.node_processor.variant_cost(variant))
     }
 
-    /// Reset all buffer sizes to the default
-    fn reset_buffer_sizes(&mut self) {
-        let default_size = self.default_buffer_size();
-        for edge in self.graph.edge_weights_mut() {
-            edge.resize(default_size);
+    /// Expected execution time of the nodes of `schedule` from `position` (except the sink) if they are
+    /// switched to their cheapest variant, and number of nodes which have a cheaper variant
+    fn expected_variants_time(&self, schedule: &[NodeIndex], position: usize) -> (f64, usize) {
+        let mut expected_time = 0.;
+        let mut nb_cheaper = 0;
+        for node in schedule[position..schedule.len() - 1].iter() {
+            let dsp_node = &self.graph[*node];
+            let processor = &dsp_node.node_processor;
+            let (variant, cheapest) = (processor.variant(), processor.nb_variants() - 1);
+            if cheapest > variant {
+                nb_cheaper += 1;
+            }
+            expected_time += dsp_node.exec_time.mean * self.variant_cost(*node, cheapest)
+                / self.variant_cost(*node, variant);
         }
+        (expected_time, nb_cheaper)
     }
 
-    /// Check that all nodes are isochronous
-    fn validate_buffer_sizes(&self) -> bool {
-        self.graph.node_indices().all(|node| {
-            let mut input_edges = self.graph.edges_directed(node, Direction::Incoming);
-            let first = input_edges
-                .next()
-                .map(|e| (e.weight().buffer().len(), e.weight().samplerate));
-            let res = if let Some((size_edge, samplerate)) = first {
-                input_edges.all(|e| {
-                    e.weight().buffer().len() == size_edge && e.weight().samplerate == samplerate
-                })
-            } else {
-                true
-            };
-            res && {
-                let mut output_edges = self.graph.edges_directed(node, Direction::Outgoing);
-                let first = output_edges
-                    .next()
-                    .map(|e| (e.weight().buffer().len(), e.weight().samplerate));
-                if let Some((size_edge, samplerate)) = first {
-                    output_edges.all(|e| {
-                        e.weight().buffer().len() == size_edge
-                            && e.weight().samplerate == samplerate
-                    })
-                } else {
-                    true
+    /// Whether switching the nodes of `schedule` from `position` to their cheapest variant is a better fix
+    /// than resampling them, when the nodes are expected to take longer than `remaining_budget`:
+    /// it is if it is enough to meet the deadline, or if it is cheaper than resampling.
+    fn variants_are_cheaper(
+        &self,
+        schedule: &[NodeIndex],
+        position: usize,
+        remaining_budget: f64,
+    ) -> bool {
+        let (variants_time, nb_cheaper) = self.expected_variants_time(schedule, position);
+        let sink_time = self.graph[self.output_node_index].exec_time.mean;
+        nb_cheaper > 0
+            && (variants_time + sink_time <= remaining_budget
+                || variants_time <= self.expected_resampled_time(schedule, position))
+    }
+
+    /// Switch the nodes of the schedule from `position` (except the sink) to their cheapest variant.
+    /// Returns the number of switched nodes.
+    fn switch_to_cheapest_variants(&mut self, position: usize) -> usize {
+        for i in position..self.schedule.len() - 1 {
+            let node = self.schedule[i];
+            let processor = &mut self.graph[node].node_processor;
+            let (variant, cheapest) = (processor.variant(), processor.nb_variants() - 1);
+            if cheapest > variant && processor.set_variant(cheapest) {
+                self.degradation.switched_variants.push((node, variant));
+            }
+        }
+        self.degradation.switched_variants.len()
+    }
+
+    /// Put back the variants changed by `switch_to_cheapest_variants` and the buffers written by the nodes
+    /// degraded by `degrade_from` to their nominal size and samplerate
+    fn restore_nominal(&mut self) {
+        for (node, variant) in self.degradation.switched_variants.drain(..) {
+            self.graph[node].node_processor.set_variant(variant);
+        }
+        if let Some(from) = self.degradation.from.take() {
+            for pooled_node in self.pooled_schedule[from..].iter() {
+                for &buffer in pooled_node.outputs.iter() {
+                    let buffer = &mut self.pool.buffers[buffer];
+                    buffer.resize(pooled_node.output_size);
+                    buffer.samplerate = pooled_node.output_samplerate;
                 }
             }
-        })
+        }
     }
 
-    /// Autoconnect ports without edges to sources and sinks. If only externals, only autoconect virtual sources and sinks(externals)
-    pub fn autoconnect(&mut self, only_externals: bool) {
-        //automatically connect to adc and dac nodes which have inlets and outlets without node on the other side.
-        let mut io_edges = Vec::new();
-        for node_index in self.graph.node_indices() {
-            let node = self.graph.node_weight(node_index).unwrap();
-            let permitted_node = if only_externals {
-                node.node_infos.class_name == "source" || node.node_infos.class_name == "sink"
-            } else {
-                true
+    /// Expected execution time of the nodes of the pooled schedule but the sink when they are executed by the
+    /// parallel executor: the bound of Graham on list scheduling, with the mean execution times of the nodes.
+    fn expected_parallel_time(&self) -> f64 {
+        let parallel = match self.parallel {
+            Some(ref parallel) => parallel,
+            None => return self.schedule_expected_time.first().cloned().unwrap_or(0.),
+        };
+        let nb_nodes = self.pooled_schedule.len() - 1;
+        //Longest path starting at every node, computed backward as the pooled schedule is in topological order
+        let mut longest = vec![0.; nb_nodes];
+        let mut total = 0.;
+        for i in (0..nb_nodes).rev() {
+            let exec_time = self.graph[self.pooled_schedule[i].node].exec_time.mean;
+            let successors = parallel.successors()[i]
+                .iter()
+                .map(|&successor| longest[successor])
+                .fold(0., f64::max);
+            longest[i] = exec_time + successors;
+            total += exec_time;
+        }
+        let critical_path = longest.iter().cloned().fold(0., f64::max);
+        let nb_threads = parallel.nb_threads() as f64;
+        total / nb_threads + (1. - 1. / nb_threads) * critical_path
+    }
+
+    /// Degrade the nodes of the pooled schedule from `position`, whose expected execution time exceeds
+    /// `remaining_budget`: they are switched to their cheapest variant when it is expected to be enough, or to cost
+    /// less than resampling, and executed at a lower samplerate otherwise.
+    /// Returns the number of degraded nodes and of resampled edges.
+    fn degrade(&mut self, position: usize, remaining_budget: f64) -> (usize, usize) {
+        if self.variants_are_cheaper(&self.schedule, position, remaining_budget) {
+            (self.switch_to_cheapest_variants(position), 0)
+        } else {
+            match self.degrade_from(position) {
+                0 => (0, 0),
+                nb_resamplers => (self.schedule.len() - 1 - position, nb_resamplers),
+            }
+        }
+    }
+
+    /// Adaptive version of the process method for the audio graph.
+    /// `rel_deadline` is the time budget for the cycle, in microseconds. By default, it should be `self.budget()`.
+    ///
+    /// If the expected remaining execution time of the schedule exceeds the remaining budget,
+    /// the remaining nodes are switched to their cheapest variant when it is expected to be enough, or to cost
+    /// less than resampling. Otherwise, they are executed at a lower samplerate, with the resamplers preallocated
+    /// for the edges going into and out of them switched in.
+    ///
+    /// Nodes are executed in the buffers of the pool, as in `process`. With the parallel executor, the nodes
+    /// are not executed in the order of the schedule, so the degradation is chosen once at the start of the cycle,
+    /// with the expected execution time of the parallel schedule.
+    pub fn process_adaptive(
+        &mut self,
+        inputs: &[DspEdge],
+        outputs: &mut [DspEdge],
+        rel_deadline: f64,
+        flags: CallbackFlags,
+    ) -> TimeMonitor {
+        let start = PreciseTime::now();
+        self.apply_transactions();
+        self.cycle += 1;
+        if self.schedule_dirty {
+            AudioGraph::output_silence(outputs);
+            return TimeMonitor {
+                quality: Quality::Normal,
+                budget: rel_deadline as i64,
+                deadline: rel_deadline as u64,
+                expected_remaining_time: 0,
+                execution_time: 0,
+                nb_degraded: 0,
+                nb_resamplers: 0,
+                nb_resampling_errors: 0,
+                callback_flags: flags,
+                choosing_duration: 0,
             };
-            if permitted_node
-                && node_index != self.input_node_index
-                && node_index != self.output_node_index
-            {
-                // Theoretical I/O
-                let nb_in_t = node.node_processor.nb_inputs() as u32;
-                let nb_out_t = node.node_processor.nb_outputs() as u32;
-                // Actually
-                let nb_in_r = self.nb_inputs(node_index);
-                let nb_out_r = self.nb_outputs(node_index);
-                //println!("In_t={};In_r={}", nb_in_t, nb_in_r);
-                //println!("Out_t={};Out_r={}", nb_out_t, nb_out_r);
-                // Check if some ports are not connected
-                if nb_in_t > nb_in_r {
-                    //Collect connected input ports
-                    let input_edges = self.inputs(node_index);
-                    let input_ports = input_edges
-                        .map(|e| e.weight().dst_port())
-                        .collect::<HashSet<_>>();
-                    //Connect them to audio source
-                    for port in 1..(nb_in_t + 1) {
-                        if !input_ports.contains(&port) {
-                            //It's a non-connected port
-                            println!(
-                                "Autoconnect edge from source to {}:{} on port {}",
-                                node.node_infos.id, node, port
-                            );
-                            io_edges.push((self.input_node_index, 1, node_index, port));
+        }
+        let interlaced_size = (self.channels * self.frames_per_buffer) as usize;
+        assert_eq!(inputs[0].buffer().len(), interlaced_size);
+        assert_eq!(outputs[0].buffer().len(), interlaced_size);
+
+        self.update_remaining_times();
+        let mut expected_remaining_time =
+            self.schedule_expected_time.first().cloned().unwrap_or(0.);
+        let mut quality = Quality::Normal;
+        let mut choosing_duration = 0;
+        let mut nb_degraded = 0;
+        let mut nb_resamplers = 0;
+        let mut can_degrade = true;
+        self.degradation.nb_resampling_errors = 0;
+
+        if self.has_source {
+            self.process_pooled_source(inputs[0].buffer());
+        }
+
+        //We assume that sink is the last node in the schedule and execute it separately
+        let sink = self.pooled_schedule.len() - 1;
+        if self.parallel.is_some() {
+            let elapsed = start.to(PreciseTime::now()).num_microseconds().unwrap() as f64;
+            let sink_time = self.graph[self.output_node_index].exec_time.mean;
+            let expected_time = self.expected_parallel_time() + sink_time;
+            if rel_deadline - elapsed < expected_time {
+                let choosing_start = PreciseTime::now();
+                expected_remaining_time = elapsed + expected_time;
+                let degraded = self.degrade(0, rel_deadline - elapsed);
+                nb_degraded = degraded.0;
+                nb_resamplers = degraded.1;
+                choosing_duration = choosing_start
+                    .to(PreciseTime::now())
+                    .num_microseconds()
+                    .unwrap();
+            }
+            if nb_degraded > 0 {
+                quality = Quality::Degraded;
+            }
+            let degraded_from = self.degradation.from;
+            let measure = quality == Quality::Normal;
+            self.degradation.nb_resampling_errors += self.process_parallel(degraded_from, measure);
+        } else {
+            for i in 0..sink {
+                if can_degrade {
+                    let elapsed = start.to(PreciseTime::now()).num_microseconds().unwrap() as f64;
+                    if rel_deadline - elapsed < self.schedule_expected_time[i] {
+                        can_degrade = false;
+                        let choosing_start = PreciseTime::now();
+                        expected_remaining_time = elapsed + self.schedule_expected_time[i];
+                        let degraded = self.degrade(i, rel_deadline - elapsed);
+                        nb_degraded = degraded.0;
+                        nb_resamplers = degraded.1;
+                        if nb_degraded > 0 {
+                            quality = Quality::Degraded;
                         }
+                        choosing_duration = choosing_start
+                            .to(PreciseTime::now())
+                            .num_microseconds()
+                            .unwrap();
                     }
                 }
-                if nb_out_t > nb_out_r {
-                    //Collect connected output ports
-                    let output_edges = self.outputs(node_index);
-                    let output_ports = output_edges
-                        .map(|e| e.weight().src_port())
-                        .collect::<HashSet<_>>();
-                    //Connect them to audio sink
-                    for port in 1..(nb_out_t + 1) {
-                        if !output_ports.contains(&port) {
-                            //It's a non-connected port
-                            println!(
-                                "Autoconnect edge to sink from {}:{} on port {}",
-                                node.node_infos.id, node, port
-                            );
-                            io_edges.push((node_index, port, self.output_node_index, 1));
-                        }
-                    }
+
+                let node_start = PreciseTime::now();
+                if self.degradation.from.map_or(false, |from| i >= from) {
+                    self.process_degraded_pooled_node(i);
+                } else {
+                    self.process_pooled_node(i);
+                }
+                if let Quality::Normal = quality {
+                    let node = self.pooled_schedule[i].node;
+                    self.graph[node].exec_time.update_time(node_start);
                 }
             }
         }
-        //Finally add the edges
-        for edge in io_edges.into_iter() {
-            let (src_id, src_port, dst_id, dst_port) = edge;
-            self.add_connection(src_id, src_port, dst_id, dst_port);
+
+        let sink_start = PreciseTime::now();
+        if self.degradation.from.is_some() {
+            self.process_degraded_pooled_sink(outputs[0].buffer_mut());
+        } else {
+            self.process_pooled_sink(outputs[0].buffer_mut());
         }
-    }
+        self.graph[self.output_node_index]
+            .exec_time
+            .update_time(sink_start);
 
-    pub fn print_schedule(&self, schedule: &[NodeIndex]) {
-        print!("The schedule is: ",);
-        for node_index in schedule.iter() {
-            let node = self.graph.node_weight(*node_index).unwrap();
-            print!(
-                "{} {} ",
-                node,
-                if node.node_infos.class_name == "real_sink" {
-                    ""
-                } else {
-                    "->"
-                }
-            );
+        if let Quality::Degraded = quality {
+            self.restore_nominal();
         }
-        println!("");
-    }
 
-    /// Adjust interchange buffers: temporary buffers used to copy audio between edges
-    fn update_temp_buffers(&mut self) {
-        for node in self.graph.node_indices() {
-            let nb_inputs = self.nb_inputs(node) as usize;
-            let nb_outputs = self.nb_outputs(node) as usize;
-            let max_inputs = std::cmp::max(nb_inputs, self.input_edges.len());
-            let max_outputs = std::cmp::max(nb_outputs, self.output_edges.len());
-            // Several edges entering the same port. Should be rare
-            // (kind of an error because we don't do automatic mixing in that case actually). Only happens
-            // when there are several virtual sinks (to the real sink node).
-            if max_inputs > self.input_edges.len() {
-                self.input_edges.resize(
-                    max_inputs,
-                    DspEdge::new(1, 1, self.size, self.nominal_samplerate),
-                );
-            }
-            // This can often happen, when a port has several edges coming from it.
-            if max_outputs > self.output_edges.len() {
-                self.output_edges.resize(
-                    max_outputs,
-                    DspEdge::new(1, 1, self.size, self.nominal_samplerate),
-                );
-            }
+        let execution_time = start.to(PreciseTime::now()).num_microseconds().unwrap();
+
+        TimeMonitor {
+            quality,
+            budget: rel_deadline as i64 - execution_time,
+            deadline: rel_deadline as u64,
+            expected_remaining_time: expected_remaining_time as u64,
+            execution_time: execution_time as u64,
+            nb_degraded: nb_degraded as u64,
+            nb_resamplers: nb_resamplers as u64,
+            nb_resampling_errors: self.degradation.nb_resampling_errors as u64,
+            callback_flags: flags,
+            choosing_duration: choosing_duration as u64,
         }
     }
 
-    pub fn update_schedule(&mut self) -> Result<(), AudioGraphError> {
-        self.update_temp_buffers();
-        self.reset_buffer_sizes();
-        self.schedule = toposort(&self.graph, None)?; //If Cycle, returns an AudioGraphError::Cycle
-        self.buffer_size_resamplers(); //Requires the topological sort
-        assert!(self.validate_buffer_sizes());
+    /// Samplerate at which a node is executed, as computed by `buffer_size_resamplers`
+    fn node_samplerate(&self, node: NodeIndex) -> u32 {
+        self.inputs(node)
+            .map(|edge| edge.weight().samplerate)
+            .min()
+            .or_else(|| {
+                self.outputs(node)
+                    .map(|edge| edge.weight().samplerate)
+                    .min()
+            })
+            .unwrap_or(self.nominal_samplerate)
+    }
 
-        self.active_component();
+    /// Static schedulability analysis using the WCETs of the audiograph file.
+    /// WCETs are given at the nominal samplerate, so they are scaled by the samplerate
+    /// at which each node is executed.
+    ///
+    /// `update_schedule` must have been called before.
+    pub fn wcet_analysis(&self) -> WcetReport {
+        let mut contributions = Vec::with_capacity(self.schedule.len());
+        // Longest path ending at a node, and predecessor on this path
+        let mut longest = vec![(0., None); self.graph.node_bound()];
 
-        if self.schedule.len() <= 100 {
-            self.print_schedule(&self.schedule);
+        for &node in self.schedule.iter() {
+            let node_infos = &self.graph[node].node_infos;
+            let rate_ratio = self.node_samplerate(node) as f64 / self.nominal_samplerate as f64;
+            let contribution = node_infos.wcet.unwrap_or(0.) * rate_ratio;
+
+            //The schedule is in topological order so all the predecessors have been visited
+            let (pred_wcet, pred) = self
+                .graph
+                .neighbors_directed(node, Direction::Incoming)
+                .map(|pred| (longest[pred.index()].0, Some(pred)))
+                .fold((0., None), |acc, x| if x.0 > acc.0 { x } else { acc });
+            longest[node.index()] = (pred_wcet + contribution, pred);
+
+            contributions.push(WcetContribution {
+                node,
+                id: if node_infos.id.is_empty() {
+                    node_infos.class_name.clone()
+                } else {
+                    node_infos.id.clone()
+                },
+                class_name: node_infos.class_name.clone(),
+                wcet: node_infos.wcet,
+                rate_ratio,
+                contribution,
+            });
         }
 
-        Ok(())
-    }
+        let total = contributions.iter().map(|c| c.contribution).sum::<f64>();
 
-    pub fn nb_active_nodes(&self) -> usize {
-        self.schedule.len()
+        let mut critical_path = Vec::new();
+        let mut critical_path_wcet = 0.;
+        let mut last = None;
+        //On ties, we prefer the nodes executed later so that the path ends at the sink
+        for &node in self.schedule.iter() {
+            if longest[node.index()].0 >= critical_path_wcet {
+                critical_path_wcet = longest[node.index()].0;
+                last = Some(node);
+            }
+        }
+        while let Some(node) = last {
+            critical_path.push(node);
+            last = longest[node.index()].1;
+        }
+        critical_path.reverse();
+
+        WcetReport {
+            total,
+            critical_path,
+            critical_path_wcet,
+            contributions,
+            deadline: self.deadline,
+            slack: self.deadline.map(|deadline| deadline - total),
+        }
     }
 }
 
@@ -686,197 +3252,29 @@ impl fmt::Display for AudioGraph {
 
 impl AudioEffect for AudioGraph {
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        self.apply_transactions();
+        if self.schedule_dirty {
+            AudioGraph::output_silence(outputs);
+            return;
+        }
         let interlaced_size = (self.channels * self.frames_per_buffer) as usize;
-        let input_buffer = &inputs[0].buffer();
-        assert_eq!(input_buffer.len(), interlaced_size);
-        let output_buffer = &mut outputs[0].buffer_mut();
-        assert_eq!(output_buffer.len(), interlaced_size);
+        assert_eq!(inputs[0].buffer().len(), interlaced_size);
+        assert_eq!(outputs[0].buffer().len(), interlaced_size);
 
-        // To prevent
         if self.has_source {
-            //println!("Executing {}", self.graph.node_weight(self.input_node_index).unwrap().node_processor);
-            //Prepare input
-            self.input_edges[0].resize(interlaced_size);
-            self.input_edges[0]
-                .buffer_mut()
-                .copy_from_slice(input_buffer);
-            //Process
-            self.graph
-                .node_weight_mut(self.input_node_index)
-                .unwrap()
-                .node_processor
-                .process(
-                    &self.input_edges[0..1],
-                    &mut self.output_edges[0..self.channels as usize],
-                );
-            //Prepare Outputs
-            //We could decrease memory usage by using a buffer pool
-            let mut edges = self.outputs_mut(self.input_node_index);
-            let mut i = 0;
-            while let Some(edge) = edges.next_edge(&self.graph) {
-                self.output_edges[i].resize(self.graph.edge_weight(edge).unwrap().buffer().len());
-                self.output_edges[i].samplerate = self.graph.edge_weight(edge).unwrap().samplerate;
-                debug_assert_eq!(
-                    self.graph.edge_weight(edge).unwrap().buffer().len(),
-                    self.output_edges[i].buffer().len()
-                );
-                self.graph
-                    .edge_weight_mut(edge)
-                    .unwrap()
-                    .buffer_mut()
-                    .copy_from_slice(self.output_edges[i].buffer());
-                i += 1;
-            }
+            self.process_pooled_source(inputs[0].buffer());
         }
-        self.input_edges[0].resize(self.size);
 
         //We assume that sink is the last node in the schedule and execute it separately
-        for node in self.schedule[0..self.schedule.len() - 1].iter() {
-            //println!("Executing {}:{}", self.graph.node_weight(*node).unwrap().node_infos.id, self.graph.node_weight(*node).unwrap().node_processor);
-
-            let (nb_inputs, nb_outputs) = {
-                let n = &self.graph.node_weight(*node).unwrap().node_processor;
-                (n.nb_inputs(), n.nb_outputs())
-            };
-
-            //Fix input_edges and output_edges buffer sizes and samplerates.
-            let mut inputs = self.inputs_mut(*node);
-            let mut i = 0;
-            while let Some(edge) = inputs.next_edge(&self.graph) {
-                self.input_edges[i].resize(self.graph.edge_weight(edge).unwrap().buffer().len());
-                self.input_edges[i].samplerate = self.graph.edge_weight(edge).unwrap().samplerate;
-                i += 1;
-            }
-            //If there are ports which are not connected, we still have to adapt their size and samplerate.
-            // If i= 0, all non connected ports
-            let buf_size = if i > 0 {
-                self.input_edges[i - 1].buffer().len()
-            } else {
-                self.frames_per_buffer as usize
-            };
-            let samplerate = if i > 0 {
-                self.input_edges[i - 1].samplerate
-            } else {
-                self.nominal_samplerate
-            };
-            for j in i..nb_inputs {
-                self.input_edges[j].resize(buf_size);
-                self.input_edges[j].samplerate = samplerate;
-            }
-
-            let mut outputs = self.outputs_mut(*node);
-            let mut i = 0;
-            while let Some(edge) = outputs.next_edge(&self.graph) {
-                self.output_edges[i].resize(self.graph.edge_weight(edge).unwrap().buffer().len());
-                self.output_edges[i].samplerate = self.graph.edge_weight(edge).unwrap().samplerate;
-                i += 1;
-            }
-            //For the remaining edges, which are actually
-            let buf_size = if i > 0 {
-                self.output_edges[i - 1].buffer().len()
-            } else {
-                self.frames_per_buffer as usize
-            };
-            let samplerate = if i > 0 {
-                self.output_edges[i - 1].samplerate
-            } else {
-                self.nominal_samplerate
-            };
-            for j in i..nb_outputs {
-                self.output_edges[j].resize(buf_size);
-                self.output_edges[j].samplerate = samplerate;
-            }
-
-            //Prepare inputs
-            // Or just use &[&DspEdge]??
-            //TODO: not fill sequentially but fill using port numbers!! (not equivalent if there are non connected ports)
-            let mut edges = self.inputs_mut(*node);
-            let mut i = 0;
-            while let Some(edge) = edges.next_edge(&self.graph) {
-                debug_assert_eq!(
-                    self.graph.edge_weight(edge).unwrap().buffer().len(),
-                    self.input_edges[i].buffer().len()
-                );
-                self.input_edges[i]
-                    .buffer_mut()
-                    .copy_from_slice(self.graph.edge_weight(edge).unwrap().buffer());
-                i += 1;
-            }
-
-            //Process
-            self.graph
-                .node_weight_mut(*node)
-                .unwrap()
-                .node_processor
-                .process(
-                    &self.input_edges[0..nb_inputs],
-                    &mut self.output_edges[0..nb_outputs],
-                );
-
-            //Prepare Outputs
-            //That's also quite inefficient!!
-            let mut edges = self.outputs_mut(*node);
-            let mut i = 0;
-            while let Some(edge) = edges.next_edge(&self.graph) {
-                debug_assert_eq!(
-                    self.graph.edge_weight(edge).unwrap().buffer().len(),
-                    self.output_edges[i].buffer().len()
-                );
-                //The size of a DspEdge is the right one (computed at scheduling)
-                self.graph
-                    .edge_weight_mut(edge)
-                    .unwrap()
-                    .buffer_mut()
-                    .copy_from_slice(self.output_edges[i].buffer());
-                i += 1;
+        if self.parallel.is_some() {
+            self.process_parallel(None, false);
+        } else {
+            for i in 0..self.pooled_schedule.len() - 1 {
+                self.process_pooled_node(i);
             }
         }
 
-        // Sink
-        //println!("Executing {}", self.graph.node_weight(self.output_node_index).unwrap().node_processor);
-        //Prepare inputs
-
-        //Quite inefficient, with allocating. Rather use a fixed vec with max number of inputs and outputs and a buffer pool
-        // Or just use &[&DspEdge]??
-        let mut edges = self.inputs_mut(self.output_node_index);
-        debug_assert_eq!(
-            self.graph
-                .node_weight(self.output_node_index)
-                .unwrap()
-                .node_infos
-                .class_name
-                .as_str(),
-            "real_sink"
-        );
-        let mut i = 0;
-        while let Some(edge) = edges.next_edge(&self.graph) {
-            self.input_edges[i].resize(self.graph.edge_weight(edge).unwrap().buffer().len());
-            self.input_edges[i].samplerate = self.graph.edge_weight(edge).unwrap().samplerate;
-            //println!("Input edge of sink: {}", i);
-            debug_assert_eq!(self.input_edges[i].samplerate, self.nominal_samplerate);
-            debug_assert_eq!(
-                self.graph.edge_weight(edge).unwrap().buffer().len(),
-                self.input_edges[i].buffer().len()
-            );
-            self.input_edges[i]
-                .buffer_mut()
-                .copy_from_slice(self.graph.edge_weight(edge).unwrap().buffer());
-            i += 1;
-        }
-        //Output edge needs to be the interlaced_size
-        self.output_edges[0].resize(interlaced_size);
-        //Process
-        self.graph
-            .node_weight_mut(self.output_node_index)
-            .unwrap()
-            .node_processor
-            .process(
-                &self.input_edges[0..self.channels as usize],
-                &mut self.output_edges[0..1],
-            );
-        //Prepare Output to soundcard
-        output_buffer.copy_from_slice(self.output_edges[0].buffer());
-        self.output_edges[0].resize(self.size);
+        self.process_pooled_sink(outputs[0].buffer_mut());
     }
 
     fn nb_inputs(&self) -> usize {
@@ -887,6 +3285,12 @@ impl AudioEffect for AudioGraph {
     }
 }
 
+/// Parameters of the oscillator and of the modulator
+pub const SINE_PARAMETERS: [Parameter; 2] = [
+    Parameter::new("freq", ParameterType::Integer, 0., 20_000., 440.),
+    Parameter::new("volume", ParameterType::Float, 0., 1., 1.),
+];
+
 fn sine_wave(phase: f32, volume: f32) -> f32 {
     use std::f64::consts::PI;
     (phase * PI as f32 * 2.0).sin() as f32 * volume
@@ -908,16 +3312,16 @@ impl Oscillator {
         }
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Oscillator {
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Oscillator, AudiographError> {
         let osc = Oscillator::new(
             0.,
-            node_infos.more["freq"]
-                .parse()
-                .expect("freq must be an integer"),
+            node_infos.attribute("freq", "an integer")?,
             node_infos.volume,
         );
-        osc.check_io_node_infos(node_infos);
-        osc
+        osc.check_io_node_infos(node_infos)?;
+        Ok(osc)
     }
 }
 
@@ -950,6 +3354,26 @@ impl AudioEffect for Oscillator {
     fn nb_outputs(&self) -> usize {
         1
     }
+
+    fn parameters(&self) -> &[Parameter] {
+        &SINE_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        match index {
+            0 => Some(self.frequency as f32),
+            1 => Some(self.volume),
+            _ => None,
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.frequency = value as u32,
+            1 => self.volume = value,
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -968,16 +3392,16 @@ impl Modulator {
         }
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Modulator {
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Modulator, AudiographError> {
         let modu = Modulator::new(
             0.,
-            node_infos.more["freq"]
-                .parse()
-                .expect("freq must be an integer"),
+            node_infos.attribute("freq", "an integer")?,
             node_infos.volume,
         );
-        modu.check_io_node_infos(node_infos);
-        modu
+        modu.check_io_node_infos(node_infos)?;
+        Ok(modu)
     }
 }
 
@@ -1016,6 +3440,26 @@ impl AudioEffect for Modulator {
     fn nb_outputs(&self) -> usize {
         1
     }
+
+    fn parameters(&self) -> &[Parameter] {
+        &SINE_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        match index {
+            0 => Some(self.frequency as f32),
+            1 => Some(self.volume),
+            _ => None,
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.frequency = value as u32,
+            1 => self.volume = value,
+            _ => unreachable!(),
+        }
+    }
 }
 
 /// Similar to :> or <: in Faust. Can be used as a mixer if :>
@@ -1041,13 +3485,22 @@ impl InputsOutputsAdaptor {
         }
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> InputsOutputsAdaptor {
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<InputsOutputsAdaptor, AudiographError> {
+        if node_infos.nb_inlets == 0 || node_infos.nb_outlets == 0 {
+            return Err(AudiographError::UnsupportedNode {
+                node: node_infos.id.clone(),
+                kind: node_infos.class_name.clone(),
+                position: node_infos.position.clone(),
+            });
+        }
         let io_adapt = InputsOutputsAdaptor::new(
             node_infos.nb_inlets as usize,
             node_infos.nb_outlets as usize,
         );
-        io_adapt.check_io_node_infos(node_infos);
-        io_adapt
+        io_adapt.check_io_node_infos(node_infos)?;
+        Ok(io_adapt)
     }
 }
 
@@ -1095,6 +3548,11 @@ impl AudioEffect for InputsOutputsAdaptor {
                 }
             }
         } else {
+            for output in outputs.iter_mut() {
+                for sample in output.buffer_mut().iter_mut() {
+                    *sample = 0.;
+                }
+            }
             for (i, group) in inputs.chunks(self.stride).enumerate() {
                 //To handle the last chunk which will be mixed in the last output with the previous chunk
                 let index = std::cmp::min(i, outputs.len() - 1);
@@ -1193,6 +3651,9 @@ impl AudioEffect for Source {
     }
 }
 
+/// Ratios supported by libsamplerate
+const RESAMPLER_RATIO: &str = "a float between 1/256 and 256";
+
 #[derive(Debug)]
 pub struct Resampler {
     resampler: samplerate::Resampler,
@@ -1205,31 +3666,36 @@ impl Resampler {
         }
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Resampler {
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Resampler, AudiographError> {
         let converter_type =
             node_infos
                 .more
                 .get("conv")
-                .map_or(samplerate::ConverterType::Linear, |s| match s.as_str() {
-                    "sinc_best" => samplerate::ConverterType::SincBestQuality,
-                    "sinc_medium" => samplerate::ConverterType::SincMediumQuality,
-                    "sinc_fastest" => samplerate::ConverterType::SincFastest,
-                    "zero_hold" => samplerate::ConverterType::ZeroOrderHold,
-                    "linear" => samplerate::ConverterType::Linear,
-                    _ => samplerate::ConverterType::Linear,
+                .map_or(samplerate::ConverterType::Linear, |s| {
+                    match s.to_string().as_str() {
+                        "sinc_best" => samplerate::ConverterType::SincBestQuality,
+                        "sinc_medium" => samplerate::ConverterType::SincMediumQuality,
+                        "sinc_fastest" => samplerate::ConverterType::SincFastest,
+                        "zero_hold" => samplerate::ConverterType::ZeroOrderHold,
+                        "linear" => samplerate::ConverterType::Linear,
+                        _ => samplerate::ConverterType::Linear,
+                    }
                 });
-        let ratio = node_infos
-            .more
-            .get("ratio")
-            .expect(&format!(
-                "Resampler {} needs explicit ratio.",
-                node_infos.id
-            ))
-            .parse()
-            .unwrap();
+        let ratio: f64 = node_infos.attribute("ratio", RESAMPLER_RATIO)?;
+        if !(1. / 256. ..=256.).contains(&ratio) {
+            return Err(AudiographError::InvalidAttribute {
+                node: node_infos.id.clone(),
+                attribute: "ratio".to_string(),
+                value: ratio.to_string(),
+                expected: RESAMPLER_RATIO,
+                position: node_infos.position.clone(),
+            });
+        }
         let resampler = Resampler::new(converter_type, ratio);
-        resampler.check_io_node_infos(node_infos);
-        resampler
+        resampler.check_io_node_infos(node_infos)?;
+        Ok(resampler)
     }
 
     pub fn get_ratio(&self) -> f64 {
@@ -1268,3 +3734,1172 @@ impl AudioEffect for Resampler {
         1
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiograph_parser::Value;
+
+    fn chain_graph(nb_modulators: usize) -> AudioGraph {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let osc_infos = audiograph_parser::Node {
+            class_name: "osc".to_string(),
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        let mut prev = audio_graph.add_node(DspNode::from_parts(
+            osc_infos,
+            Box::new(Oscillator::new(0., 440, 1.)),
+        ));
+        for i in 0..nb_modulators {
+            let mod_infos = audiograph_parser::Node {
+                class_name: "mod".to_string(),
+                nb_inlets: 1,
+                nb_outlets: 1,
+                ..Default::default()
+            };
+            let modulator = audio_graph.add_node(DspNode::from_parts(
+                mod_infos,
+                Box::new(Modulator::new(0., 200 + 10 * i as u32, 1.)),
+            ));
+            audio_graph.add_connection(prev, 1, modulator, 1).unwrap();
+            prev = modulator;
+        }
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(prev, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().expect("Cycle detected");
+        audio_graph
+    }
+
+    #[test]
+    fn test_cost_model_predictions() {
+        use cost_model::KindCost;
+
+        let mut audio_graph = chain_graph(1);
+        let mut cost_model = CostModel::new();
+        cost_model.insert(
+            "mod",
+            KindCost {
+                intercept: 10.,
+                per_frame: 0.5,
+                per_khz: 0.,
+            },
+        );
+        cost_model.insert(
+            "resampler",
+            KindCost {
+                intercept: 2.,
+                per_frame: 0.05,
+                per_khz: 0.,
+            },
+        );
+        audio_graph.set_cost_model(cost_model);
+
+        let schedule = audio_graph.schedule.clone();
+        let (position, &modulator) = schedule
+            .iter()
+            .enumerate()
+            .find(|&(_, node)| audio_graph.graph[*node].node_infos.class_name == "mod")
+            .unwrap();
+        let predicted = audio_graph.predicted_cost(modulator).unwrap();
+        assert_eq!(predicted.nominal, 42.);
+        assert_eq!(predicted.resampled, 26.);
+        assert_eq!(audio_graph.graph[modulator].mean_execution_time(), 42.);
+        assert!(audio_graph.predicted_cost(schedule[0]).is_none());
+
+        // The modulator and a downsampler and an upsampler around it
+        let expected = audio_graph.expected_resampled_time(&schedule, position);
+        assert!((expected - (26. + 2. * 5.2)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adaptive_no_degradation() {
+        let mut audio_graph = chain_graph(5);
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+
+        for _ in 0..10 {
+            let monitor =
+                audio_graph.process_adaptive(&inputs, &mut outputs, 1e9, CallbackFlags::NO_FLAG);
+            assert_eq!(monitor.quality, Quality::Normal);
+            assert_eq!(monitor.nb_resamplers, 0);
+        }
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_adaptive_degradation() {
+        let mut audio_graph = chain_graph(5);
+        let nb_nodes = audio_graph.nb_nodes();
+        let nb_edges = audio_graph.nb_edges();
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+
+        // Learn execution times first
+        for _ in 0..10 {
+            audio_graph.process_adaptive(&inputs, &mut outputs, 1e9, CallbackFlags::NO_FLAG);
+        }
+
+        for _ in 0..10 {
+            let monitor =
+                audio_graph.process_adaptive(&inputs, &mut outputs, 0., CallbackFlags::NO_FLAG);
+            assert_eq!(monitor.quality, Quality::Degraded);
+            // The whole chain runs at a lower samplerate: the oscillator does not need a downsampler
+            // so there is only one upsampler before the sink
+            assert_eq!(monitor.nb_degraded, 6);
+            assert_eq!(monitor.nb_resamplers, 1);
+            assert_eq!(monitor.nb_resampling_errors, 0);
+            assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+            // The graph is back to its nominal state at the end of the cycle
+            assert_eq!(audio_graph.nb_nodes(), nb_nodes);
+            assert_eq!(audio_graph.nb_edges(), nb_edges);
+            assert!(audio_graph.validate_buffer_sizes());
+        }
+    }
+
+    #[test]
+    fn test_degrade_middle_of_schedule() {
+        let mut audio_graph = chain_graph(5);
+        let nb_nodes = audio_graph.nb_nodes();
+        let nb_edges = audio_graph.nb_edges();
+        let schedule = audio_graph.schedule.clone();
+        let sink = audio_graph.pooled_schedule.len() - 1;
+
+        let nb_resamplers = audio_graph.degrade_from(2);
+        // One downsampled edge before the degraded part and one upsampled edge before the sink
+        assert_eq!(nb_resamplers, 2);
+        // The graph is not modified: the resamplers preallocated for the edges are switched in
+        assert_eq!(audio_graph.schedule, schedule);
+        assert_eq!(audio_graph.nb_nodes(), nb_nodes);
+        assert_eq!(audio_graph.nb_edges(), nb_edges);
+
+        for i in 0..sink {
+            if i < 2 {
+                audio_graph.process_pooled_node(i);
+            } else {
+                audio_graph.process_degraded_pooled_node(i);
+            }
+        }
+        // The degraded nodes write into the buffers of the pool at a lower samplerate
+        for pooled_node in audio_graph.pooled_schedule[2..sink].iter() {
+            let buffer = &audio_graph.pool.buffers[pooled_node.outputs[0]];
+            assert_eq!(buffer.samplerate, 22_050);
+            assert_eq!(buffer.buffer().len(), 32);
+        }
+        let mut output = vec![0.; 64];
+        audio_graph.process_degraded_pooled_sink(&mut output);
+        assert!(output.iter().any(|s| *s != 0.));
+        assert_eq!(audio_graph.degradation.nb_resampling_errors, 0);
+
+        audio_graph.restore_nominal();
+        assert!(audio_graph.degradation.from.is_none());
+        for pooled_node in audio_graph.pooled_schedule[0..sink].iter() {
+            let buffer = &audio_graph.pool.buffers[pooled_node.outputs[0]];
+            assert_eq!(buffer.samplerate, 44_100);
+            assert_eq!(buffer.buffer().len(), 64);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_uses_pooled_executor() {
+        let mut pooled = chain_graph(5);
+        let mut adaptive = chain_graph(5);
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut pooled_outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut adaptive_outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for _ in 0..10 {
+            pooled.process(&inputs, &mut pooled_outputs);
+            let monitor = adaptive.process_adaptive(
+                &inputs,
+                &mut adaptive_outputs,
+                1e9,
+                CallbackFlags::NO_FLAG,
+            );
+            assert_eq!(monitor.quality, Quality::Normal);
+            assert_eq!(pooled_outputs[0].buffer(), adaptive_outputs[0].buffer());
+        }
+    }
+
+    #[test]
+    fn test_parallel_adaptive_degradation() {
+        let audiograph_description = "
+            osc1 = {kind : \"osc\", out : 1, freq : 440,};
+            osc2 = {kind : \"osc\", out : 1, freq : 220,};
+            mod1 = {kind : \"mod\", in : 1, out : 1, freq : 200,};
+            mod2 = {kind : \"mod\", in : 1, out : 1, freq : 300,};
+            mod3 = {kind : \"mod\", in : 1, out : 1, freq : 400,};
+            mix = {kind : \"mix\", in : 3, out : 1,};
+            osc1.1 -> mod1.1 -> mix.1;
+            osc1.1 -> mod2.1 -> mix.2;
+            osc2.1 -> mod3.1 -> mix.3;
+        ";
+        let mut sequential =
+            audiograph_parser::parse_audiograph(audiograph_description, 64, 1, 44_100).unwrap();
+        sequential.update_schedule().unwrap();
+        let mut parallel =
+            audiograph_parser::parse_audiograph(audiograph_description, 64, 1, 44_100).unwrap();
+        parallel.set_nb_threads(4);
+        parallel.update_schedule().unwrap();
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut sequential_outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut parallel_outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for _ in 0..10 {
+            sequential.process_adaptive(
+                &inputs,
+                &mut sequential_outputs,
+                1e9,
+                CallbackFlags::NO_FLAG,
+            );
+            let monitor = parallel.process_adaptive(
+                &inputs,
+                &mut parallel_outputs,
+                1e9,
+                CallbackFlags::NO_FLAG,
+            );
+            assert_eq!(monitor.quality, Quality::Normal);
+            assert_eq!(sequential_outputs[0].buffer(), parallel_outputs[0].buffer());
+        }
+        for _ in 0..10 {
+            let sequential_monitor = sequential.process_adaptive(
+                &inputs,
+                &mut sequential_outputs,
+                0.,
+                CallbackFlags::NO_FLAG,
+            );
+            let parallel_monitor = parallel.process_adaptive(
+                &inputs,
+                &mut parallel_outputs,
+                0.,
+                CallbackFlags::NO_FLAG,
+            );
+            // The whole graph is degraded by both executors, with the same resamplers
+            assert_eq!(parallel_monitor.quality, Quality::Degraded);
+            assert_eq!(parallel_monitor.nb_degraded, sequential_monitor.nb_degraded);
+            assert_eq!(
+                parallel_monitor.nb_resamplers,
+                sequential_monitor.nb_resamplers
+            );
+            assert_eq!(parallel_monitor.nb_resampling_errors, 0);
+            assert_eq!(sequential_outputs[0].buffer(), parallel_outputs[0].buffer());
+        }
+        assert!(parallel_outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_resampling_error_passes_edge_through() {
+        let key = (NodeIndex::new(0), 1, NodeIndex::new(1), 1);
+        let mut resampler = EdgeResampler::new(key, 64, 44_100);
+        let mut input = DspEdge::new(1, 1, 64, 100);
+        for (i, sample) in input.buffer_mut().iter_mut().enumerate() {
+            *sample = i as f32;
+        }
+        // A ratio of 441 is out of the range of the converter
+        assert!(!resampler.resample(&input, 64, 44_100));
+        assert_eq!(resampler.buffer.buffer(), input.buffer());
+        assert_eq!(resampler.buffer.samplerate, 44_100);
+    }
+
+    #[test]
+    fn test_edge_resamplers_are_preallocated() {
+        fn nb_resamplers(audio_graph: &AudioGraph) -> usize {
+            audio_graph
+                .edge_resamplers
+                .iter()
+                .map(|resamplers| resamplers.len())
+                .sum()
+        }
+        fn resampler_buffer(
+            audio_graph: &AudioGraph,
+            key: (NodeIndex, u32, NodeIndex, u32),
+        ) -> *const f32 {
+            audio_graph
+                .edge_resamplers
+                .iter()
+                .flat_map(|resamplers| resamplers.iter())
+                .find(|resampler| resampler.key() == key)
+                .unwrap()
+                .buffer
+                .buffer()
+                .as_ptr()
+        }
+
+        let mut audio_graph = chain_graph(5);
+        // Every edge going into a node of the schedule has a resampler
+        assert_eq!(nb_resamplers(&audio_graph), audio_graph.nb_edges());
+        let resampler = audio_graph.edge_resamplers.last().unwrap()[0].key();
+        let buffer = resampler_buffer(&audio_graph, resampler);
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for _ in 0..10 {
+            audio_graph.process_adaptive(&inputs, &mut outputs, 0., CallbackFlags::NO_FLAG);
+        }
+        // The same resampler, with its buffer, is used at every degraded cycle
+        assert_eq!(resampler_buffer(&audio_graph, resampler), buffer);
+        // It has been switched in at the last cycle, so it is not reset at the next one
+        assert!(audio_graph
+            .edge_resamplers
+            .last()
+            .unwrap()
+            .iter()
+            .any(|edge_resampler| edge_resampler.last_cycle == audio_graph.cycle));
+
+        // And it is kept when the graph is rescheduled
+        let sink = audio_graph.sink_node();
+        let extra = audio_graph.add_node(DspNode::from_parts(
+            audiograph_parser::Node {
+                class_name: "osc".to_string(),
+                nb_outlets: 1,
+                ..Default::default()
+            },
+            Box::new(Oscillator::new(0., 220, 1.)),
+        ));
+        audio_graph.add_connection(extra, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        assert_eq!(nb_resamplers(&audio_graph), audio_graph.nb_edges());
+        assert_eq!(resampler_buffer(&audio_graph, resampler), buffer);
+    }
+
+    #[test]
+    fn test_cost_model_variant_costs() {
+        use faust_effect::MonoFreeverb;
+
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let freeverb_infos = audiograph_parser::Node {
+            class_name: "freeverb".to_string(),
+            nb_inlets: 1,
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        let freeverb = audio_graph.add_node(DspNode::from_parts(
+            freeverb_infos,
+            Box::new(MonoFreeverb::new(0.5, 0.5, 0.5, 0.5)),
+        ));
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(freeverb, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        audio_graph.graph[freeverb].exec_time = Stats::init(100.);
+        let schedule = audio_graph.schedule.clone();
+        let position = schedule.iter().position(|&node| node == freeverb).unwrap();
+
+        // Without a profiled cost, the one given by the node
+        let cheapest = audio_graph.graph[freeverb].node_processor.variant_cost(2);
+        let (time, nb_cheaper) = audio_graph.expected_variants_time(&schedule, position);
+        assert_eq!(nb_cheaper, 1);
+        assert!((time - 100. * cheapest).abs() < 1e-9);
+
+        let mut cost_model = CostModel::new();
+        cost_model.insert_variant_costs("freeverb", vec![1., 0.8, 0.25]);
+        audio_graph.set_cost_model(cost_model);
+        assert_eq!(audio_graph.variant_cost(freeverb, 2), 0.25);
+        let (time, _) = audio_graph.expected_variants_time(&schedule, position);
+        assert!((time - 25.).abs() < 1e-9);
+        assert!(audio_graph.variants_are_cheaper(&schedule, position, 30.));
+    }
+
+    #[test]
+    fn test_adaptive_cheaper_variants() {
+        use faust_effect::MonoFreeverb;
+
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let osc_infos = audiograph_parser::Node {
+            class_name: "osc".to_string(),
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        let freeverb_infos = audiograph_parser::Node {
+            class_name: "freeverb".to_string(),
+            nb_inlets: 1,
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        let freeverb = audio_graph.add_node(DspNode::from_parts(
+            freeverb_infos,
+            Box::new(MonoFreeverb::new(0.5, 0.5, 0.5, 0.5)),
+        ));
+        audio_graph.add_input(
+            DspNode::from_parts(osc_infos, Box::new(Oscillator::new(0., 440, 1.))),
+            1,
+            freeverb,
+            1,
+        );
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(freeverb, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let nb_nodes = audio_graph.nb_nodes();
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+
+        // Learn execution times first
+        for _ in 0..10 {
+            audio_graph.process_adaptive(&inputs, &mut outputs, 1e9, CallbackFlags::NO_FLAG);
+        }
+
+        for _ in 0..10 {
+            let monitor =
+                audio_graph.process_adaptive(&inputs, &mut outputs, 0., CallbackFlags::NO_FLAG);
+            // Switching the reverb to its cheapest variant costs less than resampling
+            assert_eq!(monitor.quality, Quality::Degraded);
+            assert_eq!(monitor.nb_degraded, 1);
+            assert_eq!(monitor.nb_resamplers, 0);
+            assert_eq!(audio_graph.nb_nodes(), nb_nodes);
+            assert_eq!(audio_graph.graph[freeverb].node_processor.variant(), 0);
+        }
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_cheaper_variants() {
+        let node_infos = |class_name: &str, nb_inlets, nb_outlets, attributes: &[(&str, f64)]| {
+            audiograph_parser::Node {
+                class_name: class_name.to_string(),
+                nb_inlets,
+                nb_outlets,
+                more: attributes
+                    .iter()
+                    .map(|&(k, v)| (k.to_string(), Value::Float(v)))
+                    .collect(),
+                ..Default::default()
+            }
+        };
+        let effects = vec![
+            node_infos(
+                "freeverb",
+                1,
+                1,
+                &[("fb1", 0.5), ("fb2", 0.5), ("damp", 0.5), ("spread", 0.5)],
+            ),
+            node_infos(
+                "zita_reverb",
+                2,
+                2,
+                &[
+                    ("rdel", 1.),
+                    ("f1", 200.),
+                    ("f2", 6000.),
+                    ("t60dc", 3.),
+                    ("t60m", 2.),
+                ],
+            ),
+            node_infos("guitar", 0, 1, &[("length", 0.75), ("pluck_position", 0.5)]),
+        ];
+
+        for effect_infos in effects.into_iter() {
+            let nb_variants = DspNode::new(effect_infos.clone(), 1)
+                .unwrap()
+                .node_processor
+                .nb_variants();
+            assert!(nb_variants > 1);
+            for variant in 0..(nb_variants + 1) {
+                let mut effect = DspNode::new(effect_infos.clone(), 1).unwrap().node_processor;
+                if variant == nb_variants {
+                    assert!(!effect.set_variant(variant));
+                    continue;
+                }
+                assert!(effect.set_variant(variant));
+                assert_eq!(effect.variant(), variant);
+                assert!(effect.variant_cost(variant) <= 1.);
+
+                let mut inputs = vec![DspEdge::new(1, 1, 64, 44_100); effect.nb_inputs()];
+                let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100); effect.nb_outputs()];
+                let mut osc = Oscillator::new(0., 440, 1.);
+                let mut has_sound = false;
+                // Long enough to get past the predelay of the reverbs
+                for _ in 0..50 {
+                    if !inputs.is_empty() {
+                        osc.process(&[], &mut inputs[0..1]);
+                    }
+                    effect.process(&inputs, &mut outputs);
+                    has_sound = has_sound
+                        || outputs
+                            .iter()
+                            .any(|output| output.buffer().iter().any(|s| *s != 0.));
+                }
+                assert!(has_sound, "{} is silent", effect);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wcet_analysis() {
+        let audiograph_description = "
+            osc = {kind : \"osc\", out : 1, freq : 440, wcet : 10.0,};
+            down = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5, wcet : 4.0,};
+            mod = {kind : \"mod\", in : 1, out : 1, freq : 200, wcet : 20.0,};
+            up = {kind : \"resampler\", in : 1, out : 1, ratio : 2.0, wcet : 4.0,};
+            osc2 = {kind : \"osc\", out : 1, freq : 220, wcet : 1.0,};
+            mix = {kind : \"mix\", in : 2, out : 1, wcet : 2.0,};
+            osc.1 -> down.1 -> mod.1 -> up.1 -> mix.1;
+            osc2.1 -> mix.2;
+            deadline = 30.0;
+        ";
+        let mut audio_graph =
+            audiograph_parser::parse_audiograph(audiograph_description, 64, 1, 44_100).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let report = audio_graph.wcet_analysis();
+
+        let contribution = |id: &str| {
+            report
+                .contributions
+                .iter()
+                .find(|c| c.id == id)
+                .unwrap()
+                .contribution
+        };
+        assert_eq!(contribution("osc"), 10.);
+        assert_eq!(contribution("down"), 4.);
+        // Executed at half the samplerate
+        assert_eq!(contribution("mod"), 10.);
+        assert_eq!(contribution("up"), 2.);
+        assert_eq!(report.total, 10. + 4. + 10. + 2. + 1. + 2.);
+        assert_eq!(report.critical_path_wcet, 10. + 4. + 10. + 2. + 2.);
+        let critical_path = report
+            .critical_path
+            .iter()
+            .map(|node| audio_graph.graph[*node].node_infos().id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(critical_path, vec!["osc", "down", "mod", "up", "mix", ""]);
+        assert_eq!(report.slack, Some(30. - 29.));
+        assert!(report.is_schedulable());
+        // Real sink has no WCET
+        assert_eq!(report.nb_missing_wcets(), 1);
+    }
+
+    /// Outputs a constant signal
+    struct Constant(f32);
+
+    impl fmt::Display for Constant {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "constant({})", self.0)
+        }
+    }
+
+    impl AudioEffect for Constant {
+        fn process(&mut self, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+            for sample in outputs[0].buffer_mut().iter_mut() {
+                *sample = self.0;
+            }
+        }
+        fn nb_inputs(&self) -> usize {
+            0
+        }
+        fn nb_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    fn constant_node(value: f32) -> DspNode {
+        let node_infos = audiograph_parser::Node {
+            class_name: "constant".to_string(),
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        DspNode::from_parts(node_infos, Box::new(Constant(value)))
+    }
+
+    fn mix_node(nb_inlets: u32, nb_outlets: u32) -> DspNode {
+        let node_infos = audiograph_parser::Node {
+            class_name: "mix".to_string(),
+            nb_inlets,
+            nb_outlets,
+            ..Default::default()
+        };
+        DspNode::new(node_infos, 1).unwrap()
+    }
+
+    #[test]
+    fn test_route_by_port() {
+        let mut audio_graph = AudioGraph::new(64, 2, 44_100);
+        let mix = audio_graph.add_node(mix_node(2, 2));
+        // Edges added in reverse order of the ports
+        audio_graph.add_input(constant_node(1.), 1, mix, 2);
+        audio_graph.add_input(constant_node(2.), 1, mix, 1);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 2, sink, 2).unwrap();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+
+        let inputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        audio_graph.process(&inputs, &mut outputs);
+        for frame in outputs[0].buffer().chunks(2) {
+            assert_eq!(frame, &[2., 1.]);
+        }
+    }
+
+    #[test]
+    fn test_sum_and_silent_inlets() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let mix = audio_graph.add_node(mix_node(2, 1));
+        // Inlet 1 is not connected and inlet 2 receives two edges
+        audio_graph.add_input(constant_node(1.), 1, mix, 2);
+        audio_graph.add_input(constant_node(3.), 1, mix, 2);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for _ in 0..3 {
+            audio_graph.process(&inputs, &mut outputs);
+            assert!(outputs[0].buffer().iter().all(|s| *s == 4.));
+        }
+    }
+
+    #[test]
+    fn test_autoconnect_per_port() {
+        let mut audio_graph = AudioGraph::new(64, 2, 44_100);
+        let mix = audio_graph.add_node(mix_node(2, 2));
+        // As many edges as inlets and outlets, but all on inlet 1 and outlet 1
+        audio_graph.add_input(constant_node(1.), 1, mix, 1);
+        audio_graph.add_input(constant_node(3.), 1, mix, 1);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.add_connection(mix, 1, sink, 2).unwrap();
+        audio_graph.autoconnect(false);
+
+        let source = audio_graph.source_node();
+        assert!(audio_graph
+            .inputs(mix)
+            .any(|edge| edge.source() == source && edge.weight().dst_port() == 2));
+        assert!(audio_graph
+            .outputs(mix)
+            .any(|edge| edge.target() == sink && edge.weight().src_port() == 2));
+        assert_eq!(audio_graph.nb_inputs(mix), 3);
+        assert_eq!(audio_graph.nb_outputs(mix), 3);
+    }
+
+    #[test]
+    fn test_route_zita_reverb() {
+        let zita_infos = audiograph_parser::Node {
+            class_name: "zita_reverb".to_string(),
+            nb_inlets: 2,
+            nb_outlets: 2,
+            more: vec![
+                ("rdel", 1),
+                ("f1", 200),
+                ("f2", 6000),
+                ("t60dc", 3),
+                ("t60m", 2),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), Value::Integer(v)))
+            .collect(),
+            ..Default::default()
+        };
+        let mut audio_graph = AudioGraph::new(64, 2, 44_100);
+        let zita = audio_graph.add_node(DspNode::new(zita_infos.clone(), 2).unwrap());
+        // Only the second inlet is connected
+        let osc_infos = audiograph_parser::Node {
+            class_name: "osc".to_string(),
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        audio_graph.add_input(
+            DspNode::from_parts(osc_infos, Box::new(Oscillator::new(0., 440, 1.))),
+            1,
+            zita,
+            2,
+        );
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(zita, 2, sink, 2).unwrap();
+        audio_graph.add_connection(zita, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+
+        // Reference: the same nodes executed directly
+        let mut reference_zita = DspNode::new(zita_infos, 2).unwrap();
+        let mut reference_osc = Oscillator::new(0., 440, 1.);
+        let mut reference_inputs = vec![DspEdge::new(1, 1, 64, 44_100); 2];
+        let mut reference_outputs = vec![DspEdge::new(1, 1, 64, 44_100); 2];
+
+        let inputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        // Long enough to get past the predelay of the reverb
+        for _ in 0..50 {
+            audio_graph.process(&inputs, &mut outputs);
+
+            reference_osc.process(&[], &mut reference_inputs[1..2]);
+            reference_zita
+                .node_processor
+                .process(&reference_inputs, &mut reference_outputs);
+
+            for (i, frame) in outputs[0].buffer().chunks(2).enumerate() {
+                assert_eq!(frame[0], reference_outputs[0].buffer()[i]);
+                assert_eq!(frame[1], reference_outputs[1].buffer()[i]);
+            }
+        }
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_route_stereo_faust_nodes() {
+        use faust_effect::{Sawtooth, Spat};
+
+        let mut audio_graph = audiograph_parser::parse_audiograph(
+            "saw = {kind : \"sawtooth\", out : 1, freq : 440,};\n\
+             pan = {kind : \"panner\", in : 1, out : 2, pan : 0.8,};\n\
+             speakers = {kind : \"sink\", in : 2,};\n\
+             saw.1 -> pan.1;\n\
+             pan.1 -> speakers.1;\n\
+             pan.2 -> speakers.2;",
+            64,
+            2,
+            44_100,
+        )
+        .unwrap();
+        audio_graph.update_schedule().unwrap();
+        let inputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        for _ in 0..4 {
+            audio_graph.process(&inputs, &mut outputs);
+            for frame in outputs[0].buffer().chunks(2) {
+                assert!((frame[1] - 4. * frame[0]).abs() < 1e-5, "{:?}", frame);
+            }
+        }
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+
+        // Four speakers, mixed two by two into the channels
+        let mut audio_graph = audiograph_parser::parse_audiograph(
+            "saw = {kind : \"sawtooth\", out : 1, freq : 440,};\n\
+             spat = {kind : \"spat\", in : 1, out : 4, rotation : 0.25, distance : 0.5,};\n\
+             down = {kind : \"mix\", in : 4, out : 2,};\n\
+             speakers = {kind : \"sink\", in : 2,};\n\
+             saw.1 -> spat.1;\n\
+             spat.1 -> down.1;\n\
+             spat.2 -> down.2;\n\
+             spat.3 -> down.3;\n\
+             spat.4 -> down.4;\n\
+             down.1 -> speakers.1;\n\
+             down.2 -> speakers.2;",
+            64,
+            2,
+            44_100,
+        )
+        .unwrap();
+        audio_graph.update_schedule().unwrap();
+
+        // Reference: the same nodes executed directly
+        let mut reference_saw = Sawtooth::new(440.);
+        let mut reference_spat = Spat::new(4, 0.25, 0.5);
+        let mut reference_inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut reference_outputs = vec![DspEdge::new(1, 1, 64, 44_100); 4];
+        for _ in 0..4 {
+            audio_graph.process(&inputs, &mut outputs);
+
+            reference_saw.process(&[], &mut reference_inputs);
+            reference_spat.process(&reference_inputs, &mut reference_outputs);
+
+            for (i, frame) in outputs[0].buffer().chunks(2).enumerate() {
+                let speaker = |j: usize| reference_outputs[j].buffer()[i];
+                assert_eq!(frame[0], speaker(0) + speaker(1));
+                assert_eq!(frame[1], speaker(2) + speaker(3));
+            }
+        }
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_pooled_executor_matches_copy() {
+        let mut inputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        for (i, sample) in inputs[0].buffer_mut().iter_mut().enumerate() {
+            *sample = i as f32 / 128.;
+        }
+        let check = |mut pooled: AudioGraph, mut copying: AudioGraph| {
+            let nb_samples = inputs[0].buffer().len();
+            let mut pooled_outputs = vec![DspEdge::new(1, 1, nb_samples, 44_100)];
+            let mut copying_outputs = vec![DspEdge::new(1, 1, nb_samples, 44_100)];
+            for _ in 0..10 {
+                pooled.process(&inputs, &mut pooled_outputs);
+                copying.process_copy(&inputs, &mut copying_outputs);
+                assert_eq!(pooled_outputs[0].buffer(), copying_outputs[0].buffer());
+            }
+            assert!(pooled_outputs[0].buffer().iter().any(|s| *s != 0.));
+        };
+
+        // Fan-out, inlets with several edges, one buffer read twice by the same node,
+        // resamplers and an inlet autoconnected to the source
+        let audiograph_description = "
+            osc = {kind : \"osc\", out : 1, freq : 440,};
+            down = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            mod = {kind : \"mod\", in : 1, out : 1, freq : 200,};
+            up = {kind : \"resampler\", in : 1, out : 1, ratio : 2.0,};
+            mix = {kind : \"mix\", in : 3, out : 2,};
+            osc.1 -> down.1 -> mod.1 -> up.1 -> mix.1;
+            osc.1 -> mix.1;
+            osc.1 -> mix.2;
+        ";
+        let parse = || {
+            let mut audio_graph =
+                 audiograph_parser::parse_audiograph(audiograph_description, 64, 2, 44_100)
+                    .unwrap();
+            audio_graph.update_schedule().unwrap();
+            audio_graph
+        };
+        check(parse(), parse());
+
+        // Several inlets without edges in the same node, which all read silence
+        let silent_inlets = || {
+            let mut audio_graph = AudioGraph::new(128, 1, 44_100);
+            let sink = audio_graph.sink_node();
+            let source = audio_graph.source_node();
+            let pass = audio_graph.add_node(mix_node(1, 1));
+            audio_graph.add_connection(source, 1, pass, 1).unwrap();
+            audio_graph.add_connection(pass, 1, sink, 1).unwrap();
+            let mix = audio_graph.add_node(mix_node(3, 1));
+            audio_graph.add_input(constant_node(3.), 1, mix, 2);
+            audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+            audio_graph.update_schedule().unwrap();
+            audio_graph
+        };
+        check(silent_inlets(), silent_inlets());
+        let mut audio_graph = silent_inlets();
+        let mut outputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        audio_graph.process(&inputs, &mut outputs);
+        for (output, input) in outputs[0].buffer().iter().zip(inputs[0].buffer()) {
+            assert_eq!(*output, input + 3.);
+        }
+    }
+
+    #[test]
+    fn test_parallel_executor_matches_sequential() {
+        // Independent branches, fan-out, resamplers and a mixer joining branches
+        let audiograph_description = "
+            osc1 = {kind : \"osc\", out : 1, freq : 440,};
+            osc2 = {kind : \"osc\", out : 1, freq : 220,};
+            osc3 = {kind : \"osc\", out : 1, freq : 330,};
+            mod1 = {kind : \"mod\", in : 1, out : 1, freq : 200,};
+            mod2 = {kind : \"mod\", in : 1, out : 1, freq : 300,};
+            mod3 = {kind : \"mod\", in : 1, out : 1, freq : 400,};
+            down = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            mod4 = {kind : \"mod\", in : 1, out : 1, freq : 100,};
+            up = {kind : \"resampler\", in : 1, out : 1, ratio : 2.0,};
+            mix = {kind : \"mix\", in : 3, out : 2,};
+            osc1.1 -> mod1.1 -> mix.1;
+            osc1.1 -> mod2.1 -> mix.1;
+            osc2.1 -> down.1 -> mod4.1 -> up.1 -> mix.2;
+            osc3.1 -> mod3.1;
+        ";
+        let mut sequential =
+            audiograph_parser::parse_audiograph(audiograph_description, 64, 2, 44_100).unwrap();
+        sequential.update_schedule().unwrap();
+        let mut parallel =
+            audiograph_parser::parse_audiograph(audiograph_description, 64, 2, 44_100).unwrap();
+        parallel.set_nb_threads(4);
+        parallel.update_schedule().unwrap();
+        // Buffers are not reused when nodes run in parallel
+        assert!(parallel.nb_pooled_buffers() > sequential.nb_pooled_buffers());
+
+        let mut inputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        for (i, sample) in inputs[0].buffer_mut().iter_mut().enumerate() {
+            *sample = i as f32 / 128.;
+        }
+        let mut sequential_outputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        let mut parallel_outputs = vec![DspEdge::new(1, 1, 128, 44_100)];
+        for _ in 0..100 {
+            sequential.process(&inputs, &mut sequential_outputs);
+            parallel.process(&inputs, &mut parallel_outputs);
+            assert_eq!(sequential_outputs[0].buffer(), parallel_outputs[0].buffer());
+        }
+        assert!(parallel_outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_edit_between_cycles() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let mix = audio_graph.add_node(mix_node(2, 1));
+        let constant1 = audio_graph.add_input(constant_node(1.), 1, mix, 1);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 1.));
+
+        // Same ports: the schedule is kept
+        audio_graph.replace_node(constant1, constant_node(2.));
+        assert!(!audio_graph.schedule_dirty);
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 2.));
+
+        let constant3 = audio_graph.add_input(constant_node(3.), 1, mix, 2);
+        // The schedule is not updated by the cycle itself
+        assert!(audio_graph.needs_schedule_update());
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 0.));
+        audio_graph.update_schedule().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 5.));
+
+        audio_graph.remove_node(constant1);
+        assert_eq!(audio_graph.graph[constant3].to_string(), "constant(3)");
+        audio_graph.update_schedule().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 3.));
+
+        // The new mixer has no second inlet, so the connection from constant3 is removed
+        audio_graph.replace_node(mix, mix_node(1, 1));
+        assert_eq!(audio_graph.nb_outputs(constant3), 0);
+        audio_graph.update_schedule().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 0.));
+        assert_eq!(audio_graph.nb_active_nodes(), 2);
+    }
+
+    #[test]
+    fn test_edit_reorders_schedule() {
+        let mut audio_graph = chain_graph(2);
+        let first_modulator = audio_graph.schedule[1];
+        // The new node comes after the modulator in the previous order
+        let constant = audio_graph.add_node(constant_node(1.));
+        audio_graph.add_connection(constant, 1, first_modulator, 1).unwrap();
+        // A connection closing a cycle is rejected when it is made
+        assert!(audio_graph.add_connection(first_modulator, 1, constant, 1).is_err());
+        audio_graph.update_schedule().unwrap();
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        audio_graph.process(&inputs, &mut outputs);
+        let position = |node| {
+            audio_graph
+                .schedule
+                .iter()
+                .position(|n| *n == node)
+                .unwrap()
+        };
+        assert!(position(constant) < position(first_modulator));
+        assert_eq!(audio_graph.nb_active_nodes(), 5);
+    }
+
+    #[test]
+    fn test_parameters() {
+        use faust_effect::{Compressor, Guitar, Transposer};
+
+        let mut osc = Oscillator::new(0., 440, 1.);
+        let names = osc
+            .parameters()
+            .iter()
+            .map(|parameter| parameter.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["freq", "volume"]);
+        assert!(osc.set_parameter_by_name("freq", 220.4));
+        assert_eq!(osc.get_parameter(0), Some(220.));
+        // Values are clamped to the range of the parameter
+        assert!(osc.set_parameter(1, 2.));
+        assert_eq!(osc.get_parameter_by_name("volume"), Some(1.));
+        assert!(!osc.set_parameter(2, 0.));
+        assert!(!osc.set_parameter_by_name("phase", 0.));
+        assert_eq!(osc.get_parameter(2), None);
+
+        let mut guitar = Guitar::new(0.5, 0.5, 0.9, 1);
+        assert!(guitar.set_parameter_by_name("trigger", 0.2));
+        assert_eq!(guitar.get_parameter_by_name("trigger"), Some(0.));
+
+        let transposer = Transposer::new(5);
+        assert_eq!(transposer.get_parameter_by_name("semitones"), Some(5.));
+
+        let mut effects: Vec<Box<AudioEffect>> = vec![
+            Box::new(osc),
+            Box::new(guitar),
+            Box::new(transposer),
+            Box::new(Compressor::new(5., -30., 0.05, 0.5)),
+        ];
+        for effect in effects.iter_mut() {
+            for index in 0..effect.parameters().len() {
+                let parameter = effect.parameters()[index].clone();
+                assert!(parameter.min <= parameter.default && parameter.default <= parameter.max);
+                assert!(effect.set_parameter(index, parameter.default));
+                assert_eq!(effect.get_parameter(index), Some(parameter.default));
+            }
+        }
+    }
+
+    #[test]
+    fn test_control_handle_edits() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let mix = audio_graph.add_node(mix_node(2, 1));
+        let constant1 = audio_graph.add_input(constant_node(1.), 1, mix, 1);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let mut control = audio_graph.control_handle(4);
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+
+        let constant2 = control.add_node(constant_node(2.));
+        control.connect(constant2, 1, mix, 2).unwrap();
+        // Nothing changes until the edits are committed
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 1.));
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 3.));
+        // The schedule was computed by the handle
+        assert!(!audio_graph.schedule_dirty);
+        assert_eq!(audio_graph.nb_active_nodes(), 4);
+
+        control.remove_node(constant1).unwrap();
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 2.));
+        assert!(control.collect_garbage() > 0);
+
+        control.replace_node(constant2, constant_node(4.)).unwrap();
+        control.commit().unwrap();
+        audio_graph.process_copy(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 4.));
+
+        assert_eq!(control.connect(mix, 1, mix, 1), Err(ControlError::Cycle));
+        assert_eq!(
+            control.connect(constant2, 2, mix, 1),
+            Err(ControlError::UnknownPort(constant2, 2))
+        );
+        assert_eq!(
+            control.remove_node(sink),
+            Err(ControlError::ExternalNode(sink))
+        );
+    }
+
+    #[test]
+    fn test_control_handle_indexes() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let mix = audio_graph.add_node(mix_node(2, 1));
+        audio_graph.add_input(constant_node(1.), 1, mix, 1);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let mut control = audio_graph.control_handle(4);
+
+        // The graph gets a node the handle does not know, with the index the handle will give to its next node
+        let unknown = audio_graph.add_node(constant_node(5.));
+        audio_graph.update_schedule().unwrap();
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let constant2 = control.add_node(constant_node(2.));
+        assert_eq!(constant2, unknown);
+        control.connect(constant2, 1, mix, 2).unwrap();
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 3.));
+
+        // The map of the indexes grows with the nodes of the handle
+        for _ in 0..10 {
+            control.add_node(constant_node(0.));
+        }
+        control.commit().unwrap();
+        control.remove_node(constant2).unwrap();
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 1.));
+        assert!(audio_graph.graph.contains_node(unknown));
+    }
+
+    #[test]
+    fn test_control_handle_graph_capacity() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let mix = audio_graph.add_node(mix_node(8, 1));
+        let constant = audio_graph.add_input(constant_node(1.), 1, mix, 1);
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let mut control = audio_graph.control_handle(4);
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        // The graph keeps a vacant index
+        control.remove_node(constant).unwrap();
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        let capacity = audio_graph.graph.capacity();
+
+        for port in 1..9 {
+            let constant = control.add_node(constant_node(1.));
+            control.connect(constant, 1, mix, port).unwrap();
+        }
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 8.));
+        // The nodes and edges have been moved to the larger graph sent by the handle
+        assert!(audio_graph.graph.capacity().0 > capacity.0);
+        assert_eq!(audio_graph.graph.capacity(), control.graph_capacity);
+        assert_eq!(audio_graph.graph.node_count(), 11);
+        assert_eq!(audio_graph.graph.edge_count(), 9);
+        assert!(control.collect_garbage() > 0);
+    }
+
+    #[test]
+    fn test_control_handle_garbage() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let mix = audio_graph.add_node(mix_node(8, 1));
+        let constants = (1..9)
+            .map(|port| audio_graph.add_input(constant_node(1.), 1, mix, port))
+            .collect::<Vec<_>>();
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(mix, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let mut control = audio_graph.control_handle(1);
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for &constant in constants.iter() {
+            control.remove_node(constant).unwrap();
+        }
+        control.commit().unwrap();
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 0.));
+        // The transaction comes back at once with the nodes, their edges and the old schedule
+        assert_eq!(control.collect_garbage(), 1 + 8 + 8 + 1);
+    }
+
+    #[test]
+    fn test_control_handle_parameters() {
+        let mut audio_graph = AudioGraph::new(64, 1, 44_100);
+        let osc_infos = audiograph_parser::Node {
+            class_name: "osc".to_string(),
+            nb_outlets: 1,
+            ..Default::default()
+        };
+        let osc = audio_graph.add_node(DspNode::from_parts(
+            osc_infos,
+            Box::new(Oscillator::new(0., 440, 1.)),
+        ));
+        let sink = audio_graph.sink_node();
+        audio_graph.add_connection(osc, 1, sink, 1).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let mut control = audio_graph.control_handle(1);
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+
+        control.set_parameter(osc, "volume", 0.).unwrap();
+        control.commit().unwrap();
+        assert_eq!(
+            control.set_parameter(osc, "phase", 0.),
+            Err(ControlError::UnknownParameter(osc, "phase".to_string()))
+        );
+        // The queue only holds one transaction
+        control.set_parameter(osc, "freq", 220.).unwrap();
+        assert_eq!(control.commit(), Err(ControlError::QueueFull));
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|s| *s == 0.));
+        control.commit().unwrap();
+    }
+
+    #[test]
+    fn test_pooled_buffers_reuse() {
+        let mut audio_graph = chain_graph(20);
+        // In a chain, a node only needs the buffer it reads and the buffer it writes
+        assert_eq!(audio_graph.nb_pooled_buffers(), 2);
+
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        audio_graph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().any(|s| *s != 0.));
+    }
+}
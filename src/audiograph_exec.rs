@@ -3,11 +3,13 @@
 extern crate audio_adaptive;
 extern crate clap;
 extern crate crossbeam_channel;
+extern crate petgraph;
 extern crate portaudio;
 extern crate rand;
 extern crate time;
 
 use crossbeam_channel::unbounded;
+use petgraph::graph::NodeIndex;
 use portaudio as pa;
 
 use std::thread;
@@ -15,21 +17,26 @@ use std::time as rust_time; //To be used for thread::sleep for instance
 
 use time::PreciseTime;
 
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::path::Path;
 
 use clap::{App, Arg, ArgGroup};
 
 use audio_adaptive::audiograph::*;
+use audio_adaptive::audiograph_optim::{fit_budget, merge_resamplers, DownsamplingParameters};
 use audio_adaptive::audiograph_parser::*;
+use audio_adaptive::cost_model::{CostModel, ProfilingGrid};
+use audio_adaptive::effect::{CallbackFlags, Quality};
+use audio_adaptive::node_registry::NodeRegistry;
 use audio_adaptive::sndfile;
 
 use rand::distributions::Uniform;
 use rand::prelude::*;
 
-const CHANNELS: i32 = 1;
 const SAMPLE_RATE: u32 = 44_100;
 const NB_CYCLES: u32 = 12000;
 const FRAMES_PER_BUFFER: usize = 512;
@@ -42,7 +49,15 @@ pub struct TimeMonitor {
     pub deadline: u64,
     /// Execution time for one cycle
     pub execution_time: i64,
-    pub callback_flags: audio_adaptive::effect::CallbackFlags,
+    pub callback_flags: CallbackFlags,
+    /// Quality chosen in adaptive mode
+    pub quality: Quality,
+    /// Number of degraded nodes in adaptive mode
+    pub nb_degraded: u64,
+    /// Number of inserted resamplers in adaptive mode
+    pub nb_resamplers: u64,
+    /// Number of resamplers which failed in adaptive mode
+    pub nb_resampling_errors: u64,
 }
 
 impl Default for TimeMonitor {
@@ -51,17 +66,46 @@ impl Default for TimeMonitor {
             budget: 0,
             deadline: 0,
             execution_time: 0,
-            callback_flags: audio_adaptive::effect::CallbackFlags::NO_FLAG,
+            callback_flags: CallbackFlags::NO_FLAG,
+            quality: Quality::Normal,
+            nb_degraded: 0,
+            nb_resamplers: 0,
+            nb_resampling_errors: 0,
         }
     }
 }
 
+/// Parse a command `<node id> <parameter> <value>` and record it in `control`
+fn set_parameter(
+    control: &mut ControlHandle,
+    node_ids: &HashMap<String, NodeIndex>,
+    command: &str,
+) -> Result<(), String> {
+    let words = command.split_whitespace().collect::<Vec<_>>();
+    if words.len() != 3 {
+        return Err(format!(
+            "Invalid command {}: expected <node id> <parameter> <value>",
+            command
+        ));
+    }
+    let node = *node_ids
+        .get(words[0])
+        .ok_or_else(|| format!("Unknown node {}", words[0]))?;
+    let value = words[2]
+        .parse::<f32>()
+        .map_err(|_| format!("Invalid value {}", words[2]))?;
+    control
+        .set_parameter(node, words[1], value)
+        .map_err(|error| format!("Impossible to set {}: {:?}", command, error))
+}
+
 //Launch a audio graph in real time
 fn real_time_run(
     mut audio_graph: AudioGraph,
     graph_name: String,
     cycles: u32,
     monitor: bool,
+    adaptive: bool,
 ) -> Result<(), pa::Error> {
     let pa = try!(pa::PortAudio::new());
 
@@ -70,6 +114,7 @@ fn real_time_run(
     let nb_nodes = audio_graph.nb_active_nodes();
     let nb_edges = audio_graph.nb_edges();
 
+    let nb_channels = audio_graph.nb_channels() as usize;
     let buffer_size = audio_graph.frames_per_buffer() * audio_graph.nb_channels();
 
     let settings = try!(pa.default_output_stream_settings(
@@ -79,6 +124,32 @@ fn real_time_run(
     ));
 
     let mut nb_cycles = 0;
+    let graph_deadline = audio_graph.deadline();
+
+    //Edits of the graph while it is running go through this handle
+    let mut control = audio_graph.control_handle(16);
+    //The handle starts with the same indexes as the graph
+    let node_ids = audio_graph
+        .graph
+        .node_indices()
+        .map(|node| (audio_graph.graph[node].node_infos().id.clone(), node))
+        .collect::<HashMap<_, _>>();
+
+    //Thread reading the commands to set the parameters of the nodes on the standard input
+    let (tx_commands, rx_commands) = unbounded::<String>();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => {
+                    if tx_commands.send(line).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
 
     //Thread to monitor the audio callback
     let (tx_monit, rx_monit) = unbounded::<TimeMonitor>();
@@ -89,16 +160,32 @@ fn real_time_run(
                 .expect("Impossible to report execution times");
             f.write_all(format!("{} {}\n", nb_nodes, nb_edges).as_bytes())
                 .unwrap();
-            f.write_all(b"Budget\tDeadline\tExecutionTime\tCallbackFlags\n")
-                .unwrap();
+            if adaptive {
+                f.write_all(b"Budget\tDeadline\tExecutionTime\tCallbackFlags\tOverrun\tQuality\tNbDegraded\tNbResamplers\tNbResamplingErrors\n")
+                    .unwrap();
+            } else {
+                f.write_all(b"Budget\tDeadline\tExecutionTime\tCallbackFlags\tOverrun\n")
+                    .unwrap();
+            }
             for monitoring_infos in rx_monit.try_iter() {
-                let seria = format!(
-                    "{}\t{}\t{}\t{:?}\n",
+                let mut seria = format!(
+                    "{}\t{}\t{}\t{:?}\t{}",
                     monitoring_infos.budget,
                     monitoring_infos.deadline,
                     monitoring_infos.execution_time,
-                    monitoring_infos.callback_flags
+                    monitoring_infos.callback_flags,
+                    monitoring_infos.budget < 0
                 );
+                if adaptive {
+                    seria += &format!(
+                        "\t{}\t{}\t{}\t{}",
+                        monitoring_infos.quality,
+                        monitoring_infos.nb_degraded,
+                        monitoring_infos.nb_resamplers,
+                        monitoring_infos.nb_resampling_errors
+                    );
+                }
+                seria.push('\n');
                 f.write_all(seria.as_bytes()).unwrap();
             }
 
@@ -115,26 +202,40 @@ fn real_time_run(
                              time,
                              flags,
                          }| {
-        debug_assert!(frames == buf_in[0].buffer().len());
-        debug_assert!(frames == buf_out[0].buffer().len());
+        debug_assert!(frames * nb_channels == buf_in[0].buffer().len());
+        debug_assert!(frames * nb_channels == buf_out[0].buffer().len());
         //time members are in seconds. We need to convert it to microseconds
-        let rel_deadline = (time.buffer_dac - time.current) * 1_000_000.; //microseconds
+        //The deadline of the audio graph, if any, takes precedence over the one given by portaudio
+        let rel_deadline = graph_deadline.unwrap_or((time.buffer_dac - time.current) * 1_000_000.); //microseconds
         nb_cycles += 1;
         let start = PreciseTime::now();
         //assert!(time.buffer_dac- time.current < 1.0);
+        let callback_flags = CallbackFlags::from_callback_flags(flags);
         buf_in[0].buffer_mut().copy_from_slice(buffer);
-        audio_graph.process(&buf_in, &mut buf_out);
+        let adaptive_monitor = if adaptive {
+            Some(audio_graph.process_adaptive(&buf_in, &mut buf_out, rel_deadline, callback_flags))
+        } else {
+            audio_graph.process(&buf_in, &mut buf_out);
+            None
+        };
         buffer.copy_from_slice(buf_out[0].buffer());
 
         let execution_time = start.to(PreciseTime::now()).num_microseconds().unwrap();
 
         if monitor {
-            let times = TimeMonitor {
+            let mut times = TimeMonitor {
                 deadline: rel_deadline as u64,
                 execution_time,
                 budget: rel_deadline as i64 - execution_time,
-                callback_flags: audio_adaptive::effect::CallbackFlags::from_callback_flags(flags),
+                callback_flags,
+                ..Default::default()
             };
+            if let Some(adaptive_monitor) = adaptive_monitor {
+                times.quality = adaptive_monitor.quality;
+                times.nb_degraded = adaptive_monitor.nb_degraded;
+                times.nb_resamplers = adaptive_monitor.nb_resamplers;
+                times.nb_resampling_errors = adaptive_monitor.nb_resampling_errors;
+            }
             tx_monit.send(times).unwrap();
         }
 
@@ -150,11 +251,23 @@ fn real_time_run(
 
     println!("Starting stream");
     try!(stream.start());
+    println!("Set the parameters of the nodes with: <node id> <parameter> <value>");
 
-    let sleep_duration = rust_time::Duration::from_millis(500);
+    let sleep_duration = rust_time::Duration::from_millis(100);
 
     while try!(stream.is_active()) {
         thread::sleep(sleep_duration);
+        for command in rx_commands.try_iter() {
+            match set_parameter(&mut control, &node_ids, &command) {
+                Ok(()) => {
+                    if let Err(error) = control.commit() {
+                        eprintln!("Impossible to send {}: {:?}", command, error);
+                    }
+                }
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+        control.collect_garbage();
     }
 
     try!(stream.stop());
@@ -169,6 +282,7 @@ fn bounce_run<'a>(
     audio_input: Option<&'a str>,
     cycles: u32,
     monitor: bool,
+    adaptive: bool,
 ) -> Result<(), &'a str> {
     let nb_frames = FRAMES_PER_BUFFER;
 
@@ -185,11 +299,20 @@ fn bounce_run<'a>(
             .expect("Impossible to report execution times");
         file.write_all(format!("{} {}\n", nb_nodes, nb_edges).as_bytes())
             .unwrap();
-        file.write_all(b"Execution time\n").unwrap();
+        if adaptive {
+            file.write_all(
+                b"Execution time\tDeadline\tOverrun\tQuality\tNbDegraded\tNbResamplers\tNbResamplingErrors\n",
+            )
+            .unwrap();
+        } else {
+            file.write_all(b"Execution time\tDeadline\tOverrun\n")
+                .unwrap();
+        }
         f = Some(file);
     }
 
-    let mut nb_channels = CHANNELS as usize;
+    //The output and the interlaced input have as many channels as the graph
+    let nb_channels = audio_graph.nb_channels() as usize;
     let mut samplerate = SAMPLE_RATE;
     let mut nb_cycles = 0;
 
@@ -200,10 +323,26 @@ fn bounce_run<'a>(
     let mut advance: Box<dyn FnMut(&mut [f32]) -> u32> = if let Some(audio_input_name) = audio_input
     {
         let mut input_file = sndfile::SndFile::open(audio_input_name)?;
-        nb_channels = input_file.nb_channels();
+        let file_channels = input_file.nb_channels();
         samplerate = input_file.samplerate() as u32;
         audio_graph.set_nominal_samplerate(samplerate);
-        Box::new(move |buf| input_file.read_float(buf) as u32)
+        let mut file_buffer = vec![0.; nb_frames * file_channels];
+        Box::new(move |buf| {
+            let nb_read = input_file.read_float(&mut file_buffer);
+            for sample in file_buffer[nb_read..].iter_mut() {
+                *sample = 0.;
+            }
+            //Channel c of the graph reads channel c of the file, looping over the channels of the file
+            for (frame, file_frame) in buf
+                .chunks_mut(nb_channels)
+                .zip(file_buffer.chunks(file_channels))
+            {
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    *sample = file_frame[channel % file_channels];
+                }
+            }
+            nb_read as u32
+        })
     } else {
         let n = (nb_frames * nb_channels) as usize;
         Box::new(move |buf| {
@@ -224,16 +363,44 @@ fn bounce_run<'a>(
     let mut output_file =
         sndfile::SndFile::open_write(graph_name + ".wav", samplerate, nb_channels as u32)?;
 
+    // Deadline of the graph if any, otherwise the duration of a buffer
+    let rel_deadline = audio_graph.budget();
+
     while advance(buf_in[0].buffer_mut()) != 0 {
         let start = PreciseTime::now();
-        audio_graph.process(&buf_in, &mut buf_out);
+        let adaptive_monitor = if adaptive {
+            Some(audio_graph.process_adaptive(
+                &buf_in,
+                &mut buf_out,
+                rel_deadline,
+                CallbackFlags::NO_FLAG,
+            ))
+        } else {
+            audio_graph.process(&buf_in, &mut buf_out);
+            None
+        };
         let execution_time = start.to(PreciseTime::now()).num_microseconds().unwrap();
 
         output_file.write_float(buf_out[0].buffer());
 
         //Reporting
         if monitor {
-            let seria = format!("{}\n", execution_time);
+            let mut seria = format!(
+                "{}\t{}\t{}",
+                execution_time,
+                rel_deadline as u64,
+                execution_time as f64 > rel_deadline
+            );
+            if let Some(adaptive_monitor) = adaptive_monitor {
+                seria += &format!(
+                    "\t{}\t{}\t{}\t{}",
+                    adaptive_monitor.quality,
+                    adaptive_monitor.nb_degraded,
+                    adaptive_monitor.nb_resamplers,
+                    adaptive_monitor.nb_resampling_errors
+                );
+            }
+            seria.push('\n');
             f.as_mut().unwrap().write_all(seria.as_bytes()).unwrap();
         }
     }
@@ -247,7 +414,7 @@ fn main() {
         .about("Execute an audio graph .ag in real time or in bounce mode and get timing information about it.")
         .arg(Arg::with_name("INPUT")
              .help("Sets the audiograph to use.")
-             .required(true)
+             .required_unless_one(&["list-nodes", "profile"])
              .index(1))
         .arg(Arg::with_name("real-time")
              .short("r")
@@ -257,6 +424,14 @@ fn main() {
               .short("b")
               .long("bounce")
               .help("Execute the graph offline (bounce), as fast as possible."))
+        .arg(Arg::with_name("analyze")
+              .long("analyze")
+              .help("Static schedulability analysis of the graph using the WCETs of its nodes. The graph is not executed."))
+        .arg(Arg::with_name("degrade")
+              .long("degrade")
+              .value_name("OUTPUT")
+              .takes_value(true)
+              .help("Downsample branches of the graph so that its WCET fits its deadline, and write the degraded graph to OUTPUT. The graph is not executed."))
         .arg(Arg::with_name("audio_input")
               .short("a")
               .long("audio-input")
@@ -273,29 +448,161 @@ fn main() {
               .short("m")
               .long("monitor")
               .help("Monitor execution and save it as a csv file."))
+        .arg(Arg::with_name("adaptive")
+              .long("adaptive")
+              .help("Degrade the end of the schedule by resampling when the deadline is about to be missed."))
+        .arg(Arg::with_name("merge-resamplers")
+              .long("merge-resamplers")
+              .help("Fuse consecutive resamplers and remove the ones which cancel each other before executing or writing the graph."))
+        .arg(Arg::with_name("channels")
+              .long("channels")
+              .value_name("N")
+              .takes_value(true)
+              .default_value("1")
+              .validator(|v| match v.parse::<usize>() {
+                  Ok(n) if n >= 1 => Ok(()),
+                  _ => Err(String::from("The number of channels must be a positive integer")),
+              })
+              .help("Number of channels of the output, to which the real sink spreads the unconnected outlets of the graph"))
+        .arg(Arg::with_name("threads")
+              .short("t")
+              .long("threads")
+              .value_name("N")
+              .takes_value(true)
+              .validator(|v| match v.parse::<usize>() {
+                  Ok(n) if n >= 1 => Ok(()),
+                  _ => Err(String::from("The number of threads must be a positive integer")),
+              })
+              .help("Number of threads executing independent branches of the graph in parallel"))
         .arg(Arg::with_name("silent")
                 .long("silent")
                 .help("No output at all on the terminal."))
+        .arg(Arg::with_name("strict")
+                .long("strict")
+                .help("Reject the nodes whose kind is unknown instead of replacing them."))
+        .arg(Arg::with_name("list-nodes")
+                .long("list-nodes")
+                .help("Print the kinds of nodes which can be used in an audiograph."))
+        .arg(Arg::with_name("profile")
+                .long("profile")
+                .value_name("OUTPUT")
+                .takes_value(true)
+                .help("Measure the execution time of every kind of node at several buffer sizes and samplerates, and write the fitted cost model to OUTPUT."))
+        .arg(Arg::with_name("cost-model")
+                .long("cost-model")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Cost model written by --profile, used to predict the execution time of the nodes when they are resampled in adaptive mode."))
         .group(ArgGroup::with_name("execution-mode")
-                .args(&["real-time", "bounce"])
+                .args(&["real-time", "bounce", "analyze", "degrade", "list-nodes", "profile"])
                 .required(true))
         .get_matches();
 
+    let mut registry = NodeRegistry::default();
+    if matches.is_present("list-nodes") {
+        print!("{}", registry);
+        return;
+    }
+    registry.set_strict(matches.is_present("strict"));
+
+    if let Some(output) = matches.value_of("profile") {
+        let cost_model = CostModel::profile(&registry, &ProfilingGrid::default());
+        cost_model.save(output).unwrap_or_else(|error| {
+            eprintln!("Impossible to write the cost model to {}: {}", output, error);
+            std::process::exit(1)
+        });
+        return;
+    }
+
     let filename = matches.value_of("INPUT").unwrap();
     //We cannot get both at the same time thanks to the ArgGroup
     let real_time = matches.is_present("real-time");
     let bounce = matches.is_present("bounce");
+    let analyze = matches.is_present("analyze");
     let nb_cycles: u32 = matches
         .value_of("cycles")
         .map_or(NB_CYCLES, |v| v.parse().unwrap_or(NB_CYCLES));
     let monitor = matches.is_present("monitor");
     let silent = matches.is_present("silent");
-
-    let mut audiograph =
-        parse_audiograph_from_file(filename, FRAMES_PER_BUFFER, 1, SAMPLE_RATE).unwrap();
+    let adaptive = matches.is_present("adaptive");
+    let merge = matches.is_present("merge-resamplers");
+    //Validated by clap
+    let nb_threads: usize = matches
+        .value_of("threads")
+        .map_or(1, |v| v.parse().unwrap());
+    let nb_channels: usize = matches.value_of("channels").unwrap().parse().unwrap();
+
+    let mut audiograph = parse_audiograph_from_file_with_registry(
+        filename,
+        &registry,
+        FRAMES_PER_BUFFER,
+        nb_channels,
+        SAMPLE_RATE,
+    )
+    .unwrap_or_else(|error| {
+        eprintln!("Impossible to load {}: {}", filename, error);
+        std::process::exit(1)
+    });
+    audiograph.set_nb_threads(nb_threads);
     audiograph
         .update_schedule()
         .expect(&format!("Audio graph in {} is cyclic!!", filename));
+    if merge {
+        let nb_removed = merge_resamplers(&mut audiograph).unwrap();
+        if !silent {
+            println!("Removed {} resamplers", nb_removed);
+        }
+    }
+
+    if let Some(cost_model_file) = matches.value_of("cost-model") {
+        let cost_model = CostModel::load(cost_model_file).unwrap_or_else(|error| {
+            eprintln!("Impossible to load {}: {}", cost_model_file, error);
+            std::process::exit(1)
+        });
+        audiograph.set_cost_model(cost_model);
+        if !silent {
+            println!("Predicted costs:");
+            for node in audiograph.graph.node_indices() {
+                if let Some(predicted) = audiograph.predicted_cost(node) {
+                    let node_infos = audiograph.graph[node].node_infos();
+                    println!(
+                        "\t{} ({}): {:.2}µs, {:.2}µs resampled",
+                        node_infos.id, node_infos.class_name, predicted.nominal, predicted.resampled
+                    );
+                }
+            }
+        }
+    }
+
+    if analyze {
+        println!("{}", audiograph.wcet_analysis());
+        return;
+    }
+
+    if let Some(output) = matches.value_of("degrade") {
+        let budget = audiograph.budget();
+        match fit_budget(&mut audiograph, budget, &DownsamplingParameters::default()) {
+            Some(choice) => {
+                println!(
+                    "Degrading {} nodes with {} resamplers. Estimated WCET: {}µs for a budget of {}µs",
+                    choice.degraded_nodes.len(),
+                    choice.nb_resamplers,
+                    choice.estimated_cost,
+                    budget
+                );
+                if merge {
+                    merge_resamplers(&mut audiograph).unwrap();
+                }
+                write_audiograph_to_file(&audiograph, output)
+                    .expect("Impossible to write degraded graph");
+            }
+            None => {
+                eprintln!("Impossible to fit the budget of {}µs", budget);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     let basename = Path::new(filename)
         .file_stem()
@@ -303,11 +610,21 @@ fn main() {
         .unwrap();
 
     if !silent {
+        if let Some(deadline) = audiograph.deadline() {
+            println!("Deadline: {}µs", deadline);
+        }
         println!("Starting processing")
     };
     let start = PreciseTime::now();
     if real_time {
-        real_time_run(audiograph, basename.to_string(), nb_cycles, monitor).unwrap();
+        real_time_run(
+            audiograph,
+            basename.to_string(),
+            nb_cycles,
+            monitor,
+            adaptive,
+        )
+        .unwrap();
     } else if bounce {
         let audio_input = matches.value_of("audio_input");
         bounce_run(
@@ -316,6 +633,7 @@ fn main() {
             audio_input,
             nb_cycles,
             monitor,
+            adaptive,
         )
         .unwrap();
     }
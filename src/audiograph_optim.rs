@@ -0,0 +1,787 @@
+//! Static transformations of audiographs.
+//! Choose which parts of an audiograph to execute at a lower samplerate
+//! so that its worst-case execution time fits a budget, and remove redundant resamplers.
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use petgraph::Direction;
+
+use std::collections::{HashMap, HashSet};
+
+use audiograph::*;
+use audiograph_parser::{self, FromValue, Value};
+
+/// Parameters of the downsampling optimizer
+#[derive(Debug, Clone, Copy)]
+pub struct DownsamplingParameters {
+    /// Ratio of the downsamplers. Upsamplers use the inverse ratio.
+    pub ratio: f64,
+    /// Estimated WCET of an inserted resampler at the nominal samplerate, in microseconds
+    pub resampler_wcet: f64,
+}
+
+impl Default for DownsamplingParameters {
+    fn default() -> Self {
+        DownsamplingParameters {
+            ratio: 0.5,
+            resampler_wcet: 15.,
+        }
+    }
+}
+
+/// Nodes chosen to be executed at a lower samplerate
+#[derive(Debug, Clone)]
+pub struct DownsamplingChoice {
+    /// Degraded nodes, in schedule order
+    pub degraded_nodes: Vec<NodeIndex>,
+    /// Estimated WCET of the graph once degraded, including the resamplers
+    pub estimated_cost: f64,
+    /// Number of resamplers to insert
+    pub nb_resamplers: usize,
+}
+
+/// Nodes that can be degraded: no input/output nodes and no resamplers already present in the graph.
+/// Nodes without inputs, such as oscillators, are always executed at the nominal samplerate
+/// as there is no edge to put a downsampler on.
+fn is_degradable(audio_graph: &AudioGraph, node: NodeIndex) -> bool {
+    let class_name = audio_graph.graph[node].node_infos().class_name.as_str();
+    audio_graph.nb_inputs(node) > 0
+        && node != audio_graph.source_node()
+        && node != audio_graph.sink_node()
+        && class_name != "resampler"
+        && class_name != "source"
+        && class_name != "sink"
+}
+
+/// Edges which need a resampler if `degraded` nodes are executed at a lower samplerate
+fn crossing_edges(
+    audio_graph: &AudioGraph,
+    active: &HashSet<NodeIndex>,
+    degraded: &HashSet<NodeIndex>,
+) -> Vec<EdgeIndex> {
+    audio_graph
+        .graph
+        .edge_references()
+        .filter(|edge| active.contains(&edge.target()))
+        .filter(|edge| degraded.contains(&edge.source()) != degraded.contains(&edge.target()))
+        .map(|edge| edge.id())
+        .collect()
+}
+
+/// Maximum number of degradable nodes for which `choose_downsampled_nodes` explores all the possible
+/// choices. Above, it uses a greedy heuristic.
+pub const EXHAUSTIVE_SEARCH_MAX_NODES: usize = 16;
+
+/// Estimated cost of an audiograph when some of its nodes are executed at a lower samplerate
+struct DownsamplingCost<'a> {
+    audio_graph: &'a AudioGraph,
+    parameters: &'a DownsamplingParameters,
+    report: WcetReport,
+    active: HashSet<NodeIndex>,
+    rate_ratios: HashMap<NodeIndex, f64>,
+}
+
+impl<'a> DownsamplingCost<'a> {
+    fn new(audio_graph: &'a AudioGraph, parameters: &'a DownsamplingParameters) -> Self {
+        let report = audio_graph.wcet_analysis();
+        let active = report
+            .contributions
+            .iter()
+            .map(|c| c.node)
+            .collect::<HashSet<_>>();
+        let rate_ratios = report
+            .contributions
+            .iter()
+            .map(|c| (c.node, c.rate_ratio))
+            .collect::<HashMap<_, _>>();
+        DownsamplingCost {
+            audio_graph,
+            parameters,
+            report,
+            active,
+            rate_ratios,
+        }
+    }
+
+    /// Cost of the nodes only, without the resamplers
+    fn nodes_cost(&self, degraded: &HashSet<NodeIndex>) -> f64 {
+        self.report
+            .contributions
+            .iter()
+            .map(|c| {
+                if degraded.contains(&c.node) {
+                    c.contribution * self.parameters.ratio
+                } else {
+                    c.contribution
+                }
+            })
+            .sum::<f64>()
+    }
+
+    fn cost(&self, degraded: &HashSet<NodeIndex>) -> f64 {
+        //As for the other nodes, the cost of a resampler depends on the samplerate of its input
+        let resamplers_cost = crossing_edges(self.audio_graph, &self.active, degraded)
+            .into_iter()
+            .map(|edge| {
+                let (src, _) = self.audio_graph.graph.edge_endpoints(edge).unwrap();
+                let rate_ratio = self.rate_ratios.get(&src).cloned().unwrap_or(1.);
+                if degraded.contains(&src) {
+                    self.parameters.resampler_wcet * rate_ratio * self.parameters.ratio
+                } else {
+                    self.parameters.resampler_wcet * rate_ratio
+                }
+            })
+            .sum::<f64>();
+        self.nodes_cost(degraded) + resamplers_cost
+    }
+
+    /// Degradable nodes, in schedule order
+    fn degradable_nodes(&self) -> Vec<NodeIndex> {
+        self.report
+            .contributions
+            .iter()
+            .map(|c| c.node)
+            .filter(|node| is_degradable(self.audio_graph, *node))
+            .collect()
+    }
+
+    /// Degradable nodes just upstream of `node`
+    fn degradable_predecessors<'b>(
+        &'b self,
+        node: NodeIndex,
+    ) -> impl Iterator<Item = NodeIndex> + 'b {
+        self.audio_graph
+            .graph
+            .neighbors_directed(node, Direction::Incoming)
+            .filter(move |pred| is_degradable(self.audio_graph, *pred))
+    }
+}
+
+/// Choose a set of branches to downsample so that the estimated WCET of the graph fits in `budget`,
+/// while keeping as many nodes as possible at the nominal samplerate.
+///
+/// A branch ending at a node is this node and all the degradable nodes upstream of it.
+/// If the graph has at most `EXHAUSTIVE_SEARCH_MAX_NODES` degradable nodes, all the unions of branches
+/// are explored with a branch and bound and the choice degrades the fewest nodes, with the lowest cost
+/// among them. Otherwise, the choice is only a heuristic: see `greedy_choice`.
+///
+/// Returns None if the budget cannot be met. `update_schedule` must have been called before.
+pub fn choose_downsampled_nodes(
+    audio_graph: &AudioGraph,
+    budget: f64,
+    parameters: &DownsamplingParameters,
+) -> Option<DownsamplingChoice> {
+    let cost = DownsamplingCost::new(audio_graph, parameters);
+    let degraded = if cost.degradable_nodes().len() <= EXHAUSTIVE_SEARCH_MAX_NODES {
+        exhaustive_choice(&cost, budget)?
+    } else {
+        greedy_choice(&cost, budget)?
+    };
+
+    Some(DownsamplingChoice {
+        degraded_nodes: cost
+            .report
+            .contributions
+            .iter()
+            .map(|c| c.node)
+            .filter(|node| degraded.contains(node))
+            .collect(),
+        estimated_cost: cost.cost(&degraded),
+        nb_resamplers: crossing_edges(audio_graph, &cost.active, &degraded).len(),
+    })
+}
+
+/// Explore all the unions of branches, that is to say the sets of degradable nodes which contain
+/// the degradable nodes upstream of each of their nodes.
+///
+/// The nodes are decided in schedule order, so that the upstream nodes of a node are decided before it.
+/// A partial choice is pruned if it already degrades more nodes than the best choice, or if the budget
+/// cannot be met even by degrading all the remaining nodes and without counting the resamplers.
+fn exhaustive_choice(cost: &DownsamplingCost, budget: f64) -> Option<HashSet<NodeIndex>> {
+    let nodes = cost.degradable_nodes();
+    //What degrading all the nodes from position i saves
+    let mut savings = vec![0.; nodes.len() + 1];
+    for (i, node) in nodes.iter().enumerate().rev() {
+        let contribution = cost
+            .report
+            .contributions
+            .iter()
+            .find(|c| c.node == *node)
+            .map_or(0., |c| c.contribution);
+        savings[i] = savings[i + 1] + (contribution * (1. - cost.parameters.ratio)).max(0.);
+    }
+
+    let mut search = BranchAndBound {
+        cost,
+        budget,
+        nodes,
+        savings,
+        best: None,
+    };
+    search.explore(0, &mut HashSet::new());
+    search.best.map(|(_, _, degraded)| degraded)
+}
+
+struct BranchAndBound<'a, 'b: 'a> {
+    cost: &'a DownsamplingCost<'b>,
+    budget: f64,
+    nodes: Vec<NodeIndex>, //Degradable nodes, in schedule order
+    savings: Vec<f64>,     //What degrading all the nodes from a position saves
+    best: Option<(usize, f64, HashSet<NodeIndex>)>, //Number of degraded nodes, cost and degraded nodes
+}
+
+impl<'a, 'b> BranchAndBound<'a, 'b> {
+    /// Decide whether to degrade the nodes from `position`, the previous ones being decided in `degraded`
+    fn explore(&mut self, position: usize, degraded: &mut HashSet<NodeIndex>) {
+        let too_many = self
+            .best
+            .as_ref()
+            .map_or(false, |&(nb_degraded, _, _)| degraded.len() > nb_degraded);
+        if too_many || self.cost.nodes_cost(degraded) - self.savings[position] > self.budget {
+            return;
+        }
+
+        if position == self.nodes.len() {
+            let new_cost = self.cost.cost(degraded);
+            let better = new_cost <= self.budget
+                && self
+                    .best
+                    .as_ref()
+                    .map_or(true, |&(nb_degraded, best_cost, _)| {
+                        degraded.len() < nb_degraded
+                            || (degraded.len() == nb_degraded && new_cost < best_cost)
+                    });
+            if better {
+                self.best = Some((degraded.len(), new_cost, degraded.clone()));
+            }
+            return;
+        }
+
+        let node = self.nodes[position];
+        self.explore(position + 1, degraded);
+        //A node can only be degraded with the degradable nodes upstream of it
+        if self
+            .cost
+            .degradable_predecessors(node)
+            .all(|pred| degraded.contains(&pred))
+        {
+            degraded.insert(node);
+            self.explore(position + 1, degraded);
+            degraded.remove(&node);
+        }
+    }
+}
+
+/// Choose branches greedily: if degrading one more branch is enough to meet the budget,
+/// the smallest such branch is chosen; otherwise, the branch with the best cost reduction per degraded node.
+///
+/// It is a heuristic: it can degrade more nodes than needed, for instance when a small branch with
+/// a good cost reduction per node is chosen first but is not part of the best choice.
+fn greedy_choice(cost: &DownsamplingCost, budget: f64) -> Option<HashSet<NodeIndex>> {
+    //Branches ending at every degradable node
+    let branches = cost
+        .degradable_nodes()
+        .into_iter()
+        .map(|node| {
+            let mut branch = HashSet::new();
+            let mut stack = vec![node];
+            while let Some(n) = stack.pop() {
+                if branch.insert(n) {
+                    stack.extend(cost.degradable_predecessors(n));
+                }
+            }
+            branch
+        })
+        .collect::<Vec<_>>();
+
+    let mut degraded = HashSet::new();
+    let mut current_cost = cost.cost(&degraded);
+
+    while current_cost > budget {
+        let candidates = branches
+            .iter()
+            .filter(|branch| !branch.is_subset(&degraded))
+            .map(|branch| {
+                let new_degraded = degraded.union(branch).cloned().collect::<HashSet<_>>();
+                let new_cost = cost.cost(&new_degraded);
+                (new_degraded, new_cost)
+            })
+            .collect::<Vec<_>>();
+
+        //Smallest branch that is enough to meet the budget
+        let sufficient = candidates
+            .iter()
+            .filter(|(_, new_cost)| *new_cost <= budget)
+            .min_by_key(|(new_degraded, _)| new_degraded.len());
+        let chosen = sufficient.or_else(|| {
+            // Best cost reduction per degraded node
+            candidates
+                .iter()
+                .filter(|(_, new_cost)| *new_cost < current_cost)
+                .max_by(|(d1, c1), (d2, c2)| {
+                    let gain1 = (current_cost - c1) / (d1.len() - degraded.len()) as f64;
+                    let gain2 = (current_cost - c2) / (d2.len() - degraded.len()) as f64;
+                    gain1.partial_cmp(&gain2).unwrap()
+                })
+        });
+
+        match chosen {
+            Some((new_degraded, new_cost)) => {
+                degraded = new_degraded.clone();
+                current_cost = *new_cost;
+            }
+            //Degrading more does not decrease the cost anymore
+            None => return None,
+        }
+    }
+
+    Some(degraded)
+}
+
+/// Insert a downsampler on every edge going into the `degraded` nodes and an upsampler on every edge
+/// going out of them, then update the schedule.
+/// Returns the number of inserted resamplers.
+pub fn downsample_nodes(
+    audio_graph: &mut AudioGraph,
+    degraded: &[NodeIndex],
+    parameters: &DownsamplingParameters,
+) -> Result<usize, AudioGraphError> {
+    let degraded = degraded.iter().cloned().collect::<HashSet<_>>();
+    //Also consider the edges coming from the source
+    let active = audio_graph.graph.node_indices().collect::<HashSet<_>>();
+    let edges = crossing_edges(audio_graph, &active, &degraded);
+
+    let nb_resamplers = edges.len();
+    for edge in edges.into_iter() {
+        let (src, dst) = audio_graph.graph.edge_endpoints(edge).unwrap();
+        let (src_port, dst_port) = {
+            let edge = &audio_graph.graph[edge];
+            (edge.src_port(), edge.dst_port())
+        };
+        let ratio = if degraded.contains(&dst) {
+            parameters.ratio
+        } else {
+            1. / parameters.ratio
+        };
+        let node_infos = audiograph_parser::Node {
+            id: format!(
+                "{}-{}",
+                if ratio < 1. { "down" } else { "up" },
+                audio_graph.nb_nodes()
+            ),
+            class_name: "resampler".to_string(),
+            nb_inlets: 1,
+            nb_outlets: 1,
+            wcet: Some(parameters.resampler_wcet),
+            more: vec![("ratio".to_string(), Value::Float(ratio))]
+                .into_iter()
+                .collect(),
+            ..audiograph_parser::Node::new()
+        };
+        let nb_channels = audio_graph.nb_channels() as usize;
+        let resampler = audio_graph.add_node(
+            DspNode::new(node_infos, nb_channels).expect("A resampler with a ratio is valid"),
+        );
+        audio_graph.remove_edge(edge);
+        audio_graph.add_connection(src, src_port, resampler, 1)?;
+        audio_graph.add_connection(resampler, 1, dst, dst_port)?;
+    }
+
+    audio_graph.update_schedule()?;
+    Ok(nb_resamplers)
+}
+
+/// Transform `audio_graph` so that its estimated WCET fits in `budget`.
+/// See `choose_downsampled_nodes` for how the degraded nodes are chosen.
+pub fn fit_budget(
+    audio_graph: &mut AudioGraph,
+    budget: f64,
+    parameters: &DownsamplingParameters,
+) -> Option<DownsamplingChoice> {
+    let choice = choose_downsampled_nodes(audio_graph, budget, parameters)?;
+    downsample_nodes(audio_graph, &choice.degraded_nodes, parameters).ok()?;
+    Some(choice)
+}
+
+/// Ratio of a resampler node, or None if it is not a resampler
+fn resampler_ratio(audio_graph: &AudioGraph, node: NodeIndex) -> Option<f64> {
+    let node_infos = audio_graph.graph[node].node_infos();
+    if node_infos.class_name == "resampler" {
+        node_infos.more.get("ratio").and_then(f64::from_value)
+    } else {
+        None
+    }
+}
+
+fn is_identity(ratio: f64) -> bool {
+    (ratio - 1.).abs() < 1e-9
+}
+
+/// Add a resampler node with the same infos as `model` but with ratio `ratio`
+fn add_resampler_like(audio_graph: &mut AudioGraph, model: NodeIndex, ratio: f64) -> NodeIndex {
+    let mut node_infos = audio_graph.graph[model].node_infos().clone();
+    node_infos
+        .more
+        .insert("ratio".to_string(), Value::Float(ratio));
+    let nb_channels = audio_graph.nb_channels() as usize;
+    audio_graph
+        .add_node(DspNode::new(node_infos, nb_channels).expect("A resampler with a ratio is valid"))
+}
+
+/// Sources (node and port) of the edges going into `node`
+fn input_ports(audio_graph: &AudioGraph, node: NodeIndex) -> Vec<(NodeIndex, u32)> {
+    audio_graph
+        .inputs(node)
+        .map(|edge| (edge.source(), edge.weight().src_port()))
+        .collect()
+}
+
+/// Destinations (node and port) of the edges going out of `node`
+fn output_ports(audio_graph: &AudioGraph, node: NodeIndex) -> Vec<(NodeIndex, u32)> {
+    audio_graph
+        .outputs(node)
+        .map(|edge| (edge.target(), edge.weight().dst_port()))
+        .collect()
+}
+
+fn remove_nodes(audio_graph: &mut AudioGraph, nodes: Vec<NodeIndex>) {
+    for node in nodes.into_iter() {
+        audio_graph.remove_node(node);
+    }
+}
+
+/// Bypass an identity resampler, or fuse a resampler with the resampler just before it
+/// if they are only connected to each other. Returns true if the graph has been modified.
+fn fuse_resamplers(audio_graph: &mut AudioGraph) -> Result<bool, AudioGraphError> {
+    for node in audio_graph.graph.node_indices() {
+        let ratio = match resampler_ratio(audio_graph, node) {
+            Some(ratio) => ratio,
+            None => continue,
+        };
+        let inputs = input_ports(audio_graph, node);
+        let outputs = output_ports(audio_graph, node);
+
+        if is_identity(ratio) {
+            for &(src, src_port) in inputs.iter() {
+                for &(dst, dst_port) in outputs.iter() {
+                    audio_graph.add_connection(src, src_port, dst, dst_port)?;
+                }
+            }
+            remove_nodes(audio_graph, vec![node]);
+            return Ok(true);
+        }
+
+        if inputs.len() != 1 {
+            continue;
+        }
+        let prev = inputs[0].0;
+        let prev_ratio = match resampler_ratio(audio_graph, prev) {
+            Some(prev_ratio) => prev_ratio,
+            None => continue,
+        };
+        if audio_graph.nb_outputs(prev) != 1 {
+            continue;
+        }
+
+        let prev_inputs = input_ports(audio_graph, prev);
+        let fused_ratio = prev_ratio * ratio;
+        if is_identity(fused_ratio) {
+            for &(src, src_port) in prev_inputs.iter() {
+                for &(dst, dst_port) in outputs.iter() {
+                    audio_graph.add_connection(src, src_port, dst, dst_port)?;
+                }
+            }
+        } else {
+            let fused = add_resampler_like(audio_graph, prev, fused_ratio);
+            for &(src, src_port) in prev_inputs.iter() {
+                audio_graph.add_connection(src, src_port, fused, 1)?;
+            }
+            for &(dst, dst_port) in outputs.iter() {
+                audio_graph.add_connection(fused, 1, dst, dst_port)?;
+            }
+        }
+        remove_nodes(audio_graph, vec![prev, node]);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Replace resamplers with the same ratio on all the edges going out of a port by only one resampler
+/// before the fan-out. Returns true if the graph has been modified.
+fn hoist_resamplers(audio_graph: &mut AudioGraph) -> Result<bool, AudioGraphError> {
+    for node in audio_graph.graph.node_indices() {
+        let mut ports = audio_graph
+            .outputs(node)
+            .map(|edge| edge.weight().src_port())
+            .collect::<Vec<_>>();
+        ports.sort();
+        ports.dedup();
+
+        for port in ports.into_iter() {
+            let resamplers = audio_graph
+                .outputs(node)
+                .filter(|edge| edge.weight().src_port() == port)
+                .map(|edge| edge.target())
+                .collect::<Vec<_>>();
+            if resamplers.len() < 2 {
+                continue;
+            }
+            let same_resamplers = resamplers.iter().all(|resampler| {
+                let infos = audio_graph.graph[*resampler].node_infos();
+                let first_infos = audio_graph.graph[resamplers[0]].node_infos();
+                resampler_ratio(audio_graph, *resampler).is_some()
+                    && audio_graph.nb_inputs(*resampler) == 1
+                    && infos.more.get("ratio") == first_infos.more.get("ratio")
+                    && infos.more.get("conv") == first_infos.more.get("conv")
+            });
+            if !same_resamplers {
+                continue;
+            }
+
+            let ratio = resampler_ratio(audio_graph, resamplers[0]).unwrap();
+            let hoisted = add_resampler_like(audio_graph, resamplers[0], ratio);
+            audio_graph.add_connection(node, port, hoisted, 1)?;
+            for resampler in resamplers.iter() {
+                for (dst, dst_port) in output_ports(audio_graph, *resampler).into_iter() {
+                    audio_graph.add_connection(hoisted, 1, dst, dst_port)?;
+                }
+            }
+            remove_nodes(audio_graph, resamplers);
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Remove redundant resamplers: consecutive resamplers are fused into one with the product of their ratios,
+/// resamplers which cancel each other are removed, and resamplers with the same ratio after a fan-out
+/// are replaced by only one resampler before the fan-out.
+///
+/// Updates the schedule and returns the number of removed resamplers.
+pub fn merge_resamplers(audio_graph: &mut AudioGraph) -> Result<usize, AudioGraphError> {
+    let nb_nodes = audio_graph.nb_nodes();
+    while fuse_resamplers(audio_graph)? || hoist_resamplers(audio_graph)? {}
+    audio_graph.update_schedule()?;
+    Ok(nb_nodes - audio_graph.nb_nodes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiograph_parser::parse_audiograph;
+
+    const TWO_BRANCHES: &str = "
+        osc1 = {kind : \"osc\", out : 1, freq : 440, wcet : 10.0,};
+        mod1 = {kind : \"mod\", in : 1, out : 1, freq : 200, wcet : 100.0,};
+        osc2 = {kind : \"osc\", out : 1, freq : 220, wcet : 10.0,};
+        mod2 = {kind : \"mod\", in : 1, out : 1, freq : 300, wcet : 20.0,};
+        mix = {kind : \"mix\", in : 2, out : 1, wcet : 5.0,};
+        osc1.1 -> mod1.1 -> mix.1;
+        osc2.1 -> mod2.1 -> mix.2;
+    ";
+
+    fn id(audio_graph: &AudioGraph, node: NodeIndex) -> &str {
+        audio_graph.graph[node].node_infos().id.as_str()
+    }
+
+    #[test]
+    fn test_no_degradation_needed() {
+        let mut audio_graph = parse_audiograph(TWO_BRANCHES, 64, 1, 44_100).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let choice =
+            choose_downsampled_nodes(&audio_graph, 1000., &DownsamplingParameters::default())
+                .unwrap();
+        assert!(choice.degraded_nodes.is_empty());
+        assert_eq!(choice.nb_resamplers, 0);
+        assert_eq!(choice.estimated_cost, 145.);
+    }
+
+    #[test]
+    fn test_degrade_heaviest_branch() {
+        let mut audio_graph = parse_audiograph(TWO_BRANCHES, 64, 1, 44_100).unwrap();
+        audio_graph.update_schedule().unwrap();
+        let parameters = DownsamplingParameters {
+            ratio: 0.5,
+            resampler_wcet: 5.,
+        };
+        // Degrading mod1 saves 50 and costs one downsampler and one upsampler at half the samplerate
+        let choice = choose_downsampled_nodes(&audio_graph, 105., &parameters).unwrap();
+        let mut degraded = choice
+            .degraded_nodes
+            .iter()
+            .map(|node| id(&audio_graph, *node))
+            .collect::<Vec<_>>();
+        degraded.sort();
+        assert_eq!(degraded, vec!["mod1"]);
+        assert_eq!(choice.nb_resamplers, 2);
+        assert_eq!(choice.estimated_cost, 145. - 50. + 5. + 2.5);
+
+        let nb_nodes = audio_graph.nb_nodes();
+        assert_eq!(
+            downsample_nodes(&mut audio_graph, &choice.degraded_nodes, &parameters).unwrap(),
+            2
+        );
+        assert_eq!(audio_graph.nb_nodes(), nb_nodes + 2);
+        let report = audio_graph.wcet_analysis();
+        assert_eq!(report.total, choice.estimated_cost);
+    }
+
+    #[test]
+    fn test_unreachable_budget() {
+        let mut audio_graph = parse_audiograph(TWO_BRANCHES, 64, 1, 44_100).unwrap();
+        audio_graph.update_schedule().unwrap();
+        assert!(fit_budget(&mut audio_graph, 10., &DownsamplingParameters::default()).is_none());
+    }
+
+    #[test]
+    fn test_exhaustive_search_beats_greedy() {
+        let mut audio_graph = parse_audiograph(
+            "
+            osc1 = {kind : \"osc\", out : 1, freq : 440, wcet : 1.0,};
+            a1 = {kind : \"mod\", in : 1, out : 1, freq : 200, wcet : 2.0,};
+            a2 = {kind : \"mod\", in : 1, out : 1, freq : 300, wcet : 2.0,};
+            a3 = {kind : \"mod\", in : 1, out : 1, freq : 400, wcet : 296.0,};
+            osc2 = {kind : \"osc\", out : 1, freq : 220, wcet : 1.0,};
+            b1 = {kind : \"mod\", in : 1, out : 1, freq : 500, wcet : 2.0,};
+            b2 = {kind : \"mod\", in : 1, out : 1, freq : 600, wcet : 178.0,};
+            osc3 = {kind : \"osc\", out : 1, freq : 110, wcet : 1.0,};
+            x = {kind : \"mod\", in : 1, out : 1, freq : 700, wcet : 120.0,};
+            osc1.1 -> a1.1 -> a2.1 -> a3.1;
+            osc2.1 -> b1.1 -> b2.1;
+            osc3.1 -> x.1;
+            ",
+            64,
+            1,
+            44_100,
+        )
+        .unwrap();
+        audio_graph.update_schedule().unwrap();
+        let parameters = DownsamplingParameters {
+            ratio: 0.5,
+            resampler_wcet: 0.,
+        };
+        // Degrading the a branch saves 150, the b branch 90 and x 60: saving 240 requires the a and b branches.
+        let budget = audio_graph.wcet_analysis().total - 240.;
+        let ids = |degraded: &HashSet<NodeIndex>| {
+            let mut ids = degraded
+                .iter()
+                .map(|node| id(&audio_graph, *node))
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+
+        // x has the best cost reduction per node, so the greedy heuristic chooses it first
+        // and then needs both the a and b branches anyway
+        let cost = DownsamplingCost::new(&audio_graph, &parameters);
+        let greedy = greedy_choice(&cost, budget).unwrap();
+        assert_eq!(ids(&greedy), vec!["a1", "a2", "a3", "b1", "b2", "x"]);
+
+        let choice = choose_downsampled_nodes(&audio_graph, budget, &parameters).unwrap();
+        let degraded = choice
+            .degraded_nodes
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        assert_eq!(ids(&degraded), vec!["a1", "a2", "a3", "b1", "b2"]);
+        assert_eq!(choice.estimated_cost, budget);
+        assert!(cost.cost(&greedy) < choice.estimated_cost);
+    }
+
+    fn nb_resamplers(audio_graph: &AudioGraph) -> usize {
+        audio_graph
+            .graph
+            .node_indices()
+            .filter(|node| resampler_ratio(audio_graph, *node).is_some())
+            .count()
+    }
+
+    #[test]
+    fn test_merge_identity_pair() {
+        let mut audio_graph = parse_audiograph(
+            "
+            osc = {kind : \"osc\", out : 1, freq : 440,};
+            down = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            up = {kind : \"resampler\", in : 1, out : 1, ratio : 2.0,};
+            mod = {kind : \"mod\", in : 1, out : 1, freq : 200,};
+            osc.1 -> down.1 -> up.1 -> mod.1;
+            ",
+            64,
+            1,
+            44_100,
+        )
+        .unwrap();
+        audio_graph.update_schedule().unwrap();
+        assert_eq!(merge_resamplers(&mut audio_graph).unwrap(), 2);
+        assert_eq!(nb_resamplers(&audio_graph), 0);
+        let osc = audio_graph
+            .graph
+            .node_indices()
+            .find(|node| id(&audio_graph, *node) == "osc")
+            .unwrap();
+        assert_eq!(
+            audio_graph
+                .outputs(osc)
+                .map(|edge| id(&audio_graph, edge.target()))
+                .collect::<Vec<_>>(),
+            vec!["mod"]
+        );
+    }
+
+    #[test]
+    fn test_merge_chained_resamplers() {
+        let mut audio_graph = parse_audiograph(
+            "
+            osc = {kind : \"osc\", out : 1, freq : 440,};
+            down1 = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            down2 = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            mod = {kind : \"mod\", in : 1, out : 1, freq : 200,};
+            up = {kind : \"resampler\", in : 1, out : 1, ratio : 4.0,};
+            osc.1 -> down1.1 -> down2.1 -> mod.1 -> up.1;
+            ",
+            64,
+            1,
+            44_100,
+        )
+        .unwrap();
+        audio_graph.update_schedule().unwrap();
+        assert_eq!(merge_resamplers(&mut audio_graph).unwrap(), 1);
+        let mut ratios = audio_graph
+            .graph
+            .node_indices()
+            .filter_map(|node| resampler_ratio(&audio_graph, node))
+            .collect::<Vec<_>>();
+        ratios.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ratios, vec![0.25, 4.0]);
+    }
+
+    #[test]
+    fn test_hoist_resamplers() {
+        let mut audio_graph = parse_audiograph(
+            "
+            osc = {kind : \"osc\", out : 1, freq : 440,};
+            down1 = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            down2 = {kind : \"resampler\", in : 1, out : 1, ratio : 0.5,};
+            mod1 = {kind : \"mod\", in : 1, out : 1, freq : 200,};
+            mod2 = {kind : \"mod\", in : 1, out : 1, freq : 300,};
+            up1 = {kind : \"resampler\", in : 1, out : 1, ratio : 2.0,};
+            up2 = {kind : \"resampler\", in : 1, out : 1, ratio : 2.0,};
+            mix = {kind : \"mix\", in : 2, out : 1,};
+            osc.1 -> down1.1 -> mod1.1 -> up1.1 -> mix.1;
+            osc.1 -> down2.1 -> mod2.1 -> up2.1 -> mix.2;
+            ",
+            64,
+            1,
+            44_100,
+        )
+        .unwrap();
+        audio_graph.update_schedule().unwrap();
+        assert_eq!(merge_resamplers(&mut audio_graph).unwrap(), 1);
+        assert_eq!(nb_resamplers(&audio_graph), 3);
+        let osc = audio_graph
+            .graph
+            .node_indices()
+            .find(|node| id(&audio_graph, *node) == "osc")
+            .unwrap();
+        assert_eq!(audio_graph.nb_outputs(osc), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Parallel execution of audiographs.
+//! A fixed pool of worker threads executes the nodes of a dependency graph as soon as all their
+//! predecessors have been executed. Worker threads are started once, outside of the audio callback,
+//! and a cycle neither takes a lock nor allocates.
+use std::cell::UnsafeCell;
+use std::hint;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Number of times an idle worker checks for a new cycle before parking
+const SPIN_LIMIT: usize = 10_000;
+/// Number of times a thread waiting for a ready node spins before yielding to other threads
+const YIELD_LIMIT: usize = 100;
+
+/// Wait a bit before checking again. Yielding avoids starving the threads
+/// executing nodes when there are more threads than cores.
+fn backoff(spins: &mut usize) {
+    if *spins < YIELD_LIMIT {
+        *spins += 1;
+        hint::spin_loop();
+    } else {
+        thread::yield_now();
+    }
+}
+
+type Task = dyn Fn(usize, usize) + Sync;
+
+/// State shared by the thread calling `run` and the worker threads
+struct Shared {
+    successors: Vec<Vec<usize>>,
+    nb_predecessors: Vec<usize>,
+    /// Nodes without predecessors
+    roots: Vec<usize>,
+    /// Number of predecessors which have not been executed yet in the current cycle
+    dependencies: Vec<AtomicUsize>,
+    /// Nodes ready to be executed, in the order they became ready, plus one. 0 means not pushed yet.
+    ready: Vec<AtomicUsize>,
+    /// Next position in `ready` to pop from
+    head: AtomicUsize,
+    /// Next position in `ready` to push to
+    tail: AtomicUsize,
+    /// Number of nodes which have not been executed yet in the current cycle
+    remaining: AtomicUsize,
+    cycle: AtomicUsize,
+    stop: AtomicBool,
+    /// Function executing a node in the current cycle. It is only valid during `run`.
+    task: UnsafeCell<Option<*const Task>>,
+}
+
+// The task is only called during `run`, which waits for all the nodes to be executed before returning,
+// and it is required to be Sync.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn push(&self, node: usize) {
+        let position = self.tail.fetch_add(1, Ordering::AcqRel);
+        self.ready[position].store(node + 1, Ordering::Release);
+    }
+
+    /// Get a ready node, waiting for one if necessary. Returns None when all the nodes of the cycle
+    /// have been taken.
+    fn pop(&self) -> Option<usize> {
+        let nb_nodes = self.successors.len();
+        let mut spins = 0;
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if head >= nb_nodes {
+                return None;
+            }
+            if head >= self.tail.load(Ordering::Acquire) {
+                //Nodes still to execute are waiting for their predecessors
+                backoff(&mut spins);
+                continue;
+            }
+            if self
+                .head
+                .compare_exchange_weak(head, head + 1, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                //The node may have been reserved but not written yet by the thread pushing it
+                loop {
+                    let node = self.ready[head].load(Ordering::Acquire);
+                    if node != 0 {
+                        return Some(node - 1);
+                    }
+                    backoff(&mut spins);
+                }
+            }
+        }
+    }
+
+    /// Execute ready nodes until all the nodes of the cycle have been taken
+    fn execute(&self, worker: usize) {
+        while let Some(node) = self.pop() {
+            let task = unsafe { &*(*self.task.get()).expect("No task for the cycle") };
+            task(worker, node);
+            for &successor in self.successors[node].iter() {
+                if self.dependencies[successor].fetch_sub(1, Ordering::AcqRel) == 1 {
+                    self.push(successor);
+                }
+            }
+            self.remaining.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// Executor of a dependency graph on a fixed pool of threads.
+/// Nodes are identified by their index in `successors`.
+pub struct ParallelExecutor {
+    shared: Arc<Shared>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl ParallelExecutor {
+    /// Start `nb_threads - 1` worker threads: the thread calling `run` also executes nodes.
+    /// `successors` must describe an acyclic graph.
+    pub fn new(nb_threads: usize, successors: Vec<Vec<usize>>) -> ParallelExecutor {
+        assert!(nb_threads >= 1);
+        let nb_nodes = successors.len();
+        let mut nb_predecessors = vec![0; nb_nodes];
+        for node_successors in successors.iter() {
+            for &successor in node_successors.iter() {
+                nb_predecessors[successor] += 1;
+            }
+        }
+        let roots = (0..nb_nodes)
+            .filter(|&node| nb_predecessors[node] == 0)
+            .collect::<Vec<_>>();
+        assert!(nb_nodes == 0 || !roots.is_empty());
+
+        let shared = Arc::new(Shared {
+            successors,
+            nb_predecessors,
+            roots,
+            dependencies: (0..nb_nodes).map(|_| AtomicUsize::new(0)).collect(),
+            ready: (0..nb_nodes).map(|_| AtomicUsize::new(0)).collect(),
+            head: AtomicUsize::new(nb_nodes),
+            tail: AtomicUsize::new(nb_nodes),
+            remaining: AtomicUsize::new(0),
+            cycle: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+            task: UnsafeCell::new(None),
+        });
+
+        let workers = (1..nb_threads)
+            .map(|worker| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("audiograph-worker-{}", worker))
+                    .spawn(move || {
+                        let mut seen_cycle = 0;
+                        loop {
+                            let mut spins = 0;
+                            loop {
+                                if shared.stop.load(Ordering::Acquire) {
+                                    return;
+                                }
+                                let cycle = shared.cycle.load(Ordering::Acquire);
+                                if cycle != seen_cycle {
+                                    seen_cycle = cycle;
+                                    break;
+                                }
+                                if spins < SPIN_LIMIT {
+                                    spins += 1;
+                                    hint::spin_loop();
+                                } else {
+                                    thread::park();
+                                }
+                            }
+                            shared.execute(worker);
+                        }
+                    })
+                    .expect("Impossible to start a worker thread")
+            })
+            .collect();
+
+        ParallelExecutor { shared, workers }
+    }
+
+    /// Number of threads executing nodes, including the one calling `run`
+    pub fn nb_threads(&self) -> usize {
+        self.workers.len() + 1
+    }
+
+    pub fn nb_nodes(&self) -> usize {
+        self.shared.successors.len()
+    }
+
+    pub fn successors(&self) -> &[Vec<usize>] {
+        &self.shared.successors
+    }
+
+    /// Execute every node once, a node being executed only after all its predecessors.
+    /// `task(worker, node)` is called concurrently by several threads, on different nodes;
+    /// `worker` is 0 for the calling thread and is never shared by two threads at the same time.
+    pub fn run<'a, F: Fn(usize, usize) + Sync + 'a>(&self, task: &'a F) {
+        let shared = &*self.shared;
+        let nb_nodes = shared.successors.len();
+        if nb_nodes == 0 {
+            return;
+        }
+
+        //All the nodes of the previous cycle have been taken, so workers cannot pop anything
+        //until the roots are pushed.
+        let task: *const (dyn Fn(usize, usize) + Sync + 'a) = task;
+        unsafe {
+            //The lifetime of the task is erased, but it is not used any more after the end of `run`
+            *shared.task.get() = Some(mem::transmute::<
+                *const (dyn Fn(usize, usize) + Sync + 'a),
+                *const Task,
+            >(task));
+        }
+        for (dependencies, &nb) in shared
+            .dependencies
+            .iter()
+            .zip(shared.nb_predecessors.iter())
+        {
+            dependencies.store(nb, Ordering::Relaxed);
+        }
+        for ready in shared.ready.iter() {
+            ready.store(0, Ordering::Relaxed);
+        }
+        shared.remaining.store(nb_nodes, Ordering::Relaxed);
+        shared.tail.store(0, Ordering::Release);
+        shared.head.store(0, Ordering::Release);
+        for &root in shared.roots.iter() {
+            shared.push(root);
+        }
+
+        //Wake up the workers
+        shared.cycle.fetch_add(1, Ordering::AcqRel);
+        for worker in self.workers.iter() {
+            worker.thread().unpark();
+        }
+
+        shared.execute(0);
+        let mut spins = 0;
+        while shared.remaining.load(Ordering::Acquire) != 0 {
+            backoff(&mut spins);
+        }
+    }
+}
+
+impl Drop for ParallelExecutor {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        for worker in self.workers.iter() {
+            worker.thread().unpark();
+        }
+        for worker in self.workers.drain(..) {
+            worker.join().expect("Worker thread panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_dependencies_order() {
+        // Diamond 0 -> {1, 2} -> 3, plus an independent node 4
+        let executor = ParallelExecutor::new(3, vec![vec![1, 2], vec![3], vec![3], vec![], vec![]]);
+        for _ in 0..100 {
+            let order = Mutex::new(Vec::new());
+            executor.run(&|_worker, node| order.lock().unwrap().push(node));
+            let order = order.into_inner().unwrap();
+            assert_eq!(order.len(), 5);
+            let position = |node| order.iter().position(|&n| n == node).unwrap();
+            assert!(position(0) < position(1));
+            assert!(position(0) < position(2));
+            assert!(position(1) < position(3));
+            assert!(position(2) < position(3));
+        }
+    }
+}
@@ -1,18 +1,471 @@
 //! Parse a fileformat describing audiographs
 use pest::Parser;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io;
 
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use petgraph::graph::NodeIndex;
 
 use pest::error::Error as ParseError;
 
 use audiograph::*;
+use node_registry::{NodeRegistry, DEFAULT_REGISTRY};
 
-#[derive(Debug, Default)]
+/// Line and column in the text of an audiograph, starting at 1. Line 0 means that the position is unknown.
+/// `file` is the file the audiograph was read from, if any.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub file: Option<Arc<Path>>,
+}
+
+impl Position {
+    fn new(span: pest::Span, file: &Option<Arc<Path>>) -> Position {
+        let (line, column) = span.start_pos().line_col();
+        Position {
+            line,
+            column,
+            file: file.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An included file cannot be read or parsed. `position` is the include statement.
+#[derive(Debug)]
+pub struct IncludeError {
+    pub path: PathBuf,
+    pub error: AudiographError,
+    pub position: Position,
+}
+
+/// The pest error and the include error are boxed to keep the `Result`s of the parser small
+#[derive(Debug)]
+pub enum AudiographError {
+    Syntax(Box<ParseError<Rule>>),
+    Io(io::Error),
+    /// An edge refers to a node which is not defined
+    UnknownNode {
+        id: String,
+        position: Position,
+    },
+    /// An edge uses an outlet (resp. inlet) greater than the number of outlets (resp. inlets) of a node
+    PortOutOfRange {
+        node: String,
+        port: u32,
+        nb_ports: u32,
+        position: Position,
+    },
+    MissingAttribute {
+        node: String,
+        attribute: String,
+        position: Position,
+    },
+    InvalidAttribute {
+        node: String,
+        attribute: String,
+        value: String,
+        expected: &'static str,
+        position: Position,
+    },
+    /// The kind of node cannot be built from these attributes
+    UnsupportedNode {
+        node: String,
+        kind: String,
+        position: Position,
+    },
+    /// The node is part of a cycle
+    Cycle {
+        node: String,
+        position: Position,
+    },
+    /// Two nodes, or two sub-graphs, have the same id
+    DuplicateDefinition {
+        id: String,
+        position: Position,
+    },
+    /// A node is an instance of a sub-graph which is not defined
+    UndefinedSubgraph {
+        node: String,
+        subgraph: String,
+        position: Position,
+    },
+    /// A sub-graph contains an instance of itself, directly or not
+    RecursiveSubgraph {
+        node: String,
+        subgraph: String,
+        position: Position,
+    },
+    Include(Box<IncludeError>),
+    /// A file includes itself, directly or not
+    IncludeCycle {
+        path: PathBuf,
+        position: Position,
+    },
+    /// The inlets and outlets declared for a node are not the ones of its processor
+    PortCountMismatch {
+        node: String,
+        declared: (u32, u32),
+        expected: (u32, u32),
+        position: Position,
+    },
+    /// An inlet of a node is not connected to anything
+    UnconnectedInlet {
+        node: String,
+        port: u32,
+        position: Position,
+    },
+    /// All the problems found in an audiograph
+    Diagnostics(Vec<AudiographError>),
+    /// A constant is used before being defined with let
+    UndefinedConstant {
+        name: String,
+        position: Position,
+    },
+    /// An expression cannot be computed, for instance an addition of booleans
+    InvalidExpression {
+        expression: String,
+        reason: &'static str,
+        position: Position,
+    },
+}
+
+impl AudiographError {
+    /// Where the error is in the text of the audiograph
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            AudiographError::Syntax(ref error) => {
+                let (line, column) = match error.line_col {
+                    pest::error::LineColLocation::Pos(pos) => pos,
+                    pest::error::LineColLocation::Span(start, _) => start,
+                };
+                Some(Position {
+                    line,
+                    column,
+                    file: None,
+                })
+            }
+            AudiographError::Io(_) => None,
+            AudiographError::Diagnostics(ref errors) => {
+                errors.iter().filter_map(|e| e.position()).next()
+            }
+            //Syntax errors are located in the included file
+            AudiographError::Include(ref include) if include.error.position().is_some() => {
+                include.error.position().map(|position| Position {
+                    file: Some(Arc::from(include.path.as_path())),
+                    ..position
+                })
+            }
+            AudiographError::Include(ref include) => {
+                if include.position.line > 0 {
+                    Some(include.position.clone())
+                } else {
+                    None
+                }
+            }
+            AudiographError::UnknownNode { ref position, .. }
+            | AudiographError::PortOutOfRange { ref position, .. }
+            | AudiographError::MissingAttribute { ref position, .. }
+            | AudiographError::InvalidAttribute { ref position, .. }
+            | AudiographError::UnsupportedNode { ref position, .. }
+            | AudiographError::Cycle { ref position, .. }
+            | AudiographError::DuplicateDefinition { ref position, .. }
+            | AudiographError::UndefinedSubgraph { ref position, .. }
+            | AudiographError::RecursiveSubgraph { ref position, .. }
+            | AudiographError::IncludeCycle { ref position, .. }
+            | AudiographError::PortCountMismatch { ref position, .. }
+            | AudiographError::UnconnectedInlet { ref position, .. }
+            | AudiographError::UndefinedConstant { ref position, .. }
+            | AudiographError::InvalidExpression { ref position, .. } => {
+                if position.line > 0 {
+                    Some(position.clone())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for AudiographError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            //The pest error already shows the position
+            AudiographError::Syntax(ref error) => return write!(f, "{}", error),
+            AudiographError::Io(ref error) => return write!(f, "{}", error),
+            AudiographError::Include(ref include) => {
+                if include.position.line > 0 {
+                    write!(f, "{}: ", include.position)?;
+                }
+                return write!(
+                    f,
+                    "cannot include {}: {}",
+                    include.path.display(),
+                    include.error
+                );
+            }
+            AudiographError::Diagnostics(ref errors) => {
+                write!(f, "{} problems in the audiograph", errors.len())?;
+                for error in errors.iter() {
+                    write!(f, "\n{}", error)?;
+                }
+                return Ok(());
+            }
+            _ => (),
+        }
+        if let Some(position) = self.position() {
+            write!(f, "{}: ", position)?;
+        }
+        match *self {
+            AudiographError::UnknownNode { ref id, .. } => write!(f, "undefined node {}", id),
+            AudiographError::PortOutOfRange {
+                ref node,
+                port,
+                nb_ports,
+                ..
+            } => write!(
+                f,
+                "port {} of node {} does not exist: ports go from 1 to {}",
+                port, node, nb_ports
+            ),
+            AudiographError::MissingAttribute {
+                ref node,
+                ref attribute,
+                ..
+            } => write!(f, "node {} needs attribute {}", node, attribute),
+            AudiographError::InvalidAttribute {
+                ref node,
+                ref attribute,
+                ref value,
+                expected,
+                ..
+            } => write!(
+                f,
+                "attribute {} of node {} is {} but must be {}",
+                attribute, node, value, expected
+            ),
+            AudiographError::UnsupportedNode {
+                ref node, ref kind, ..
+            } => write!(f, "node {} of kind {} is not supported", node, kind),
+            AudiographError::Cycle { ref node, .. } => {
+                write!(f, "node {} is part of a cycle", node)
+            }
+            AudiographError::DuplicateDefinition { ref id, .. } => {
+                write!(f, "{} is defined several times", id)
+            }
+            AudiographError::UndefinedSubgraph {
+                ref node,
+                ref subgraph,
+                ..
+            } => write!(f, "node {} uses undefined sub-graph {}", node, subgraph),
+            AudiographError::RecursiveSubgraph {
+                ref node,
+                ref subgraph,
+                ..
+            } => write!(
+                f,
+                "node {} instantiates sub-graph {} inside itself",
+                node, subgraph
+            ),
+            AudiographError::IncludeCycle { ref path, .. } => {
+                write!(f, "{} includes itself", path.display())
+            }
+            AudiographError::PortCountMismatch {
+                ref node,
+                declared,
+                expected,
+                ..
+            } => write!(
+                f,
+                "node {} declares {} inlets and {} outlets but has {} inlets and {} outlets",
+                node, declared.0, declared.1, expected.0, expected.1
+            ),
+            AudiographError::UnconnectedInlet { ref node, port, .. } => {
+                write!(f, "inlet {} of node {} is not connected", port, node)
+            }
+            AudiographError::UndefinedConstant { ref name, .. } => {
+                write!(f, "undefined constant {}", name)
+            }
+            AudiographError::InvalidExpression {
+                ref expression,
+                reason,
+                ..
+            } => write!(f, "invalid expression {}: {}", expression, reason),
+            AudiographError::Syntax(_)
+            | AudiographError::Io(_)
+            | AudiographError::Include(_)
+            | AudiographError::Diagnostics(_) => unreachable!(),
+        }
+    }
+}
+
+impl Error for AudiographError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            AudiographError::Syntax(ref error) => Some(error.as_ref()),
+            AudiographError::Io(ref error) => Some(error),
+            AudiographError::Include(ref include) => Some(&include.error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError<Rule>> for AudiographError {
+    fn from(error: ParseError<Rule>) -> AudiographError {
+        AudiographError::Syntax(Box::new(error))
+    }
+}
+
+impl From<io::Error> for AudiographError {
+    fn from(error: io::Error) -> AudiographError {
+        AudiographError::Io(error)
+    }
+}
+
+/// Value of an attribute or of a constant
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+}
+
+impl Value {
+    fn number(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(i) => Some(i as f64),
+            Value::Float(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn negate(&self) -> Option<Value> {
+        match *self {
+            Value::Integer(i) => Some(
+                i.checked_neg()
+                    .map_or(Value::Float(-(i as f64)), Value::Integer),
+            ),
+            Value::Float(x) => Some(Value::Float(-x)),
+            _ => None,
+        }
+    }
+
+    /// `operator` is one of the rules add, sub, mul and div. Operations on integers give integers,
+    /// except for divisions and overflows.
+    fn arithmetic(&self, operator: Rule, rhs: &Value) -> Option<Value> {
+        if let (&Value::Integer(a), &Value::Integer(b)) = (self, rhs) {
+            let result = match operator {
+                Rule::add => a.checked_add(b),
+                Rule::sub => a.checked_sub(b),
+                Rule::mul => a.checked_mul(b),
+                _ => None,
+            };
+            if let Some(result) = result {
+                return Some(Value::Integer(result));
+            }
+        }
+        let (a, b) = (self.number()?, rhs.number()?);
+        let result = match operator {
+            Rule::add => a + b,
+            Rule::sub => a - b,
+            Rule::mul => a * b,
+            Rule::div => a / b,
+            _ => unreachable!(),
+        };
+        Some(Value::Float(result))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Integer(i) => write!(f, "{}", i),
+            //Debug always shows a dot or an exponent
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::String(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Conversion of the value of an attribute to the type a node expects.
+/// Strings are parsed, so that quoted numbers are still accepted.
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+macro_rules! from_value_integer {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Option<$t> {
+                match *value {
+                    Value::Integer(i) => <$t>::try_from(i).ok(),
+                    //Beyond 2^53, floats are not exact integers anymore
+                    Value::Float(x) if x.fract() == 0. && x.abs() < 9_007_199_254_740_992. => {
+                        <$t>::try_from(x as i64).ok()
+                    }
+                    Value::String(ref s) => s.parse().ok(),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+from_value_integer!(i32, i64, u32, usize);
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Option<f64> {
+        match *value {
+            Value::String(ref s) => s.parse().ok(),
+            _ => value.number(),
+        }
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: &Value) -> Option<f32> {
+        match *value {
+            Value::String(ref s) => s.parse().ok(),
+            _ => value.number().map(|x| x as f32),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Option<bool> {
+        match *value {
+            Value::Boolean(b) => Some(b),
+            Value::String(ref s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Option<String> {
+        Some(value.to_string())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct Node {
     pub id: String,
     pub nb_inlets: u32,
@@ -20,8 +473,11 @@ pub struct Node {
     pub class_name: String,
     pub text: Option<String>,
     pub wcet: Option<f64>,
-    pub more: HashMap<String, String>,
+    /// The other attributes
+    pub more: HashMap<String, Value>,
     pub volume: f32,
+    /// Where the node is defined
+    pub position: Position,
 }
 
 impl Node {
@@ -35,138 +491,973 @@ impl Node {
             wcet: None,
             volume: 1.,
             more: HashMap::new(),
+            position: Position::default(),
         }
     }
+
+    /// Check that the node declares `nb_inlets` inlets and `nb_outlets` outlets
+    pub fn check_ports(&self, nb_inlets: u32, nb_outlets: u32) -> Result<(), AudiographError> {
+        if (self.nb_inlets, self.nb_outlets) == (nb_inlets, nb_outlets) {
+            Ok(())
+        } else {
+            Err(AudiographError::PortCountMismatch {
+                node: self.id.clone(),
+                declared: (self.nb_inlets, self.nb_outlets),
+                expected: (nb_inlets, nb_outlets),
+                position: self.position.clone(),
+            })
+        }
+    }
+
+    /// Value of attribute `name` in `more`. `expected` describes the values it can take.
+    pub fn attribute<T: FromValue>(
+        &self,
+        name: &str,
+        expected: &'static str,
+    ) -> Result<T, AudiographError> {
+        let value = self
+            .more
+            .get(name)
+            .ok_or_else(|| AudiographError::MissingAttribute {
+                node: self.id.clone(),
+                attribute: name.to_string(),
+                position: self.position.clone(),
+            })?;
+        T::from_value(value).ok_or_else(|| AudiographError::InvalidAttribute {
+            node: self.id.clone(),
+            attribute: name.to_string(),
+            value: value.to_string(),
+            expected,
+            position: self.position.clone(),
+        })
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Edge {
     src_id: String,
     src_port: u32,
+    src_position: Position,
     dst_id: String,
     dst_port: u32,
+    dst_position: Position,
+}
+
+/// Node, port and position of a port_ident
+type PortIdent = (String, u32, Position);
+
+/// A named sub-graph, instantiated by nodes of kind `@name`:
+///
+/// ```text
+/// graph chain {
+///     inlets = c.1;
+///     outlets = f.1;
+///     c = {kind : "compressor", in : 1, out : 1, ...};
+///     f = {kind : "freeverb", in : 1, out : 1, ...};
+///     c.1 -> f.1;
+/// };
+/// fx1 = {kind : "@chain"};
+/// ```
+///
+/// Inlet (resp. outlet) `i` of an instance is the `i`-th port listed in `inlets` (resp. `outlets`).
+/// The nodes of instance `fx1` get ids prefixed by `fx1-`.
+#[derive(Debug)]
+struct Subgraph {
+    position: Position,
+    /// Canonical path of the included file defining the sub-graph. None if it is not defined in an included file.
+    origin: Option<PathBuf>,
+    inlets: Vec<PortIdent>,
+    outlets: Vec<PortIdent>,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+/// Exposed ports of an instance of a sub-graph, as ports of the expanded nodes
+struct Instance {
+    inlets: Vec<(String, u32)>,
+    outlets: Vec<(String, u32)>,
+}
+
+/// Port of an expanded node for port `port` of node `id` in a scope
+fn resolve_port(
+    instances: &HashMap<String, Instance>,
+    prefix: &str,
+    id: &str,
+    port: u32,
+    position: &Position,
+    outlet: bool,
+) -> Result<(String, u32), AudiographError> {
+    match instances.get(id) {
+        Some(instance) => {
+            let ports = if outlet {
+                &instance.outlets
+            } else {
+                &instance.inlets
+            };
+            (port as usize)
+                .checked_sub(1)
+                .and_then(|i| ports.get(i))
+                .cloned()
+                .ok_or_else(|| AudiographError::PortOutOfRange {
+                    node: format!("{}{}", prefix, id),
+                    port,
+                    nb_ports: ports.len() as u32,
+                    position: position.clone(),
+                })
+        }
+        None => Ok((format!("{}{}", prefix, id), port)),
+    }
+}
+
+/// Replace the instances of sub-graphs among `nodes` by the nodes they contain, with ids prefixed by `prefix`,
+/// and add the nodes and edges to `flat_nodes` and `flat_edges`.
+/// `stack` contains the sub-graphs being expanded, to detect recursive definitions.
+/// Returns the instances in this scope.
+fn expand_subgraphs(
+    nodes: &[Node],
+    edges: &[Edge],
+    prefix: &str,
+    subgraphs: &HashMap<String, Subgraph>,
+    stack: &mut Vec<String>,
+    flat_nodes: &mut Vec<Node>,
+    flat_edges: &mut Vec<Edge>,
+) -> Result<HashMap<String, Instance>, AudiographError> {
+    let mut instances = HashMap::new();
+    let mut ids = HashSet::new();
+
+    for node in nodes.iter() {
+        if !ids.insert(node.id.as_str()) {
+            return Err(AudiographError::DuplicateDefinition {
+                id: format!("{}{}", prefix, node.id),
+                position: node.position.clone(),
+            });
+        }
+        if !node.class_name.starts_with('@') {
+            let mut node = node.clone();
+            node.id = format!("{}{}", prefix, node.id);
+            flat_nodes.push(node);
+            continue;
+        }
+
+        let name = &node.class_name[1..];
+        let subgraph = subgraphs
+            .get(name)
+            .ok_or_else(|| AudiographError::UndefinedSubgraph {
+                node: node.id.clone(),
+                subgraph: name.to_string(),
+                position: node.position.clone(),
+            })?;
+        if stack.iter().any(|s| s == name) {
+            return Err(AudiographError::RecursiveSubgraph {
+                node: node.id.clone(),
+                subgraph: name.to_string(),
+                position: node.position.clone(),
+            });
+        }
+        //in and out are optional but must match the exposed ports
+        let check_count = |attribute: &str, count: u32, nb_ports: usize| {
+            if count == 0 || count as usize == nb_ports {
+                Ok(())
+            } else {
+                Err(AudiographError::InvalidAttribute {
+                    node: node.id.clone(),
+                    attribute: attribute.to_string(),
+                    value: count.to_string(),
+                    expected: "the number of exposed ports of the sub-graph",
+                    position: node.position.clone(),
+                })
+            }
+        };
+        check_count("in", node.nb_inlets, subgraph.inlets.len())?;
+        check_count("out", node.nb_outlets, subgraph.outlets.len())?;
+
+        stack.push(name.to_string());
+        let inner_prefix = format!("{}{}-", prefix, node.id);
+        let inner_instances = expand_subgraphs(
+            &subgraph.nodes,
+            &subgraph.edges,
+            &inner_prefix,
+            subgraphs,
+            stack,
+            flat_nodes,
+            flat_edges,
+        )?;
+        stack.pop();
+
+        let exposed = |ports: &[PortIdent], outlet: bool| {
+            ports
+                .iter()
+                .map(|&(ref id, port, ref position)| {
+                    resolve_port(&inner_instances, &inner_prefix, id, port, position, outlet)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let instance = Instance {
+            inlets: exposed(&subgraph.inlets, false)?,
+            outlets: exposed(&subgraph.outlets, true)?,
+        };
+        instances.insert(node.id.clone(), instance);
+    }
+
+    for edge in edges.iter() {
+        let (src_id, src_port) = resolve_port(
+            &instances,
+            prefix,
+            &edge.src_id,
+            edge.src_port,
+            &edge.src_position,
+            true,
+        )?;
+        let (dst_id, dst_port) = resolve_port(
+            &instances,
+            prefix,
+            &edge.dst_id,
+            edge.dst_port,
+            &edge.dst_position,
+            false,
+        )?;
+        flat_edges.push(Edge {
+            src_id,
+            src_port,
+            dst_id,
+            dst_port,
+            ..edge.clone()
+        });
+    }
+
+    Ok(instances)
 }
 
 #[derive(Parser)]
 #[grammar = "audiograph.pest"]
 pub struct AudiographParser;
 
-pub fn parse_audiograph(
+/// Statements of an .ag file, with the ones of the files it includes
+#[derive(Default)]
+struct Statements {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    subgraphs: HashMap<String, Subgraph>,
+    deadline: Option<f64>,
+}
+
+/// Parse the statements of `audiograph`, read from `file` if any.
+/// `includes` are the canonical paths of the files being included, to detect include cycles.
+fn parse_statements(
     audiograph: &str,
-    buffer_size: usize,
-    nb_channels: usize,
-    samplerate: u32,
-) -> Result<AudioGraph, ParseError<Rule>> {
-    let audiograph = AudiographParser::parse(Rule::file, audiograph)?
+    file: Option<Arc<Path>>,
+    includes: &mut Vec<PathBuf>,
+) -> Result<Statements, AudiographError> {
+    let audiograph = AudiographParser::parse(Rule::file, audiograph)
+        .map_err(|error| match file {
+            Some(ref file) => error.with_path(&file.to_string_lossy()),
+            None => error,
+        })?
         .next()
         .unwrap();
 
     use pest::iterators::*;
 
-    fn parse_node(pair: Pair<Rule>) -> Node {
-        let mut inner_rules = pair.into_inner();
+    /// Value of a string or of an expression, which can use the constants defined so far
+    fn evaluate(
+        pair: Pair<Rule>,
+        constants: &HashMap<String, Value>,
+        file: &Option<Arc<Path>>,
+    ) -> Result<Value, AudiographError> {
+        let expression = pair.as_str();
+        let position = Position::new(pair.as_span(), file);
+        let invalid = |reason| AudiographError::InvalidExpression {
+            expression: expression.to_string(),
+            reason,
+            position: position.clone(),
+        };
+        match pair.as_rule() {
+            Rule::string => Ok(Value::String(unescape(
+                pair.into_inner().next().unwrap().as_str(),
+            ))),
+            Rule::fnumber => Ok(Value::Float(expression.parse().unwrap())),
+            //Too large integers become floats
+            Rule::inumber => Ok(expression
+                .parse()
+                .map(Value::Integer)
+                .unwrap_or_else(|_| Value::Float(expression.parse().unwrap()))),
+            Rule::boolean => Ok(Value::Boolean(expression == "true")),
+            Rule::constant => constants.get(expression).cloned().ok_or_else(|| {
+                AudiographError::UndefinedConstant {
+                    name: expression.to_string(),
+                    position: position.clone(),
+                }
+            }),
+            Rule::expression => evaluate(pair.into_inner().next().unwrap(), constants, file),
+            Rule::unary => {
+                let mut inner_rules = pair.into_inner().collect::<Vec<_>>();
+                let value = evaluate(inner_rules.pop().unwrap(), constants, file)?;
+                let nb_minus = inner_rules.iter().filter(|s| s.as_str() == "-").count();
+                if inner_rules.is_empty() {
+                    Ok(value)
+                } else if value.number().is_none() {
+                    Err(invalid("only numbers have a sign"))
+                } else if nb_minus % 2 == 1 {
+                    Ok(value.negate().unwrap())
+                } else {
+                    Ok(value)
+                }
+            }
+            Rule::sum | Rule::product => {
+                let mut inner_rules = pair.into_inner();
+                let mut value = evaluate(inner_rules.next().unwrap(), constants, file)?;
+                while let Some(operator) = inner_rules.next() {
+                    let rhs = evaluate(inner_rules.next().unwrap(), constants, file)?;
+                    value = value
+                        .arithmetic(operator.as_rule(), &rhs)
+                        .ok_or_else(|| invalid("arithmetic is only defined on numbers"))?;
+                }
+                Ok(value)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse_node(
+        pair: Pair<Rule>,
+        constants: &HashMap<String, Value>,
+        file: &Option<Arc<Path>>,
+    ) -> Result<Node, AudiographError> {
         let mut node = Node::new();
+        node.position = Position::new(pair.as_span(), file);
+        let mut inner_rules = pair.into_inner();
         node.id = inner_rules.next().unwrap().as_str().to_string();
         //Attributes
         for attribute in inner_rules {
+            let position = Position::new(attribute.as_span(), file);
             let mut attr = attribute.into_inner();
-            let id = attr.next().unwrap().as_str().trim_matches('\"');
-            let v = attr.next().unwrap().as_str().trim_matches('\"');
+            let id = attr.next().unwrap().as_str();
+            let v = evaluate(attr.next().unwrap(), constants, file)?;
+            fn parse_value<T: FromValue>(
+                node: &Node,
+                id: &str,
+                v: &Value,
+                expected: &'static str,
+                position: Position,
+            ) -> Result<T, AudiographError> {
+                T::from_value(v).ok_or_else(|| AudiographError::InvalidAttribute {
+                    node: node.id.clone(),
+                    attribute: id.to_string(),
+                    value: v.to_string(),
+                    expected,
+                    position,
+                })
+            }
             match id {
-                "in" => node.nb_inlets = v.parse().unwrap(),
-                "out" => node.nb_outlets = v.parse().unwrap(),
+                "in" => node.nb_inlets = parse_value(&node, id, &v, "an integer", position)?,
+                "out" => node.nb_outlets = parse_value(&node, id, &v, "an integer", position)?,
                 "text" => node.text = Some(v.to_string()),
                 "kind" => node.class_name = v.to_string(),
-                "wcet" => node.wcet = Some(v.parse().unwrap()),
-                "volume" => node.volume = v.parse().unwrap(),
+                "wcet" => node.wcet = Some(parse_value(&node, id, &v, "a float", position)?),
+                "volume" => node.volume = parse_value(&node, id, &v, "a float", position)?,
                 _ => {
-                    node.more.insert(id.to_string(), v.to_string());
+                    node.more.insert(id.to_string(), v);
                 }
             }
         }
-        node
+        Ok(node)
     }
 
     use std::vec::IntoIter;
 
-    fn parse_edge(pair: Pair<Rule>) -> IntoIter<Edge> {
+    /// Node and port of a port_ident
+    fn parse_port_ident(pair: Pair<Rule>, file: &Option<Arc<Path>>) -> PortIdent {
+        let position = Position::new(pair.as_span(), file);
+        let mut port_ident = pair.into_inner();
+        let id = port_ident.next().unwrap().as_str().to_string();
+        //Too large to be a port anyway
+        let port = port_ident
+            .next()
+            .unwrap()
+            .as_str()
+            .parse()
+            .unwrap_or(u32::max_value());
+        (id, port, position)
+    }
+
+    fn parse_edge(pair: Pair<Rule>, file: &Option<Arc<Path>>) -> IntoIter<Edge> {
         let mut inner_rules = pair.into_inner();
-        let mut port_ident = inner_rules.next().unwrap().into_inner();
-        let mut src_id = port_ident.next().unwrap().as_str().to_string();
-        let mut src_port = port_ident.next().unwrap().as_str().parse().unwrap();
+        let (mut src_id, mut src_port, mut src_position) =
+            parse_port_ident(inner_rules.next().unwrap(), file);
 
         let mut edges = Vec::new();
 
         for inner_rule in inner_rules {
-            port_ident = inner_rule.into_inner().next().unwrap().into_inner();
-            let dst_id = port_ident.next().unwrap().as_str().to_string();
-            let dst_port = port_ident.next().unwrap().as_str().parse().unwrap();
+            let (dst_id, dst_port, dst_position) =
+                parse_port_ident(inner_rule.into_inner().next().unwrap(), file);
             edges.push(Edge {
                 src_id,
                 src_port,
+                src_position,
                 dst_id: dst_id.clone(),
                 dst_port,
+                dst_position: dst_position.clone(),
             });
             src_id = dst_id;
             src_port = dst_port;
+            src_position = dst_position;
         }
         edges.into_iter()
     }
 
-    let (nodes, edges): (Vec<_>, Vec<_>) = audiograph
-        .into_inner()
-        .flat_map(|r| r.into_inner())
-        .filter(|ref r| r.as_rule() != Rule::deadline)
-        //.inspect(|x| println!("Statement: {:?}.", x))
-        .partition(|ref r| r.as_rule() == Rule::node);
+    fn parse_deadline(
+        pair: Pair<Rule>,
+        constants: &HashMap<String, Value>,
+        file: &Option<Arc<Path>>,
+    ) -> Result<f64, AudiographError> {
+        let expression = pair.into_inner().next().unwrap();
+        let (text, position) = (
+            expression.as_str(),
+            Position::new(expression.as_span(), file),
+        );
+        let deadline = evaluate(expression, constants, file)?;
+        f64::from_value(&deadline).ok_or_else(|| AudiographError::InvalidExpression {
+            expression: text.to_string(),
+            reason: "a deadline is a number",
+            position,
+        })
+    }
+
+    fn parse_subgraph(
+        pair: Pair<Rule>,
+        constants: &HashMap<String, Value>,
+        file: &Option<Arc<Path>>,
+    ) -> Result<(String, Subgraph), AudiographError> {
+        let position = Position::new(pair.as_span(), file);
+        let mut inner_rules = pair.into_inner().skip(1);
+        let name = inner_rules.next().unwrap().as_str().to_string();
+        let mut subgraph = Subgraph {
+            position,
+            origin: None,
+            inlets: Vec::new(),
+            outlets: Vec::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        for statement in inner_rules.flat_map(|r| r.into_inner()) {
+            match statement.as_rule() {
+                Rule::inlets | Rule::outlets => {
+                    let rule = statement.as_rule();
+                    let ports = statement
+                        .into_inner()
+                        .next()
+                        .unwrap()
+                        .into_inner()
+                        .map(|pair| parse_port_ident(pair, file));
+                    if rule == Rule::inlets {
+                        subgraph.inlets.extend(ports);
+                    } else {
+                        subgraph.outlets.extend(ports);
+                    }
+                }
+                Rule::node => subgraph.nodes.push(parse_node(statement, constants, file)?),
+                Rule::edges => subgraph.edges.extend(parse_edge(statement, file)),
+                _ => unreachable!(),
+            }
+        }
+        Ok((name, subgraph))
+    }
+
+    /// Statements of the included file, with ids prefixed by the name after `as`,
+    /// or by the name of the file
+    fn parse_include(
+        pair: Pair<Rule>,
+        file: &Option<Arc<Path>>,
+        includes: &mut Vec<PathBuf>,
+    ) -> Result<Statements, AudiographError> {
+        let position = Position::new(pair.as_span(), file);
+        let mut inner_rules = pair.into_inner().skip(1);
+        let name = unescape(inner_rules.next().unwrap().into_inner().as_str());
+        //Relative to the including file
+        let path = match *file {
+            Some(ref file) => file.parent().unwrap_or(Path::new("")).join(&name),
+            None => PathBuf::from(&name),
+        };
+        let prefix = match inner_rules.nth(1) {
+            Some(ident) => ident.as_str().to_string(),
+            None => path
+                .file_stem()
+                .map_or(name.clone(), |stem| stem.to_string_lossy().into_owned()),
+        };
+
+        let include_error = |error: AudiographError| {
+            AudiographError::Include(Box::new(IncludeError {
+                path: path.clone(),
+                error,
+                position: position.clone(),
+            }))
+        };
+        let canonical_path = path
+            .canonicalize()
+            .map_err(|error| include_error(error.into()))?;
+        if includes.contains(&canonical_path) {
+            return Err(AudiographError::IncludeCycle { path, position });
+        }
+        let mut text = String::new();
+        File::open(&path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|error| include_error(error.into()))?;
+
+        includes.push(canonical_path);
+        let mut statements = parse_statements(&text, Some(Arc::from(path.as_path())), includes)
+            .map_err(|error| match error {
+                AudiographError::Syntax(_) => include_error(error),
+                error => error,
+            })?;
+        let canonical_path = includes.pop();
+        for subgraph in statements.subgraphs.values_mut() {
+            if subgraph.origin.is_none() {
+                subgraph.origin = canonical_path.clone();
+            }
+        }
+
+        let prefixed = |id: &str| format!("{}-{}", prefix, id);
+        let nodes = statements
+            .nodes
+            .into_iter()
+            .map(|node| Node {
+                id: prefixed(&node.id),
+                ..node
+            })
+            .collect();
+        let edges = statements
+            .edges
+            .into_iter()
+            .map(|edge| Edge {
+                src_id: prefixed(&edge.src_id),
+                dst_id: prefixed(&edge.dst_id),
+                ..edge
+            })
+            .collect();
+        //The deadline is the one of the including file
+        Ok(Statements {
+            nodes,
+            edges,
+            subgraphs: statements.subgraphs,
+            deadline: None,
+        })
+    }
+
+    /// Sub-graphs are not prefixed. A file included several times, for instance by two included files,
+    /// defines its sub-graphs only once.
+    fn add_subgraph(
+        statements: &mut Statements,
+        name: String,
+        subgraph: Subgraph,
+    ) -> Result<(), AudiographError> {
+        if let Some(defined) = statements.subgraphs.get(&name) {
+            if defined.origin.is_some() && defined.origin == subgraph.origin {
+                return Ok(());
+            }
+            return Err(AudiographError::DuplicateDefinition {
+                id: name,
+                position: subgraph.position,
+            });
+        }
+        statements.subgraphs.insert(name, subgraph);
+        Ok(())
+    }
+
+    let mut statements = Statements::default();
+    //Constants are local to a file
+    let mut constants = HashMap::new();
+    for statement in audiograph.into_inner().flat_map(|r| r.into_inner()) {
+        match statement.as_rule() {
+            Rule::let_statement => {
+                let position = Position::new(statement.as_span(), &file);
+                let mut inner_rules = statement.into_inner().skip(1);
+                let name = inner_rules.next().unwrap().as_str().to_string();
+                let value = evaluate(inner_rules.next().unwrap(), &constants, &file)?;
+                if constants.insert(name.clone(), value).is_some() {
+                    return Err(AudiographError::DuplicateDefinition { id: name, position });
+                }
+            }
+            //If there are several deadlines, the last one wins
+            Rule::deadline => {
+                statements.deadline = Some(parse_deadline(statement, &constants, &file)?)
+            }
+            Rule::node => statements
+                .nodes
+                .push(parse_node(statement, &constants, &file)?),
+            Rule::edges => statements.edges.extend(parse_edge(statement, &file)),
+            Rule::subgraph => {
+                let (name, subgraph) = parse_subgraph(statement, &constants, &file)?;
+                add_subgraph(&mut statements, name, subgraph)?;
+            }
+            Rule::include => {
+                let included = parse_include(statement, &file, includes)?;
+                statements.nodes.extend(included.nodes);
+                statements.edges.extend(included.edges);
+                for (name, subgraph) in included.subgraphs.into_iter() {
+                    add_subgraph(&mut statements, name, subgraph)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(statements)
+}
 
-    let nodes = nodes.into_iter().map(parse_node).collect::<Vec<_>>();
-    let edges = edges.into_iter().flat_map(parse_edge).collect::<Vec<_>>();
+/// Build an audiograph from the statements of an .ag file, and check it.
+/// If there are several problems, they are all returned in `AudiographError::Diagnostics`.
+fn build_audiograph(
+    statements: Statements,
+    registry: &NodeRegistry,
+    buffer_size: usize,
+    nb_channels: usize,
+    samplerate: u32,
+) -> Result<AudioGraph, AudiographError> {
+    //Sub-graphs are expanded into a flat list of nodes and edges
+    let (mut nodes, mut edges) = (Vec::new(), Vec::new());
+    expand_subgraphs(
+        &statements.nodes,
+        &statements.edges,
+        "",
+        &statements.subgraphs,
+        &mut Vec::new(),
+        &mut nodes,
+        &mut edges,
+    )?;
     let mut node_indexes: HashMap<String, NodeIndex> = HashMap::new();
+    let mut node_ports: HashMap<String, (u32, u32)> = HashMap::new();
 
     let mut audiograph = AudioGraph::new(buffer_size as u32, nb_channels as u32, samplerate);
+    if let Some(deadline) = statements.deadline {
+        audiograph.set_deadline(deadline);
+    }
 
     let mut has_sink = false;
+    //Problems are collected instead of stopping at the first one
+    let mut diagnostics = Vec::new();
 
     for node_infos in nodes.into_iter() {
         let id = node_infos.id.clone();
+        //Prefixed ids of nodes in sub-graphs could clash with top-level ids
+        if node_ports.contains_key(&id) {
+            diagnostics.push(AudiographError::DuplicateDefinition {
+                id,
+                position: node_infos.position,
+            });
+            continue;
+        }
         if node_infos.class_name == "source" || node_infos.class_name == "sink" {
             has_sink = true
         }
-        let node = DspNode::new(node_infos, nb_channels);
-        let node_index = audiograph.add_node(node);
-        node_indexes.insert(id, node_index);
+        node_ports.insert(id.clone(), (node_infos.nb_inlets, node_infos.nb_outlets));
+        match registry.create_node(node_infos, nb_channels) {
+            Ok(node) => {
+                let node_index = audiograph.add_node(node);
+                node_indexes.insert(id, node_index);
+            }
+            Err(error) => diagnostics.push(error),
+        }
     }
 
+    //Ports are checked against the declared ports, even for nodes which could not be built
+    let check_port = |id: &String, port: u32, position: &Position, outlet: bool| {
+        let (nb_inlets, nb_outlets) =
+            *node_ports
+                .get(id)
+                .ok_or_else(|| AudiographError::UnknownNode {
+                    id: id.clone(),
+                    position: position.clone(),
+                })?;
+        let nb_ports = if outlet { nb_outlets } else { nb_inlets };
+        if port < 1 || port > nb_ports {
+            return Err(AudiographError::PortOutOfRange {
+                node: id.clone(),
+                port,
+                nb_ports,
+                position: position.clone(),
+            });
+        }
+        Ok(node_indexes.get(id).cloned())
+    };
+
     for edge in edges.iter() {
-        let src_node = node_indexes[&edge.src_id];
-        let dst_node = node_indexes[&edge.dst_id];
-        audiograph.add_connection(src_node, edge.src_port, dst_node, edge.dst_port);
+        let src_node = check_port(&edge.src_id, edge.src_port, &edge.src_position, true);
+        let dst_node = check_port(&edge.dst_id, edge.dst_port, &edge.dst_position, false);
+        match (src_node, dst_node) {
+            (Ok(Some(src_node)), Ok(Some(dst_node))) => {
+                if audiograph
+                    .add_connection(src_node, edge.src_port, dst_node, edge.dst_port)
+                    .is_err()
+                {
+                    diagnostics.push(AudiographError::Cycle {
+                        node: edge.dst_id.clone(),
+                        position: edge.dst_position.clone(),
+                    });
+                }
+            }
+            (src_node, dst_node) => {
+                diagnostics.extend(src_node.err());
+                diagnostics.extend(dst_node.err());
+            }
+        }
+    }
+
+    if diagnostics.is_empty() {
+        //If the audio graph has not virtual sink, we autoconnect with whatever non connected ports it has, by setting only at false.
+        audiograph.autoconnect(has_sink);
+
+        //With a virtual sink, only the source and the sink are autoconnected.
+        //Inlets of nodes with a variable number of inlets, such as mixers, are optional.
+        for node in audiograph.graph.node_indices() {
+            let required_inlets = registry
+                .metadata(&audiograph.graph[node].node_infos().class_name)
+                .map_or(false, |metadata| metadata.nb_inlets.is_some());
+            if !required_inlets
+                || node == audiograph.source_node()
+                || node == audiograph.sink_node()
+            {
+                continue;
+            }
+            let connected = audiograph
+                .inputs(node)
+                .map(|edge| edge.weight().dst_port())
+                .collect::<HashSet<_>>();
+            let node_infos = audiograph.graph[node].node_infos();
+            for port in 1..=node_infos.nb_inlets {
+                if !connected.contains(&port) {
+                    diagnostics.push(AudiographError::UnconnectedInlet {
+                        node: node_infos.id.clone(),
+                        port,
+                        position: node_infos.position.clone(),
+                    });
+                }
+            }
+        }
     }
 
-    //If the audio graph has not virtual sink, we autoconnect with whatever non connected ports it has, by setting only at false.
-    audiograph.autoconnect(has_sink);
+    match diagnostics.len() {
+        0 => Ok(audiograph),
+        1 => Err(diagnostics.pop().unwrap()),
+        _ => Err(AudiographError::Diagnostics(diagnostics)),
+    }
+}
 
-    Ok(audiograph)
+/// Parse an audiograph with the built-in kinds of nodes. Included files are relative to the current directory.
+pub fn parse_audiograph(
+    audiograph: &str,
+    buffer_size: usize,
+    nb_channels: usize,
+    samplerate: u32,
+) -> Result<AudioGraph, AudiographError> {
+    parse_audiograph_with_registry(
+        audiograph,
+        &DEFAULT_REGISTRY,
+        buffer_size,
+        nb_channels,
+        samplerate,
+    )
+}
+
+/// Parse an audiograph whose kinds of nodes are in `registry`
+pub fn parse_audiograph_with_registry(
+    audiograph: &str,
+    registry: &NodeRegistry,
+    buffer_size: usize,
+    nb_channels: usize,
+    samplerate: u32,
+) -> Result<AudioGraph, AudiographError> {
+    let statements = parse_statements(audiograph, None, &mut Vec::new())?;
+    build_audiograph(statements, registry, buffer_size, nb_channels, samplerate)
 }
 
+/// Parse an audiograph from a file, with the built-in kinds of nodes. Included files are relative to the including file.
 pub fn parse_audiograph_from_file(
     filename: &str,
     buffer_size: usize,
     nb_channels: usize,
     samplerate: u32,
-) -> Result<AudioGraph, ParseError<Rule>> {
+) -> Result<AudioGraph, AudiographError> {
+    parse_audiograph_from_file_with_registry(
+        filename,
+        &DEFAULT_REGISTRY,
+        buffer_size,
+        nb_channels,
+        samplerate,
+    )
+}
+
+/// Parse an audiograph from a file, whose kinds of nodes are in `registry`
+pub fn parse_audiograph_from_file_with_registry(
+    filename: &str,
+    registry: &NodeRegistry,
+    buffer_size: usize,
+    nb_channels: usize,
+    samplerate: u32,
+) -> Result<AudioGraph, AudiographError> {
     let path = Path::new(filename);
-    let mut file = File::open(&path).expect("Impossible to open file.");
+    let mut file = File::open(&path)?;
     let mut s = String::new();
-    file.read_to_string(&mut s)
-        .expect("Impossible to read file.");
-    parse_audiograph(&s, buffer_size, nb_channels, samplerate)
+    file.read_to_string(&mut s)?;
+    let statements = parse_statements(&s, Some(Arc::from(path)), &mut vec![path.canonicalize()?])?;
+    build_audiograph(statements, registry, buffer_size, nb_channels, samplerate)
+}
+
+/// Escape a string so that it can be written between quotes in the .ag format
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Inverse of `escape`, for the content of a string rule
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        //The grammar only accepts valid escape sequences
+        match chars.next().unwrap() {
+            'b' => unescaped.push('\u{8}'),
+            'f' => unescaped.push('\u{c}'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            't' => unescaped.push('\t'),
+            'u' => {
+                let code = chars.by_ref().take(4).collect::<String>();
+                let code = u32::from_str_radix(&code, 16).unwrap();
+                unescaped.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Write a float so that it is parsed back to the same value.
+/// Infinite and NaN values cannot be written as expressions: they are written as strings.
+fn write_float<F: fmt::Debug + Into<f64> + Copy>(value: F) -> String {
+    if value.into().is_finite() {
+        format!("{:?}", value)
+    } else {
+        format!("\"{:?}\"", value)
+    }
+}
+
+fn write_value(value: &Value) -> String {
+    match *value {
+        Value::Float(x) => write_float(x),
+        Value::String(ref s) => format!("\"{}\"", escape(s)),
+        ref value => value.to_string(),
+    }
+}
+
+fn is_ident(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars.next().map_or(false, |c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Write an audiograph in the .ag format. All the fields of the nodes are written, so that
+/// parsing the result gives back the same graph.
+/// Nodes without a valid and unique id get a new one.
+/// The real source and sink are not written: they are connected again by `autoconnect` when parsing.
+pub fn write_audiograph(audiograph: &AudioGraph) -> String {
+    let mut ag = String::new();
+    let is_real_io =
+        |node: NodeIndex| node == audiograph.source_node() || node == audiograph.sink_node();
+    let nodes = audiograph
+        .graph
+        .node_indices()
+        .filter(|n| !is_real_io(*n))
+        .collect::<Vec<_>>();
+
+    let mut ids = HashMap::new();
+    let mut used_ids = HashSet::new();
+    //Valid ids are kept first, so that generated ones cannot clash with them
+    for &node in nodes.iter() {
+        let id = &audiograph.graph[node].node_infos().id;
+        if is_ident(id) && used_ids.insert(id.clone()) {
+            ids.insert(node, id.clone());
+        }
+    }
+    for &node in nodes.iter() {
+        if !ids.contains_key(&node) {
+            let mut id = format!("node{}", node.index());
+            while !used_ids.insert(id.clone()) {
+                id.push('-');
+            }
+            ids.insert(node, id);
+        }
+    }
+
+    for &node in nodes.iter() {
+        let node_infos = audiograph.graph[node].node_infos();
+        ag += &format!(
+            "{} = {{kind : \"{}\", in : {}, out : {},",
+            ids[&node],
+            escape(&node_infos.class_name),
+            node_infos.nb_inlets,
+            node_infos.nb_outlets
+        );
+        if let Some(ref text) = node_infos.text {
+            ag += &format!(" text : \"{}\",", escape(text));
+        }
+        if let Some(wcet) = node_infos.wcet {
+            ag += &format!(" wcet : {},", write_float(wcet));
+        }
+        ag += &format!(" volume : {},", write_float(node_infos.volume));
+        //Sorted to get a deterministic output
+        let mut more = node_infos.more.iter().collect::<Vec<_>>();
+        more.sort_by_key(|&(key, _)| key);
+        for (key, value) in more.into_iter() {
+            ag += &format!(" {} : {},", key, write_value(value));
+        }
+        ag += "};\n";
+    }
+
+    for edge in audiograph.graph.edge_indices() {
+        let (src, dst) = audiograph.graph.edge_endpoints(edge).unwrap();
+        if !is_real_io(src) && !is_real_io(dst) {
+            let edge = &audiograph.graph[edge];
+            ag += &format!(
+                "{}.{} -> {}.{};\n",
+                ids[&src],
+                edge.src_port(),
+                ids[&dst],
+                edge.dst_port()
+            );
+        }
+    }
+
+    if let Some(deadline) = audiograph.deadline() {
+        ag += &format!("deadline = {};\n", write_float(deadline));
+    }
+    ag
+}
+
+pub fn write_audiograph_to_file(audiograph: &AudioGraph, filename: &str) -> io::Result<()> {
+    let mut file = File::create(filename)?;
+    file.write_all(write_audiograph(audiograph).as_bytes())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::prelude::*;
 
     #[test]
     fn parse_audiograph_test() {
@@ -188,6 +1479,17 @@ mod tests {
         );
         assert!(audiograph.nb_nodes() == 5);
         assert!(audiograph.nb_edges() == 6);
+        assert_eq!(audiograph.deadline(), Some(50.0));
+    }
+
+    #[test]
+    fn build_audiograph_no_deadline_test() {
+        let audiograph =
+            parse_audiograph("a = {kind : \"osc\", out : 1, freq : 440,};", 64, 1, 44_100)
+                .expect("Impossible to parse audiograph.");
+        assert_eq!(audiograph.deadline(), None);
+        // Duration of a buffer of 64 samples at 44.1kHz
+        assert!((audiograph.budget() - 64. / 44_100. * 1_000_000.).abs() < 1e-6);
     }
 
     #[test]
@@ -203,10 +1505,604 @@ mod tests {
         assert!(audiograph.nb_edges() == 7);
     }
 
+    #[test]
+    fn audiograph_errors_test() {
+        let parse = |ag: &str| parse_audiograph(ag, 64, 1, 44_100).err().unwrap();
+        let osc = "a = {kind : \"osc\", out : 1, freq : 440,};\n";
+
+        let error = parse("a = {kind : \"osc\", out : 1, freq : 440,}\n");
+        assert!(matches!(error, AudiographError::Syntax(_)));
+        assert_eq!(
+            error.position(),
+            Some(Position {
+                line: 1,
+                column: 40,
+                file: None,
+            })
+        );
+
+        match parse(&format!("{}a.1 -> b.1;", osc)) {
+            AudiographError::UnknownNode { id, position } => {
+                assert_eq!(id, "b");
+                assert_eq!((position.line, position.column), (2, 8));
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+
+        match parse(&format!("{}a.2 -> a.1;", osc)) {
+            AudiographError::Diagnostics(errors) => match errors.as_slice() {
+                [AudiographError::PortOutOfRange { node, port, .. }, AudiographError::PortOutOfRange { nb_ports: 0, .. }] =>
+                {
+                    assert_eq!((node.as_str(), *port), ("a", 2))
+                }
+                errors => panic!("Unexpected errors {:?}", errors),
+            },
+            error => panic!("Unexpected error {}", error),
+        }
+
+        match parse("a = {kind : \"osc\", out : 1,};") {
+            AudiographError::MissingAttribute {
+                node, attribute, ..
+            } => assert_eq!((node.as_str(), attribute.as_str()), ("a", "freq")),
+            error => panic!("Unexpected error {}", error),
+        }
+
+        let error = parse("\na = {kind : \"osc\", out : 1.5, freq : 440,};");
+        assert!(matches!(error, AudiographError::InvalidAttribute { .. }));
+        assert_eq!(
+            error.position(),
+            Some(Position {
+                line: 2,
+                column: 20,
+                file: None,
+            })
+        );
+        assert!(matches!(
+            parse("a = {kind : \"osc\", out : 1, freq : \"high\",};"),
+            AudiographError::InvalidAttribute { .. }
+        ));
+
+        for ratio in ["\"fast\"", "0.0", "-2.0", "1000.0"].iter() {
+            assert!(matches!(
+                parse(&format!(
+                    "a = {{kind : \"resampler\", in : 1, out : 1, ratio : {},}};",
+                    ratio
+                )),
+                AudiographError::InvalidAttribute { ref attribute, .. } if attribute == "ratio"
+            ));
+        }
+        assert!(matches!(
+            parse("a = {kind : \"unknown\", in : 1,};"),
+            AudiographError::UnsupportedNode { .. }
+        ));
+
+        let error = parse(
+            "a = {kind : \"mix\", in : 2, out : 1,};\n\
+             b = {kind : \"mod\", in : 1, out : 1, freq : 3,};\n\
+             a.1 -> b.1 -> a.2;",
+        );
+        // The edge closing the cycle is rejected
+        assert!(matches!(error, AudiographError::Cycle { ref node, .. } if node == "a"));
+        assert!(error.to_string().contains("cycle"));
+
+        assert!(matches!(
+            parse_audiograph_from_file("does_not_exist.ag", 64, 1, 44_100),
+            Err(AudiographError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn validation_test() {
+        let error = parse_audiograph(
+            "a = {kind : \"osc\", in : 1, out : 1, freq : 440,};\n\
+             b = {kind : \"zita_reverb\", in : 2, out : 2, rdel : 60, f1 : 200, f2 : 6000, t60dc : 3, t60m : 2,};\n\
+             c = {kind : \"sink\", in : 1, out : 1,};\n\
+             d = {kind : \"mod\", in : 1, out : 1,};\n\
+             a.1 -> b.3;\n\
+             b.1 -> c.1;",
+            64,
+            1,
+            44_100,
+        )
+        .err()
+        .unwrap();
+        let errors = match error {
+            AudiographError::Diagnostics(ref errors) => errors,
+            error => panic!("Unexpected error {}", error),
+        };
+        assert_eq!(errors.len(), 4, "{}", error);
+        assert!(matches!(
+            errors[0],
+            AudiographError::PortCountMismatch {
+                declared: (1, 1),
+                expected: (0, 1),
+                ..
+            }
+        ));
+        assert!(matches!(
+            errors[1],
+            AudiographError::PortCountMismatch { .. }
+        ));
+        assert!(matches!(
+            errors[2],
+            AudiographError::MissingAttribute { .. }
+        ));
+        assert!(matches!(
+            errors[3],
+            AudiographError::PortOutOfRange { port: 3, .. }
+        ));
+        assert_eq!(error.position().unwrap().line, 1);
+        assert!(error.to_string().starts_with("4 problems"));
+
+        //With a virtual sink, unconnected inlets are not connected to the real source
+        match parse_audiograph(
+            "a = {kind : \"source\", out : 1,};\n\
+             b = {kind : \"zita_reverb\", in : 2, out : 2, rdel : 60, f1 : 200, f2 : 6000, t60dc : 3, t60m : 2,};\n\
+             c = {kind : \"sink\", in : 2,};\n\
+             a.1 -> b.1;\n\
+             b.1 -> c.1;\n\
+             b.2 -> c.2;",
+            64,
+            1,
+            44_100,
+        ) {
+            Err(AudiographError::UnconnectedInlet { node, port, .. }) => {
+                assert_eq!((node.as_str(), port), ("b", 2))
+            }
+            result => panic!("Unexpected result {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn faust_kinds_test() {
+        let nodes = [
+            ("brass", "out : 1, tube_length : 3, mute : 0.6, lips_tension : 0.9, pressure : 0.8,"),
+            ("violin", "out : 1, string_length : 2, bow_pressure : 0.4, bow_velocity : 0.5, bow_position : 0.6,"),
+            ("wah", "in : 1, out : 1, freq : 1200,"),
+            ("panner", "in : 1, out : 2, pan : 0.3,"),
+            ("spat", "in : 1, out : 4, rotation : 0.25, distance : 0.5,"),
+            ("pink_noise", "out : 1,"),
+            ("sawtooth", "out : 1, freq : 220,"),
+            ("feedbackcomb", "in : 1, out : 1, delay : 8, gain : 0.5,"),
+        ];
+        for &(kind, attributes) in nodes.iter() {
+            let ag = format!("a = {{kind : \"{}\", {}}};", kind, attributes);
+            let audiograph = parse_audiograph(&ag, 64, 1, 44_100)
+                .unwrap_or_else(|error| panic!("Cannot parse {}: {}", kind, error));
+            let (nodes, _) = graph_summary(&audiograph);
+            assert_eq!(nodes.len(), 1);
+            assert_eq!(nodes[0].3, kind);
+        }
+    }
+
+    #[test]
+    fn subgraph_test() {
+        let chain = "graph chain {\n\
+                     inlets = a.1;\n\
+                     outlets = b.1, b.2;\n\
+                     a = {kind : \"mod\", in : 1, out : 1, freq : 3,};\n\
+                     b = {kind : \"mix\", in : 1, out : 2,};\n\
+                     a.1 -> b.1;\n\
+                     };\n\
+                     graph double {\n\
+                     inlets = c1.1;\n\
+                     outlets = c2.2;\n\
+                     c1 = {kind : \"@chain\",};\n\
+                     c2 = {kind : \"@chain\", in : 1, out : 2,};\n\
+                     c1.1 -> c2.1;\n\
+                     };\n";
+        let audiograph = parse_audiograph(
+            &format!(
+                "{}o = {{kind : \"osc\", out : 1, freq : 440,}};\n\
+                 fx1 = {{kind : \"@double\",}};\n\
+                 fx2 = {{kind : \"@chain\",}};\n\
+                 o.1 -> fx1.1 -> fx2.1;",
+                chain
+            ),
+            64,
+            1,
+            44_100,
+        )
+        .expect("Impossible to parse audiograph.");
+        let (nodes, edges) = graph_summary(&audiograph);
+        let ids = nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            ["fx1-c1-a", "fx1-c1-b", "fx1-c2-a", "fx1-c2-b", "fx2-a", "fx2-b", "o"]
+        );
+        for edge in [
+            ("o", 1, "fx1-c1-a", 1),
+            ("fx1-c1-a", 1, "fx1-c1-b", 1),
+            ("fx1-c1-b", 1, "fx1-c2-a", 1),
+            ("fx1-c2-b", 2, "fx2-a", 1),
+        ]
+        .iter()
+        {
+            let edge = (edge.0.to_string(), edge.1, edge.2.to_string(), edge.3);
+            assert!(edges.contains(&edge), "Missing edge {:?}", edge);
+        }
+
+        let parse = |ag: &str| {
+            parse_audiograph(&format!("{}{}", chain, ag), 64, 1, 44_100)
+                .err()
+                .unwrap()
+        };
+        assert!(matches!(
+            parse("fx = {kind : \"@unknown\",};"),
+            AudiographError::UndefinedSubgraph { .. }
+        ));
+        assert!(matches!(
+            parse("fx = {kind : \"@chain\", out : 1,};"),
+            AudiographError::InvalidAttribute { .. }
+        ));
+        match parse("fx = {kind : \"@chain\",};\nfx.3 -> fx.1;") {
+            AudiographError::PortOutOfRange { node, nb_ports, .. } => {
+                assert_eq!((node.as_str(), nb_ports), ("fx", 2))
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+        assert!(matches!(
+            parse(
+                "fx = {kind : \"@chain\",};\nfx-a = {kind : \"mod\", in : 1, out : 1, freq : 3,};"
+            ),
+            AudiographError::DuplicateDefinition { .. }
+        ));
+        assert!(matches!(
+            parse("graph chain {};"),
+            AudiographError::DuplicateDefinition { .. }
+        ));
+        match parse(
+            "graph ping { p = {kind : \"@pong\",}; };\n\
+             graph pong { p = {kind : \"@ping\",}; };\n\
+             fx = {kind : \"@ping\",};",
+        ) {
+            AudiographError::RecursiveSubgraph { subgraph, .. } => assert_eq!(subgraph, "ping"),
+            error => panic!("Unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn include_test() {
+        let dir = std::env::temp_dir().join("audiograph_include_test");
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        let write = |name: &str, ag: &str| {
+            let path = dir.join(name);
+            File::create(&path)
+                .and_then(|mut f| f.write_all(ag.as_bytes()))
+                .unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        let parse = |path: &str| parse_audiograph_from_file(path, 64, 1, 44_100);
+
+        write(
+            "lib/osc.ag",
+            "o = {kind : \"osc\", out : 1, freq : 440,};\n\
+             graph chain {\n\
+             inlets = m.1;\n\
+             outlets = m.1;\n\
+             m = {kind : \"mod\", in : 1, out : 1, freq : 3,};\n\
+             };\n\
+             deadline = 10.0;",
+        );
+        write(
+            "lib/effects.ag",
+            "include \"osc.ag\";\n\
+             m = {kind : \"mod\", in : 1, out : 1, freq : 5,};\n\
+             osc-o.1 -> m.1;",
+        );
+        let main = write(
+            "main.ag",
+            "include \"lib/effects.ag\" as fx;\n\
+             c = {kind : \"@chain\",};\n\
+             fx-m.1 -> c.1;\n\
+             deadline = 50.0;",
+        );
+        let audiograph = parse(&main).expect("Impossible to parse audiograph.");
+        let (nodes, edges) = graph_summary(&audiograph);
+        let ids = nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["c-m", "fx-m", "fx-osc-o"]);
+        assert!(edges.contains(&("fx-osc-o".to_string(), 1, "fx-m".to_string(), 1)));
+        assert!(edges.contains(&("fx-m".to_string(), 1, "c-m".to_string(), 1)));
+        assert_eq!(audiograph.deadline(), Some(50.0));
+
+        //Two included files include the same library
+        write(
+            "lib/reverb.ag",
+            "include \"osc.ag\";\n\
+             r = {kind : \"mod\", in : 1, out : 1, freq : 7,};",
+        );
+        let main = write(
+            "main.ag",
+            "include \"lib/effects.ag\" as fx;\n\
+             include \"lib/reverb.ag\" as rv;\n\
+             c = {kind : \"@chain\",};\n\
+             fx-m.1 -> c.1;\n\
+             c.1 -> rv-r.1;",
+        );
+        let audiograph = parse(&main).expect("Impossible to parse audiograph.");
+        let (nodes, _) = graph_summary(&audiograph);
+        let ids = nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["c-m", "fx-m", "fx-osc-o", "rv-osc-o", "rv-r"]);
+        //But two files cannot define the same sub-graph
+        write("lib/chain.ag", "graph chain {};");
+        let main = write(
+            "main.ag",
+            "include \"lib/osc.ag\";\ninclude \"lib/chain.ag\";",
+        );
+        assert!(matches!(
+            parse(&main).err().unwrap(),
+            AudiographError::DuplicateDefinition { .. }
+        ));
+
+        //Errors point into the included file
+        let bad = write(
+            "lib/bad.ag",
+            "a = {kind : \"osc\", out : 1, freq : 440,};\na.1 -> b.1;",
+        );
+        let main = write("main.ag", "\ninclude \"lib/bad.ag\" as bad;");
+        let error = parse(&main).err().unwrap();
+        assert!(matches!(error, AudiographError::UnknownNode { .. }));
+        let position = error.position().unwrap();
+        assert_eq!((position.line, position.column), (2, 8));
+        assert_eq!(position.file.unwrap().as_ref(), Path::new(&bad));
+
+        write("lib/bad.ag", "\na = {kind : \"osc\"}");
+        let error = parse(&main).err().unwrap();
+        assert!(matches!(error, AudiographError::Include(_)));
+        let position = error.position().unwrap();
+        assert_eq!(position.line, 2);
+        assert_eq!(position.file.unwrap().as_ref(), Path::new(&bad));
+
+        let main = write("main.ag", "include \"lib/missing.ag\";");
+        match parse(&main).err().unwrap() {
+            AudiographError::Include(include) => {
+                assert!(matches!(include.error, AudiographError::Io(_)))
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+
+        write("lib/loop.ag", "include \"../main.ag\";");
+        let main = write("main.ag", "include \"lib/loop.ag\";");
+        match parse(&main).err().unwrap() {
+            AudiographError::IncludeCycle { position, .. } => {
+                assert_eq!(
+                    position.file.unwrap().as_ref(),
+                    dir.join("lib/loop.ag").as_path()
+                )
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+    }
+
+    /// Random .ag file with nodes that can be built, and edges going forward only
+    fn random_audiograph<R: Rng>(rng: &mut R) -> String {
+        let texts = [
+            "plain",
+            "with \\\"quotes\\\"",
+            "back\\\\slash",
+            "new\\nline\\ttab",
+            "été",
+        ];
+        let nb_nodes = rng.gen_range(1, 8);
+        let mut ports = Vec::new();
+        let mut ag = String::new();
+        for i in 0..nb_nodes {
+            let (kind, nb_inlets, nb_outlets) = match rng.gen_range(0, 3) {
+                0 if i == 0 => ("osc", 0, 1),
+                0 | 1 => ("mod", 1, 1),
+                _ => ("mix", rng.gen_range(1, 4), rng.gen_range(1, 4)),
+            };
+            ag += &format!("n{} = {{kind : \"{}\",", i, kind);
+            if nb_inlets > 0 {
+                ag += &format!(" in : {},", nb_inlets);
+            }
+            ag += &format!(
+                " out : {}, freq : {},",
+                nb_outlets,
+                rng.gen_range(1, 20_000)
+            );
+            if rng.gen() {
+                ag += &format!(" text : \"{}\",", texts.choose(rng).unwrap());
+            }
+            if rng.gen() {
+                ag += &format!(" wcet : {:.9},", rng.gen::<f64>() * 100.);
+            }
+            if rng.gen() {
+                ag += &format!(" volume : \"{}\",", rng.gen_range(-1f32, 1f32));
+            }
+            if rng.gen() {
+                ag += &format!(" note : \"{}\",", texts.choose(rng).unwrap());
+            }
+            if rng.gen() {
+                ag += &format!(
+                    " gain : {:e}, steps : {}, bypass : {},",
+                    rng.gen_range(-1e3, 1e3),
+                    rng.gen_range(-100, 100),
+                    rng.gen::<bool>()
+                );
+            }
+            ag += "};\n";
+            ports.push((nb_inlets, nb_outlets));
+        }
+        for _ in 0..rng.gen_range(0, 2 * nb_nodes) {
+            let src = rng.gen_range(0, nb_nodes);
+            let dst = rng.gen_range(0, nb_nodes);
+            if src < dst && ports[dst].0 > 0 {
+                ag += &format!(
+                    "n{}.{} -> n{}.{};\n",
+                    src,
+                    rng.gen_range(1, ports[src].1 + 1),
+                    dst,
+                    rng.gen_range(1, ports[dst].0 + 1)
+                );
+            }
+        }
+        if rng.gen() {
+            ag += &format!("deadline = {:.3};\n", rng.gen_range(1., 100.));
+        }
+        ag
+    }
+
+    /// Nodes and edges of an audiograph, identified by node ids
+    fn graph_summary(
+        audiograph: &AudioGraph,
+    ) -> (
+        Vec<(
+            String,
+            u32,
+            u32,
+            String,
+            Option<String>,
+            Option<f64>,
+            f32,
+            Vec<(String, Value)>,
+        )>,
+        Vec<(String, u32, String, u32)>,
+    ) {
+        let id = |node: NodeIndex| {
+            if node == audiograph.source_node() {
+                "<source>".to_string()
+            } else if node == audiograph.sink_node() {
+                "<sink>".to_string()
+            } else {
+                audiograph.graph[node].node_infos().id.clone()
+            }
+        };
+        let mut nodes = audiograph
+            .graph
+            .node_indices()
+            .filter(|&n| n != audiograph.source_node() && n != audiograph.sink_node())
+            .map(|n| {
+                let infos = audiograph.graph[n].node_infos();
+                let mut more = infos
+                    .more
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<Vec<_>>();
+                more.sort_by(|a, b| a.0.cmp(&b.0));
+                (
+                    infos.id.clone(),
+                    infos.nb_inlets,
+                    infos.nb_outlets,
+                    infos.class_name.clone(),
+                    infos.text.clone(),
+                    infos.wcet,
+                    infos.volume,
+                    more,
+                )
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut edges = audiograph
+            .graph
+            .edge_indices()
+            .map(|e| {
+                let (src, dst) = audiograph.graph.edge_endpoints(e).unwrap();
+                let edge = &audiograph.graph[e];
+                (id(src), edge.src_port(), id(dst), edge.dst_port())
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+        (nodes, edges)
+    }
+
+    #[test]
+    fn write_audiograph_round_trip() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for _ in 0..200 {
+            let ag = random_audiograph(&mut rng);
+            let audiograph = parse_audiograph(&ag, 64, 1, 44_100)
+                .unwrap_or_else(|e| panic!("Invalid random graph {}:\n{}", e, ag));
+            let written = write_audiograph(&audiograph);
+            let reparsed = parse_audiograph(&written, 64, 1, 44_100)
+                .unwrap_or_else(|e| panic!("Invalid written graph {}:\n{}", e, written));
+
+            assert_eq!(
+                graph_summary(&audiograph),
+                graph_summary(&reparsed),
+                "{}",
+                ag
+            );
+            assert_eq!(audiograph.deadline(), reparsed.deadline());
+            assert_eq!(written, write_audiograph(&reparsed));
+        }
+    }
+
+    #[test]
+    fn write_audiograph_values() {
+        assert_eq!(write_float(3f32), "3.0");
+        assert_eq!(write_float(0.25f64), "0.25");
+        assert_eq!(write_float(-0.5f32), "-0.5");
+        assert_eq!(write_float(1e-7f64), "1e-7");
+        assert_eq!(write_float(std::f64::INFINITY), "\"inf\"");
+        let text = "a \"quoted\" \\ text\n\u{1}";
+        assert_eq!(unescape(&escape(text)), text);
+        assert!(AudiographParser::parse(Rule::string, &format!("\"{}\"", escape(text))).is_ok());
+    }
+
+    #[test]
+    fn expressions_test() {
+        let audiograph = parse_audiograph(
+            "let sr_half = 0.5;\n\
+             let steps = -(3 - 5) * 2;\n\
+             let name = \"preserve\";\n\
+             a = {kind : \"osc\", out : 1, freq : 880 / 4 * 2, gain : sr_half-1.5e1,\n\
+             on : true, steps : steps + 1, mode : name, ratio : \"2.0\", volume : -sr_half,};\n\
+             deadline = 1e2 * sr_half;",
+            64,
+            1,
+            44_100,
+        )
+        .expect("Impossible to parse audiograph.");
+        let node = audiograph
+            .graph
+            .node_indices()
+            .map(|n| audiograph.graph[n].node_infos())
+            .find(|n| n.id == "a")
+            .unwrap();
+        assert_eq!(node.more["freq"], Value::Float(440.));
+        assert_eq!(node.attribute::<u32>("freq", "an integer").unwrap(), 440);
+        assert_eq!(node.more["gain"], Value::Float(-14.5));
+        assert_eq!(node.more["on"], Value::Boolean(true));
+        assert_eq!(node.more["steps"], Value::Integer(5));
+        assert_eq!(node.more["mode"], Value::String("preserve".to_string()));
+        assert_eq!(node.attribute::<f32>("ratio", "a float").unwrap(), 2.);
+        assert_eq!(node.volume, -0.5);
+        assert_eq!(audiograph.deadline(), Some(50.));
+
+        let parse = |ag: &str| parse_audiograph(ag, 64, 1, 44_100).err().unwrap();
+        match parse("a = {kind : \"osc\", out : 1, freq : 2 * f,};\nlet f = 3;") {
+            AudiographError::UndefinedConstant { name, position } => {
+                assert_eq!(name, "f");
+                assert_eq!((position.line, position.column), (1, 40));
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+        assert!(matches!(
+            parse("let a = true + 1;"),
+            AudiographError::InvalidExpression { .. }
+        ));
+        assert!(matches!(
+            parse("let a = 1;\nlet a = 2;"),
+            AudiographError::DuplicateDefinition { .. }
+        ));
+        assert!(matches!(
+            parse("deadline = false;"),
+            AudiographError::InvalidExpression { .. }
+        ));
+        assert!(matches!(
+            parse("a = {kind : \"osc\", out : -1, freq : 440,};"),
+            AudiographError::InvalidAttribute { .. }
+        ));
+    }
+
     #[test]
     fn audiograph_ident() {
         assert!(AudiographParser::parse(Rule::ident, "rte45").is_ok());
         assert!(AudiographParser::parse(Rule::ident, "56rhe").is_err());
+        assert!(AudiographParser::parse(Rule::ident, "bow_pressure").is_ok());
+        assert!(AudiographParser::parse(Rule::ident, "_plop").is_err());
+        assert!(is_ident("bow_pressure") && is_ident("fx-1") && !is_ident("_plop"));
     }
 
     #[test]
@@ -234,6 +2130,8 @@ mod tests {
         assert!(AudiographParser::parse(Rule::fnumber, "45.").is_ok());
         assert!(AudiographParser::parse(Rule::fnumber, "3").is_err());
         assert!(AudiographParser::parse(Rule::fnumber, "3 . 3").is_err());
+        assert!(AudiographParser::parse(Rule::fnumber, "3e-5").is_ok());
+        assert!(AudiographParser::parse(Rule::fnumber, "2.5E+3").is_ok());
     }
 
     #[test]
@@ -253,6 +2151,9 @@ mod tests {
         assert!(AudiographParser::parse(Rule::attribute, "kind : \"test\",").is_ok());
         assert!(AudiographParser::parse(Rule::attribute, "plop : 3,").is_ok());
         assert!(AudiographParser::parse(Rule::attribute, "plop : 3.5,").is_ok());
+        assert!(AudiographParser::parse(Rule::attribute, "plop : -3.5,").is_ok());
+        assert!(AudiographParser::parse(Rule::attribute, "plop : false,").is_ok());
+        assert!(AudiographParser::parse(Rule::attribute, "plop : (a + 1) / b,").is_ok());
         assert!(AudiographParser::parse(Rule::attribute, "plop : ,").is_err());
         assert!(AudiographParser::parse(Rule::attribute, "plop : ").is_err());
         assert!(AudiographParser::parse(Rule::attribute, "plop : 3").is_err());
@@ -266,5 +2167,4 @@ mod tests {
         assert!(AudiographParser::parse(Rule::node, "p = { test : 3 }").is_err());
         assert!(AudiographParser::parse(Rule::node, "= { test : 3, }").is_err());
     }
-
 }
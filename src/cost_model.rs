@@ -0,0 +1,591 @@
+//! Execution cost of the kinds of nodes depending on the size of the buffers and the samplerate.
+//! Every kind of a `NodeRegistry` is profiled on a grid of buffer sizes and samplerates, and a linear
+//! model is fitted on the measurements, so that the cost of a node can be predicted both when it is
+//! executed at the nominal samplerate and when it is resampled in adaptive mode.
+//! The kinds with variants are profiled for each of their variants, so that the cost of switching
+//! a node to a cheaper variant can be predicted as well.
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use time::PreciseTime;
+
+use audiograph::*;
+use audiograph_parser::{AudiographError, Node, Value};
+use node_registry::{NodeMetadata, NodeRegistry};
+
+/// Cycles executed before measuring, so that the initialisations at the first cycle are not measured
+const WARMUP_CYCLES: usize = 4;
+
+/// Buffer sizes, samplerates and number of measured cycles at which the kinds of nodes are profiled
+#[derive(Debug, Clone)]
+pub struct ProfilingGrid {
+    pub buffer_sizes: Vec<usize>,
+    pub samplerates: Vec<u32>,
+    /// Number of cycles executed for each buffer size and samplerate
+    pub nb_cycles: usize,
+}
+
+impl Default for ProfilingGrid {
+    fn default() -> Self {
+        ProfilingGrid {
+            buffer_sizes: vec![64, 128, 256, 512, 1024],
+            samplerates: vec![11_025, 22_050, 44_100, 48_000],
+            nb_cycles: 100,
+        }
+    }
+}
+
+/// Mean execution time of one cycle, in microseconds, for buffers of `frames` samples at `samplerate`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub frames: usize,
+    pub samplerate: u32,
+    pub time: f64,
+}
+
+/// Cost of a kind of node, in microseconds: `intercept + per_frame * frames + per_khz * samplerate / 1000`.
+/// The samplerate term accounts for the state of the effects, such as delay lines, which grows with the samplerate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KindCost {
+    pub intercept: f64,
+    pub per_frame: f64,
+    pub per_khz: f64,
+}
+
+impl KindCost {
+    /// Least squares fit of the measurements. The terms which do not vary in the measurements are left to 0.
+    pub fn fit(measurements: &[Measurement]) -> Option<KindCost> {
+        let first = measurements.first()?;
+        let frames_vary = measurements.iter().any(|m| m.frames != first.frames);
+        let samplerates_vary = measurements
+            .iter()
+            .any(|m| m.samplerate != first.samplerate);
+
+        let features = measurements
+            .iter()
+            .map(|m| {
+                let mut row = vec![1.];
+                if frames_vary {
+                    row.push(m.frames as f64);
+                }
+                if samplerates_vary {
+                    row.push(m.samplerate as f64 / 1000.);
+                }
+                row
+            })
+            .collect::<Vec<_>>();
+        let times = measurements.iter().map(|m| m.time).collect::<Vec<_>>();
+        let mut coefficients = least_squares(&features, &times)?.into_iter();
+
+        let intercept = coefficients.next().unwrap();
+        let per_frame = if frames_vary {
+            coefficients.next().unwrap()
+        } else {
+            0.
+        };
+        let per_khz = if samplerates_vary {
+            coefficients.next().unwrap()
+        } else {
+            0.
+        };
+        Some(KindCost {
+            intercept,
+            per_frame,
+            per_khz,
+        })
+    }
+
+    /// Predicted execution time of one cycle in microseconds
+    pub fn predict(&self, frames: usize, samplerate: u32) -> f64 {
+        let time = self.intercept
+            + self.per_frame * frames as f64
+            + self.per_khz * samplerate as f64 / 1000.;
+        time.max(0.)
+    }
+}
+
+/// Solve the normal equations of the linear least squares problem `features * x = values`
+fn least_squares(features: &[Vec<f64>], values: &[f64]) -> Option<Vec<f64>> {
+    let n = features.first()?.len();
+    //Augmented matrix [A^T A | A^T b]
+    let mut system = vec![vec![0.; n + 1]; n];
+    for (row, value) in features.iter().zip(values) {
+        for i in 0..n {
+            for j in 0..n {
+                system[i][j] += row[i] * row[j];
+            }
+            system[i][n] += row[i] * value;
+        }
+    }
+
+    //A measurement which is not a number has no solution
+    if system.iter().any(|row| row.iter().any(|x| x.is_nan())) {
+        return None;
+    }
+
+    //Gaussian elimination with partial pivoting
+    for col in 0..n {
+        let mut pivot = col;
+        for row in col + 1..n {
+            match system[row][col]
+                .abs()
+                .partial_cmp(&system[pivot][col].abs())
+            {
+                Some(Ordering::Greater) => pivot = row,
+                Some(_) => {}
+                None => return None,
+            }
+        }
+        if system[pivot][col].is_nan() || system[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        system.swap(col, pivot);
+        let (upper, lower) = system.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for row in lower.iter_mut() {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * p;
+            }
+        }
+    }
+    let mut solution = vec![0.; n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n)
+            .map(|k| system[row][k] * solution[k])
+            .sum::<f64>();
+        solution[row] = (system[row][n] - sum) / system[row][row];
+    }
+    Some(solution)
+}
+
+/// Predicted costs of a node, in microseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictedCost {
+    /// When the node is executed at its samplerate
+    pub nominal: f64,
+    /// When the node is downsampled by `DEGRADATION_RATIO` in adaptive mode
+    pub resampled: f64,
+}
+
+/// Cost of every profiled kind of node
+#[derive(Debug, Clone, Default)]
+pub struct CostModel {
+    kinds: BTreeMap<String, KindCost>,
+    /// Execution time of each variant relative to the nominal one, for the kinds with variants
+    variants: BTreeMap<String, Vec<f64>>,
+}
+
+impl CostModel {
+    pub fn new() -> CostModel {
+        CostModel::default()
+    }
+
+    /// Profile all the kinds of `registry`. The kinds which cannot be built with the default values
+    /// of their parameters are skipped, with an error on stderr.
+    pub fn profile(registry: &NodeRegistry, grid: &ProfilingGrid) -> CostModel {
+        let mut cost_model = CostModel::new();
+        let kinds = registry
+            .kinds()
+            .map(|(kind, _)| kind.to_string())
+            .collect::<Vec<_>>();
+        for kind in kinds {
+            if let Err(error) = cost_model.profile_kind(registry, &kind, grid) {
+                eprintln!("Cannot profile {}: {}", kind, error);
+            }
+        }
+        cost_model
+    }
+
+    /// Measure every variant of `kind` at every point of `grid` and add the fitted cost of the nominal variant,
+    /// and the relative costs of the variants, to the model
+    pub fn profile_kind(
+        &mut self,
+        registry: &NodeRegistry,
+        kind: &str,
+        grid: &ProfilingGrid,
+    ) -> Result<(), AudiographError> {
+        let metadata = registry
+            .metadata(kind)
+            .ok_or_else(|| AudiographError::UnsupportedNode {
+                node: format!("profiled_{}", kind),
+                kind: kind.to_string(),
+                position: Default::default(),
+            })?;
+        let node_infos = profiled_node(kind, metadata);
+        let (_, processor) = registry.create_node(node_infos.clone(), 1)?.into_parts();
+        let mut total_times = vec![0.; processor.nb_variants()];
+        let mut measurements = Vec::new();
+        for &samplerate in grid.samplerates.iter() {
+            for &frames in grid.buffer_sizes.iter() {
+                for (variant, total_time) in total_times.iter_mut().enumerate() {
+                    let time = measure(
+                        registry,
+                        &node_infos,
+                        variant,
+                        frames,
+                        samplerate,
+                        grid.nb_cycles,
+                    )?;
+                    *total_time += time;
+                    if variant == 0 {
+                        measurements.push(Measurement {
+                            frames,
+                            samplerate,
+                            time,
+                        });
+                    }
+                }
+            }
+        }
+        if let Some(cost) = KindCost::fit(&measurements) {
+            self.insert(kind, cost);
+        }
+        if total_times.len() > 1 && total_times[0] > 0. {
+            let costs = total_times
+                .iter()
+                .map(|time| time / total_times[0])
+                .collect();
+            self.insert_variant_costs(kind, costs);
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, kind: &str, cost: KindCost) {
+        self.kinds.insert(kind.to_string(), cost);
+    }
+
+    pub fn cost(&self, kind: &str) -> Option<&KindCost> {
+        self.kinds.get(kind)
+    }
+
+    /// `costs` are the execution times of the variants of `kind` relative to the nominal variant
+    pub fn insert_variant_costs(&mut self, kind: &str, costs: Vec<f64>) {
+        self.variants.insert(kind.to_string(), costs);
+    }
+
+    /// Execution times of the variants of `kind` relative to the nominal variant, if they were profiled
+    pub fn variant_costs(&self, kind: &str) -> Option<&[f64]> {
+        self.variants.get(kind).map(|costs| costs.as_slice())
+    }
+
+    pub fn variant_cost(&self, kind: &str, variant: usize) -> Option<f64> {
+        self.variant_costs(kind)?.get(variant).cloned()
+    }
+
+    /// Profiled kinds, in alphabetical order
+    pub fn kinds(&self) -> impl Iterator<Item = (&str, &KindCost)> {
+        self.kinds.iter().map(|(name, cost)| (name.as_str(), cost))
+    }
+
+    /// Predicted costs of a node of `kind` executed with buffers of `frames` samples at `samplerate`,
+    /// and when its buffers and samplerate are scaled by `DEGRADATION_RATIO`
+    pub fn predict(&self, kind: &str, frames: usize, samplerate: u32) -> Option<PredictedCost> {
+        self.cost(kind).map(|cost| PredictedCost {
+            nominal: cost.predict(frames, samplerate),
+            resampled: cost.predict(
+                (frames as f64 * DEGRADATION_RATIO) as usize,
+                (samplerate as f64 * DEGRADATION_RATIO) as u32,
+            ),
+        })
+    }
+
+    /// Write the model as a tab-separated file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<CostModel> {
+        let file = File::open(path)?;
+        let mut cost_model = CostModel::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "line {}: expected a kind, 3 numbers and the costs of the variants",
+                        number + 1
+                    ),
+                )
+            };
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() < 4 {
+                return Err(invalid());
+            }
+            let numbers = fields[1..]
+                .iter()
+                .map(|field| field.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+            let (coefficients, variant_costs) = numbers.split_at(3);
+            if !variant_costs.is_empty() {
+                let costs = Some(1.).into_iter().chain(variant_costs.iter().cloned());
+                cost_model.insert_variant_costs(fields[0], costs.collect());
+            }
+            cost_model.insert(
+                fields[0],
+                KindCost {
+                    intercept: coefficients[0],
+                    per_frame: coefficients[1],
+                    per_khz: coefficients[2],
+                },
+            );
+        }
+        Ok(cost_model)
+    }
+}
+
+impl fmt::Display for CostModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "# Time in µs = intercept + per_frame * frames + per_khz * samplerate / 1000"
+        )?;
+        writeln!(
+            f,
+            "# The next columns are the costs of variants 1, 2... relative to the nominal variant"
+        )?;
+        writeln!(f, "# kind\tintercept\tper_frame\tper_khz")?;
+        for (kind, cost) in self.kinds() {
+            write!(
+                f,
+                "{}\t{}\t{}\t{}",
+                kind, cost.intercept, cost.per_frame, cost.per_khz
+            )?;
+            for variant_cost in self.variant_costs(kind).unwrap_or(&[]).iter().skip(1) {
+                write!(f, "\t{}", variant_cost)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Node of `kind` with the default values of its parameters, and one port when the number of ports
+/// is given by the node
+fn profiled_node(kind: &str, metadata: &NodeMetadata) -> Node {
+    let mut node_infos = Node {
+        id: format!("profiled_{}", kind),
+        class_name: kind.to_string(),
+        nb_inlets: metadata.nb_inlets.unwrap_or(1),
+        nb_outlets: metadata.nb_outlets.unwrap_or(1),
+        ..Default::default()
+    };
+    for parameter in metadata.parameters.iter() {
+        let value = match parameter.kind {
+            ParameterType::Float => Value::Float(parameter.default as f64),
+            ParameterType::Integer => Value::Integer(parameter.default.round() as i64),
+            ParameterType::Boolean => Value::Boolean(parameter.default != 0.),
+        };
+        node_infos.more.insert(parameter.name.to_string(), value);
+    }
+    //Resamplers are profiled as the downsamplers inserted in adaptive mode
+    if kind == "resampler" {
+        node_infos
+            .more
+            .insert("ratio".to_string(), Value::Float(DEGRADATION_RATIO));
+    }
+    node_infos
+}
+
+/// Execution time of each variant of `kind` relative to its nominal variant, for buffers of
+/// `frames` samples at `samplerate`. All the variants cost 1 if the nominal one is too fast to be measured.
+pub fn measure_variant_costs(
+    registry: &NodeRegistry,
+    kind: &str,
+    frames: usize,
+    samplerate: u32,
+    nb_cycles: usize,
+) -> Result<Vec<f64>, AudiographError> {
+    let metadata = registry
+        .metadata(kind)
+        .ok_or_else(|| AudiographError::UnsupportedNode {
+            node: format!("profiled_{}", kind),
+            kind: kind.to_string(),
+            position: Default::default(),
+        })?;
+    let node_infos = profiled_node(kind, metadata);
+    let (_, processor) = registry.create_node(node_infos.clone(), 1)?.into_parts();
+    let nb_variants = processor.nb_variants();
+    let times = (0..nb_variants)
+        .map(|variant| {
+            measure(
+                registry,
+                &node_infos,
+                variant,
+                frames,
+                samplerate,
+                nb_cycles,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if times[0] > 0. {
+        Ok(times.iter().map(|time| time / times[0]).collect())
+    } else {
+        //Too fast to be measured: the variants are not cheaper
+        Ok(vec![1.; times.len()])
+    }
+}
+
+/// Mean execution time of one cycle of `variant` of a new node built from `node_infos`, in microseconds
+fn measure(
+    registry: &NodeRegistry,
+    node_infos: &Node,
+    variant: usize,
+    frames: usize,
+    samplerate: u32,
+    nb_cycles: usize,
+) -> Result<f64, AudiographError> {
+    let (_, mut processor) = registry.create_node(node_infos.clone(), 1)?.into_parts();
+    let has_variant = processor.set_variant(variant);
+    debug_assert!(has_variant);
+    let ratio = if node_infos.class_name == "resampler" {
+        DEGRADATION_RATIO
+    } else {
+        1.
+    };
+
+    let mut input = DspEdge::new(1, 1, frames, samplerate);
+    for (i, sample) in input.buffer_mut().iter_mut().enumerate() {
+        *sample = (2. * PI * 440. * i as f64 / samplerate as f64).sin() as f32 * 0.5;
+    }
+    let inputs = vec![input; processor.nb_inputs()];
+    let mut outputs = vec![
+        DspEdge::new(
+            1,
+            1,
+            (frames as f64 * ratio) as usize,
+            (samplerate as f64 * ratio) as u32
+        );
+        processor.nb_outputs()
+    ];
+
+    for _ in 0..WARMUP_CYCLES {
+        processor.process(&inputs, &mut outputs);
+    }
+    let start = PreciseTime::now();
+    for _ in 0..nb_cycles {
+        processor.process(&inputs, &mut outputs);
+    }
+    let elapsed = start.to(PreciseTime::now()).num_nanoseconds().unwrap();
+    Ok(elapsed as f64 / 1000. / nb_cycles.max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_linear_costs() {
+        let mut measurements = Vec::new();
+        for &samplerate in [22_050, 44_100, 48_000].iter() {
+            for &frames in [64, 256, 1024].iter() {
+                measurements.push(Measurement {
+                    frames,
+                    samplerate,
+                    time: 3. + 0.5 * frames as f64 + 0.1 * samplerate as f64 / 1000.,
+                });
+            }
+        }
+        let cost = KindCost::fit(&measurements).unwrap();
+        assert!((cost.intercept - 3.).abs() < 1e-6);
+        assert!((cost.per_frame - 0.5).abs() < 1e-9);
+        assert!((cost.per_khz - 0.1).abs() < 1e-7);
+        assert!((cost.predict(512, 32_000) - 262.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fit_single_samplerate() {
+        let measurements = [64, 128]
+            .iter()
+            .map(|&frames| Measurement {
+                frames,
+                samplerate: 44_100,
+                time: 2. * frames as f64,
+            })
+            .collect::<Vec<_>>();
+        let cost = KindCost::fit(&measurements).unwrap();
+        assert!(cost.intercept.abs() < 1e-9);
+        assert!((cost.per_frame - 2.).abs() < 1e-9);
+        assert_eq!(cost.per_khz, 0.);
+        assert!(KindCost::fit(&[]).is_none());
+
+        let mut measurements = measurements;
+        measurements[1].time = ::std::f64::NAN;
+        assert!(KindCost::fit(&measurements).is_none());
+    }
+
+    #[test]
+    fn test_predict_resampled() {
+        let mut cost_model = CostModel::new();
+        cost_model.insert(
+            "mod",
+            KindCost {
+                intercept: 1.,
+                per_frame: 0.1,
+                per_khz: 0.,
+            },
+        );
+        let predicted = cost_model.predict("mod", 512, 44_100).unwrap();
+        assert!((predicted.nominal - 52.2).abs() < 1e-9);
+        assert!((predicted.resampled - 26.6).abs() < 1e-9);
+        assert!(cost_model.predict("freeverb", 512, 44_100).is_none());
+    }
+
+    #[test]
+    fn test_measure_variant_costs() {
+        let registry = NodeRegistry::default();
+        let costs = measure_variant_costs(&registry, "freeverb", 64, 44_100, 10).unwrap();
+        assert_eq!(costs.len(), 3);
+        assert_eq!(costs[0], 1.);
+        assert!(costs.iter().all(|cost| cost.is_finite() && *cost > 0.));
+        let costs = measure_variant_costs(&registry, "mod", 64, 44_100, 10).unwrap();
+        assert_eq!(costs, vec![1.]);
+        assert!(measure_variant_costs(&registry, "theremin", 64, 44_100, 10).is_err());
+    }
+
+    #[test]
+    fn test_profile_and_reload() {
+        let registry = NodeRegistry::default();
+        let grid = ProfilingGrid {
+            buffer_sizes: vec![64, 256],
+            samplerates: vec![22_050, 44_100],
+            nb_cycles: 10,
+        };
+        let mut cost_model = CostModel::new();
+        for kind in ["mod", "resampler", "mix", "freeverb"].iter() {
+            cost_model.profile_kind(&registry, kind, &grid).unwrap();
+        }
+        assert!(cost_model
+            .profile_kind(&registry, "theremin", &grid)
+            .is_err());
+        assert_eq!(cost_model.kinds().count(), 4);
+        assert!(cost_model.variant_costs("mod").is_none());
+        let variant_costs = cost_model.variant_costs("freeverb").unwrap();
+        assert_eq!(variant_costs.len(), 3);
+        assert_eq!(variant_costs[0], 1.);
+
+        let path = std::env::temp_dir().join(format!(
+            "audiograph_cost_model_test_{}.tsv",
+            std::process::id()
+        ));
+        cost_model.save(&path).unwrap();
+        let loaded = CostModel::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for (kind, cost) in cost_model.kinds() {
+            assert_eq!(loaded.cost(kind), Some(cost));
+            assert_eq!(loaded.variant_costs(kind), cost_model.variant_costs(kind));
+        }
+    }
+}
@@ -117,6 +117,8 @@ pub struct TimeMonitor {
     ///Number of degraded effects
     pub nb_degraded: u64,
     pub nb_resamplers: u64, //Number of inserted resamplers
+    ///Number of resamplers which failed, the edge being passed through without filtering
+    pub nb_resampling_errors: u64,
     pub callback_flags: CallbackFlags,
     ///Duration taken to compute the degradation
     pub choosing_duration: u64,
@@ -140,7 +142,7 @@ pub struct AudioGraph<T: Copy + AudioEffect + fmt::Display + Eq> {
     temp_buffer: Vec<f32>, //Used for mixing in the exhaustive strategy
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Quality {
     Normal,
     Degraded,
@@ -533,6 +535,7 @@ impl<T: fmt::Display + AudioEffect + Eq + Hash + Copy> AudioGraph<T> {
             execution_time: start.to(PreciseTime::now()).num_microseconds().unwrap() as u64,
             callback_flags: flags,
             nb_resamplers: 0,
+            nb_resampling_errors: 0,
             choosing_duration: 0,
             nb_degraded: 0,
         }
@@ -766,6 +769,7 @@ impl<T: fmt::Display + AudioEffect + Eq + Hash + Copy> AudioGraph<T> {
             execution_time: start.to(PreciseTime::now()).num_microseconds().unwrap() as u64,
             callback_flags: flags,
             nb_resamplers,
+            nb_resampling_errors: 0,
             choosing_duration: choosing_duration.num_microseconds().unwrap() as u64,
             nb_degraded: first_degraded_node.map_or(0, |n| self.schedule.len() as u64 - n),
         }
@@ -981,6 +985,7 @@ impl<T: fmt::Display + AudioEffect + Eq + Hash + Copy> AudioGraph<T> {
             execution_time: start.to(PreciseTime::now()).num_microseconds().unwrap() as u64,
             callback_flags: flags,
             nb_resamplers,
+            nb_resampling_errors: 0,
             choosing_duration: choosing_duration.num_microseconds().unwrap() as u64,
             nb_degraded: first_degraded_node.map_or(0, |n| self.schedule.len() as u64 - n),
         }
@@ -1,17 +1,357 @@
 //! Audio effects
-//! Generated using the rust backend from Faust code
+//! Written by hand, for the effects which declare `hand_ported` in faust-effects/: the ones with
+//! variants (guitar, zita_reverb, mono_freeverb) and spat, whose number of outputs is set by the node,
+//! are ported from the output of the rust backend of Faust.
+//! panner, brass, violin, wah, pink_noise, sawtooth and feedbackcomb are generated in `faust_generated` once
+//! the output of faust is committed for them, or with the `faust` feature. Until then, a port by hand of
+//! `sp.panner` and approximations of the other .dsp files below stand in for them.
+//! The other effects are in `faust_generated`.
 
 use audiograph::*;
 use audiograph_parser;
+use audiograph_parser::{AudiographError, Value};
 
 use std::fmt;
 
 use amath;
 
+// Ported by hand before being generated from faust-effects/
+pub use faust_generated::{Autowah, Compressor, Cubicnl, Transpose as Transposer};
+
 pub fn faustpower2_f(value: f32) -> f32 {
     return value * value;
 }
 
+/// Default duration of the ramps of the parameters, in seconds
+pub const DEFAULT_SMOOTHING_TIME: f32 = 0.01;
+
+/// Smoothing of the parameters of an effect with linear ramps, so that changing
+/// a parameter between two cycles does not cause clicks. Only float parameters are smoothed:
+/// integer and boolean ones are not continuous and take their new value at once.
+///
+/// A ramp reaches its target after the smoothing time exactly, which bounds the number of samples
+/// processed one by one after a change.
+#[derive(Debug, Clone)]
+pub struct Smoother {
+    targets: Vec<f32>,
+    values: Vec<f32>,
+    steps: Vec<f32>,       //Increment of each value per sample
+    remaining: Vec<usize>, //Number of samples before each value reaches its target
+    smoothed: Vec<bool>,
+    smoothing_time: f32,
+    samplerate: u32,
+    settled: bool,
+}
+
+impl Smoother {
+    /// Start with the default values of the parameters
+    pub fn new(parameters: &[Parameter]) -> Smoother {
+        Smoother {
+            targets: parameters
+                .iter()
+                .map(|parameter| parameter.default)
+                .collect(),
+            values: parameters
+                .iter()
+                .map(|parameter| parameter.default)
+                .collect(),
+            steps: vec![0.; parameters.len()],
+            remaining: vec![0; parameters.len()],
+            smoothed: parameters
+                .iter()
+                .map(|parameter| parameter.kind == ParameterType::Float)
+                .collect(),
+            smoothing_time: DEFAULT_SMOOTHING_TIME,
+            samplerate: 44_100,
+            settled: true,
+        }
+    }
+
+    /// Duration of the ramp of a parameter to its new value, in seconds.
+    /// 0 disables smoothing.
+    pub fn set_smoothing_time(&mut self, smoothing_time: f32) {
+        assert!(smoothing_time >= 0.);
+        self.smoothing_time = smoothing_time;
+    }
+
+    pub fn smoothing_time(&self) -> f32 {
+        self.smoothing_time
+    }
+
+    /// Ramps started afterwards last the smoothing time at `samplerate`
+    pub fn set_samplerate(&mut self, samplerate: u32) {
+        self.samplerate = samplerate;
+    }
+
+    /// Value the parameter at `index` is moving to
+    pub fn target(&self, index: usize) -> f32 {
+        self.targets[index]
+    }
+
+    /// Current value of the parameter at `index`
+    pub fn value(&self, index: usize) -> f32 {
+        self.values[index]
+    }
+
+    pub fn set_target(&mut self, index: usize, value: f32) {
+        self.targets[index] = value;
+        if self.values[index] == value {
+            self.remaining[index] = 0;
+            return;
+        }
+        // Integer and boolean values change at once, but the sliders still have to be updated
+        // at the next tick
+        self.settled = false;
+        let length = if self.smoothed[index] {
+            (self.smoothing_time * self.samplerate as f32).round() as usize
+        } else {
+            0
+        };
+        if length == 0 {
+            self.values[index] = value;
+            self.remaining[index] = 0;
+        } else {
+            self.steps[index] = (value - self.values[index]) / length as f32;
+            self.remaining[index] = length;
+        }
+    }
+
+    /// Jump to the targets
+    pub fn skip(&mut self) {
+        self.values.copy_from_slice(&self.targets);
+        for remaining in self.remaining.iter_mut() {
+            *remaining = 0;
+        }
+        self.settled = true;
+    }
+
+    /// All the parameters have reached their targets
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Move the parameters towards their targets by `nb_samples` samples
+    pub fn tick(&mut self, nb_samples: usize) {
+        let mut settled = true;
+        for index in 0..self.values.len() {
+            if self.remaining[index] > nb_samples {
+                self.values[index] += self.steps[index] * nb_samples as f32;
+                self.remaining[index] -= nb_samples;
+                settled = false;
+            } else {
+                self.values[index] = self.targets[index];
+                self.remaining[index] = 0;
+            }
+        }
+        self.settled = settled;
+    }
+}
+
+/// What a Faust effect does with its state when the samplerate of its inputs changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateSwitch {
+    /// Clear the delay lines and the recursive state
+    Reset,
+    /// Recompute the constants and resample the delay lines, so that reverberation tails
+    /// and resonances go on. The delay lines are resampled over the next cycles, see `Rescaling`.
+    Preserve,
+}
+
+impl RateSwitch {
+    /// `rate_switch : "preserve"` in the attributes of a node selects `Preserve`
+    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> RateSwitch {
+        match node_infos.more.get("rate_switch") {
+            Some(&Value::String(ref s)) if s == "preserve" => RateSwitch::Preserve,
+            _ => RateSwitch::Reset,
+        }
+    }
+}
+
+/// Number of samples of delay lines rescaled per cycle after a switch with `RateSwitch::Preserve`
+pub const RESCALING_BUDGET: usize = 8192;
+
+/// Resampling of the delay lines of an effect after a samplerate switch, so that a delay of `d` samples
+/// reads what was written `d / ratio` samples ago. It is spread over several cycles, each rescaling at most
+/// `RESCALING_BUDGET` samples. Delays are counted from the index of the next sample at the switch:
+/// the samples written since then are already at the new samplerate and are left as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rescaling {
+    ratio: f32,
+    start: i32,
+    /// Delay line being rescaled
+    line: usize,
+    /// Number of delays of the line already rescaled
+    done: usize,
+    active: bool,
+}
+
+impl Rescaling {
+    /// Start rescaling by `ratio` the delay lines whose next sample is at `iota`.
+    /// A rescaling still in progress is abandoned where it is.
+    pub fn start(&mut self, ratio: f32, iota: i32) {
+        *self = Rescaling {
+            ratio,
+            start: iota,
+            line: 0,
+            done: 0,
+            active: true,
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Rescale at most `budget` delays of `line`, whose next sample is now at `iota`. It is done in place,
+    /// in the order in which the samples still to be read are not overwritten.
+    /// Returns the number of rescaled delays.
+    fn rescale(&mut self, line: &mut [f32], iota: i32, budget: usize) -> usize {
+        let len = line.len();
+        debug_assert!(len.is_power_of_two());
+        let mask = len as i32 - 1;
+        let start = self.start;
+        let index = |delay: usize| ((start - delay as i32) & mask) as usize;
+        //Delays which still hold samples written before the switch
+        let valid = len.saturating_sub(iota.wrapping_sub(start) as usize);
+        let nb_delays = std::cmp::min(budget, len - self.done);
+        for k in self.done..self.done + nb_delays {
+            let delay = if self.ratio < 1. { k + 1 } else { len - k };
+            if delay > valid {
+                continue;
+            }
+            //A delay of 0 is the slot for the next sample
+            let old_delay = (delay as f32 / self.ratio).max(1.);
+            let before = old_delay.floor() as usize;
+            let frac = old_delay - before as f32;
+            let sample = |delay: usize| {
+                if delay <= valid {
+                    line[index(delay)]
+                } else {
+                    0.
+                }
+            };
+            let value = sample(before) * (1. - frac) + sample(before + 1) * frac;
+            line[index(delay)] = value;
+        }
+        self.done += nb_delays;
+        if self.done == len {
+            self.line += 1;
+            self.done = 0;
+        }
+        nb_delays
+    }
+}
+
+/// Next value of the pseudo-random generator of `no.noise`
+fn faust_random(seed: i32) -> i32 {
+    seed.wrapping_mul(1103515245).wrapping_add(12345)
+}
+
+/// Read `line` `delay` samples before `iota`, with a linear interpolation as in `de.fdelay`
+fn fractional_delay(line: &[f32], iota: i32, delay: f32) -> f32 {
+    debug_assert!(line.len().is_power_of_two());
+    let mask = line.len() as i32 - 1;
+    let before = delay.floor();
+    let frac = delay - before;
+    let before = before as i32;
+    line[((iota - before) & mask) as usize] * (1. - frac)
+        + line[((iota - before - 1) & mask) as usize] * frac
+}
+
+/// Effect generated by Faust, whose sliders are smoothed
+pub trait FaustDsp {
+    fn smoother(&mut self) -> &mut Smoother;
+
+    /// Copy the current values of the parameters into the sliders
+    fn update_sliders(&mut self);
+
+    /// Process samples `start..end` of the buffers with the current values of the sliders
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]);
+
+    /// Give their new values to the sliders without smoothing
+    fn skip_smoothing(&mut self) {
+        self.smoother().skip();
+        self.update_sliders();
+    }
+
+    /// See `Smoother::set_smoothing_time`
+    fn set_smoothing_time(&mut self, smoothing_time: f32) {
+        self.smoother().set_smoothing_time(smoothing_time);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch);
+
+    /// Change the samplerate without clearing the state of the effect. See `RateSwitch::Preserve`.
+    fn switch_samplerate(&mut self, sample_rate: i32);
+
+    /// Delay line `index` of the effect, rescaled after a switch with `RateSwitch::Preserve`,
+    /// or None after the last one
+    fn delay_line(&mut self, _index: usize) -> Option<&mut [f32]> {
+        None
+    }
+
+    /// Index of the next sample in the delay lines
+    fn iota(&self) -> i32 {
+        0
+    }
+
+    /// Rescaling of the delay lines in progress, for the effects which have some
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        None
+    }
+
+    /// Go on rescaling the delay lines after a switch with `RateSwitch::Preserve`, for at most
+    /// `RESCALING_BUDGET` samples
+    fn continue_rescaling(&mut self) {
+        let mut rescaling = match self.rescaling() {
+            Some(rescaling) if rescaling.is_active() => *rescaling,
+            _ => return,
+        };
+        let iota = self.iota();
+        let mut budget = RESCALING_BUDGET;
+        while budget > 0 {
+            match self.delay_line(rescaling.line) {
+                Some(line) => budget -= rescaling.rescale(line, iota, budget),
+                None => {
+                    rescaling.active = false;
+                    break;
+                }
+            }
+        }
+        if let Some(state) = self.rescaling() {
+            *state = rescaling;
+        }
+    }
+}
+
+/// Process the buffers at once if the parameters do not change, or sample by sample while they are
+/// moving towards their new values, so that the sliders follow a ramp without steps.
+/// The terms of an effect which depend only on the parameters are then recomputed for every sample,
+/// for the smoothing time after a change.
+pub fn compute_smoothed<T: FaustDsp>(dsp: &mut T, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+    let count = outputs[0].buffer().len();
+    dsp.continue_rescaling();
+    dsp.smoother().set_samplerate(outputs[0].samplerate);
+    let mut start = 0;
+    while start < count && !dsp.smoother().is_settled() {
+        dsp.smoother().tick(1);
+        dsp.update_sliders();
+        dsp.compute(start, start + 1, inputs, outputs);
+        start += 1;
+    }
+    if start < count {
+        dsp.compute(start, count, inputs, outputs);
+    }
+}
+
+pub const GUITAR_PARAMETERS: [Parameter; 4] = [
+    Parameter::new("length", ParameterType::Float, 0.1, 2., 0.75),
+    Parameter::new("pluck_position", ParameterType::Float, 0., 1., 0.5),
+    Parameter::new("gain", ParameterType::Float, 0., 1., 0.9),
+    Parameter::new("trigger", ParameterType::Boolean, 0., 1., 1.),
+];
+
+/// Variant 1 reads the delay lines of the string with a linear interpolation
 pub struct Guitar {
     fDummy: f32,
     iRec10: [i32; 2],
@@ -25,7 +365,7 @@ pub struct Guitar {
     fRec25: [f32; 2],
     fRec27: [f32; 4],
     IOTA: i32,
-    fRec28: [f32; 2048],
+    fRec28: Vec<f32>,
     fVec0: [f32; 2],
     fHslider2: f32,
     fConst3: f32,
@@ -39,14 +379,18 @@ pub struct Guitar {
     fConst5: f32,
     fConst6: f32,
     fVec3: [f32; 2],
-    fRec26: [f32; 2048],
+    fRec26: Vec<f32>,
     fRec19: [f32; 2],
-    fRec16: [f32; 2048],
+    fRec16: Vec<f32>,
     fRec18: [f32; 2],
     fRec15: [f32; 4],
     iRec6: [i32; 2],
-    fRec2: [f32; 2048],
+    fRec2: Vec<f32>,
     fRec0: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+    rescaling: Rescaling,
+    variant: usize,
 }
 
 impl Guitar {
@@ -64,7 +408,7 @@ impl Guitar {
             fRec25: [0.0; 2],
             fRec27: [0.0; 4],
             IOTA: 0,
-            fRec28: [0.0; 2048],
+            fRec28: vec![0.0; 2048],
             fVec0: [0.0; 2],
             fHslider2: 0.0,
             fConst3: 0.0,
@@ -78,14 +422,18 @@ impl Guitar {
             fConst5: 0.0,
             fConst6: 0.0,
             fVec3: [0.0; 2],
-            fRec26: [0.0; 2048],
+            fRec26: vec![0.0; 2048],
             fRec19: [0.0; 2],
-            fRec16: [0.0; 2048],
+            fRec16: vec![0.0; 2048],
             fRec18: [0.0; 2],
             fRec15: [0.0; 4],
             iRec6: [0; 2],
-            fRec2: [0.0; 2048],
+            fRec2: vec![0.0; 2048],
             fRec0: [0.0; 2],
+            smoother: Smoother::new(&GUITAR_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+            rescaling: Rescaling::default(),
+            variant: 0,
         }
     }
 
@@ -176,29 +524,29 @@ impl Guitar {
         gain: f32,
         trigger: u32,
     ) {
-        self.fHslider0 = length;
-        self.fHslider1 = pluck_position;
-        self.fHslider2 = gain;
-        self.fButton0 = trigger as f32;
+        self.smoother.set_target(0, length);
+        self.smoother.set_target(1, pluck_position);
+        self.smoother.set_target(2, gain);
+        self.smoother.set_target(3, trigger as f32);
     }
 
     pub fn new(length: f32, pluck_position: f32, gain: f32, trigger: u32) -> Guitar {
         let mut modu = Guitar::init();
         modu.instanceInit(44_100);
         modu.setControlVariables(length, pluck_position, gain, trigger);
+        modu.skip_smoothing();
         modu
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Guitar {
-        let length = node_infos.more["length"]
-            .parse()
-            .expect("length must be a float");
-        let pluck_position = node_infos.more["pluck_position"]
-            .parse()
-            .expect("pluck_position must be a float");
-        let modu = Guitar::new(length, pluck_position, 0.9, 1);
-        modu.check_io_node_infos(node_infos);
-        modu
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Guitar, AudiographError> {
+        let length = node_infos.attribute("length", "a float")?;
+        let pluck_position = node_infos.attribute("pluck_position", "a float")?;
+        let mut modu = Guitar::new(length, pluck_position, 0.9, 1);
+        modu.check_io_node_infos(node_infos)?;
+        modu.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(modu)
     }
 }
 
@@ -216,18 +564,108 @@ impl AudioEffect for Guitar {
         return 1;
     }
 
+    fn parameters(&self) -> &[Parameter] {
+        &GUITAR_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn nb_variants(&self) -> usize {
+        2
+    }
+
+    fn variant(&self) -> usize {
+        self.variant
+    }
+
+    fn set_variant(&mut self, variant: usize) -> bool {
+        if variant < self.nb_variants() {
+            self.variant = variant;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn variant_cost(&self, variant: usize) -> f64 {
+        //Measured with cost_model::measure_variant_costs in release mode
+        match variant {
+            1 => 0.83,
+            _ => 1.,
+        }
+    }
+
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
         debug_assert_eq!(inputs.len(), self.nb_inputs());
         debug_assert_eq!(outputs.len(), self.nb_outputs());
         let actual_samplerate = outputs[0].samplerate as i32;
-        let output = outputs[0].buffer_mut();
-        let count = output.len();
 
         //Constants have to be changed if we change the samplerate...
-        // We should smooth it actually...
         if self.fSampleRate != actual_samplerate {
-            self.instanceInit(actual_samplerate);
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Guitar {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+        self.fHslider1 = self.smoother.value(1);
+        self.fHslider2 = self.smoother.value(2);
+        self.fButton0 = self.smoother.value(3);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        let ratio = sample_rate as f32 / self.fSampleRate as f32;
+        self.rescaling.start(ratio, self.IOTA);
+        self.instanceConstants(sample_rate);
+    }
+
+    fn delay_line(&mut self, index: usize) -> Option<&mut [f32]> {
+        match index {
+            0 => Some(&mut self.fRec28),
+            1 => Some(&mut self.fRec26),
+            2 => Some(&mut self.fRec16),
+            3 => Some(&mut self.fRec2),
+            _ => None,
         }
+    }
+
+    fn iota(&self) -> i32 {
+        self.IOTA
+    }
+
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        Some(&mut self.rescaling)
+    }
+
+    fn compute(&mut self, start: usize, end: usize, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
         let fSlow0: f32 = self.fHslider0 as f32;
         let fSlow1: f32 = fSlow0 + -0.100000001;
         let fSlow2: f32 = self.fHslider1 as f32;
@@ -304,6 +742,10 @@ impl AudioEffect for Guitar {
         let iSlow67: i32 = iSlow44 + 1;
         let iSlow68: i32 = iSlow48 + 1;
         let iSlow69: i32 = iSlow51 + 1;
+        //Variant 1 uses a linear interpolation instead of a 4th order Lagrange one
+        let linear = self.variant == 1;
+        let fSlowL0: f32 = f32::min(self.fConst2, f32::max(0.0, fSlow3));
+        let fSlowL1: f32 = f32::min(self.fConst2, f32::max(0.0, fSlow28));
         for i in 0..count {
             self.iRec10[0] = 0;
             let mut iRec11: i32 = self.iRec10[1];
@@ -311,12 +753,16 @@ impl AudioEffect for Guitar {
                 - (0.997843683
                     * ((0.699999988 * self.fRec15[2])
                         + (0.150000006 * (self.fRec15[1] + self.fRec15[3]))));
-            self.fRec22[0] = (fSlow10 * self.fRec2[((self.IOTA - iSlow13) & 2047) as usize])
-                + (fSlow14
-                    * ((((fSlow15 * self.fRec2[((self.IOTA - iSlow17) & 2047) as usize])
-                        + (fSlow18 * self.fRec2[((self.IOTA - iSlow20) & 2047) as usize]))
-                        + (fSlow22 * self.fRec2[((self.IOTA - iSlow24) & 2047) as usize]))
-                        + (fSlow25 * self.fRec2[((self.IOTA - iSlow27) & 2047) as usize])));
+            self.fRec22[0] = if linear {
+                fractional_delay(&self.fRec2, self.IOTA, fSlowL0 + 1.0)
+            } else {
+                (fSlow10 * self.fRec2[((self.IOTA - iSlow13) & 2047) as usize])
+                    + (fSlow14
+                        * ((((fSlow15 * self.fRec2[((self.IOTA - iSlow17) & 2047) as usize])
+                            + (fSlow18 * self.fRec2[((self.IOTA - iSlow20) & 2047) as usize]))
+                            + (fSlow22 * self.fRec2[((self.IOTA - iSlow24) & 2047) as usize]))
+                            + (fSlow25 * self.fRec2[((self.IOTA - iSlow27) & 2047) as usize])))
+            };
             self.fRec25[0] = (0.0500000007 * self.fRec25[1]) + (0.949999988 * self.fRec22[1]);
             let mut fRec23: f32 = self.fRec25[0];
             self.fRec27[0] = self.fRec0[1];
@@ -324,13 +770,17 @@ impl AudioEffect for Guitar {
                 * (0.997843683
                     * ((0.699999988 * self.fRec27[2])
                         + (0.150000006 * (self.fRec27[1] + self.fRec27[3]))));
-            self.fVec0[0] = (fSlow35 * self.fRec28[((self.IOTA - iSlow38) & 2047) as usize])
-                + (fSlow39
-                    * ((((fSlow40 * self.fRec28[((self.IOTA - iSlow42) & 2047) as usize])
-                        + (fSlow43 * self.fRec28[((self.IOTA - iSlow45) & 2047) as usize]))
-                        + (fSlow47 * self.fRec28[((self.IOTA - iSlow49) & 2047) as usize]))
-                        + (fSlow50 * self.fRec28[((self.IOTA - iSlow52) & 2047) as usize])));
-            self.iRec30[0] = (1103515245 * self.iRec30[1]) + 12345;
+            self.fVec0[0] = if linear {
+                fractional_delay(&self.fRec28, self.IOTA, fSlowL1 + 2.0)
+            } else {
+                (fSlow35 * self.fRec28[((self.IOTA - iSlow38) & 2047) as usize])
+                    + (fSlow39
+                        * ((((fSlow40 * self.fRec28[((self.IOTA - iSlow42) & 2047) as usize])
+                            + (fSlow43 * self.fRec28[((self.IOTA - iSlow45) & 2047) as usize]))
+                            + (fSlow47 * self.fRec28[((self.IOTA - iSlow49) & 2047) as usize]))
+                            + (fSlow50 * self.fRec28[((self.IOTA - iSlow52) & 2047) as usize])))
+            };
+            self.iRec30[0] = faust_random(self.iRec30[1]);
             self.fRec29[0] = (4.65661287e-10 * (self.iRec30[0] as f32))
                 - (fSlow57 * ((fSlow58 * self.fRec29[2]) + (fSlow59 * self.fRec29[1])));
             self.fVec1[0] = fSlow60;
@@ -368,22 +818,30 @@ impl AudioEffect for Guitar {
             self.fRec26[(self.IOTA & 2047) as usize] = (0.0500000007
                 * self.fRec26[((self.IOTA - 1) & 2047) as usize])
                 + (0.949999988 * self.fVec3[1]);
-            let mut fRec24: f32 = (fSlow10 * self.fRec26[((self.IOTA - iSlow12) & 2047) as usize])
-                + (fSlow14
-                    * ((((fSlow15 * self.fRec26[((self.IOTA - iSlow16) & 2047) as usize])
-                        + (fSlow18 * self.fRec26[((self.IOTA - iSlow19) & 2047) as usize]))
-                        + (fSlow22 * self.fRec26[((self.IOTA - iSlow23) & 2047) as usize]))
-                        + (fSlow25 * self.fRec26[((self.IOTA - iSlow26) & 2047) as usize])));
+            let mut fRec24: f32 = if linear {
+                fractional_delay(&self.fRec26, self.IOTA, fSlowL0)
+            } else {
+                (fSlow10 * self.fRec26[((self.IOTA - iSlow12) & 2047) as usize])
+                    + (fSlow14
+                        * ((((fSlow15 * self.fRec26[((self.IOTA - iSlow16) & 2047) as usize])
+                            + (fSlow18 * self.fRec26[((self.IOTA - iSlow19) & 2047) as usize]))
+                            + (fSlow22 * self.fRec26[((self.IOTA - iSlow23) & 2047) as usize]))
+                            + (fSlow25 * self.fRec26[((self.IOTA - iSlow26) & 2047) as usize])))
+            };
             self.fRec19[0] = fRec23;
             let mut fRec20: f32 = fTemp1 + self.fRec19[1];
             let mut fRec21: f32 = fRec24;
             self.fRec16[(self.IOTA & 2047) as usize] = fRec20;
-            let mut fRec17: f32 = (fSlow35 * self.fRec16[((self.IOTA - iSlow65) & 2047) as usize])
-                + (fSlow39
-                    * ((((fSlow40 * self.fRec16[((self.IOTA - iSlow66) & 2047) as usize])
-                        + (fSlow43 * self.fRec16[((self.IOTA - iSlow67) & 2047) as usize]))
-                        + (fSlow47 * self.fRec16[((self.IOTA - iSlow68) & 2047) as usize]))
-                        + (fSlow50 * self.fRec16[((self.IOTA - iSlow69) & 2047) as usize])));
+            let mut fRec17: f32 = if linear {
+                fractional_delay(&self.fRec16, self.IOTA, fSlowL1 + 1.0)
+            } else {
+                (fSlow35 * self.fRec16[((self.IOTA - iSlow65) & 2047) as usize])
+                    + (fSlow39
+                        * ((((fSlow40 * self.fRec16[((self.IOTA - iSlow66) & 2047) as usize])
+                            + (fSlow43 * self.fRec16[((self.IOTA - iSlow67) & 2047) as usize]))
+                            + (fSlow47 * self.fRec16[((self.IOTA - iSlow68) & 2047) as usize]))
+                            + (fSlow50 * self.fRec16[((self.IOTA - iSlow69) & 2047) as usize])))
+            };
             self.fRec18[0] = fRec21;
             self.fRec15[0] = self.fRec18[1];
             let mut fRec12: f32 = self.fRec15[1];
@@ -402,7 +860,7 @@ impl AudioEffect for Guitar {
             self.iRec10[1] = self.iRec10[0];
             self.fRec22[1] = self.fRec22[0];
             self.fRec25[1] = self.fRec25[0];
-            for j0 in 4..0 {
+            for j0 in (1..4).rev() {
                 self.fRec27[j0] = self.fRec27[j0 - 1];
             }
             self.IOTA = self.IOTA + 1;
@@ -416,7 +874,7 @@ impl AudioEffect for Guitar {
             self.fVec3[1] = self.fVec3[0];
             self.fRec19[1] = self.fRec19[0];
             self.fRec18[1] = self.fRec18[0];
-            for j1 in 4..0 {
+            for j1 in (1..4).rev() {
                 self.fRec15[j1] = self.fRec15[j1 - 1];
             }
             self.iRec6[1] = self.iRec6[0];
@@ -425,120 +883,19 @@ impl AudioEffect for Guitar {
     }
 }
 
-/****************************************
-** Transposer
-****************************************/
-
-pub struct Transposer {
-    fDummy: f32,
-    IOTA: i32,
-    fVec0: [f32; 131072],
-    fHslider0: u32,
-    fHslider1: u32,
-    fRec0: [f32; 2],
-    fHslider2: i32,
-}
-
-impl Transposer {
-    pub fn init() -> Transposer {
-        Transposer {
-            fDummy: 0 as f32,
-            IOTA: 0,
-            fVec0: [0.0; 131072],
-            fHslider0: 128,
-            fHslider1: 32,
-            fRec0: [0.0; 2],
-            fHslider2: 0,
-        }
-    }
-
-    pub fn instanceClear(&mut self) {
-        self.IOTA = 0;
-        for l0 in 0..131072 {
-            self.fVec0[l0 as usize] = 0.0;
-        }
-        for l1 in 0..2 {
-            self.fRec0[l1 as usize] = 0.0;
-        }
-    }
-
-    pub fn new(semitones: i32) -> Transposer {
-        let mut transposer = Transposer::init();
-        transposer.fHslider2 = semitones;
-        transposer
-    }
-
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Transposer {
-        let semitones: i32 = node_infos.more["semitones"]
-            .parse()
-            .expect("semitones must be a positive or negative integer");
-        let transposer = Transposer::new(semitones);
-        transposer.check_io_node_infos(node_infos);
-        transposer
-    }
-}
-
-impl fmt::Display for Transposer {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "transposer({})", self.fHslider2)
-    }
-}
-
-impl AudioEffect for Transposer {
-    fn nb_inputs(&self) -> usize {
-        1
-    }
-    fn nb_outputs(&self) -> usize {
-        1
-    }
-    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
-        debug_assert_eq!(inputs.len(), self.nb_inputs());
-        debug_assert_eq!(outputs.len(), self.nb_outputs());
-
-        let input = inputs[0].buffer();
-        let output = outputs[0].buffer_mut();
-        let count = output.len();
-
-        let fSlow0: f32 = self.fHslider0 as f32;
-        let fSlow1: f32 = f32::powf(2.0, 0.0833333358 * (self.fHslider1 as f32));
-        let fSlow2: f32 = 1.0 / (self.fHslider2 as f32);
-        for i in 0..count {
-            let mut fTemp0: f32 = input[i as usize] as f32;
-            self.fVec0[(self.IOTA & 131071) as usize] = fTemp0;
-            self.fRec0[0] = amath::fmod(fSlow0 + ((self.fRec0[1] + 1.0) - fSlow1), fSlow0);
-            let mut iTemp1: i32 = self.fRec0[0] as i32;
-            let mut fTemp2: f32 = f32::floor(self.fRec0[0]);
-            let mut fTemp3: f32 = 1.0 - self.fRec0[0];
-            let mut fTemp4: f32 = f32::min(fSlow2 * self.fRec0[0], 1.0);
-            let mut fTemp5: f32 = fSlow0 + self.fRec0[0];
-            let mut iTemp6: i32 = fTemp5 as i32;
-            let mut fTemp7: f32 = f32::floor(fTemp5);
-            output[i as usize] = ((((self.fVec0[((self.IOTA
-                - std::cmp::min(65537, std::cmp::max(0, iTemp1)))
-                & 131071) as usize]
-                * (fTemp2 + fTemp3))
-                + ((self.fRec0[0] - fTemp2)
-                    * self.fVec0[((self.IOTA - std::cmp::min(65537, std::cmp::max(0, iTemp1 + 1)))
-                        & 131071) as usize]))
-                * fTemp4)
-                + (((self.fVec0[((self.IOTA - std::cmp::min(65537, std::cmp::max(0, iTemp6)))
-                    & 131071) as usize]
-                    * ((fTemp7 + fTemp3) - fSlow0))
-                    + ((fSlow0 + (self.fRec0[0] - fTemp7))
-                        * self.fVec0[((self.IOTA
-                            - std::cmp::min(65537, std::cmp::max(0, iTemp6 + 1)))
-                            & 131071) as usize]))
-                    * (1.0 - fTemp4))) as f32;
-            self.IOTA = self.IOTA + 1;
-            self.fRec0[1] = self.fRec0[0];
-        }
-    }
-}
-
 /****************************************
 ** Zita reverb stereo
 ****************************************/
 
+pub const ZITA_REVERB_PARAMETERS: [Parameter; 5] = [
+    Parameter::new("rdel", ParameterType::Float, 20., 100., 60.),
+    Parameter::new("f1", ParameterType::Integer, 50., 1000., 200.),
+    Parameter::new("f2", ParameterType::Integer, 1500., 23_520., 6000.),
+    Parameter::new("t60dc", ParameterType::Float, 1., 8., 3.),
+    Parameter::new("t60m", ParameterType::Float, 1., 8., 2.),
+];
+
+/// Variant 1 only runs half of the delay lines of the feedback delay network
 pub struct ZitaReverb {
     fDummy: f32,
     fSampleRate: i32,
@@ -554,85 +911,85 @@ pub struct ZitaReverb {
     fRec11: [f32; 2],
     fRec10: [f32; 2],
     IOTA: i32,
-    fVec0: [f32; 65536],
+    fVec0: Vec<f32>,
     fHslider4: f32,
     fConst5: f32,
     fConst6: f32,
-    fVec1: [f32; 16384],
+    fVec1: Vec<f32>,
     fConst7: f32,
     fHslider5: f32,
-    fVec2: [f32; 2048],
+    fVec2: Vec<f32>,
     fConst8: f32,
     fRec8: [f32; 2],
     fConst9: f32,
     fConst10: f32,
     fRec15: [f32; 2],
     fRec14: [f32; 2],
-    fVec3: [f32; 65536],
+    fVec3: Vec<f32>,
     fConst11: f32,
     fConst12: f32,
-    fVec4: [f32; 4096],
+    fVec4: Vec<f32>,
     fConst13: f32,
     fRec12: [f32; 2],
     fConst14: f32,
     fConst15: f32,
     fRec19: [f32; 2],
     fRec18: [f32; 2],
-    fVec5: [f32; 65536],
+    fVec5: Vec<f32>,
     fConst16: f32,
     fConst17: f32,
-    fVec6: [f32; 4096],
+    fVec6: Vec<f32>,
     fConst18: f32,
     fRec16: [f32; 2],
     fConst19: f32,
     fConst20: f32,
     fRec23: [f32; 2],
     fRec22: [f32; 2],
-    fVec7: [f32; 65536],
+    fVec7: Vec<f32>,
     fConst21: f32,
     fConst22: f32,
-    fVec8: [f32; 4096],
+    fVec8: Vec<f32>,
     fConst23: f32,
     fRec20: [f32; 2],
     fConst24: f32,
     fConst25: f32,
     fRec27: [f32; 2],
     fRec26: [f32; 2],
-    fVec9: [f32; 32768],
+    fVec9: Vec<f32>,
     fConst26: f32,
     fConst27: f32,
-    fVec10: [f32; 16384],
-    fVec11: [f32; 2048],
+    fVec10: Vec<f32>,
+    fVec11: Vec<f32>,
     fConst28: f32,
     fRec24: [f32; 2],
     fConst29: f32,
     fConst30: f32,
     fRec31: [f32; 2],
     fRec30: [f32; 2],
-    fVec12: [f32; 32768],
+    fVec12: Vec<f32>,
     fConst31: f32,
     fConst32: f32,
-    fVec13: [f32; 4096],
+    fVec13: Vec<f32>,
     fConst33: f32,
     fRec28: [f32; 2],
     fConst34: f32,
     fConst35: f32,
     fRec35: [f32; 2],
     fRec34: [f32; 2],
-    fVec14: [f32; 65536],
+    fVec14: Vec<f32>,
     fConst36: f32,
     fConst37: f32,
-    fVec15: [f32; 4096],
+    fVec15: Vec<f32>,
     fConst38: f32,
     fRec32: [f32; 2],
     fConst39: f32,
     fConst40: f32,
     fRec39: [f32; 2],
     fRec38: [f32; 2],
-    fVec16: [f32; 32768],
+    fVec16: Vec<f32>,
     fConst41: f32,
     fConst42: f32,
-    fVec17: [f32; 2048],
+    fVec17: Vec<f32>,
     fConst43: f32,
     fRec36: [f32; 2],
     fRec0: [f32; 3],
@@ -643,6 +1000,10 @@ pub struct ZitaReverb {
     fRec5: [f32; 3],
     fRec6: [f32; 3],
     fRec7: [f32; 3],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+    rescaling: Rescaling,
+    variant: usize,
 }
 
 impl ZitaReverb {
@@ -662,85 +1023,85 @@ impl ZitaReverb {
             fRec11: [0.0; 2],
             fRec10: [0.0; 2],
             IOTA: 0,
-            fVec0: [0.0; 65536],
+            fVec0: vec![0.0; 65536],
             fHslider4: 0.0,
             fConst5: 0.0,
             fConst6: 0.0,
-            fVec1: [0.0; 16384],
+            fVec1: vec![0.0; 16384],
             fConst7: 0.0,
             fHslider5: 0.0,
-            fVec2: [0.0; 2048],
+            fVec2: vec![0.0; 2048],
             fConst8: 0.0,
             fRec8: [0.0; 2],
             fConst9: 0.0,
             fConst10: 0.0,
             fRec15: [0.0; 2],
             fRec14: [0.0; 2],
-            fVec3: [0.0; 65536],
+            fVec3: vec![0.0; 65536],
             fConst11: 0.0,
             fConst12: 0.0,
-            fVec4: [0.0; 4096],
+            fVec4: vec![0.0; 4096],
             fConst13: 0.0,
             fRec12: [0.0; 2],
             fConst14: 0.0,
             fConst15: 0.0,
             fRec19: [0.0; 2],
             fRec18: [0.0; 2],
-            fVec5: [0.0; 65536],
+            fVec5: vec![0.0; 65536],
             fConst16: 0.0,
             fConst17: 0.0,
-            fVec6: [0.0; 4096],
+            fVec6: vec![0.0; 4096],
             fConst18: 0.0,
             fRec16: [0.0; 2],
             fConst19: 0.0,
             fConst20: 0.0,
             fRec23: [0.0; 2],
             fRec22: [0.0; 2],
-            fVec7: [0.0; 65536],
+            fVec7: vec![0.0; 65536],
             fConst21: 0.0,
             fConst22: 0.0,
-            fVec8: [0.0; 4096],
+            fVec8: vec![0.0; 4096],
             fConst23: 0.0,
             fRec20: [0.0; 2],
             fConst24: 0.0,
             fConst25: 0.0,
             fRec27: [0.0; 2],
             fRec26: [0.0; 2],
-            fVec9: [0.0; 32768],
+            fVec9: vec![0.0; 32768],
             fConst26: 0.0,
             fConst27: 0.0,
-            fVec10: [0.0; 16384],
-            fVec11: [0.0; 2048],
+            fVec10: vec![0.0; 16384],
+            fVec11: vec![0.0; 2048],
             fConst28: 0.0,
             fRec24: [0.0; 2],
             fConst29: 0.0,
             fConst30: 0.0,
             fRec31: [0.0; 2],
             fRec30: [0.0; 2],
-            fVec12: [0.0; 32768],
+            fVec12: vec![0.0; 32768],
             fConst31: 0.0,
             fConst32: 0.0,
-            fVec13: [0.0; 4096],
+            fVec13: vec![0.0; 4096],
             fConst33: 0.0,
             fRec28: [0.0; 2],
             fConst34: 0.0,
             fConst35: 0.0,
             fRec35: [0.0; 2],
             fRec34: [0.0; 2],
-            fVec14: [0.0; 65536],
+            fVec14: vec![0.0; 65536],
             fConst36: 0.0,
             fConst37: 0.0,
-            fVec15: [0.0; 4096],
+            fVec15: vec![0.0; 4096],
             fConst38: 0.0,
             fRec32: [0.0; 2],
             fConst39: 0.0,
             fConst40: 0.0,
             fRec39: [0.0; 2],
             fRec38: [0.0; 2],
-            fVec16: [0.0; 32768],
+            fVec16: vec![0.0; 32768],
             fConst41: 0.0,
             fConst42: 0.0,
-            fVec17: [0.0; 2048],
+            fVec17: vec![0.0; 2048],
             fConst43: 0.0,
             fRec36: [0.0; 2],
             fRec0: [0.0; 3],
@@ -751,6 +1112,10 @@ impl ZitaReverb {
             fRec5: [0.0; 3],
             fRec6: [0.0; 3],
             fRec7: [0.0; 3],
+            smoother: Smoother::new(&ZITA_REVERB_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+            rescaling: Rescaling::default(),
+            variant: 0,
         }
     }
 
@@ -970,82 +1335,93 @@ impl ZitaReverb {
         t60m: f32,
         fsmax: u32,
     ) {
-        self.fHslider0 = t60m;
-        self.fHslider1 = f2 as f32;
-        self.fHslider2 = t60dc;
-        self.fHslider3 = f1 as f32;
+        self.smoother.set_target(0, rdel);
+        self.smoother.set_target(1, f1 as f32);
+        self.smoother.set_target(2, f2 as f32);
+        self.smoother.set_target(3, t60dc);
+        self.smoother.set_target(4, t60m);
         self.fHslider4 = fsmax as f32;
-        self.fHslider5 = rdel;
     }
 
     pub fn new(rdel: f32, f1: u32, f2: u32, t60dc: f32, t60m: f32, fsmax: u32) -> ZitaReverb {
         let mut zita_reverb = ZitaReverb::init();
         zita_reverb.instanceInit(44_100);
         zita_reverb.setControlVariables(rdel, f1, f2, t60dc, t60m, fsmax);
+        zita_reverb.skip_smoothing();
         zita_reverb
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> ZitaReverb {
-        let rdel = node_infos.more["rdel"]
-            .parse()
-            .expect("rdel must be a float");
-        let f1 = node_infos.more["f1"]
-            .parse()
-            .expect("f1 must be an integer");
-        let f2 = node_infos.more["f2"]
-            .parse()
-            .expect("f2 must be an integer");
-        let t60dc = node_infos.more["t60dc"]
-            .parse()
-            .expect("t60dc must be a float");
-        let t60m = node_infos.more["t60m"]
-            .parse()
-            .expect("t60m must be a float");
-        let zita_reverb = ZitaReverb::new(rdel, f1, f2, t60dc, t60m, 96_200);
-        zita_reverb.check_io_node_infos(node_infos);
-        zita_reverb
-    }
-}
-
-impl fmt::Display for ZitaReverb {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "zita_reverb({}, {}, {}, {}, {})",
-            self.fHslider5, self.fHslider3, self.fHslider1, self.fHslider2, self.fHslider0
-        )
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<ZitaReverb, AudiographError> {
+        let rdel = node_infos.attribute("rdel", "a float")?;
+        let f1 = node_infos.attribute("f1", "an integer")?;
+        let f2 = node_infos.attribute("f2", "an integer")?;
+        let t60dc = node_infos.attribute("t60dc", "a float")?;
+        let t60m = node_infos.attribute("t60m", "a float")?;
+        let mut zita_reverb = ZitaReverb::new(rdel, f1, f2, t60dc, t60m, 96_200);
+        zita_reverb.check_io_node_infos(node_infos)?;
+        zita_reverb.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(zita_reverb)
     }
-}
 
-impl AudioEffect for ZitaReverb {
-    fn nb_inputs(&self) -> usize {
-        return 2;
-    }
-    fn nb_outputs(&self) -> usize {
-        return 2;
+    /// Clear the odd delay lines of the feedback delay network and their filters, which stay idle
+    /// in variant 1, so that they do not replay a stale tail when going back to the nominal variant
+    fn clear_odd_lines(&mut self) {
+        for line in [
+            &mut self.fVec3,
+            &mut self.fVec4,
+            &mut self.fVec7,
+            &mut self.fVec8,
+            &mut self.fVec12,
+            &mut self.fVec13,
+            &mut self.fVec16,
+            &mut self.fVec17,
+        ]
+        .iter_mut()
+        {
+            for sample in line.iter_mut() {
+                *sample = 0.0;
+            }
+        }
+        for state in [
+            &mut self.fRec12,
+            &mut self.fRec14,
+            &mut self.fRec15,
+            &mut self.fRec20,
+            &mut self.fRec22,
+            &mut self.fRec23,
+            &mut self.fRec28,
+            &mut self.fRec30,
+            &mut self.fRec31,
+            &mut self.fRec36,
+            &mut self.fRec38,
+            &mut self.fRec39,
+        ]
+        .iter_mut()
+        {
+            **state = [0.0; 2];
+        }
     }
 
-    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
-        debug_assert_eq!(inputs.len(), self.nb_inputs());
-        debug_assert_eq!(outputs.len(), self.nb_outputs());
-        let actual_samplerate = outputs[0].samplerate as i32;
-        let input0 = inputs[0].buffer();
-        let input1 = inputs[1].buffer();
-        debug_assert_eq!(input0.len(), input1.len());
-
-        // let output0 = outputs[0].buffer_mut();
-        // let output1 = outputs[1].buffer_mut();
-        debug_assert_eq!(outputs[0].buffer().len(), outputs[1].buffer().len());
-
-        debug_assert_eq!(input0.len(), outputs[0].buffer().len());
-
-        let count = input1.len();
+    /// Cheaper variant with the even delay lines of the feedback delay network only.
+    /// The restriction of the 8x8 Hadamard matrix to these lines is a 4x4 Hadamard matrix,
+    /// so the network stays lossless once the gain of the lines is raised from 1/sqrt(8) to 1/2.
+    /// The odd delay lines are cleared when switching to it.
+    fn compute_four_lines(
+        &mut self,
+        start: usize,
+        end: usize,
+        inputs: &[DspEdge],
+        outputs: &mut [DspEdge],
+    ) {
+        let input0 = &inputs[0].buffer()[start..end];
+        let input1 = &inputs[1].buffer()[start..end];
+        let (outputs0, outputs1) = outputs.split_at_mut(1);
+        let output0 = &mut outputs0[0].buffer_mut()[start..end];
+        let output1 = &mut outputs1[0].buffer_mut()[start..end];
+        let count = output0.len();
 
-        //Constants have to be changed if we change the samplerate...
-        // We should smooth it actually...
-        if self.fSampleRate != actual_samplerate {
-            self.instanceInit(actual_samplerate);
-        }
         let fSlow0: f32 = self.fHslider0 as f32;
         let fSlow1: f32 = f32::exp(self.fConst2 / fSlow0);
         let fSlow2: f32 = faustpower2_f(fSlow1);
@@ -1086,35 +1462,6 @@ impl AudioEffect for ZitaReverb {
             (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow16)))) as i32) as f32,
             self.fConst8,
         ) as i32;
-        let fSlow20: f32 = f32::exp(self.fConst10 / fSlow0);
-        let fSlow21: f32 = faustpower2_f(fSlow20);
-        let fSlow22: f32 = 1.0 - (fSlow21 * fSlow3);
-        let fSlow23: f32 = 1.0 - fSlow21;
-        let fSlow24: f32 = fSlow22 / fSlow23;
-        let fSlow25: f32 = f32::sqrt(f32::max(
-            0.0,
-            (faustpower2_f(fSlow22) / faustpower2_f(fSlow23)) + -1.0,
-        ));
-        let fSlow26: f32 = fSlow24 - fSlow25;
-        let fSlow27: f32 = fSlow20 * (fSlow25 + (1.0 - fSlow24));
-        let fSlow28: f32 = (f32::exp(self.fConst10 / fSlow10) / fSlow20) + -1.0;
-        let fSlow29: f32 = f32::floor((0.0273330007 * fSlow15) + 0.5);
-        let iSlow30: i32 = f32::min(
-            (f32::powf(
-                2.0,
-                f32::max(
-                    1.0,
-                    f32::ceil(
-                        1.44269502 * f32::ln(f32::floor((0.256891012 * fSlow15) + 0.5) - fSlow29),
-                    ),
-                ),
-            ) as i32) as f32,
-            self.fConst12,
-        ) as i32;
-        let iSlow31: i32 = f32::min(
-            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow29)))) as i32) as f32,
-            self.fConst13,
-        ) as i32;
         let fSlow32: f32 = f32::exp(self.fConst15 / fSlow0);
         let fSlow33: f32 = faustpower2_f(fSlow32);
         let fSlow34: f32 = 1.0 - (fSlow33 * fSlow3);
@@ -1144,35 +1491,6 @@ impl AudioEffect for ZitaReverb {
             (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow41)))) as i32) as f32,
             self.fConst18,
         ) as i32;
-        let fSlow44: f32 = f32::exp(self.fConst20 / fSlow0);
-        let fSlow45: f32 = faustpower2_f(fSlow44);
-        let fSlow46: f32 = 1.0 - (fSlow45 * fSlow3);
-        let fSlow47: f32 = 1.0 - fSlow45;
-        let fSlow48: f32 = fSlow46 / fSlow47;
-        let fSlow49: f32 = f32::sqrt(f32::max(
-            0.0,
-            (faustpower2_f(fSlow46) / faustpower2_f(fSlow47)) + -1.0,
-        ));
-        let fSlow50: f32 = fSlow48 - fSlow49;
-        let fSlow51: f32 = fSlow44 * (fSlow49 + (1.0 - fSlow48));
-        let fSlow52: f32 = (f32::exp(self.fConst20 / fSlow10) / fSlow44) + -1.0;
-        let fSlow53: f32 = f32::floor((0.0244210009 * fSlow15) + 0.5);
-        let iSlow54: i32 = f32::min(
-            (f32::powf(
-                2.0,
-                f32::max(
-                    1.0,
-                    f32::ceil(
-                        1.44269502 * f32::ln(f32::floor((0.210389003 * fSlow15) + 0.5) - fSlow53),
-                    ),
-                ),
-            ) as i32) as f32,
-            self.fConst22,
-        ) as i32;
-        let iSlow55: i32 = f32::min(
-            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow53)))) as i32) as f32,
-            self.fConst23,
-        ) as i32;
         let fSlow56: f32 = f32::exp(self.fConst25 / fSlow0);
         let fSlow57: f32 = faustpower2_f(fSlow56);
         let fSlow58: f32 = 1.0 - (fSlow57 * fSlow3);
@@ -1200,35 +1518,6 @@ impl AudioEffect for ZitaReverb {
             (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow65)))) as i32) as f32,
             self.fConst28,
         ) as i32;
-        let fSlow68: f32 = f32::exp(self.fConst30 / fSlow0);
-        let fSlow69: f32 = faustpower2_f(fSlow68);
-        let fSlow70: f32 = 1.0 - (fSlow69 * fSlow3);
-        let fSlow71: f32 = 1.0 - fSlow69;
-        let fSlow72: f32 = fSlow70 / fSlow71;
-        let fSlow73: f32 = f32::sqrt(f32::max(
-            0.0,
-            (faustpower2_f(fSlow70) / faustpower2_f(fSlow71)) + -1.0,
-        ));
-        let fSlow74: f32 = fSlow72 - fSlow73;
-        let fSlow75: f32 = fSlow68 * (fSlow73 + (1.0 - fSlow72));
-        let fSlow76: f32 = (f32::exp(self.fConst30 / fSlow10) / fSlow68) + -1.0;
-        let fSlow77: f32 = f32::floor((0.0316039994 * fSlow15) + 0.5);
-        let iSlow78: i32 = f32::min(
-            (f32::powf(
-                2.0,
-                f32::max(
-                    1.0,
-                    f32::ceil(
-                        1.44269502 * f32::ln(f32::floor((0.127837002 * fSlow15) + 0.5) - fSlow77),
-                    ),
-                ),
-            ) as i32) as f32,
-            self.fConst32,
-        ) as i32;
-        let iSlow79: i32 = f32::min(
-            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow77)))) as i32) as f32,
-            self.fConst33,
-        ) as i32;
         let fSlow80: f32 = f32::exp(self.fConst35 / fSlow0);
         let fSlow81: f32 = faustpower2_f(fSlow80);
         let fSlow82: f32 = 1.0 - (fSlow81 * fSlow3);
@@ -1258,45 +1547,13 @@ impl AudioEffect for ZitaReverb {
             (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow89)))) as i32) as f32,
             self.fConst38,
         ) as i32;
-        let fSlow92: f32 = f32::exp(self.fConst40 / fSlow0);
-        let fSlow93: f32 = faustpower2_f(fSlow92);
-        let fSlow94: f32 = 1.0 - (fSlow93 * fSlow3);
-        let fSlow95: f32 = 1.0 - fSlow93;
-        let fSlow96: f32 = fSlow94 / fSlow95;
-        let fSlow97: f32 = f32::sqrt(f32::max(
-            0.0,
-            (faustpower2_f(fSlow94) / faustpower2_f(fSlow95)) + -1.0,
-        ));
-        let fSlow98: f32 = fSlow96 - fSlow97;
-        let fSlow99: f32 = fSlow92 * (fSlow97 + (1.0 - fSlow96));
-        let fSlow100: f32 = (f32::exp(self.fConst40 / fSlow10) / fSlow92) + -1.0;
-        let fSlow101: f32 = f32::floor((0.0203460008 * fSlow15) + 0.5);
-        let iSlow102: i32 = f32::min(
-            (f32::powf(
-                2.0,
-                f32::max(
-                    1.0,
-                    f32::ceil(
-                        1.44269502 * f32::ln(f32::floor((0.153128996 * fSlow15) + 0.5) - fSlow101),
-                    ),
-                ),
-            ) as i32) as f32,
-            self.fConst42,
-        ) as i32;
-        let iSlow103: i32 = f32::min(
-            (f32::powf(
-                2.0,
-                f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow101))),
-            ) as i32) as f32,
-            self.fConst43,
-        ) as i32;
         for i in 0..count {
             self.fRec11[0] =
                 0.0 - (fSlow13 * ((fSlow14 * self.fRec11[1]) - (self.fRec7[1] + self.fRec7[2])));
             self.fRec10[0] =
                 (fSlow8 * self.fRec10[1]) + (fSlow9 * (self.fRec7[1] + (fSlow11 * self.fRec11[0])));
             self.fVec0[(self.IOTA & 65535) as usize] =
-                (0.353553385 * self.fRec10[0]) + 9.99999968e-21;
+                (0.5 * self.fRec10[0]) + 9.99999968e-21;
             self.fVec1[(self.IOTA & 16383) as usize] = input1[i as usize] as f32;
             let mut fTemp0: f32 =
                 0.300000012 * self.fVec1[((self.IOTA - iSlow18) & 16383) as usize];
@@ -1306,47 +1563,23 @@ impl AudioEffect for ZitaReverb {
             self.fVec2[(self.IOTA & 2047) as usize] = fTemp1;
             self.fRec8[0] = self.fVec2[((self.IOTA - iSlow19) & 2047) as usize];
             let mut fRec9: f32 = 0.0 - (0.600000024 * fTemp1);
-            self.fRec15[0] =
-                0.0 - (fSlow13 * ((fSlow14 * self.fRec15[1]) - (self.fRec3[1] + self.fRec3[2])));
-            self.fRec14[0] = (fSlow26 * self.fRec14[1])
-                + (fSlow27 * (self.fRec3[1] + (fSlow28 * self.fRec15[0])));
-            self.fVec3[(self.IOTA & 65535) as usize] =
-                (0.353553385 * self.fRec14[0]) + 9.99999968e-21;
-            let mut fTemp2: f32 = ((0.600000024 * self.fRec12[1])
-                + self.fVec3[((self.IOTA - iSlow30) & 65535) as usize])
-                - fTemp0;
-            self.fVec4[(self.IOTA & 4095) as usize] = fTemp2;
-            self.fRec12[0] = self.fVec4[((self.IOTA - iSlow31) & 4095) as usize];
-            let mut fRec13: f32 = 0.0 - (0.600000024 * fTemp2);
             self.fRec19[0] =
                 0.0 - (fSlow13 * ((fSlow14 * self.fRec19[1]) - (self.fRec5[1] + self.fRec5[2])));
             self.fRec18[0] = (fSlow38 * self.fRec18[1])
                 + (fSlow39 * (self.fRec5[1] + (fSlow40 * self.fRec19[0])));
             self.fVec5[(self.IOTA & 65535) as usize] =
-                (0.353553385 * self.fRec18[0]) + 9.99999968e-21;
+                (0.5 * self.fRec18[0]) + 9.99999968e-21;
             let mut fTemp3: f32 = (0.600000024 * self.fRec16[1])
                 + (fTemp0 + self.fVec5[((self.IOTA - iSlow42) & 65535) as usize]);
             self.fVec6[(self.IOTA & 4095) as usize] = fTemp3;
             self.fRec16[0] = self.fVec6[((self.IOTA - iSlow43) & 4095) as usize];
             let mut fRec17: f32 = 0.0 - (0.600000024 * fTemp3);
-            self.fRec23[0] =
-                0.0 - (fSlow13 * ((fSlow14 * self.fRec23[1]) - (self.fRec1[1] + self.fRec1[2])));
-            self.fRec22[0] = (fSlow50 * self.fRec22[1])
-                + (fSlow51 * (self.fRec1[1] + (fSlow52 * self.fRec23[0])));
-            self.fVec7[(self.IOTA & 65535) as usize] =
-                (0.353553385 * self.fRec22[0]) + 9.99999968e-21;
-            let mut fTemp4: f32 = fTemp0
-                + ((0.600000024 * self.fRec20[1])
-                    + self.fVec7[((self.IOTA - iSlow54) & 65535) as usize]);
-            self.fVec8[(self.IOTA & 4095) as usize] = fTemp4;
-            self.fRec20[0] = self.fVec8[((self.IOTA - iSlow55) & 4095) as usize];
-            let mut fRec21: f32 = 0.0 - (0.600000024 * fTemp4);
             self.fRec27[0] =
                 0.0 - (fSlow13 * ((fSlow14 * self.fRec27[1]) - (self.fRec6[1] + self.fRec6[2])));
             self.fRec26[0] = (fSlow62 * self.fRec26[1])
                 + (fSlow63 * (self.fRec6[1] + (fSlow64 * self.fRec27[0])));
             self.fVec9[(self.IOTA & 32767) as usize] =
-                (0.353553385 * self.fRec26[0]) + 9.99999968e-21;
+                (0.5 * self.fRec26[0]) + 9.99999968e-21;
             self.fVec10[(self.IOTA & 16383) as usize] = input0[i as usize] as f32;
             let mut fTemp5: f32 =
                 0.300000012 * self.fVec10[((self.IOTA - iSlow18) & 16383) as usize];
@@ -1355,124 +1588,44 @@ impl AudioEffect for ZitaReverb {
             self.fVec11[(self.IOTA & 2047) as usize] = fTemp6;
             self.fRec24[0] = self.fVec11[((self.IOTA - iSlow67) & 2047) as usize];
             let mut fRec25: f32 = 0.600000024 * fTemp6;
-            self.fRec31[0] =
-                0.0 - (fSlow13 * ((fSlow14 * self.fRec31[1]) - (self.fRec2[1] + self.fRec2[2])));
-            self.fRec30[0] = (fSlow74 * self.fRec30[1])
-                + (fSlow75 * (self.fRec2[1] + (fSlow76 * self.fRec31[0])));
-            self.fVec12[(self.IOTA & 32767) as usize] =
-                (0.353553385 * self.fRec30[0]) + 9.99999968e-21;
-            let mut fTemp7: f32 = self.fVec12[((self.IOTA - iSlow78) & 32767) as usize]
-                - (fTemp5 + (0.600000024 * self.fRec28[1]));
-            self.fVec13[(self.IOTA & 4095) as usize] = fTemp7;
-            self.fRec28[0] = self.fVec13[((self.IOTA - iSlow79) & 4095) as usize];
-            let mut fRec29: f32 = 0.600000024 * fTemp7;
             self.fRec35[0] =
                 0.0 - (fSlow13 * ((fSlow14 * self.fRec35[1]) - (self.fRec4[1] + self.fRec4[2])));
             self.fRec34[0] = (fSlow86 * self.fRec34[1])
                 + (fSlow87 * (self.fRec4[1] + (fSlow88 * self.fRec35[0])));
             self.fVec14[(self.IOTA & 65535) as usize] =
-                (0.353553385 * self.fRec34[0]) + 9.99999968e-21;
+                (0.5 * self.fRec34[0]) + 9.99999968e-21;
             let mut fTemp8: f32 = (fTemp5 + self.fVec14[((self.IOTA - iSlow90) & 65535) as usize])
                 - (0.600000024 * self.fRec32[1]);
             self.fVec15[(self.IOTA & 4095) as usize] = fTemp8;
             self.fRec32[0] = self.fVec15[((self.IOTA - iSlow91) & 4095) as usize];
             let mut fRec33: f32 = 0.600000024 * fTemp8;
-            self.fRec39[0] =
-                0.0 - (fSlow13 * ((fSlow14 * self.fRec39[1]) - (self.fRec0[1] + self.fRec0[2])));
-            self.fRec38[0] = (fSlow98 * self.fRec38[1])
-                + (fSlow99 * (self.fRec0[1] + (fSlow100 * self.fRec39[0])));
-            self.fVec16[(self.IOTA & 32767) as usize] =
-                (0.353553385 * self.fRec38[0]) + 9.99999968e-21;
-            let mut fTemp9: f32 = (self.fVec16[((self.IOTA - iSlow102) & 32767) as usize] + fTemp5)
-                - (0.600000024 * self.fRec36[1]);
-            self.fVec17[(self.IOTA & 2047) as usize] = fTemp9;
-            self.fRec36[0] = self.fVec17[((self.IOTA - iSlow103) & 2047) as usize];
-            let mut fRec37: f32 = 0.600000024 * fTemp9;
-            let mut fTemp10: f32 = self.fRec12[1] + self.fRec8[1];
-            let mut fTemp11: f32 = self.fRec32[1] + (self.fRec36[1] + fTemp10);
-            self.fRec0[0] = fRec9
-                + (fRec13
-                    + (fRec17
-                        + (fRec21
-                            + (fRec25
-                                + (fRec29
-                                    + (fRec33
-                                        + (fRec37
-                                            + (self.fRec16[1]
-                                                + (self.fRec20[1]
-                                                    + (self.fRec24[1]
-                                                        + (self.fRec28[1] + fTemp11)))))))))));
-            let mut fTemp12: f32 = self.fRec36[1] + self.fRec32[1];
-            self.fRec1[0] = (fRec25
-                + (fRec29 + (fRec33 + (fRec37 + (self.fRec24[1] + (fTemp12 + self.fRec28[1]))))))
-                - (fRec9
-                    + (fRec13
-                        + (fRec17 + (fRec21 + (self.fRec16[1] + (self.fRec20[1] + fTemp10))))));
-            self.fRec2[0] = (fRec17
-                + (fRec21 + (fRec33 + (fRec37 + (self.fRec16[1] + (fTemp12 + self.fRec20[1]))))))
-                - (fRec9
-                    + (fRec13
-                        + (fRec25 + (fRec29 + (self.fRec24[1] + (self.fRec28[1] + fTemp10))))));
-            self.fRec3[0] = (fRec9 + (fRec13 + (fRec33 + (fRec37 + fTemp11))))
-                - (fRec17
-                    + (fRec21
-                        + (fRec25
-                            + (fRec29
-                                + (self.fRec16[1]
-                                    + ((self.fRec28[1] + self.fRec24[1]) + self.fRec20[1]))))));
-            let mut fTemp13: f32 = self.fRec36[1] + self.fRec12[1];
-            let mut fTemp14: f32 = self.fRec32[1] + self.fRec8[1];
-            self.fRec4[0] = (fRec13
-                + (fRec21 + (fRec29 + (fRec37 + (self.fRec20[1] + (self.fRec28[1] + fTemp13))))))
-                - (fRec9
-                    + (fRec17
-                        + (fRec25 + (fRec33 + (self.fRec16[1] + (self.fRec24[1] + fTemp14))))));
-            let mut fTemp15: f32 = self.fRec36[1] + self.fRec8[1];
-            let mut fTemp16: f32 = self.fRec32[1] + self.fRec12[1];
-            self.fRec5[0] = (fRec9
-                + (fRec17 + (fRec29 + (fRec37 + (self.fRec16[1] + (self.fRec28[1] + fTemp15))))))
-                - (fRec13
-                    + (fRec21
-                        + (fRec25 + (fRec33 + (self.fRec20[1] + (self.fRec24[1] + fTemp16))))));
-            self.fRec6[0] = (fRec9
-                + (fRec21 + (fRec25 + (fRec37 + (self.fRec20[1] + (self.fRec24[1] + fTemp15))))))
-                - (fRec13
-                    + (fRec17
-                        + (fRec29 + (fRec33 + (self.fRec16[1] + (self.fRec28[1] + fTemp16))))));
-            self.fRec7[0] = (fRec13
-                + (fRec17 + (fRec25 + (fRec37 + (self.fRec16[1] + (self.fRec24[1] + fTemp13))))))
-                - (fRec9
-                    + (fRec21
-                        + (fRec29 + (fRec33 + (self.fRec20[1] + (self.fRec28[1] + fTemp14))))));
-            outputs[0].buffer_mut()[i as usize] =
-                (0.370000005 * (self.fRec1[0] + self.fRec2[0])) as f32;
-            outputs[1].buffer_mut()[i as usize] =
-                (0.370000005 * (self.fRec1[0] - self.fRec2[0])) as f32;
+            let mut fTemp10: f32 = fRec9 + self.fRec8[1];
+            let mut fTemp11: f32 = fRec17 + self.fRec16[1];
+            let mut fTemp12: f32 = fRec25 + self.fRec24[1];
+            let mut fTemp13: f32 = fRec33 + self.fRec32[1];
+            self.fRec0[0] = fTemp10 + (fTemp11 + (fTemp12 + fTemp13));
+            self.fRec1[0] = (fTemp12 + fTemp13) - (fTemp10 + fTemp11);
+            self.fRec2[0] = (fTemp11 + fTemp13) - (fTemp10 + fTemp12);
+            self.fRec3[0] = (fTemp10 + fTemp13) - (fTemp11 + fTemp12);
+            self.fRec4[0] = 0.0 - self.fRec0[0];
+            self.fRec5[0] = 0.0 - self.fRec1[0];
+            self.fRec6[0] = 0.0 - self.fRec2[0];
+            self.fRec7[0] = 0.0 - self.fRec3[0];
+            output0[i as usize] = (0.370000005 * (self.fRec1[0] + self.fRec2[0])) as f32;
+            output1[i as usize] = (0.370000005 * (self.fRec1[0] - self.fRec2[0])) as f32;
             self.fRec11[1] = self.fRec11[0];
             self.fRec10[1] = self.fRec10[0];
             self.IOTA = self.IOTA + 1;
             self.fRec8[1] = self.fRec8[0];
-            self.fRec15[1] = self.fRec15[0];
-            self.fRec14[1] = self.fRec14[0];
-            self.fRec12[1] = self.fRec12[0];
             self.fRec19[1] = self.fRec19[0];
             self.fRec18[1] = self.fRec18[0];
             self.fRec16[1] = self.fRec16[0];
-            self.fRec23[1] = self.fRec23[0];
-            self.fRec22[1] = self.fRec22[0];
-            self.fRec20[1] = self.fRec20[0];
             self.fRec27[1] = self.fRec27[0];
             self.fRec26[1] = self.fRec26[0];
             self.fRec24[1] = self.fRec24[0];
-            self.fRec31[1] = self.fRec31[0];
-            self.fRec30[1] = self.fRec30[0];
-            self.fRec28[1] = self.fRec28[0];
             self.fRec35[1] = self.fRec35[0];
             self.fRec34[1] = self.fRec34[0];
             self.fRec32[1] = self.fRec32[0];
-            self.fRec39[1] = self.fRec39[0];
-            self.fRec38[1] = self.fRec38[0];
-            self.fRec36[1] = self.fRec36[0];
             self.fRec0[2] = self.fRec0[1];
             self.fRec0[1] = self.fRec0[0];
             self.fRec1[2] = self.fRec1[1];
@@ -1493,245 +1646,2144 @@ impl AudioEffect for ZitaReverb {
     }
 }
 
-/****************************************
-** Mono freeverb
-****************************************/
-
-pub struct MonoFreeverb {
-    fDummy: f32,
-    fHslider0: f32,
-    fHslider1: f32,
-    fRec9: [f32; 2],
-    IOTA: i32,
-    fVec0: [f32; 8192],
-    fSampleRate: i32,
-    fConst0: f32,
-    fConst1: f32,
-    fHslider2: f32,
-    fRec8: [f32; 2],
-    fRec11: [f32; 2],
-    fVec1: [f32; 8192],
-    fConst2: f32,
-    fRec10: [f32; 2],
-    fRec13: [f32; 2],
-    fVec2: [f32; 8192],
-    fConst3: f32,
-    fRec12: [f32; 2],
-    fRec15: [f32; 2],
-    fVec3: [f32; 8192],
-    fConst4: f32,
-    fRec14: [f32; 2],
-    fRec17: [f32; 2],
-    fVec4: [f32; 8192],
-    fConst5: f32,
-    fRec16: [f32; 2],
-    fRec19: [f32; 2],
-    fVec5: [f32; 8192],
-    fConst6: f32,
-    fRec18: [f32; 2],
-    fRec21: [f32; 2],
-    fVec6: [f32; 8192],
-    fConst7: f32,
-    fRec20: [f32; 2],
-    fRec23: [f32; 2],
-    fVec7: [f32; 8192],
-    fConst8: f32,
-    fRec22: [f32; 2],
-    fHslider3: f32,
-    fVec8: [f32; 2048],
-    fConst9: f32,
-    fRec6: [f32; 2],
-    fVec9: [f32; 2048],
-    fConst10: f32,
-    fRec4: [f32; 2],
-    fVec10: [f32; 2048],
-    fConst11: f32,
-    fRec2: [f32; 2],
-    fVec11: [f32; 1024],
-    fConst12: f32,
-    fRec0: [f32; 2],
+impl fmt::Display for ZitaReverb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "zita_reverb({}, {}, {}, {}, {})",
+            self.fHslider5, self.fHslider3, self.fHslider1, self.fHslider2, self.fHslider0
+        )
+    }
 }
 
-impl MonoFreeverb {
-    pub fn init() -> MonoFreeverb {
-        MonoFreeverb {
-            fDummy: 0 as f32,
-            fHslider0: 0.0,
-            fHslider1: 0.0,
+impl AudioEffect for ZitaReverb {
+    fn nb_inputs(&self) -> usize {
+        return 2;
+    }
+    fn nb_outputs(&self) -> usize {
+        return 2;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &ZITA_REVERB_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn nb_variants(&self) -> usize {
+        2
+    }
+
+    fn variant(&self) -> usize {
+        self.variant
+    }
+
+    fn set_variant(&mut self, variant: usize) -> bool {
+        if variant < self.nb_variants() {
+            if variant == 1 && self.variant != 1 {
+                self.clear_odd_lines();
+            }
+            self.variant = variant;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn variant_cost(&self, variant: usize) -> f64 {
+        //Measured with cost_model::measure_variant_costs in release mode
+        match variant {
+            1 => 0.57,
+            _ => 1.,
+        }
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.fSampleRate != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for ZitaReverb {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider5 = self.smoother.value(0);
+        self.fHslider3 = self.smoother.value(1);
+        self.fHslider1 = self.smoother.value(2);
+        self.fHslider2 = self.smoother.value(3);
+        self.fHslider0 = self.smoother.value(4);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        let ratio = sample_rate as f32 / self.fSampleRate as f32;
+        self.rescaling.start(ratio, self.IOTA);
+        self.instanceConstants(sample_rate);
+    }
+
+    fn delay_line(&mut self, index: usize) -> Option<&mut [f32]> {
+        match index {
+            0 => Some(&mut self.fVec0),
+            1 => Some(&mut self.fVec1),
+            2 => Some(&mut self.fVec2),
+            3 => Some(&mut self.fVec3),
+            4 => Some(&mut self.fVec4),
+            5 => Some(&mut self.fVec5),
+            6 => Some(&mut self.fVec6),
+            7 => Some(&mut self.fVec7),
+            8 => Some(&mut self.fVec8),
+            9 => Some(&mut self.fVec9),
+            10 => Some(&mut self.fVec10),
+            11 => Some(&mut self.fVec11),
+            12 => Some(&mut self.fVec12),
+            13 => Some(&mut self.fVec13),
+            14 => Some(&mut self.fVec14),
+            15 => Some(&mut self.fVec15),
+            16 => Some(&mut self.fVec16),
+            17 => Some(&mut self.fVec17),
+            _ => None,
+        }
+    }
+
+    fn iota(&self) -> i32 {
+        self.IOTA
+    }
+
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        Some(&mut self.rescaling)
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        if self.variant == 1 {
+            return self.compute_four_lines(start, end, inputs, outputs);
+        }
+        let input0 = &inputs[0].buffer()[start..end];
+        let input1 = &inputs[1].buffer()[start..end];
+        let (outputs0, outputs1) = outputs.split_at_mut(1);
+        let output0 = &mut outputs0[0].buffer_mut()[start..end];
+        let output1 = &mut outputs1[0].buffer_mut()[start..end];
+        let count = output0.len();
+
+        let fSlow0: f32 = self.fHslider0 as f32;
+        let fSlow1: f32 = f32::exp(self.fConst2 / fSlow0);
+        let fSlow2: f32 = faustpower2_f(fSlow1);
+        let fSlow3: f32 = f32::cos(self.fConst3 * (self.fHslider1 as f32));
+        let fSlow4: f32 = 1.0 - (fSlow2 * fSlow3);
+        let fSlow5: f32 = 1.0 - fSlow2;
+        let fSlow6: f32 = fSlow4 / fSlow5;
+        let fSlow7: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow4) / faustpower2_f(fSlow5)) + -1.0,
+        ));
+        let fSlow8: f32 = fSlow6 - fSlow7;
+        let fSlow9: f32 = fSlow1 * (fSlow7 + (1.0 - fSlow6));
+        let fSlow10: f32 = self.fHslider2 as f32;
+        let fSlow11: f32 = (f32::exp(self.fConst2 / fSlow10) / fSlow1) + -1.0;
+        let fSlow12: f32 = 1.0 / f32::tan(self.fConst4 * (self.fHslider3 as f32));
+        let fSlow13: f32 = 1.0 / (fSlow12 + 1.0);
+        let fSlow14: f32 = 1.0 - fSlow12;
+        let fSlow15: f32 = self.fHslider4 as f32;
+        let fSlow16: f32 = f32::floor((0.0191229992 * fSlow15) + 0.5);
+        let iSlow17: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.219990999 * fSlow15) + 0.5) - fSlow16),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst6,
+        ) as i32;
+        let iSlow18: i32 = f32::min(
+            8192.0,
+            f32::max(0.0, self.fConst7 * (self.fHslider5 as f32)),
+        ) as i32;
+        let iSlow19: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow16)))) as i32) as f32,
+            self.fConst8,
+        ) as i32;
+        let fSlow20: f32 = f32::exp(self.fConst10 / fSlow0);
+        let fSlow21: f32 = faustpower2_f(fSlow20);
+        let fSlow22: f32 = 1.0 - (fSlow21 * fSlow3);
+        let fSlow23: f32 = 1.0 - fSlow21;
+        let fSlow24: f32 = fSlow22 / fSlow23;
+        let fSlow25: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow22) / faustpower2_f(fSlow23)) + -1.0,
+        ));
+        let fSlow26: f32 = fSlow24 - fSlow25;
+        let fSlow27: f32 = fSlow20 * (fSlow25 + (1.0 - fSlow24));
+        let fSlow28: f32 = (f32::exp(self.fConst10 / fSlow10) / fSlow20) + -1.0;
+        let fSlow29: f32 = f32::floor((0.0273330007 * fSlow15) + 0.5);
+        let iSlow30: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.256891012 * fSlow15) + 0.5) - fSlow29),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst12,
+        ) as i32;
+        let iSlow31: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow29)))) as i32) as f32,
+            self.fConst13,
+        ) as i32;
+        let fSlow32: f32 = f32::exp(self.fConst15 / fSlow0);
+        let fSlow33: f32 = faustpower2_f(fSlow32);
+        let fSlow34: f32 = 1.0 - (fSlow33 * fSlow3);
+        let fSlow35: f32 = 1.0 - fSlow33;
+        let fSlow36: f32 = fSlow34 / fSlow35;
+        let fSlow37: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow34) / faustpower2_f(fSlow35)) + -1.0,
+        ));
+        let fSlow38: f32 = fSlow36 - fSlow37;
+        let fSlow39: f32 = fSlow32 * (fSlow37 + (1.0 - fSlow36));
+        let fSlow40: f32 = (f32::exp(self.fConst15 / fSlow10) / fSlow32) + -1.0;
+        let fSlow41: f32 = f32::floor((0.0292910002 * fSlow15) + 0.5);
+        let iSlow42: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.192303002 * fSlow15) + 0.5) - fSlow41),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst17,
+        ) as i32;
+        let iSlow43: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow41)))) as i32) as f32,
+            self.fConst18,
+        ) as i32;
+        let fSlow44: f32 = f32::exp(self.fConst20 / fSlow0);
+        let fSlow45: f32 = faustpower2_f(fSlow44);
+        let fSlow46: f32 = 1.0 - (fSlow45 * fSlow3);
+        let fSlow47: f32 = 1.0 - fSlow45;
+        let fSlow48: f32 = fSlow46 / fSlow47;
+        let fSlow49: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow46) / faustpower2_f(fSlow47)) + -1.0,
+        ));
+        let fSlow50: f32 = fSlow48 - fSlow49;
+        let fSlow51: f32 = fSlow44 * (fSlow49 + (1.0 - fSlow48));
+        let fSlow52: f32 = (f32::exp(self.fConst20 / fSlow10) / fSlow44) + -1.0;
+        let fSlow53: f32 = f32::floor((0.0244210009 * fSlow15) + 0.5);
+        let iSlow54: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.210389003 * fSlow15) + 0.5) - fSlow53),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst22,
+        ) as i32;
+        let iSlow55: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow53)))) as i32) as f32,
+            self.fConst23,
+        ) as i32;
+        let fSlow56: f32 = f32::exp(self.fConst25 / fSlow0);
+        let fSlow57: f32 = faustpower2_f(fSlow56);
+        let fSlow58: f32 = 1.0 - (fSlow57 * fSlow3);
+        let fSlow59: f32 = 1.0 - fSlow57;
+        let fSlow60: f32 = fSlow58 / fSlow59;
+        let fSlow61: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow58) / faustpower2_f(fSlow59)) + -1.0,
+        ));
+        let fSlow62: f32 = fSlow60 - fSlow61;
+        let fSlow63: f32 = fSlow56 * (fSlow61 + (1.0 - fSlow60));
+        let fSlow64: f32 = (f32::exp(self.fConst25 / fSlow10) / fSlow56) + -1.0;
+        let fSlow65: f32 = f32::floor((0.0134579996 * fSlow15) + 0.5);
+        let iSlow66: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(1.44269502 * f32::ln(f32::floor((0.125 * fSlow15) + 0.5) - fSlow65)),
+                ),
+            ) as i32) as f32,
+            self.fConst27,
+        ) as i32;
+        let iSlow67: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow65)))) as i32) as f32,
+            self.fConst28,
+        ) as i32;
+        let fSlow68: f32 = f32::exp(self.fConst30 / fSlow0);
+        let fSlow69: f32 = faustpower2_f(fSlow68);
+        let fSlow70: f32 = 1.0 - (fSlow69 * fSlow3);
+        let fSlow71: f32 = 1.0 - fSlow69;
+        let fSlow72: f32 = fSlow70 / fSlow71;
+        let fSlow73: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow70) / faustpower2_f(fSlow71)) + -1.0,
+        ));
+        let fSlow74: f32 = fSlow72 - fSlow73;
+        let fSlow75: f32 = fSlow68 * (fSlow73 + (1.0 - fSlow72));
+        let fSlow76: f32 = (f32::exp(self.fConst30 / fSlow10) / fSlow68) + -1.0;
+        let fSlow77: f32 = f32::floor((0.0316039994 * fSlow15) + 0.5);
+        let iSlow78: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.127837002 * fSlow15) + 0.5) - fSlow77),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst32,
+        ) as i32;
+        let iSlow79: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow77)))) as i32) as f32,
+            self.fConst33,
+        ) as i32;
+        let fSlow80: f32 = f32::exp(self.fConst35 / fSlow0);
+        let fSlow81: f32 = faustpower2_f(fSlow80);
+        let fSlow82: f32 = 1.0 - (fSlow81 * fSlow3);
+        let fSlow83: f32 = 1.0 - fSlow81;
+        let fSlow84: f32 = fSlow82 / fSlow83;
+        let fSlow85: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow82) / faustpower2_f(fSlow83)) + -1.0,
+        ));
+        let fSlow86: f32 = fSlow84 - fSlow85;
+        let fSlow87: f32 = fSlow80 * (fSlow85 + (1.0 - fSlow84));
+        let fSlow88: f32 = (f32::exp(self.fConst35 / fSlow10) / fSlow80) + -1.0;
+        let fSlow89: f32 = f32::floor((0.0229039993 * fSlow15) + 0.5);
+        let iSlow90: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.174713001 * fSlow15) + 0.5) - fSlow89),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst37,
+        ) as i32;
+        let iSlow91: i32 = f32::min(
+            (f32::powf(2.0, f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow89)))) as i32) as f32,
+            self.fConst38,
+        ) as i32;
+        let fSlow92: f32 = f32::exp(self.fConst40 / fSlow0);
+        let fSlow93: f32 = faustpower2_f(fSlow92);
+        let fSlow94: f32 = 1.0 - (fSlow93 * fSlow3);
+        let fSlow95: f32 = 1.0 - fSlow93;
+        let fSlow96: f32 = fSlow94 / fSlow95;
+        let fSlow97: f32 = f32::sqrt(f32::max(
+            0.0,
+            (faustpower2_f(fSlow94) / faustpower2_f(fSlow95)) + -1.0,
+        ));
+        let fSlow98: f32 = fSlow96 - fSlow97;
+        let fSlow99: f32 = fSlow92 * (fSlow97 + (1.0 - fSlow96));
+        let fSlow100: f32 = (f32::exp(self.fConst40 / fSlow10) / fSlow92) + -1.0;
+        let fSlow101: f32 = f32::floor((0.0203460008 * fSlow15) + 0.5);
+        let iSlow102: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(
+                    1.0,
+                    f32::ceil(
+                        1.44269502 * f32::ln(f32::floor((0.153128996 * fSlow15) + 0.5) - fSlow101),
+                    ),
+                ),
+            ) as i32) as f32,
+            self.fConst42,
+        ) as i32;
+        let iSlow103: i32 = f32::min(
+            (f32::powf(
+                2.0,
+                f32::max(1.0, f32::ceil(1.44269502 * f32::ln(fSlow101))),
+            ) as i32) as f32,
+            self.fConst43,
+        ) as i32;
+        for i in 0..count {
+            self.fRec11[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec11[1]) - (self.fRec7[1] + self.fRec7[2])));
+            self.fRec10[0] =
+                (fSlow8 * self.fRec10[1]) + (fSlow9 * (self.fRec7[1] + (fSlow11 * self.fRec11[0])));
+            self.fVec0[(self.IOTA & 65535) as usize] =
+                (0.353553385 * self.fRec10[0]) + 9.99999968e-21;
+            self.fVec1[(self.IOTA & 16383) as usize] = input1[i as usize] as f32;
+            let mut fTemp0: f32 =
+                0.300000012 * self.fVec1[((self.IOTA - iSlow18) & 16383) as usize];
+            let mut fTemp1: f32 = ((0.600000024 * self.fRec8[1])
+                + self.fVec0[((self.IOTA - iSlow17) & 65535) as usize])
+                - fTemp0;
+            self.fVec2[(self.IOTA & 2047) as usize] = fTemp1;
+            self.fRec8[0] = self.fVec2[((self.IOTA - iSlow19) & 2047) as usize];
+            let mut fRec9: f32 = 0.0 - (0.600000024 * fTemp1);
+            self.fRec15[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec15[1]) - (self.fRec3[1] + self.fRec3[2])));
+            self.fRec14[0] = (fSlow26 * self.fRec14[1])
+                + (fSlow27 * (self.fRec3[1] + (fSlow28 * self.fRec15[0])));
+            self.fVec3[(self.IOTA & 65535) as usize] =
+                (0.353553385 * self.fRec14[0]) + 9.99999968e-21;
+            let mut fTemp2: f32 = ((0.600000024 * self.fRec12[1])
+                + self.fVec3[((self.IOTA - iSlow30) & 65535) as usize])
+                - fTemp0;
+            self.fVec4[(self.IOTA & 4095) as usize] = fTemp2;
+            self.fRec12[0] = self.fVec4[((self.IOTA - iSlow31) & 4095) as usize];
+            let mut fRec13: f32 = 0.0 - (0.600000024 * fTemp2);
+            self.fRec19[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec19[1]) - (self.fRec5[1] + self.fRec5[2])));
+            self.fRec18[0] = (fSlow38 * self.fRec18[1])
+                + (fSlow39 * (self.fRec5[1] + (fSlow40 * self.fRec19[0])));
+            self.fVec5[(self.IOTA & 65535) as usize] =
+                (0.353553385 * self.fRec18[0]) + 9.99999968e-21;
+            let mut fTemp3: f32 = (0.600000024 * self.fRec16[1])
+                + (fTemp0 + self.fVec5[((self.IOTA - iSlow42) & 65535) as usize]);
+            self.fVec6[(self.IOTA & 4095) as usize] = fTemp3;
+            self.fRec16[0] = self.fVec6[((self.IOTA - iSlow43) & 4095) as usize];
+            let mut fRec17: f32 = 0.0 - (0.600000024 * fTemp3);
+            self.fRec23[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec23[1]) - (self.fRec1[1] + self.fRec1[2])));
+            self.fRec22[0] = (fSlow50 * self.fRec22[1])
+                + (fSlow51 * (self.fRec1[1] + (fSlow52 * self.fRec23[0])));
+            self.fVec7[(self.IOTA & 65535) as usize] =
+                (0.353553385 * self.fRec22[0]) + 9.99999968e-21;
+            let mut fTemp4: f32 = fTemp0
+                + ((0.600000024 * self.fRec20[1])
+                    + self.fVec7[((self.IOTA - iSlow54) & 65535) as usize]);
+            self.fVec8[(self.IOTA & 4095) as usize] = fTemp4;
+            self.fRec20[0] = self.fVec8[((self.IOTA - iSlow55) & 4095) as usize];
+            let mut fRec21: f32 = 0.0 - (0.600000024 * fTemp4);
+            self.fRec27[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec27[1]) - (self.fRec6[1] + self.fRec6[2])));
+            self.fRec26[0] = (fSlow62 * self.fRec26[1])
+                + (fSlow63 * (self.fRec6[1] + (fSlow64 * self.fRec27[0])));
+            self.fVec9[(self.IOTA & 32767) as usize] =
+                (0.353553385 * self.fRec26[0]) + 9.99999968e-21;
+            self.fVec10[(self.IOTA & 16383) as usize] = input0[i as usize] as f32;
+            let mut fTemp5: f32 =
+                0.300000012 * self.fVec10[((self.IOTA - iSlow18) & 16383) as usize];
+            let mut fTemp6: f32 = self.fVec9[((self.IOTA - iSlow66) & 32767) as usize]
+                - (fTemp5 + (0.600000024 * self.fRec24[1]));
+            self.fVec11[(self.IOTA & 2047) as usize] = fTemp6;
+            self.fRec24[0] = self.fVec11[((self.IOTA - iSlow67) & 2047) as usize];
+            let mut fRec25: f32 = 0.600000024 * fTemp6;
+            self.fRec31[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec31[1]) - (self.fRec2[1] + self.fRec2[2])));
+            self.fRec30[0] = (fSlow74 * self.fRec30[1])
+                + (fSlow75 * (self.fRec2[1] + (fSlow76 * self.fRec31[0])));
+            self.fVec12[(self.IOTA & 32767) as usize] =
+                (0.353553385 * self.fRec30[0]) + 9.99999968e-21;
+            let mut fTemp7: f32 = self.fVec12[((self.IOTA - iSlow78) & 32767) as usize]
+                - (fTemp5 + (0.600000024 * self.fRec28[1]));
+            self.fVec13[(self.IOTA & 4095) as usize] = fTemp7;
+            self.fRec28[0] = self.fVec13[((self.IOTA - iSlow79) & 4095) as usize];
+            let mut fRec29: f32 = 0.600000024 * fTemp7;
+            self.fRec35[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec35[1]) - (self.fRec4[1] + self.fRec4[2])));
+            self.fRec34[0] = (fSlow86 * self.fRec34[1])
+                + (fSlow87 * (self.fRec4[1] + (fSlow88 * self.fRec35[0])));
+            self.fVec14[(self.IOTA & 65535) as usize] =
+                (0.353553385 * self.fRec34[0]) + 9.99999968e-21;
+            let mut fTemp8: f32 = (fTemp5 + self.fVec14[((self.IOTA - iSlow90) & 65535) as usize])
+                - (0.600000024 * self.fRec32[1]);
+            self.fVec15[(self.IOTA & 4095) as usize] = fTemp8;
+            self.fRec32[0] = self.fVec15[((self.IOTA - iSlow91) & 4095) as usize];
+            let mut fRec33: f32 = 0.600000024 * fTemp8;
+            self.fRec39[0] =
+                0.0 - (fSlow13 * ((fSlow14 * self.fRec39[1]) - (self.fRec0[1] + self.fRec0[2])));
+            self.fRec38[0] = (fSlow98 * self.fRec38[1])
+                + (fSlow99 * (self.fRec0[1] + (fSlow100 * self.fRec39[0])));
+            self.fVec16[(self.IOTA & 32767) as usize] =
+                (0.353553385 * self.fRec38[0]) + 9.99999968e-21;
+            let mut fTemp9: f32 = (self.fVec16[((self.IOTA - iSlow102) & 32767) as usize] + fTemp5)
+                - (0.600000024 * self.fRec36[1]);
+            self.fVec17[(self.IOTA & 2047) as usize] = fTemp9;
+            self.fRec36[0] = self.fVec17[((self.IOTA - iSlow103) & 2047) as usize];
+            let mut fRec37: f32 = 0.600000024 * fTemp9;
+            let mut fTemp10: f32 = self.fRec12[1] + self.fRec8[1];
+            let mut fTemp11: f32 = self.fRec32[1] + (self.fRec36[1] + fTemp10);
+            self.fRec0[0] = fRec9
+                + (fRec13
+                    + (fRec17
+                        + (fRec21
+                            + (fRec25
+                                + (fRec29
+                                    + (fRec33
+                                        + (fRec37
+                                            + (self.fRec16[1]
+                                                + (self.fRec20[1]
+                                                    + (self.fRec24[1]
+                                                        + (self.fRec28[1] + fTemp11)))))))))));
+            let mut fTemp12: f32 = self.fRec36[1] + self.fRec32[1];
+            self.fRec1[0] = (fRec25
+                + (fRec29 + (fRec33 + (fRec37 + (self.fRec24[1] + (fTemp12 + self.fRec28[1]))))))
+                - (fRec9
+                    + (fRec13
+                        + (fRec17 + (fRec21 + (self.fRec16[1] + (self.fRec20[1] + fTemp10))))));
+            self.fRec2[0] = (fRec17
+                + (fRec21 + (fRec33 + (fRec37 + (self.fRec16[1] + (fTemp12 + self.fRec20[1]))))))
+                - (fRec9
+                    + (fRec13
+                        + (fRec25 + (fRec29 + (self.fRec24[1] + (self.fRec28[1] + fTemp10))))));
+            self.fRec3[0] = (fRec9 + (fRec13 + (fRec33 + (fRec37 + fTemp11))))
+                - (fRec17
+                    + (fRec21
+                        + (fRec25
+                            + (fRec29
+                                + (self.fRec16[1]
+                                    + ((self.fRec28[1] + self.fRec24[1]) + self.fRec20[1]))))));
+            let mut fTemp13: f32 = self.fRec36[1] + self.fRec12[1];
+            let mut fTemp14: f32 = self.fRec32[1] + self.fRec8[1];
+            self.fRec4[0] = (fRec13
+                + (fRec21 + (fRec29 + (fRec37 + (self.fRec20[1] + (self.fRec28[1] + fTemp13))))))
+                - (fRec9
+                    + (fRec17
+                        + (fRec25 + (fRec33 + (self.fRec16[1] + (self.fRec24[1] + fTemp14))))));
+            let mut fTemp15: f32 = self.fRec36[1] + self.fRec8[1];
+            let mut fTemp16: f32 = self.fRec32[1] + self.fRec12[1];
+            self.fRec5[0] = (fRec9
+                + (fRec17 + (fRec29 + (fRec37 + (self.fRec16[1] + (self.fRec28[1] + fTemp15))))))
+                - (fRec13
+                    + (fRec21
+                        + (fRec25 + (fRec33 + (self.fRec20[1] + (self.fRec24[1] + fTemp16))))));
+            self.fRec6[0] = (fRec9
+                + (fRec21 + (fRec25 + (fRec37 + (self.fRec20[1] + (self.fRec24[1] + fTemp15))))))
+                - (fRec13
+                    + (fRec17
+                        + (fRec29 + (fRec33 + (self.fRec16[1] + (self.fRec28[1] + fTemp16))))));
+            self.fRec7[0] = (fRec13
+                + (fRec17 + (fRec25 + (fRec37 + (self.fRec16[1] + (self.fRec24[1] + fTemp13))))))
+                - (fRec9
+                    + (fRec21
+                        + (fRec29 + (fRec33 + (self.fRec20[1] + (self.fRec28[1] + fTemp14))))));
+            output0[i as usize] = (0.370000005 * (self.fRec1[0] + self.fRec2[0])) as f32;
+            output1[i as usize] = (0.370000005 * (self.fRec1[0] - self.fRec2[0])) as f32;
+            self.fRec11[1] = self.fRec11[0];
+            self.fRec10[1] = self.fRec10[0];
+            self.IOTA = self.IOTA + 1;
+            self.fRec8[1] = self.fRec8[0];
+            self.fRec15[1] = self.fRec15[0];
+            self.fRec14[1] = self.fRec14[0];
+            self.fRec12[1] = self.fRec12[0];
+            self.fRec19[1] = self.fRec19[0];
+            self.fRec18[1] = self.fRec18[0];
+            self.fRec16[1] = self.fRec16[0];
+            self.fRec23[1] = self.fRec23[0];
+            self.fRec22[1] = self.fRec22[0];
+            self.fRec20[1] = self.fRec20[0];
+            self.fRec27[1] = self.fRec27[0];
+            self.fRec26[1] = self.fRec26[0];
+            self.fRec24[1] = self.fRec24[0];
+            self.fRec31[1] = self.fRec31[0];
+            self.fRec30[1] = self.fRec30[0];
+            self.fRec28[1] = self.fRec28[0];
+            self.fRec35[1] = self.fRec35[0];
+            self.fRec34[1] = self.fRec34[0];
+            self.fRec32[1] = self.fRec32[0];
+            self.fRec39[1] = self.fRec39[0];
+            self.fRec38[1] = self.fRec38[0];
+            self.fRec36[1] = self.fRec36[0];
+            self.fRec0[2] = self.fRec0[1];
+            self.fRec0[1] = self.fRec0[0];
+            self.fRec1[2] = self.fRec1[1];
+            self.fRec1[1] = self.fRec1[0];
+            self.fRec2[2] = self.fRec2[1];
+            self.fRec2[1] = self.fRec2[0];
+            self.fRec3[2] = self.fRec3[1];
+            self.fRec3[1] = self.fRec3[0];
+            self.fRec4[2] = self.fRec4[1];
+            self.fRec4[1] = self.fRec4[0];
+            self.fRec5[2] = self.fRec5[1];
+            self.fRec5[1] = self.fRec5[0];
+            self.fRec6[2] = self.fRec6[1];
+            self.fRec6[1] = self.fRec6[0];
+            self.fRec7[2] = self.fRec7[1];
+            self.fRec7[1] = self.fRec7[0];
+        }
+    }
+}
+
+/****************************************
+** Mono freeverb
+****************************************/
+
+pub const FREEVERB_PARAMETERS: [Parameter; 4] = [
+    Parameter::new("fb1", ParameterType::Float, 0., 1., 0.5),
+    Parameter::new("fb2", ParameterType::Float, 0., 1., 0.5),
+    Parameter::new("damp", ParameterType::Float, 0., 1., 0.5),
+    Parameter::new("spread", ParameterType::Float, 0., 1., 0.5),
+];
+
+/// Variants 1 and 2 only run half of the comb filters, and variant 2 half of the allpass filters
+pub struct MonoFreeverb {
+    fDummy: f32,
+    fHslider0: f32,
+    fHslider1: f32,
+    fRec9: [f32; 2],
+    IOTA: i32,
+    fVec0: Vec<f32>,
+    fSampleRate: i32,
+    fConst0: f32,
+    fConst1: f32,
+    fHslider2: f32,
+    fRec8: [f32; 2],
+    fRec11: [f32; 2],
+    fVec1: Vec<f32>,
+    fConst2: f32,
+    fRec10: [f32; 2],
+    fRec13: [f32; 2],
+    fVec2: Vec<f32>,
+    fConst3: f32,
+    fRec12: [f32; 2],
+    fRec15: [f32; 2],
+    fVec3: Vec<f32>,
+    fConst4: f32,
+    fRec14: [f32; 2],
+    fRec17: [f32; 2],
+    fVec4: Vec<f32>,
+    fConst5: f32,
+    fRec16: [f32; 2],
+    fRec19: [f32; 2],
+    fVec5: Vec<f32>,
+    fConst6: f32,
+    fRec18: [f32; 2],
+    fRec21: [f32; 2],
+    fVec6: Vec<f32>,
+    fConst7: f32,
+    fRec20: [f32; 2],
+    fRec23: [f32; 2],
+    fVec7: Vec<f32>,
+    fConst8: f32,
+    fRec22: [f32; 2],
+    fHslider3: f32,
+    fVec8: Vec<f32>,
+    fConst9: f32,
+    fRec6: [f32; 2],
+    fVec9: Vec<f32>,
+    fConst10: f32,
+    fRec4: [f32; 2],
+    fVec10: Vec<f32>,
+    fConst11: f32,
+    fRec2: [f32; 2],
+    fVec11: Vec<f32>,
+    fConst12: f32,
+    fRec0: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+    rescaling: Rescaling,
+    variant: usize,
+}
+
+impl MonoFreeverb {
+    pub fn init() -> MonoFreeverb {
+        MonoFreeverb {
+            fDummy: 0 as f32,
+            fHslider0: 0.0,
+            fHslider1: 0.0,
             fRec9: [0.0; 2],
             IOTA: 0,
-            fVec0: [0.0; 8192],
+            fVec0: vec![0.0; 8192],
+            fSampleRate: 0,
+            fConst0: 0.0,
+            fConst1: 0.0,
+            fHslider2: 0.0,
+            fRec8: [0.0; 2],
+            fRec11: [0.0; 2],
+            fVec1: vec![0.0; 8192],
+            fConst2: 0.0,
+            fRec10: [0.0; 2],
+            fRec13: [0.0; 2],
+            fVec2: vec![0.0; 8192],
+            fConst3: 0.0,
+            fRec12: [0.0; 2],
+            fRec15: [0.0; 2],
+            fVec3: vec![0.0; 8192],
+            fConst4: 0.0,
+            fRec14: [0.0; 2],
+            fRec17: [0.0; 2],
+            fVec4: vec![0.0; 8192],
+            fConst5: 0.0,
+            fRec16: [0.0; 2],
+            fRec19: [0.0; 2],
+            fVec5: vec![0.0; 8192],
+            fConst6: 0.0,
+            fRec18: [0.0; 2],
+            fRec21: [0.0; 2],
+            fVec6: vec![0.0; 8192],
+            fConst7: 0.0,
+            fRec20: [0.0; 2],
+            fRec23: [0.0; 2],
+            fVec7: vec![0.0; 8192],
+            fConst8: 0.0,
+            fRec22: [0.0; 2],
+            fHslider3: 0.0,
+            fVec8: vec![0.0; 2048],
+            fConst9: 0.0,
+            fRec6: [0.0; 2],
+            fVec9: vec![0.0; 2048],
+            fConst10: 0.0,
+            fRec4: [0.0; 2],
+            fVec10: vec![0.0; 2048],
+            fConst11: 0.0,
+            fRec2: [0.0; 2],
+            fVec11: vec![0.0; 1024],
+            fConst12: 0.0,
+            fRec0: [0.0; 2],
+            smoother: Smoother::new(&FREEVERB_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+            rescaling: Rescaling::default(),
+            variant: 0,
+        }
+    }
+
+    pub fn instanceResetUserInterface(&mut self) {
+        self.fHslider0 = 0.5;
+        self.fHslider1 = 0.5;
+        self.fHslider2 = 0.5;
+        self.fHslider3 = 0.5;
+    }
+
+    pub fn instanceClear(&mut self) {
+        for l0 in 0..2 {
+            self.fRec9[l0 as usize] = 0.0;
+        }
+        self.IOTA = 0;
+        for l1 in 0..8192 {
+            self.fVec0[l1 as usize] = 0.0;
+        }
+        for l2 in 0..2 {
+            self.fRec8[l2 as usize] = 0.0;
+        }
+        for l3 in 0..2 {
+            self.fRec11[l3 as usize] = 0.0;
+        }
+        for l4 in 0..8192 {
+            self.fVec1[l4 as usize] = 0.0;
+        }
+        for l5 in 0..2 {
+            self.fRec10[l5 as usize] = 0.0;
+        }
+        for l6 in 0..2 {
+            self.fRec13[l6 as usize] = 0.0;
+        }
+        for l7 in 0..8192 {
+            self.fVec2[l7 as usize] = 0.0;
+        }
+        for l8 in 0..2 {
+            self.fRec12[l8 as usize] = 0.0;
+        }
+        for l9 in 0..2 {
+            self.fRec15[l9 as usize] = 0.0;
+        }
+        for l10 in 0..8192 {
+            self.fVec3[l10 as usize] = 0.0;
+        }
+        for l11 in 0..2 {
+            self.fRec14[l11 as usize] = 0.0;
+        }
+        for l12 in 0..2 {
+            self.fRec17[l12 as usize] = 0.0;
+        }
+        for l13 in 0..8192 {
+            self.fVec4[l13 as usize] = 0.0;
+        }
+        for l14 in 0..2 {
+            self.fRec16[l14 as usize] = 0.0;
+        }
+        for l15 in 0..2 {
+            self.fRec19[l15 as usize] = 0.0;
+        }
+        for l16 in 0..8192 {
+            self.fVec5[l16 as usize] = 0.0;
+        }
+        for l17 in 0..2 {
+            self.fRec18[l17 as usize] = 0.0;
+        }
+        for l18 in 0..2 {
+            self.fRec21[l18 as usize] = 0.0;
+        }
+        for l19 in 0..8192 {
+            self.fVec6[l19 as usize] = 0.0;
+        }
+        for l20 in 0..2 {
+            self.fRec20[l20 as usize] = 0.0;
+        }
+        for l21 in 0..2 {
+            self.fRec23[l21 as usize] = 0.0;
+        }
+        for l22 in 0..8192 {
+            self.fVec7[l22 as usize] = 0.0;
+        }
+        for l23 in 0..2 {
+            self.fRec22[l23 as usize] = 0.0;
+        }
+        for l24 in 0..2048 {
+            self.fVec8[l24 as usize] = 0.0;
+        }
+        for l25 in 0..2 {
+            self.fRec6[l25 as usize] = 0.0;
+        }
+        for l26 in 0..2048 {
+            self.fVec9[l26 as usize] = 0.0;
+        }
+        for l27 in 0..2 {
+            self.fRec4[l27 as usize] = 0.0;
+        }
+        for l28 in 0..2048 {
+            self.fVec10[l28 as usize] = 0.0;
+        }
+        for l29 in 0..2 {
+            self.fRec2[l29 as usize] = 0.0;
+        }
+        for l30 in 0..1024 {
+            self.fVec11[l30 as usize] = 0.0;
+        }
+        for l31 in 0..2 {
+            self.fRec0[l31 as usize] = 0.0;
+        }
+    }
+
+    pub fn instanceConstants(&mut self, sample_rate: i32) {
+        self.fSampleRate = sample_rate;
+        self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
+        self.fConst1 = ((0.0253061224 * self.fConst0) as i32) as f32;
+        self.fConst2 = ((0.0269387756 * self.fConst0) as i32) as f32;
+        self.fConst3 = ((0.0289569162 * self.fConst0) as i32) as f32;
+        self.fConst4 = ((0.0307482984 * self.fConst0) as i32) as f32;
+        self.fConst5 = ((0.0322448984 * self.fConst0) as i32) as f32;
+        self.fConst6 = ((0.033809524 * self.fConst0) as i32) as f32;
+        self.fConst7 = ((0.0353061222 * self.fConst0) as i32) as f32;
+        self.fConst8 = ((0.0366666652 * self.fConst0) as i32) as f32;
+        self.fConst9 = ((0.0126077095 * self.fConst0) as i32) as f32;
+        self.fConst10 = ((0.00999999978 * self.fConst0) as i32) as f32;
+        self.fConst11 = ((0.00773242628 * self.fConst0) as i32) as f32;
+        self.fConst12 = ((0.00510204071 * self.fConst0) as i32) as f32;
+    }
+
+    pub fn instanceInit(&mut self, sample_rate: i32) {
+        self.instanceConstants(sample_rate);
+        self.instanceResetUserInterface();
+        self.instanceClear();
+    }
+
+    pub fn new(fb1: f32, fb2: f32, damp: f32, spread: f32) -> MonoFreeverb {
+        let mut mono_freeverb = MonoFreeverb::init();
+        mono_freeverb.instanceInit(44_100);
+        mono_freeverb.setControlVariables(fb1, fb2, damp, spread);
+        mono_freeverb.skip_smoothing();
+        mono_freeverb
+    }
+
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<MonoFreeverb, AudiographError> {
+        let fb1 = node_infos.attribute("fb1", "a float in [0,1]")?;
+        let fb2 = node_infos.attribute("fb2", "a float in [0,1]")?;
+        let damp = node_infos.attribute("damp", "a float in [0,1]")?;
+        let spread = node_infos.attribute("spread", "a float in [0,1]")?;
+        let mut mono_freeverb = MonoFreeverb::new(fb1, fb2, damp, spread);
+        mono_freeverb.check_io_node_infos(node_infos)?;
+        mono_freeverb.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(mono_freeverb)
+    }
+
+    pub fn setControlVariables(&mut self, fb1: f32, fb2: f32, damp: f32, spread: f32) {
+        self.smoother.set_target(0, fb1);
+        self.smoother.set_target(1, fb2);
+        self.smoother.set_target(2, damp);
+        self.smoother.set_target(3, spread);
+    }
+
+    /// Clear the odd comb filters, which stay idle in variants 1 and 2
+    fn clear_odd_combs(&mut self) {
+        for line in [
+            &mut self.fVec1,
+            &mut self.fVec3,
+            &mut self.fVec5,
+            &mut self.fVec7,
+        ]
+        .iter_mut()
+        {
+            for sample in line.iter_mut() {
+                *sample = 0.0;
+            }
+        }
+        for state in [
+            &mut self.fRec10,
+            &mut self.fRec11,
+            &mut self.fRec14,
+            &mut self.fRec15,
+            &mut self.fRec18,
+            &mut self.fRec19,
+            &mut self.fRec22,
+            &mut self.fRec23,
+        ]
+        .iter_mut()
+        {
+            **state = [0.0; 2];
+        }
+    }
+
+    /// Clear the first two allpass filters, which stay idle in variant 2
+    fn clear_first_allpasses(&mut self) {
+        for line in [&mut self.fVec8, &mut self.fVec9].iter_mut() {
+            for sample in line.iter_mut() {
+                *sample = 0.0;
+            }
+        }
+        self.fRec6 = [0.0; 2];
+        self.fRec4 = [0.0; 2];
+    }
+
+    /// Cheaper variants with every other comb filter, whose sum is doubled to keep the same level.
+    /// Variant 2 also skips the first two allpass filters of the diffusion.
+    /// The filters which stay idle are cleared when switching to a variant.
+    fn compute_four_combs(
+        &mut self,
+        start: usize,
+        end: usize,
+        inputs: &[DspEdge],
+        outputs: &mut [DspEdge],
+    ) {
+        let input = &inputs[0].buffer()[start..end];
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
+        let fSlow0: f32 = self.fHslider0 as f32;
+        let fSlow1: f32 = self.fHslider1 as f32;
+        let fSlow2: f32 = 1.0 - fSlow1;
+        let fSlow3: f32 = self.fHslider2 as f32;
+        let iSlow4: i32 = (self.fConst1 + fSlow3) as i32;
+        let iSlow6: i32 = (self.fConst3 + fSlow3) as i32;
+        let iSlow8: i32 = (self.fConst5 + fSlow3) as i32;
+        let iSlow10: i32 = (self.fConst7 + fSlow3) as i32;
+        let fSlow12: f32 = self.fHslider3 as f32;
+        let fSlow13: f32 = fSlow3 + -1.0;
+        let iSlow14: i32 = f32::min(1024.0, f32::max(0.0, self.fConst9 + fSlow13)) as i32;
+        let iSlow15: i32 = f32::min(1024.0, f32::max(0.0, self.fConst10 + fSlow13)) as i32;
+        let iSlow16: i32 = f32::min(1024.0, f32::max(0.0, self.fConst11 + fSlow13)) as i32;
+        let iSlow17: i32 = f32::min(1024.0, f32::max(0.0, self.fConst12 + fSlow13)) as i32;
+        let all_allpasses = self.variant == 1;
+        for i in 0..count {
+            let mut fTemp0: f32 = input[i as usize] as f32;
+            self.fRec9[0] = (fSlow1 * self.fRec9[1]) + (fSlow2 * self.fRec8[1]);
+            self.fVec0[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec9[0]);
+            self.fRec8[0] = self.fVec0[((self.IOTA - iSlow4) & 8191) as usize];
+            self.fRec13[0] = (fSlow1 * self.fRec13[1]) + (fSlow2 * self.fRec12[1]);
+            self.fVec2[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec13[0]);
+            self.fRec12[0] = self.fVec2[((self.IOTA - iSlow6) & 8191) as usize];
+            self.fRec17[0] = (fSlow1 * self.fRec17[1]) + (fSlow2 * self.fRec16[1]);
+            self.fVec4[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec17[0]);
+            self.fRec16[0] = self.fVec4[((self.IOTA - iSlow8) & 8191) as usize];
+            self.fRec21[0] = (fSlow1 * self.fRec21[1]) + (fSlow2 * self.fRec20[1]);
+            self.fVec6[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec21[0]);
+            self.fRec20[0] = self.fVec6[((self.IOTA - iSlow10) & 8191) as usize];
+            let mut fTemp1: f32 =
+                2.0 * (((self.fRec8[0] + self.fRec12[0]) + self.fRec16[0]) + self.fRec20[0]);
+            if all_allpasses {
+                let mut fTemp2: f32 = fTemp1 + (fSlow12 * self.fRec6[1]);
+                self.fVec8[(self.IOTA & 2047) as usize] = fTemp2;
+                self.fRec6[0] = self.fVec8[((self.IOTA - iSlow14) & 2047) as usize];
+                let mut fRec7: f32 = 0.0 - (fSlow12 * fTemp2);
+                let mut fTemp3: f32 = self.fRec6[1] + (fRec7 + (fSlow12 * self.fRec4[1]));
+                self.fVec9[(self.IOTA & 2047) as usize] = fTemp3;
+                self.fRec4[0] = self.fVec9[((self.IOTA - iSlow15) & 2047) as usize];
+                let mut fRec5: f32 = 0.0 - (fSlow12 * fTemp3);
+                fTemp1 = self.fRec4[1] + fRec5;
+            }
+            let mut fTemp4: f32 = fTemp1 + (fSlow12 * self.fRec2[1]);
+            self.fVec10[(self.IOTA & 2047) as usize] = fTemp4;
+            self.fRec2[0] = self.fVec10[((self.IOTA - iSlow16) & 2047) as usize];
+            let mut fRec3: f32 = 0.0 - (fSlow12 * fTemp4);
+            let mut fTemp5: f32 = self.fRec2[1] + (fRec3 + (fSlow12 * self.fRec0[1]));
+            self.fVec11[(self.IOTA & 1023) as usize] = fTemp5;
+            self.fRec0[0] = self.fVec11[((self.IOTA - iSlow17) & 1023) as usize];
+            let mut fRec1: f32 = 0.0 - (fSlow12 * fTemp5);
+            output[i as usize] = (fRec1 + self.fRec0[1]) as f32;
+            self.fRec9[1] = self.fRec9[0];
+            self.IOTA = self.IOTA + 1;
+            self.fRec8[1] = self.fRec8[0];
+            self.fRec13[1] = self.fRec13[0];
+            self.fRec12[1] = self.fRec12[0];
+            self.fRec17[1] = self.fRec17[0];
+            self.fRec16[1] = self.fRec16[0];
+            self.fRec21[1] = self.fRec21[0];
+            self.fRec20[1] = self.fRec20[0];
+            self.fRec6[1] = self.fRec6[0];
+            self.fRec4[1] = self.fRec4[0];
+            self.fRec2[1] = self.fRec2[0];
+            self.fRec0[1] = self.fRec0[0];
+        }
+    }
+}
+
+impl fmt::Display for MonoFreeverb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "freeverb({}, {}, {}, {})",
+            self.fHslider0, self.fHslider3, self.fHslider1, self.fHslider2
+        )
+    }
+}
+
+impl AudioEffect for MonoFreeverb {
+    fn nb_inputs(&self) -> usize {
+        return 1;
+    }
+    fn nb_outputs(&self) -> usize {
+        return 1;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &FREEVERB_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn nb_variants(&self) -> usize {
+        3
+    }
+
+    fn variant(&self) -> usize {
+        self.variant
+    }
+
+    fn set_variant(&mut self, variant: usize) -> bool {
+        if variant < self.nb_variants() {
+            if variant > 0 && self.variant == 0 {
+                self.clear_odd_combs();
+            }
+            if variant == 2 && self.variant != 2 {
+                self.clear_first_allpasses();
+            }
+            self.variant = variant;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn variant_cost(&self, variant: usize) -> f64 {
+        //Measured with cost_model::measure_variant_costs in release mode
+        match variant {
+            1 => 0.68,
+            2 => 0.56,
+            _ => 1.,
+        }
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.fSampleRate != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for MonoFreeverb {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+        self.fHslider3 = self.smoother.value(1);
+        self.fHslider1 = self.smoother.value(2);
+        self.fHslider2 = self.smoother.value(3);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        let ratio = sample_rate as f32 / self.fSampleRate as f32;
+        self.rescaling.start(ratio, self.IOTA);
+        self.instanceConstants(sample_rate);
+    }
+
+    fn delay_line(&mut self, index: usize) -> Option<&mut [f32]> {
+        match index {
+            0 => Some(&mut self.fVec0),
+            1 => Some(&mut self.fVec1),
+            2 => Some(&mut self.fVec2),
+            3 => Some(&mut self.fVec3),
+            4 => Some(&mut self.fVec4),
+            5 => Some(&mut self.fVec5),
+            6 => Some(&mut self.fVec6),
+            7 => Some(&mut self.fVec7),
+            8 => Some(&mut self.fVec8),
+            9 => Some(&mut self.fVec9),
+            10 => Some(&mut self.fVec10),
+            11 => Some(&mut self.fVec11),
+            _ => None,
+        }
+    }
+
+    fn iota(&self) -> i32 {
+        self.IOTA
+    }
+
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        Some(&mut self.rescaling)
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        if self.variant > 0 {
+            return self.compute_four_combs(start, end, inputs, outputs);
+        }
+        let input = &inputs[0].buffer()[start..end];
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
+        let fSlow0: f32 = self.fHslider0 as f32;
+        let fSlow1: f32 = self.fHslider1 as f32;
+        let fSlow2: f32 = 1.0 - fSlow1;
+        let fSlow3: f32 = self.fHslider2 as f32;
+        let iSlow4: i32 = (self.fConst1 + fSlow3) as i32;
+        let iSlow5: i32 = (self.fConst2 + fSlow3) as i32;
+        let iSlow6: i32 = (self.fConst3 + fSlow3) as i32;
+        let iSlow7: i32 = (self.fConst4 + fSlow3) as i32;
+        let iSlow8: i32 = (self.fConst5 + fSlow3) as i32;
+        let iSlow9: i32 = (self.fConst6 + fSlow3) as i32;
+        let iSlow10: i32 = (self.fConst7 + fSlow3) as i32;
+        let iSlow11: i32 = (self.fConst8 + fSlow3) as i32;
+        let fSlow12: f32 = self.fHslider3 as f32;
+        let fSlow13: f32 = fSlow3 + -1.0;
+        let iSlow14: i32 = f32::min(1024.0, f32::max(0.0, self.fConst9 + fSlow13)) as i32;
+        let iSlow15: i32 = f32::min(1024.0, f32::max(0.0, self.fConst10 + fSlow13)) as i32;
+        let iSlow16: i32 = f32::min(1024.0, f32::max(0.0, self.fConst11 + fSlow13)) as i32;
+        let iSlow17: i32 = f32::min(1024.0, f32::max(0.0, self.fConst12 + fSlow13)) as i32;
+        for i in 0..count {
+            let mut fTemp0: f32 = input[i as usize] as f32;
+            self.fRec9[0] = (fSlow1 * self.fRec9[1]) + (fSlow2 * self.fRec8[1]);
+            self.fVec0[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec9[0]);
+            self.fRec8[0] = self.fVec0[((self.IOTA - iSlow4) & 8191) as usize];
+            self.fRec11[0] = (fSlow1 * self.fRec11[1]) + (fSlow2 * self.fRec10[1]);
+            self.fVec1[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec11[0]);
+            self.fRec10[0] = self.fVec1[((self.IOTA - iSlow5) & 8191) as usize];
+            self.fRec13[0] = (fSlow1 * self.fRec13[1]) + (fSlow2 * self.fRec12[1]);
+            self.fVec2[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec13[0]);
+            self.fRec12[0] = self.fVec2[((self.IOTA - iSlow6) & 8191) as usize];
+            self.fRec15[0] = (fSlow1 * self.fRec15[1]) + (fSlow2 * self.fRec14[1]);
+            self.fVec3[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec15[0]);
+            self.fRec14[0] = self.fVec3[((self.IOTA - iSlow7) & 8191) as usize];
+            self.fRec17[0] = (fSlow1 * self.fRec17[1]) + (fSlow2 * self.fRec16[1]);
+            self.fVec4[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec17[0]);
+            self.fRec16[0] = self.fVec4[((self.IOTA - iSlow8) & 8191) as usize];
+            self.fRec19[0] = (fSlow1 * self.fRec19[1]) + (fSlow2 * self.fRec18[1]);
+            self.fVec5[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec19[0]);
+            self.fRec18[0] = self.fVec5[((self.IOTA - iSlow9) & 8191) as usize];
+            self.fRec21[0] = (fSlow1 * self.fRec21[1]) + (fSlow2 * self.fRec20[1]);
+            self.fVec6[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec21[0]);
+            self.fRec20[0] = self.fVec6[((self.IOTA - iSlow10) & 8191) as usize];
+            self.fRec23[0] = (fSlow1 * self.fRec23[1]) + (fSlow2 * self.fRec22[1]);
+            self.fVec7[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec23[0]);
+            self.fRec22[0] = self.fVec7[((self.IOTA - iSlow11) & 8191) as usize];
+            let mut fTemp1: f32 = (((((((self.fRec8[0] + self.fRec10[0]) + self.fRec12[0])
+                + self.fRec14[0])
+                + self.fRec16[0])
+                + self.fRec18[0])
+                + self.fRec20[0])
+                + self.fRec22[0])
+                + (fSlow12 * self.fRec6[1]);
+            self.fVec8[(self.IOTA & 2047) as usize] = fTemp1;
+            self.fRec6[0] = self.fVec8[((self.IOTA - iSlow14) & 2047) as usize];
+            let mut fRec7: f32 = 0.0 - (fSlow12 * fTemp1);
+            let mut fTemp2: f32 = self.fRec6[1] + (fRec7 + (fSlow12 * self.fRec4[1]));
+            self.fVec9[(self.IOTA & 2047) as usize] = fTemp2;
+            self.fRec4[0] = self.fVec9[((self.IOTA - iSlow15) & 2047) as usize];
+            let mut fRec5: f32 = 0.0 - (fSlow12 * fTemp2);
+            let mut fTemp3: f32 = self.fRec4[1] + (fRec5 + (fSlow12 * self.fRec2[1]));
+            self.fVec10[(self.IOTA & 2047) as usize] = fTemp3;
+            self.fRec2[0] = self.fVec10[((self.IOTA - iSlow16) & 2047) as usize];
+            let mut fRec3: f32 = 0.0 - (fSlow12 * fTemp3);
+            let mut fTemp4: f32 = self.fRec2[1] + (fRec3 + (fSlow12 * self.fRec0[1]));
+            self.fVec11[(self.IOTA & 1023) as usize] = fTemp4;
+            self.fRec0[0] = self.fVec11[((self.IOTA - iSlow17) & 1023) as usize];
+            let mut fRec1: f32 = 0.0 - (fSlow12 * fTemp4);
+            output[i as usize] = (fRec1 + self.fRec0[1]) as f32;
+            self.fRec9[1] = self.fRec9[0];
+            self.IOTA = self.IOTA + 1;
+            self.fRec8[1] = self.fRec8[0];
+            self.fRec11[1] = self.fRec11[0];
+            self.fRec10[1] = self.fRec10[0];
+            self.fRec13[1] = self.fRec13[0];
+            self.fRec12[1] = self.fRec12[0];
+            self.fRec15[1] = self.fRec15[0];
+            self.fRec14[1] = self.fRec14[0];
+            self.fRec17[1] = self.fRec17[0];
+            self.fRec16[1] = self.fRec16[0];
+            self.fRec19[1] = self.fRec19[0];
+            self.fRec18[1] = self.fRec18[0];
+            self.fRec21[1] = self.fRec21[0];
+            self.fRec20[1] = self.fRec20[0];
+            self.fRec23[1] = self.fRec23[0];
+            self.fRec22[1] = self.fRec22[0];
+            self.fRec6[1] = self.fRec6[0];
+            self.fRec4[1] = self.fRec4[0];
+            self.fRec2[1] = self.fRec2[0];
+            self.fRec0[1] = self.fRec0[0];
+        }
+    }
+}
+
+/****************************************
+** Brass
+****************************************/
+
+pub const BRASS_PARAMETERS: [Parameter; 4] = [
+    Parameter::new("tube_length", ParameterType::Float, 0.2, 3., 3.),
+    Parameter::new("mute", ParameterType::Float, 0., 1., 0.6),
+    Parameter::new("lips_tension", ParameterType::Float, 0., 1., 0.9),
+    Parameter::new("pressure", ParameterType::Float, 0., 1., 0.8),
+];
+
+/// Stand-in for faust-effects/brass.dsp, `pm.brassModel` with its arguments as parameters, while it is not
+/// generated. Approximation written by hand and not ported from the code generated by Faust: lips
+/// excited by a constant pressure, coupled to a tube as in STK's brass model, but with simpler filters
+/// than the Faust library.
+pub struct Brass {
+    fSampleRate: i32,
+    fConst0: f32,
+    fConst1: f32,
+    fConst2: f32,
+    fHslider0: f32,
+    fHslider1: f32,
+    fHslider2: f32,
+    fHslider3: f32,
+    IOTA: i32,
+    fVec0: Vec<f32>,
+    fRec0: [f32; 3],
+    fVec1: [f32; 2],
+    fRec1: [f32; 2],
+    fRec2: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+    rescaling: Rescaling,
+}
+
+impl Brass {
+    pub fn init() -> Brass {
+        Brass {
+            fSampleRate: 0,
+            fConst0: 0.0,
+            fConst1: 0.0,
+            fConst2: 0.0,
+            fHslider0: 0.0,
+            fHslider1: 0.0,
+            fHslider2: 0.0,
+            fHslider3: 0.0,
+            IOTA: 0,
+            fVec0: vec![0.0; 4096],
+            fRec0: [0.0; 3],
+            fVec1: [0.0; 2],
+            fRec1: [0.0; 2],
+            fRec2: [0.0; 2],
+            smoother: Smoother::new(&BRASS_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+            rescaling: Rescaling::default(),
+        }
+    }
+
+    pub fn instanceResetUserInterface(&mut self) {
+        self.fHslider0 = 3.0;
+        self.fHslider1 = 0.600000024;
+        self.fHslider2 = 0.899999976;
+        self.fHslider3 = 0.800000012;
+    }
+
+    pub fn instanceClear(&mut self) {
+        self.IOTA = 0;
+        for l0 in 0..4096 {
+            self.fVec0[l0 as usize] = 0.0;
+        }
+        for l1 in 0..3 {
+            self.fRec0[l1 as usize] = 0.0;
+        }
+        for l2 in 0..2 {
+            self.fVec1[l2 as usize] = 0.0;
+        }
+        for l3 in 0..2 {
+            self.fRec1[l3 as usize] = 0.0;
+        }
+        for l4 in 0..2 {
+            self.fRec2[l4 as usize] = 0.0;
+        }
+    }
+
+    pub fn instanceConstants(&mut self, sample_rate: i32) {
+        self.fSampleRate = sample_rate;
+        self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
+        // Round trip in the tube, in samples per meter
+        self.fConst1 = 0.00588235306 * self.fConst0;
+        self.fConst2 = 6.28318548 / self.fConst0;
+    }
+
+    pub fn instanceInit(&mut self, sample_rate: i32) {
+        self.instanceConstants(sample_rate);
+        self.instanceResetUserInterface();
+        self.instanceClear();
+    }
+
+    pub fn new(tube_length: f32, mute: f32, lips_tension: f32, pressure: f32) -> Brass {
+        let mut brass = Brass::init();
+        brass.instanceInit(44_100);
+        brass.setControlVariables(tube_length, mute, lips_tension, pressure);
+        brass.skip_smoothing();
+        brass
+    }
+
+    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Result<Brass, AudiographError> {
+        let tube_length = node_infos.attribute("tube_length", "a length in meters")?;
+        let mute = node_infos.attribute("mute", "a float in [0,1]")?;
+        let lips_tension = node_infos.attribute("lips_tension", "a float in [0,1]")?;
+        let pressure = node_infos.attribute("pressure", "a float in [0,1]")?;
+        let mut brass = Brass::new(tube_length, mute, lips_tension, pressure);
+        brass.check_io_node_infos(node_infos)?;
+        brass.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(brass)
+    }
+
+    pub fn setControlVariables(
+        &mut self,
+        tube_length: f32,
+        mute: f32,
+        lips_tension: f32,
+        pressure: f32,
+    ) {
+        self.smoother.set_target(0, tube_length);
+        self.smoother.set_target(1, mute);
+        self.smoother.set_target(2, lips_tension);
+        self.smoother.set_target(3, pressure);
+    }
+}
+
+impl fmt::Display for Brass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "brass({}, {}, {}, {})",
+            self.fHslider0, self.fHslider1, self.fHslider2, self.fHslider3
+        )
+    }
+}
+
+impl AudioEffect for Brass {
+    fn nb_inputs(&self) -> usize {
+        return 0;
+    }
+    fn nb_outputs(&self) -> usize {
+        return 1;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &BRASS_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.fSampleRate != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Brass {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+        self.fHslider1 = self.smoother.value(1);
+        self.fHslider2 = self.smoother.value(2);
+        self.fHslider3 = self.smoother.value(3);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        let ratio = sample_rate as f32 / self.fSampleRate as f32;
+        self.rescaling.start(ratio, self.IOTA);
+        self.instanceConstants(sample_rate);
+    }
+
+    fn delay_line(&mut self, index: usize) -> Option<&mut [f32]> {
+        match index {
+            0 => Some(&mut self.fVec0),
+            _ => None,
+        }
+    }
+
+    fn iota(&self) -> i32 {
+        self.IOTA
+    }
+
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        Some(&mut self.rescaling)
+    }
+
+    fn compute(&mut self, start: usize, end: usize, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
+        let fSlow0: f32 = self.fHslider0 as f32;
+        let fSlow1: f32 = f32::min(4094.0, f32::max(1.0, self.fConst1 * fSlow0));
+        // The lips resonate around a harmonic of the tube chosen by their tension
+        let fSlow2: f32 = (170.0 / fSlow0) * f32::powf(4.0, (2.0 * (self.fHslider2 as f32)) - 1.0);
+        let fSlow3: f32 = -1.99399996 * f32::cos(self.fConst2 * fSlow2);
+        // Gain of 3 at DC, so that the lips neither stay closed nor saturate on long tubes
+        let fSlow4: f32 = 3.0 * (fSlow3 + 1.99400902);
+        let fSlow5: f32 = 0.300000012 * (self.fHslider3 as f32);
+        let fSlow6: f32 = 0.949999988 * (1.0 - (self.fHslider1 as f32));
+        let fSlow7: f32 = 1.0 - fSlow6;
+        for i in 0..count {
+            let mut fTemp0: f32 = fractional_delay(&self.fVec0, self.IOTA, fSlow1);
+            let mut fTemp1: f32 = 0.850000024 * fTemp0;
+            self.fRec0[0] = (fSlow4 * (fSlow5 - fTemp1))
+                - ((fSlow3 * self.fRec0[1]) + (0.994009018 * self.fRec0[2]));
+            let mut fTemp2: f32 = f32::min(1.0, faustpower2_f(self.fRec0[0]));
+            let mut fTemp3: f32 = (fTemp2 * fSlow5) + ((1.0 - fTemp2) * fTemp1);
+            self.fVec1[0] = fTemp3;
+            self.fRec1[0] = (fTemp3 - self.fVec1[1]) + (0.99000001 * self.fRec1[1]);
+            self.fVec0[(self.IOTA & 4095) as usize] = self.fRec1[0];
+            self.fRec2[0] = (fSlow7 * fTemp0) + (fSlow6 * self.fRec2[1]);
+            output[i as usize] = self.fRec2[0] as f32;
+            self.IOTA = self.IOTA + 1;
+            self.fRec0[2] = self.fRec0[1];
+            self.fRec0[1] = self.fRec0[0];
+            self.fVec1[1] = self.fVec1[0];
+            self.fRec1[1] = self.fRec1[0];
+            self.fRec2[1] = self.fRec2[0];
+        }
+    }
+}
+
+/****************************************
+** Violin
+****************************************/
+
+pub const VIOLIN_PARAMETERS: [Parameter; 4] = [
+    Parameter::new("string_length", ParameterType::Float, 0.2, 3., 2.),
+    Parameter::new("bow_pressure", ParameterType::Float, 0., 1., 0.4),
+    Parameter::new("bow_velocity", ParameterType::Float, 0., 1., 0.5),
+    Parameter::new("bow_position", ParameterType::Float, 0., 1., 0.6),
+];
+
+/// Stand-in for faust-effects/violin.dsp, `pm.violinModel` with its arguments as parameters, while it is
+/// not generated. Approximation written by hand and not ported from the code generated by Faust: a string
+/// bowed at constant velocity as in STK's bowed string model, with a simpler bow table and body filter
+/// than the Faust library.
+pub struct Violin {
+    fSampleRate: i32,
+    fConst0: f32,
+    fConst1: f32,
+    fConst2: f32,
+    fConst3: f32,
+    fHslider0: f32,
+    fHslider1: f32,
+    fHslider2: f32,
+    fHslider3: f32,
+    IOTA: i32,
+    fVec0: Vec<f32>,
+    fVec1: Vec<f32>,
+    fRec0: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+    rescaling: Rescaling,
+}
+
+impl Violin {
+    pub fn init() -> Violin {
+        Violin {
+            fSampleRate: 0,
+            fConst0: 0.0,
+            fConst1: 0.0,
+            fConst2: 0.0,
+            fConst3: 0.0,
+            fHslider0: 0.0,
+            fHslider1: 0.0,
+            fHslider2: 0.0,
+            fHslider3: 0.0,
+            IOTA: 0,
+            fVec0: vec![0.0; 4096],
+            fVec1: vec![0.0; 4096],
+            fRec0: [0.0; 2],
+            smoother: Smoother::new(&VIOLIN_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+            rescaling: Rescaling::default(),
+        }
+    }
+
+    pub fn instanceResetUserInterface(&mut self) {
+        self.fHslider0 = 2.0;
+        self.fHslider1 = 0.400000006;
+        self.fHslider2 = 0.5;
+        self.fHslider3 = 0.600000024;
+    }
+
+    pub fn instanceClear(&mut self) {
+        self.IOTA = 0;
+        for l0 in 0..4096 {
+            self.fVec0[l0 as usize] = 0.0;
+        }
+        for l1 in 0..4096 {
+            self.fVec1[l1 as usize] = 0.0;
+        }
+        for l2 in 0..2 {
+            self.fRec0[l2 as usize] = 0.0;
+        }
+    }
+
+    pub fn instanceConstants(&mut self, sample_rate: i32) {
+        self.fSampleRate = sample_rate;
+        self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
+        // Period of the string, in samples per meter
+        self.fConst1 = 0.00588235306 * self.fConst0;
+        // Losses at the bridge
+        self.fConst2 = 0.75 - (4410.0 / self.fConst0);
+        self.fConst3 = 0.949999988 * (1.0 - self.fConst2);
+    }
+
+    pub fn instanceInit(&mut self, sample_rate: i32) {
+        self.instanceConstants(sample_rate);
+        self.instanceResetUserInterface();
+        self.instanceClear();
+    }
+
+    pub fn new(
+        string_length: f32,
+        bow_pressure: f32,
+        bow_velocity: f32,
+        bow_position: f32,
+    ) -> Violin {
+        let mut violin = Violin::init();
+        violin.instanceInit(44_100);
+        violin.setControlVariables(string_length, bow_pressure, bow_velocity, bow_position);
+        violin.skip_smoothing();
+        violin
+    }
+
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Violin, AudiographError> {
+        let string_length = node_infos.attribute("string_length", "a length in meters")?;
+        let bow_pressure = node_infos.attribute("bow_pressure", "a float in [0,1]")?;
+        let bow_velocity = node_infos.attribute("bow_velocity", "a float in [0,1]")?;
+        let bow_position = node_infos.attribute("bow_position", "a float in [0,1]")?;
+        let mut violin = Violin::new(string_length, bow_pressure, bow_velocity, bow_position);
+        violin.check_io_node_infos(node_infos)?;
+        violin.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(violin)
+    }
+
+    pub fn setControlVariables(
+        &mut self,
+        string_length: f32,
+        bow_pressure: f32,
+        bow_velocity: f32,
+        bow_position: f32,
+    ) {
+        self.smoother.set_target(0, string_length);
+        self.smoother.set_target(1, bow_pressure);
+        self.smoother.set_target(2, bow_velocity);
+        self.smoother.set_target(3, bow_position);
+    }
+}
+
+impl fmt::Display for Violin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "violin({}, {}, {}, {})",
+            self.fHslider0, self.fHslider1, self.fHslider2, self.fHslider3
+        )
+    }
+}
+
+impl AudioEffect for Violin {
+    fn nb_inputs(&self) -> usize {
+        return 0;
+    }
+    fn nb_outputs(&self) -> usize {
+        return 1;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &VIOLIN_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.fSampleRate != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Violin {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+        self.fHslider1 = self.smoother.value(1);
+        self.fHslider2 = self.smoother.value(2);
+        self.fHslider3 = self.smoother.value(3);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        let ratio = sample_rate as f32 / self.fSampleRate as f32;
+        self.rescaling.start(ratio, self.IOTA);
+        self.instanceConstants(sample_rate);
+    }
+
+    fn delay_line(&mut self, index: usize) -> Option<&mut [f32]> {
+        match index {
+            0 => Some(&mut self.fVec0),
+            1 => Some(&mut self.fVec1),
+            _ => None,
+        }
+    }
+
+    fn iota(&self) -> i32 {
+        self.IOTA
+    }
+
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        Some(&mut self.rescaling)
+    }
+
+    fn compute(&mut self, start: usize, end: usize, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
+        let fSlow0: f32 = self.fConst1 * (self.fHslider0 as f32);
+        let fSlow1: f32 = self.fHslider3 as f32;
+        // Bow to bridge and bow to nut
+        let fSlow2: f32 = f32::min(4094.0, f32::max(1.0, fSlow0 * fSlow1));
+        let fSlow3: f32 = f32::min(4094.0, f32::max(1.0, fSlow0 * (1.0 - fSlow1)));
+        let fSlow4: f32 = 5.0 - (4.0 * (self.fHslider1 as f32));
+        let fSlow5: f32 = 0.0299999993 + (0.200000003 * (self.fHslider2 as f32));
+        for i in 0..count {
+            let mut fTemp0: f32 = fractional_delay(&self.fVec0, self.IOTA, fSlow2);
+            self.fRec0[0] = (self.fConst3 * fTemp0) + (self.fConst2 * self.fRec0[1]);
+            let mut fTemp1: f32 = 0.0 - self.fRec0[0];
+            let mut fTemp2: f32 = 0.0 - fractional_delay(&self.fVec1, self.IOTA, fSlow3);
+            let mut fTemp3: f32 = fSlow5 - (fTemp1 + fTemp2);
+            let mut fTemp4: f32 = fTemp3
+                * f32::max(
+                    0.00999999978,
+                    f32::min(
+                        0.980000019,
+                        f32::powf(f32::abs(fSlow4 * fTemp3) + 0.75, -4.0),
+                    ),
+                );
+            self.fVec1[(self.IOTA & 4095) as usize] = fTemp1 + fTemp4;
+            self.fVec0[(self.IOTA & 4095) as usize] = fTemp2 + fTemp4;
+            output[i as usize] = fTemp0 as f32;
+            self.IOTA = self.IOTA + 1;
+            self.fRec0[1] = self.fRec0[0];
+        }
+    }
+}
+
+/****************************************
+** Wah
+****************************************/
+
+pub const WAH_PARAMETERS: [Parameter; 1] = [Parameter::new(
+    "freq",
+    ParameterType::Float,
+    20.,
+    10_000.,
+    893.,
+)];
+
+/// Stand-in for faust-effects/wah.dsp, `ve.wah4`, while it is not generated. Approximation written by hand
+/// and not ported from the code generated by Faust: a Moog ladder filter with a resonance of 0.8.
+pub struct Wah {
+    fSampleRate: i32,
+    fConst0: f32,
+    fConst1: f32,
+    fHslider0: f32,
+    fRec0: [f32; 2],
+    fRec1: [f32; 2],
+    fRec2: [f32; 2],
+    fRec3: [f32; 2],
+    fRec4: [f32; 2],
+    fRec5: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+}
+
+impl Wah {
+    pub fn init() -> Wah {
+        Wah {
             fSampleRate: 0,
             fConst0: 0.0,
             fConst1: 0.0,
-            fHslider2: 0.0,
-            fRec8: [0.0; 2],
-            fRec11: [0.0; 2],
-            fVec1: [0.0; 8192],
-            fConst2: 0.0,
-            fRec10: [0.0; 2],
-            fRec13: [0.0; 2],
-            fVec2: [0.0; 8192],
-            fConst3: 0.0,
-            fRec12: [0.0; 2],
-            fRec15: [0.0; 2],
-            fVec3: [0.0; 8192],
-            fConst4: 0.0,
-            fRec14: [0.0; 2],
-            fRec17: [0.0; 2],
-            fVec4: [0.0; 8192],
-            fConst5: 0.0,
-            fRec16: [0.0; 2],
-            fRec19: [0.0; 2],
-            fVec5: [0.0; 8192],
-            fConst6: 0.0,
-            fRec18: [0.0; 2],
-            fRec21: [0.0; 2],
-            fVec6: [0.0; 8192],
-            fConst7: 0.0,
-            fRec20: [0.0; 2],
-            fRec23: [0.0; 2],
-            fVec7: [0.0; 8192],
-            fConst8: 0.0,
-            fRec22: [0.0; 2],
-            fHslider3: 0.0,
-            fVec8: [0.0; 2048],
-            fConst9: 0.0,
-            fRec6: [0.0; 2],
-            fVec9: [0.0; 2048],
-            fConst10: 0.0,
-            fRec4: [0.0; 2],
-            fVec10: [0.0; 2048],
-            fConst11: 0.0,
-            fRec2: [0.0; 2],
-            fVec11: [0.0; 1024],
-            fConst12: 0.0,
+            fHslider0: 0.0,
             fRec0: [0.0; 2],
+            fRec1: [0.0; 2],
+            fRec2: [0.0; 2],
+            fRec3: [0.0; 2],
+            fRec4: [0.0; 2],
+            fRec5: [0.0; 2],
+            smoother: Smoother::new(&WAH_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+        }
+    }
+
+    pub fn instanceResetUserInterface(&mut self) {
+        self.fHslider0 = 893.0;
+    }
+
+    pub fn instanceClear(&mut self) {
+        for l0 in 0..2 {
+            self.fRec0[l0 as usize] = 0.0;
+        }
+        for l1 in 0..2 {
+            self.fRec1[l1 as usize] = 0.0;
+        }
+        for l2 in 0..2 {
+            self.fRec2[l2 as usize] = 0.0;
+        }
+        for l3 in 0..2 {
+            self.fRec3[l3 as usize] = 0.0;
+        }
+        for l4 in 0..2 {
+            self.fRec4[l4 as usize] = 0.0;
+        }
+        for l5 in 0..2 {
+            self.fRec5[l5 as usize] = 0.0;
+        }
+    }
+
+    pub fn instanceConstants(&mut self, sample_rate: i32) {
+        self.fSampleRate = sample_rate;
+        self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
+        self.fConst1 = 6.28318548 / self.fConst0;
+    }
+
+    pub fn instanceInit(&mut self, sample_rate: i32) {
+        self.instanceConstants(sample_rate);
+        self.instanceResetUserInterface();
+        self.instanceClear();
+    }
+
+    pub fn new(freq: f32) -> Wah {
+        let mut wah = Wah::init();
+        wah.instanceInit(44_100);
+        wah.setControlVariables(freq);
+        wah.skip_smoothing();
+        wah
+    }
+
+    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Result<Wah, AudiographError> {
+        let freq = node_infos.attribute("freq", "a frequency in Hz")?;
+        let mut wah = Wah::new(freq);
+        wah.check_io_node_infos(node_infos)?;
+        wah.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(wah)
+    }
+
+    pub fn setControlVariables(&mut self, freq: f32) {
+        self.smoother.set_target(0, freq);
+    }
+}
+
+impl fmt::Display for Wah {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "wah({})", self.fHslider0)
+    }
+}
+
+impl AudioEffect for Wah {
+    fn nb_inputs(&self) -> usize {
+        return 1;
+    }
+    fn nb_outputs(&self) -> usize {
+        return 1;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &WAH_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.fSampleRate != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Wah {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        //Only filter states, which can be kept as they are
+        self.instanceConstants(sample_rate);
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let input = &inputs[0].buffer()[start..end];
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
+        let fSlow0: f32 = 0.00100000005 * (self.fHslider0 as f32);
+        for i in 0..count {
+            self.fRec0[0] = fSlow0 + (0.999000013 * self.fRec0[1]);
+            let mut fTemp0: f32 = self.fConst1 * self.fRec0[0];
+            let mut fTemp1: f32 = 1.0 - fTemp0;
+            self.fRec1[0] = ((input[i as usize] as f32) - (3.20000005 * self.fRec5[1]))
+                + (fTemp1 * self.fRec1[1]);
+            self.fRec2[0] = self.fRec1[0] + (fTemp1 * self.fRec2[1]);
+            self.fRec3[0] = self.fRec2[0] + (fTemp1 * self.fRec3[1]);
+            self.fRec4[0] = self.fRec3[0] + (fTemp1 * self.fRec4[1]);
+            self.fRec5[0] = self.fRec4[0] * faustpower2_f(faustpower2_f(fTemp0));
+            output[i as usize] = (0.356500387 * self.fRec5[0]) as f32;
+            self.fRec0[1] = self.fRec0[0];
+            self.fRec1[1] = self.fRec1[0];
+            self.fRec2[1] = self.fRec2[0];
+            self.fRec3[1] = self.fRec3[0];
+            self.fRec4[1] = self.fRec4[0];
+            self.fRec5[1] = self.fRec5[0];
+        }
+    }
+}
+
+/****************************************
+** Panner
+****************************************/
+
+pub const PANNER_PARAMETERS: [Parameter; 1] =
+    [Parameter::new("pan", ParameterType::Float, 0., 1., 0.8)];
+
+/// Stand-in for faust-effects/panner.dsp while it is not generated, ported by hand from `sp.panner`:
+/// mono to stereo, from left at 0 to right at 1.
+pub struct Panner {
+    fSampleRate: i32,
+    fHslider0: f32,
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+}
+
+impl Panner {
+    pub fn init() -> Panner {
+        Panner {
+            fSampleRate: 0,
+            fHslider0: 0.0,
+            smoother: Smoother::new(&PANNER_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
+        }
+    }
+
+    pub fn instanceResetUserInterface(&mut self) {
+        self.fHslider0 = 0.800000012;
+    }
+
+    pub fn instanceClear(&mut self) {}
+
+    pub fn instanceConstants(&mut self, sample_rate: i32) {
+        self.fSampleRate = sample_rate;
+    }
+
+    pub fn instanceInit(&mut self, sample_rate: i32) {
+        self.instanceConstants(sample_rate);
+        self.instanceResetUserInterface();
+        self.instanceClear();
+    }
+
+    pub fn new(pan: f32) -> Panner {
+        let mut panner = Panner::init();
+        panner.instanceInit(44_100);
+        panner.setControlVariables(pan);
+        panner.skip_smoothing();
+        panner
+    }
+
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Panner, AudiographError> {
+        let pan = node_infos.attribute("pan", "a float in [0,1]")?;
+        let mut panner = Panner::new(pan);
+        panner.check_io_node_infos(node_infos)?;
+        panner.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(panner)
+    }
+
+    pub fn setControlVariables(&mut self, pan: f32) {
+        self.smoother.set_target(0, pan);
+    }
+}
+
+impl fmt::Display for Panner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "panner({})", self.fHslider0)
+    }
+}
+
+impl AudioEffect for Panner {
+    fn nb_inputs(&self) -> usize {
+        return 1;
+    }
+    fn nb_outputs(&self) -> usize {
+        return 2;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &PANNER_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.fSampleRate != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Panner {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        //No state
+        self.instanceConstants(sample_rate);
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let input = &inputs[0].buffer()[start..end];
+        let (left, right) = outputs.split_at_mut(1);
+        let output0 = &mut left[0].buffer_mut()[start..end];
+        let output1 = &mut right[0].buffer_mut()[start..end];
+        let count = output0.len();
+
+        let fSlow0: f32 = self.fHslider0 as f32;
+        let fSlow1: f32 = 1.0 - fSlow0;
+        for i in 0..count {
+            let mut fTemp0: f32 = input[i as usize] as f32;
+            output0[i as usize] = (fSlow1 * fTemp0) as f32;
+            output1[i as usize] = (fSlow0 * fTemp0) as f32;
+        }
+    }
+}
+
+/****************************************
+** Spatializer
+****************************************/
+
+pub const SPAT_PARAMETERS: [Parameter; 2] = [
+    Parameter::new("rotation", ParameterType::Float, 0., 1., 0.7),
+    Parameter::new("distance", ParameterType::Float, 0., 1., 0.9),
+];
+
+/// `sp.spat` on `nb_outputs` speakers placed on a circle. The source turns with `rotation`
+/// and is heard on fewer speakers as `distance` grows.
+pub struct Spat {
+    nb_outputs: usize,
+    fSampleRate: i32,
+    fHslider0: f32,
+    fHslider1: f32,
+    /// Smoothed gain of each speaker
+    fRec0: Vec<[f32; 2]>,
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+}
+
+impl Spat {
+    pub fn init(nb_outputs: usize) -> Spat {
+        assert!(nb_outputs >= 1);
+        Spat {
+            nb_outputs,
+            fSampleRate: 0,
+            fHslider0: 0.0,
+            fHslider1: 0.0,
+            fRec0: vec![[0.0; 2]; nb_outputs],
+            smoother: Smoother::new(&SPAT_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
         }
     }
 
     pub fn instanceResetUserInterface(&mut self) {
-        self.fHslider0 = 0.5;
-        self.fHslider1 = 0.5;
-        self.fHslider2 = 0.5;
-        self.fHslider3 = 0.5;
+        self.fHslider0 = 0.699999988;
+        self.fHslider1 = 0.899999976;
     }
 
     pub fn instanceClear(&mut self) {
-        for l0 in 0..2 {
-            self.fRec9[l0 as usize] = 0.0;
-        }
-        self.IOTA = 0;
-        for l1 in 0..8192 {
-            self.fVec0[l1 as usize] = 0.0;
-        }
-        for l2 in 0..2 {
-            self.fRec8[l2 as usize] = 0.0;
-        }
-        for l3 in 0..2 {
-            self.fRec11[l3 as usize] = 0.0;
-        }
-        for l4 in 0..8192 {
-            self.fVec1[l4 as usize] = 0.0;
-        }
-        for l5 in 0..2 {
-            self.fRec10[l5 as usize] = 0.0;
-        }
-        for l6 in 0..2 {
-            self.fRec13[l6 as usize] = 0.0;
-        }
-        for l7 in 0..8192 {
-            self.fVec2[l7 as usize] = 0.0;
-        }
-        for l8 in 0..2 {
-            self.fRec12[l8 as usize] = 0.0;
-        }
-        for l9 in 0..2 {
-            self.fRec15[l9 as usize] = 0.0;
-        }
-        for l10 in 0..8192 {
-            self.fVec3[l10 as usize] = 0.0;
-        }
-        for l11 in 0..2 {
-            self.fRec14[l11 as usize] = 0.0;
-        }
-        for l12 in 0..2 {
-            self.fRec17[l12 as usize] = 0.0;
-        }
-        for l13 in 0..8192 {
-            self.fVec4[l13 as usize] = 0.0;
-        }
-        for l14 in 0..2 {
-            self.fRec16[l14 as usize] = 0.0;
-        }
-        for l15 in 0..2 {
-            self.fRec19[l15 as usize] = 0.0;
-        }
-        for l16 in 0..8192 {
-            self.fVec5[l16 as usize] = 0.0;
-        }
-        for l17 in 0..2 {
-            self.fRec18[l17 as usize] = 0.0;
-        }
-        for l18 in 0..2 {
-            self.fRec21[l18 as usize] = 0.0;
-        }
-        for l19 in 0..8192 {
-            self.fVec6[l19 as usize] = 0.0;
-        }
-        for l20 in 0..2 {
-            self.fRec20[l20 as usize] = 0.0;
-        }
-        for l21 in 0..2 {
-            self.fRec23[l21 as usize] = 0.0;
-        }
-        for l22 in 0..8192 {
-            self.fVec7[l22 as usize] = 0.0;
-        }
-        for l23 in 0..2 {
-            self.fRec22[l23 as usize] = 0.0;
-        }
-        for l24 in 0..2048 {
-            self.fVec8[l24 as usize] = 0.0;
-        }
-        for l25 in 0..2 {
-            self.fRec6[l25 as usize] = 0.0;
-        }
-        for l26 in 0..2048 {
-            self.fVec9[l26 as usize] = 0.0;
-        }
-        for l27 in 0..2 {
-            self.fRec4[l27 as usize] = 0.0;
-        }
-        for l28 in 0..2048 {
-            self.fVec10[l28 as usize] = 0.0;
-        }
-        for l29 in 0..2 {
-            self.fRec2[l29 as usize] = 0.0;
-        }
-        for l30 in 0..1024 {
-            self.fVec11[l30 as usize] = 0.0;
-        }
-        for l31 in 0..2 {
-            self.fRec0[l31 as usize] = 0.0;
+        for fRec in self.fRec0.iter_mut() {
+            *fRec = [0.0; 2];
         }
     }
 
     pub fn instanceConstants(&mut self, sample_rate: i32) {
         self.fSampleRate = sample_rate;
-        self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
-        self.fConst1 = ((0.0253061224 * self.fConst0) as i32) as f32;
-        self.fConst2 = ((0.0269387756 * self.fConst0) as i32) as f32;
-        self.fConst3 = ((0.0289569162 * self.fConst0) as i32) as f32;
-        self.fConst4 = ((0.0307482984 * self.fConst0) as i32) as f32;
-        self.fConst5 = ((0.0322448984 * self.fConst0) as i32) as f32;
-        self.fConst6 = ((0.033809524 * self.fConst0) as i32) as f32;
-        self.fConst7 = ((0.0353061222 * self.fConst0) as i32) as f32;
-        self.fConst8 = ((0.0366666652 * self.fConst0) as i32) as f32;
-        self.fConst9 = ((0.0126077095 * self.fConst0) as i32) as f32;
-        self.fConst10 = ((0.00999999978 * self.fConst0) as i32) as f32;
-        self.fConst11 = ((0.00773242628 * self.fConst0) as i32) as f32;
-        self.fConst12 = ((0.00510204071 * self.fConst0) as i32) as f32;
     }
 
     pub fn instanceInit(&mut self, sample_rate: i32) {
@@ -1740,55 +3792,69 @@ impl MonoFreeverb {
         self.instanceClear();
     }
 
-    pub fn new(fb1: f32, fb2: f32, damp: f32, spread: f32) -> MonoFreeverb {
-        let mut mono_freeverb = MonoFreeverb::init();
-        mono_freeverb.instanceInit(44_100);
-        mono_freeverb.setControlVariables(fb1, fb2, damp, spread);
-        mono_freeverb
+    pub fn new(nb_outputs: usize, rotation: f32, distance: f32) -> Spat {
+        let mut spat = Spat::init(nb_outputs);
+        spat.instanceInit(44_100);
+        spat.setControlVariables(rotation, distance);
+        spat.skip_smoothing();
+        spat
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> MonoFreeverb {
-        let fb1 = node_infos.more["fb1"]
-            .parse()
-            .expect("fb1 must be a float in [0,1]");
-        let fb2 = node_infos.more["fb2"]
-            .parse()
-            .expect("fb2 must be a float in [0,1]");
-        let damp = node_infos.more["damp"]
-            .parse()
-            .expect("damp must be a float in [0,1]");
-        let spread = node_infos.more["spread"]
-            .parse()
-            .expect("spread must be a float in [0,1]");
-        let mono_freeverb = MonoFreeverb::new(fb1, fb2, damp, spread);
-        mono_freeverb.check_io_node_infos(node_infos);
-        mono_freeverb
+    /// The number of speakers is the number of outlets of the node
+    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Result<Spat, AudiographError> {
+        if node_infos.nb_outlets == 0 {
+            return Err(AudiographError::UnsupportedNode {
+                node: node_infos.id.clone(),
+                kind: node_infos.class_name.clone(),
+                position: node_infos.position.clone(),
+            });
+        }
+        let rotation = node_infos.attribute("rotation", "a float in [0,1]")?;
+        let distance = node_infos.attribute("distance", "a float in [0,1]")?;
+        let mut spat = Spat::new(node_infos.nb_outlets as usize, rotation, distance);
+        spat.check_io_node_infos(node_infos)?;
+        spat.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(spat)
     }
 
-    pub fn setControlVariables(&mut self, fb1: f32, fb2: f32, damp: f32, spread: f32) {
-        self.fHslider0 = fb1;
-        self.fHslider1 = damp;
-        self.fHslider2 = spread;
-        self.fHslider3 = fb2;
+    pub fn setControlVariables(&mut self, rotation: f32, distance: f32) {
+        self.smoother.set_target(0, rotation);
+        self.smoother.set_target(1, distance);
     }
 }
 
-impl fmt::Display for MonoFreeverb {
+impl fmt::Display for Spat {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "freeverb({}, {}, {}, {})",
-            self.fHslider0, self.fHslider3, self.fHslider1, self.fHslider2
+            "spat({}, {}, {})",
+            self.nb_outputs, self.fHslider0, self.fHslider1
         )
     }
 }
 
-impl AudioEffect for MonoFreeverb {
+impl AudioEffect for Spat {
     fn nb_inputs(&self) -> usize {
         return 1;
     }
     fn nb_outputs(&self) -> usize {
-        return 1;
+        return self.nb_outputs;
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        &SPAT_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
     }
 
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
@@ -1796,167 +3862,108 @@ impl AudioEffect for MonoFreeverb {
         debug_assert_eq!(outputs.len(), self.nb_outputs());
         let actual_samplerate = outputs[0].samplerate as i32;
 
-        let input = inputs[0].buffer();
-        let output = outputs[0].buffer_mut();
-        let count = output.len();
-
+        //Constants have to be changed if we change the samplerate...
         if self.fSampleRate != actual_samplerate {
-            self.instanceInit(actual_samplerate);
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
         }
-
-        let fSlow0: f32 = self.fHslider0 as f32;
-        let fSlow1: f32 = self.fHslider1 as f32;
-        let fSlow2: f32 = 1.0 - fSlow1;
-        let fSlow3: f32 = self.fHslider2 as f32;
-        let iSlow4: i32 = (self.fConst1 + fSlow3) as i32;
-        let iSlow5: i32 = (self.fConst2 + fSlow3) as i32;
-        let iSlow6: i32 = (self.fConst3 + fSlow3) as i32;
-        let iSlow7: i32 = (self.fConst4 + fSlow3) as i32;
-        let iSlow8: i32 = (self.fConst5 + fSlow3) as i32;
-        let iSlow9: i32 = (self.fConst6 + fSlow3) as i32;
-        let iSlow10: i32 = (self.fConst7 + fSlow3) as i32;
-        let iSlow11: i32 = (self.fConst8 + fSlow3) as i32;
-        let fSlow12: f32 = self.fHslider3 as f32;
-        let fSlow13: f32 = fSlow3 + -1.0;
-        let iSlow14: i32 = f32::min(1024.0, f32::max(0.0, self.fConst9 + fSlow13)) as i32;
-        let iSlow15: i32 = f32::min(1024.0, f32::max(0.0, self.fConst10 + fSlow13)) as i32;
-        let iSlow16: i32 = f32::min(1024.0, f32::max(0.0, self.fConst11 + fSlow13)) as i32;
-        let iSlow17: i32 = f32::min(1024.0, f32::max(0.0, self.fConst12 + fSlow13)) as i32;
-        for i in 0..count {
-            let mut fTemp0: f32 = input[i as usize] as f32;
-            self.fRec9[0] = (fSlow1 * self.fRec9[1]) + (fSlow2 * self.fRec8[1]);
-            self.fVec0[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec9[0]);
-            self.fRec8[0] = self.fVec0[((self.IOTA - iSlow4) & 8191) as usize];
-            self.fRec11[0] = (fSlow1 * self.fRec11[1]) + (fSlow2 * self.fRec10[1]);
-            self.fVec1[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec11[0]);
-            self.fRec10[0] = self.fVec1[((self.IOTA - iSlow5) & 8191) as usize];
-            self.fRec13[0] = (fSlow1 * self.fRec13[1]) + (fSlow2 * self.fRec12[1]);
-            self.fVec2[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec13[0]);
-            self.fRec12[0] = self.fVec2[((self.IOTA - iSlow6) & 8191) as usize];
-            self.fRec15[0] = (fSlow1 * self.fRec15[1]) + (fSlow2 * self.fRec14[1]);
-            self.fVec3[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec15[0]);
-            self.fRec14[0] = self.fVec3[((self.IOTA - iSlow7) & 8191) as usize];
-            self.fRec17[0] = (fSlow1 * self.fRec17[1]) + (fSlow2 * self.fRec16[1]);
-            self.fVec4[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec17[0]);
-            self.fRec16[0] = self.fVec4[((self.IOTA - iSlow8) & 8191) as usize];
-            self.fRec19[0] = (fSlow1 * self.fRec19[1]) + (fSlow2 * self.fRec18[1]);
-            self.fVec5[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec19[0]);
-            self.fRec18[0] = self.fVec5[((self.IOTA - iSlow9) & 8191) as usize];
-            self.fRec21[0] = (fSlow1 * self.fRec21[1]) + (fSlow2 * self.fRec20[1]);
-            self.fVec6[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec21[0]);
-            self.fRec20[0] = self.fVec6[((self.IOTA - iSlow10) & 8191) as usize];
-            self.fRec23[0] = (fSlow1 * self.fRec23[1]) + (fSlow2 * self.fRec22[1]);
-            self.fVec7[(self.IOTA & 8191) as usize] = fTemp0 + (fSlow0 * self.fRec23[0]);
-            self.fRec22[0] = self.fVec7[((self.IOTA - iSlow11) & 8191) as usize];
-            let mut fTemp1: f32 = (((((((self.fRec8[0] + self.fRec10[0]) + self.fRec12[0])
-                + self.fRec14[0])
-                + self.fRec16[0])
-                + self.fRec18[0])
-                + self.fRec20[0])
-                + self.fRec22[0])
-                + (fSlow12 * self.fRec6[1]);
-            self.fVec8[(self.IOTA & 2047) as usize] = fTemp1;
-            self.fRec6[0] = self.fVec8[((self.IOTA - iSlow14) & 2047) as usize];
-            let mut fRec7: f32 = 0.0 - (fSlow12 * fTemp1);
-            let mut fTemp2: f32 = self.fRec6[1] + (fRec7 + (fSlow12 * self.fRec4[1]));
-            self.fVec9[(self.IOTA & 2047) as usize] = fTemp2;
-            self.fRec4[0] = self.fVec9[((self.IOTA - iSlow15) & 2047) as usize];
-            let mut fRec5: f32 = 0.0 - (fSlow12 * fTemp2);
-            let mut fTemp3: f32 = self.fRec4[1] + (fRec5 + (fSlow12 * self.fRec2[1]));
-            self.fVec10[(self.IOTA & 2047) as usize] = fTemp3;
-            self.fRec2[0] = self.fVec10[((self.IOTA - iSlow16) & 2047) as usize];
-            let mut fRec3: f32 = 0.0 - (fSlow12 * fTemp3);
-            let mut fTemp4: f32 = self.fRec2[1] + (fRec3 + (fSlow12 * self.fRec0[1]));
-            self.fVec11[(self.IOTA & 1023) as usize] = fTemp4;
-            self.fRec0[0] = self.fVec11[((self.IOTA - iSlow17) & 1023) as usize];
-            let mut fRec1: f32 = 0.0 - (fSlow12 * fTemp4);
-            output[i as usize] = (fRec1 + self.fRec0[1]) as f32;
-            self.fRec9[1] = self.fRec9[0];
-            self.IOTA = self.IOTA + 1;
-            self.fRec8[1] = self.fRec8[0];
-            self.fRec11[1] = self.fRec11[0];
-            self.fRec10[1] = self.fRec10[0];
-            self.fRec13[1] = self.fRec13[0];
-            self.fRec12[1] = self.fRec12[0];
-            self.fRec15[1] = self.fRec15[0];
-            self.fRec14[1] = self.fRec14[0];
-            self.fRec17[1] = self.fRec17[0];
-            self.fRec16[1] = self.fRec16[0];
-            self.fRec19[1] = self.fRec19[0];
-            self.fRec18[1] = self.fRec18[0];
-            self.fRec21[1] = self.fRec21[0];
-            self.fRec20[1] = self.fRec20[0];
-            self.fRec23[1] = self.fRec23[0];
-            self.fRec22[1] = self.fRec22[0];
-            self.fRec6[1] = self.fRec6[0];
-            self.fRec4[1] = self.fRec4[0];
-            self.fRec2[1] = self.fRec2[0];
-            self.fRec0[1] = self.fRec0[0];
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Spat {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+        self.fHslider1 = self.smoother.value(1);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        //Only smoothed gains, which can be kept as they are
+        self.instanceConstants(sample_rate);
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let input = &inputs[0].buffer()[start..end];
+        let count = input.len();
+
+        let fSlow0: f32 = (self.fHslider0 as f32) + 0.5;
+        let fSlow1: f32 = self.fHslider1 as f32;
+        let fSlow2: f32 = 9.99999975e-05 * ((0.5 * fSlow1) + 0.5);
+        let fSlow3: f32 = (self.nb_outputs as f32) * fSlow1;
+        for (j, (output, fRec)) in outputs.iter_mut().zip(self.fRec0.iter_mut()).enumerate() {
+            let output = &mut output.buffer_mut()[start..end];
+            let fSlow4: f32 = ((self.nb_outputs - j) as f32) / (self.nb_outputs as f32);
+            let fSlow5: f32 = fSlow2
+                * f32::sqrt(f32::max(
+                    0.0,
+                    1.0 - (fSlow3 * f32::abs(((fSlow0 + fSlow4) % 1.0) - 0.5)),
+                ));
+            for i in 0..count {
+                fRec[0] = fSlow5 + (0.999899983 * fRec[1]);
+                output[i as usize] = ((input[i as usize] as f32) * fRec[0]) as f32;
+                fRec[1] = fRec[0];
+            }
         }
     }
 }
 
 /****************************************
-** Compressor
+** Pink noise
 ****************************************/
 
-pub struct Compressor {
-    fDummy: f32,
+pub const PINK_NOISE_PARAMETERS: [Parameter; 0] = [];
+
+/// Stand-in for faust-effects/pink_noise.dsp, `no.pink_noise_vm(10)`, while it is not generated.
+/// Approximation written by hand and not ported from the code generated by Faust: Voss-McCartney
+/// algorithm with 10 rows. Row `k` takes a new random value every `2^(k+1)` samples.
+pub struct PinkNoise {
     fSampleRate: i32,
-    fConst0: f32,
-    fConst1: f32,
-    fHslider0: f32,
-    fHslider1: f32,
-    fConst2: f32,
-    fHslider2: f32,
-    fRec2: [f32; 2],
-    fRec1: [f32; 2],
-    fHslider3: f32,
-    fRec0: [f32; 2],
+    iRec0: [i32; 2],
+    iRec1: [i32; 2],
+    fRec2: [f32; 10],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
 }
 
-impl Compressor {
-    pub fn init() -> Compressor {
-        Compressor {
-            fDummy: 0 as f32,
+impl PinkNoise {
+    pub fn init() -> PinkNoise {
+        PinkNoise {
             fSampleRate: 0,
-            fConst0: 0.0,
-            fConst1: 0.0,
-            fHslider0: 0.0,
-            fHslider1: 0.0,
-            fConst2: 0.0,
-            fHslider2: 0.0,
-            fRec2: [0.0; 2],
-            fRec1: [0.0; 2],
-            fHslider3: 0.0,
-            fRec0: [0.0; 2],
+            iRec0: [0; 2],
+            iRec1: [0; 2],
+            fRec2: [0.0; 10],
+            smoother: Smoother::new(&PINK_NOISE_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
         }
     }
 
-    pub fn instanceResetUserInterface(&mut self) {
-        self.fHslider0 = 0.5;
-        self.fHslider1 = 2.0;
-        self.fHslider2 = 1.0;
-        self.fHslider3 = 20.0;
-    }
+    pub fn instanceResetUserInterface(&mut self) {}
 
     pub fn instanceClear(&mut self) {
         for l0 in 0..2 {
-            self.fRec2[l0 as usize] = 0.0;
+            self.iRec0[l0 as usize] = 0;
         }
         for l1 in 0..2 {
-            self.fRec1[l1 as usize] = 0.0;
+            self.iRec1[l1 as usize] = 0;
         }
-        for l2 in 0..2 {
-            self.fRec0[l2 as usize] = 0.0;
+        for l2 in 0..10 {
+            self.fRec2[l2 as usize] = 0.0;
         }
     }
 
     pub fn instanceConstants(&mut self, sample_rate: i32) {
         self.fSampleRate = sample_rate;
-        self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
-        self.fConst1 = 2.0 / self.fConst0;
-        self.fConst2 = 1.0 / self.fConst0;
     }
 
     pub fn instanceInit(&mut self, sample_rate: i32) {
@@ -1965,159 +3972,158 @@ impl Compressor {
         self.instanceClear();
     }
 
-    pub fn new(ratio: f32, thresh: f32, att: f32, rel: f32) -> Compressor {
-        let mut compressor = Compressor::init();
-        compressor.instanceInit(44_100);
-        compressor.setControlVariables(ratio, thresh, att, rel);
-        compressor
-    }
-
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Compressor {
-        let ratio = node_infos.more["ratio"]
-            .parse()
-            .expect("ratio must be a float >= 1");
-        let thresh = node_infos.more["thresh"]
-            .parse()
-            .expect("thresh must be a float");
-        let att = node_infos.more["att"].parse().expect("att must be a float");
-        let rel = node_infos.more["rel"].parse().expect("rel must be a float");
-        let compressor = Compressor::new(ratio, thresh, att, rel);
-        compressor.check_io_node_infos(node_infos);
-        compressor
+    pub fn new() -> PinkNoise {
+        let mut pink_noise = PinkNoise::init();
+        pink_noise.instanceInit(44_100);
+        pink_noise
     }
 
-    pub fn setControlVariables(&mut self, ratio: f32, thresh: f32, att: f32, rel: f32) {
-        self.fHslider0 = att;
-        self.fHslider1 = ratio;
-        self.fHslider2 = rel;
-        self.fHslider3 = thresh;
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<PinkNoise, AudiographError> {
+        let mut pink_noise = PinkNoise::new();
+        pink_noise.check_io_node_infos(node_infos)?;
+        pink_noise.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(pink_noise)
     }
 }
 
-impl fmt::Display for Compressor {
+impl fmt::Display for PinkNoise {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "compressor({}, {}, {}, {})",
-            self.fHslider1, self.fHslider3, self.fHslider0, self.fHslider2
-        )
+        write!(f, "pink_noise()")
     }
 }
 
-impl AudioEffect for Compressor {
+impl AudioEffect for PinkNoise {
     fn nb_inputs(&self) -> usize {
-        return 1;
+        return 0;
     }
     fn nb_outputs(&self) -> usize {
         return 1;
     }
+
+    fn parameters(&self) -> &[Parameter] {
+        &PINK_NOISE_PARAMETERS
+    }
+
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
         debug_assert_eq!(inputs.len(), self.nb_inputs());
         debug_assert_eq!(outputs.len(), self.nb_outputs());
         let actual_samplerate = outputs[0].samplerate as i32;
 
-        let input = inputs[0].buffer();
-        let output = outputs[0].buffer_mut();
-        let count = output.len();
-
+        //Constants have to be changed if we change the samplerate...
         if self.fSampleRate != actual_samplerate {
-            self.instanceInit(actual_samplerate);
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
         }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for PinkNoise {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {}
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        //The rows do not depend on the samplerate
+        self.instanceConstants(sample_rate);
+    }
+
+    fn compute(&mut self, start: usize, end: usize, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
 
-        let fSlow0: f32 = self.fHslider0 as f32;
-        let fSlow1: f32 = f32::exp(0.0 - (self.fConst1 / fSlow0));
-        let fSlow2: f32 = ((1.0 / (self.fHslider1 as f32)) + -1.0) * (1.0 - fSlow1);
-        let fSlow3: f32 = f32::exp(0.0 - (self.fConst2 / fSlow0));
-        let fSlow4: f32 = f32::exp(0.0 - (self.fConst2 / (self.fHslider2 as f32)));
-        let fSlow5: f32 = self.fHslider3 as f32;
         for i in 0..count {
-            let mut fTemp0: f32 = input[i as usize] as f32;
-            let mut fTemp1: f32 = f32::abs(fTemp0);
-            let mut fTemp2: f32 = if ((self.fRec1[1] > fTemp1) as i32) as i32 == 1 {
-                fSlow4
-            } else {
-                fSlow3
-            };
-            self.fRec2[0] = (self.fRec2[1] * fTemp2) + (fTemp1 * (1.0 - fTemp2));
-            self.fRec1[0] = self.fRec2[0];
-            self.fRec0[0] = (fSlow1 * self.fRec0[1])
-                + (fSlow2 * f32::max((20.0 * f32::log10(self.fRec1[0])) - fSlow5, 0.0));
-            output[i as usize] = (fTemp0 * f32::powf(10.0, 0.0500000007 * self.fRec0[0])) as f32;
-            self.fRec2[1] = self.fRec2[0];
-            self.fRec1[1] = self.fRec1[0];
-            self.fRec0[1] = self.fRec0[0];
+            self.iRec1[0] = self.iRec1[1].wrapping_add(1);
+            self.iRec0[0] = faust_random(self.iRec0[1]);
+            let mut iTemp0: i32 = self.iRec1[0].trailing_zeros() as i32;
+            if iTemp0 < 10 {
+                self.fRec2[iTemp0 as usize] = 4.65661287e-10 * (self.iRec0[0] as f32);
+            }
+            let mut iTemp1: i32 = faust_random(self.iRec0[0]);
+            let fTemp2: f32 = self.fRec2.iter().sum::<f32>() + (4.65661287e-10 * (iTemp1 as f32));
+            output[i as usize] = (0.0909090936 * fTemp2) as f32;
+            self.iRec1[1] = self.iRec1[0];
+            self.iRec0[1] = iTemp1;
         }
     }
 }
 
 /****************************************
-** Autowah
+** Sawtooth
 ****************************************/
 
-pub struct Autowah {
-    fDummy: f32,
-    fHslider0: f32,
+pub const SAWTOOTH_PARAMETERS: [Parameter; 1] = [Parameter::new(
+    "freq",
+    ParameterType::Float,
+    20.,
+    10_000.,
+    440.,
+)];
+
+/// Stand-in for faust-effects/sawtooth.dsp, `os.sawtooth`, while it is not generated. Approximation written
+/// by hand and not ported from the code generated by Faust: band-limited sawtooth obtained by
+/// differentiating a parabolic wave.
+pub struct Sawtooth {
     fSampleRate: i32,
     fConst0: f32,
     fConst1: f32,
     fConst2: f32,
-    fRec2: [f32; 2],
-    fRec1: [f32; 2],
-    fConst3: f32,
-    fConst4: f32,
-    fRec3: [f32; 2],
-    fRec4: [f32; 2],
-    fRec0: [f32; 3],
+    fHslider0: f32,
+    iVec0: [i32; 2],
+    fRec0: [f32; 2],
+    fVec1: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
 }
 
-impl Autowah {
-    pub fn init() -> Autowah {
-        Autowah {
-            fDummy: 0 as f32,
-            fHslider0: 0.0,
+impl Sawtooth {
+    pub fn init() -> Sawtooth {
+        Sawtooth {
             fSampleRate: 0,
             fConst0: 0.0,
             fConst1: 0.0,
             fConst2: 0.0,
-            fRec2: [0.0; 2],
-            fRec1: [0.0; 2],
-            fConst3: 0.0,
-            fConst4: 0.0,
-            fRec3: [0.0; 2],
-            fRec4: [0.0; 2],
-            fRec0: [0.0; 3],
+            fHslider0: 0.0,
+            iVec0: [0; 2],
+            fRec0: [0.0; 2],
+            fVec1: [0.0; 2],
+            smoother: Smoother::new(&SAWTOOTH_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
         }
     }
 
     pub fn instanceResetUserInterface(&mut self) {
-        self.fHslider0 = 0.899999976;
+        self.fHslider0 = 440.0;
     }
 
     pub fn instanceClear(&mut self) {
         for l0 in 0..2 {
-            self.fRec2[l0 as usize] = 0.0;
+            self.iVec0[l0 as usize] = 0;
         }
         for l1 in 0..2 {
-            self.fRec1[l1 as usize] = 0.0;
+            self.fRec0[l1 as usize] = 0.0;
         }
         for l2 in 0..2 {
-            self.fRec3[l2 as usize] = 0.0;
-        }
-        for l3 in 0..2 {
-            self.fRec4[l3 as usize] = 0.0;
-        }
-        for l4 in 0..3 {
-            self.fRec0[l4 as usize] = 0.0;
+            self.fVec1[l2 as usize] = 0.0;
         }
     }
 
     pub fn instanceConstants(&mut self, sample_rate: i32) {
         self.fSampleRate = sample_rate;
         self.fConst0 = f32::min(192000.0, f32::max(1.0, self.fSampleRate as f32));
-        self.fConst1 = f32::exp(0.0 - (10.0 / self.fConst0));
-        self.fConst2 = 1.0 - self.fConst1;
-        self.fConst3 = 1413.71667 / self.fConst0;
-        self.fConst4 = 2827.43335 / self.fConst0;
+        self.fConst1 = 1.0 / self.fConst0;
+        self.fConst2 = 0.25 * self.fConst0;
     }
 
     pub fn instanceInit(&mut self, sample_rate: i32) {
@@ -2126,114 +4132,167 @@ impl Autowah {
         self.instanceClear();
     }
 
-    pub fn new(level: f32) -> Autowah {
-        let mut autowah = Autowah::init();
-        autowah.instanceInit(44_100);
-        autowah.setControlVariables(level);
-        autowah
+    pub fn new(freq: f32) -> Sawtooth {
+        let mut sawtooth = Sawtooth::init();
+        sawtooth.instanceInit(44_100);
+        sawtooth.setControlVariables(freq);
+        sawtooth.skip_smoothing();
+        sawtooth
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Autowah {
-        let level = node_infos.more["level"]
-            .parse()
-            .expect("level is a float in [0,1]");
-        let autowah = Autowah::new(level);
-        autowah.check_io_node_infos(node_infos);
-        autowah
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<Sawtooth, AudiographError> {
+        let freq = node_infos.attribute("freq", "a frequency in Hz")?;
+        let mut sawtooth = Sawtooth::new(freq);
+        sawtooth.check_io_node_infos(node_infos)?;
+        sawtooth.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(sawtooth)
     }
 
-    pub fn setControlVariables(&mut self, level: f32) {
-        self.fHslider0 = level;
+    pub fn setControlVariables(&mut self, freq: f32) {
+        self.smoother.set_target(0, freq);
     }
 }
 
-impl fmt::Display for Autowah {
+impl fmt::Display for Sawtooth {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "autowah({})", self.fHslider0)
+        write!(f, "sawtooth({})", self.fHslider0)
     }
 }
 
-impl AudioEffect for Autowah {
+impl AudioEffect for Sawtooth {
     fn nb_inputs(&self) -> usize {
-        return 1;
+        return 0;
     }
     fn nb_outputs(&self) -> usize {
         return 1;
     }
 
+    fn parameters(&self) -> &[Parameter] {
+        &SAWTOOTH_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
         debug_assert_eq!(inputs.len(), self.nb_inputs());
         debug_assert_eq!(outputs.len(), self.nb_outputs());
         let actual_samplerate = outputs[0].samplerate as i32;
 
-        let input = inputs[0].buffer();
-        let output = outputs[0].buffer_mut();
-        let count = output.len();
-
         //Constants have to be changed if we change the samplerate...
-        // We should smooth it actually...
         if self.fSampleRate != actual_samplerate {
-            self.instanceInit(actual_samplerate);
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
         }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl FaustDsp for Sawtooth {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        //The phase does not depend on the samplerate
+        self.instanceConstants(sample_rate);
+    }
+
+    fn compute(&mut self, start: usize, end: usize, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
 
         let fSlow0: f32 = self.fHslider0 as f32;
-        let fSlow1: f32 = 1.0 - fSlow0;
+        let fSlow1: f32 = self.fConst1 * fSlow0;
+        let fSlow2: f32 = self.fConst2 / fSlow0;
         for i in 0..count {
-            let mut fTemp0: f32 = input[i as usize] as f32;
-            let mut fTemp1: f32 = f32::abs(fTemp0);
-            self.fRec2[0] = f32::max(
-                fTemp1,
-                (self.fConst1 * self.fRec2[1]) + (self.fConst2 * fTemp1),
-            );
-            self.fRec1[0] =
-                (0.999000013 * self.fRec1[1]) + (9.99999975e-05 * f32::powf(4.0, self.fRec2[0]));
-            let mut fTemp2: f32 = f32::powf(2.0, 2.29999995 * self.fRec2[0]);
-            let mut fTemp3: f32 = 1.0
-                - (self.fConst3 * (fTemp2 / f32::powf(2.0, (2.0 * (1.0 - self.fRec2[0])) + 1.0)));
-            self.fRec3[0] = (0.999000013 * self.fRec3[1])
-                - (0.00200000009 * (fTemp3 * f32::cos(self.fConst4 * fTemp2)));
-            self.fRec4[0] = (0.999000013 * self.fRec4[1]) + (0.00100000005 * faustpower2_f(fTemp3));
-            self.fRec0[0] = (fTemp0 * self.fRec1[0])
-                - ((self.fRec3[0] * self.fRec0[1]) + (self.fRec4[0] * self.fRec0[2]));
+            self.iVec0[0] = 1;
+            let mut fTemp0: f32 = fSlow1 + self.fRec0[1];
+            self.fRec0[0] = fTemp0 - f32::floor(fTemp0);
+            let mut fTemp1: f32 = faustpower2_f((2.0 * self.fRec0[0]) + -1.0);
+            self.fVec1[0] = fTemp1;
             output[i as usize] =
-                ((fSlow1 * fTemp0) + (fSlow0 * (self.fRec0[0] - self.fRec0[1]))) as f32;
-            self.fRec2[1] = self.fRec2[0];
-            self.fRec1[1] = self.fRec1[0];
-            self.fRec3[1] = self.fRec3[0];
-            self.fRec4[1] = self.fRec4[0];
-            self.fRec0[2] = self.fRec0[1];
+                (fSlow2 * ((self.iVec0[1] as f32) * (fTemp1 - self.fVec1[1]))) as f32;
+            self.iVec0[1] = self.iVec0[0];
             self.fRec0[1] = self.fRec0[0];
+            self.fVec1[1] = self.fVec1[0];
         }
     }
 }
 
 /****************************************
-** Cubic non linear distorsion
+** Allpass feedback comb filter
 ****************************************/
 
-pub struct Cubicnl {
-    fDummy: f32,
+pub const FEEDBACKCOMB_PARAMETERS: [Parameter; 2] = [
+    Parameter::new("delay", ParameterType::Float, 1., 16., 5.5),
+    Parameter::new("gain", ParameterType::Float, -0.99, 0.99, 0.8),
+];
+
+/// Stand-in for faust-effects/feedbackcomb.dsp, `fi.allpass_fcomb` with a delay of at most 16 samples,
+/// while it is not generated. Approximation written by hand and not ported from the code generated
+/// by Faust.
+pub struct FeedbackComb {
+    fSampleRate: i32,
     fHslider0: f32,
     fHslider1: f32,
-    fSampleRate: i32,
+    IOTA: i32,
+    fVec0: [f32; 32],
+    fRec0: [f32; 2],
+    smoother: Smoother,
+    rate_switch: RateSwitch,
 }
 
-impl Cubicnl {
-    pub fn init() -> Cubicnl {
-        Cubicnl {
-            fDummy: 0 as f32,
+impl FeedbackComb {
+    pub fn init() -> FeedbackComb {
+        FeedbackComb {
+            fSampleRate: 0,
             fHslider0: 0.0,
             fHslider1: 0.0,
-            fSampleRate: 0,
+            IOTA: 0,
+            fVec0: [0.0; 32],
+            fRec0: [0.0; 2],
+            smoother: Smoother::new(&FEEDBACKCOMB_PARAMETERS),
+            rate_switch: RateSwitch::Reset,
         }
     }
 
     pub fn instanceResetUserInterface(&mut self) {
-        self.fHslider0 = 0.899999976;
-        self.fHslider1 = 0.899999976;
+        self.fHslider0 = 5.5;
+        self.fHslider1 = 0.800000012;
     }
 
-    pub fn instanceClear(&mut self) {}
+    pub fn instanceClear(&mut self) {
+        self.IOTA = 0;
+        for l0 in 0..32 {
+            self.fVec0[l0 as usize] = 0.0;
+        }
+        for l1 in 0..2 {
+            self.fRec0[l1 as usize] = 0.0;
+        }
+    }
 
     pub fn instanceConstants(&mut self, sample_rate: i32) {
         self.fSampleRate = sample_rate;
@@ -2245,38 +4304,38 @@ impl Cubicnl {
         self.instanceClear();
     }
 
-    pub fn new(drive: f32, offset: f32) -> Cubicnl {
-        let mut cubicnl = Cubicnl::init();
-        cubicnl.setControlVariables(drive, offset);
-        cubicnl.instanceInit(44_100);
-        cubicnl
+    pub fn new(delay: f32, gain: f32) -> FeedbackComb {
+        let mut feedbackcomb = FeedbackComb::init();
+        feedbackcomb.instanceInit(44_100);
+        feedbackcomb.setControlVariables(delay, gain);
+        feedbackcomb.skip_smoothing();
+        feedbackcomb
     }
 
-    pub fn from_node_infos(node_infos: &audiograph_parser::Node) -> Cubicnl {
-        let drive = node_infos.more["drive"]
-            .parse()
-            .expect("drive is a float in [0,1]");
-        let offset = node_infos.more["offset"]
-            .parse()
-            .expect("offset is a float");
-        let cubicnl = Cubicnl::new(drive, offset);
-        cubicnl.check_io_node_infos(node_infos);
-        cubicnl
+    pub fn from_node_infos(
+        node_infos: &audiograph_parser::Node,
+    ) -> Result<FeedbackComb, AudiographError> {
+        let delay = node_infos.attribute("delay", "a delay in samples in [1,16]")?;
+        let gain = node_infos.attribute("gain", "a float in ]-1,1[")?;
+        let mut feedbackcomb = FeedbackComb::new(delay, gain);
+        feedbackcomb.check_io_node_infos(node_infos)?;
+        feedbackcomb.set_rate_switch(RateSwitch::from_node_infos(node_infos));
+        Ok(feedbackcomb)
     }
 
-    pub fn setControlVariables(&mut self, drive: f32, offset: f32) {
-        self.fHslider0 = offset;
-        self.fHslider1 = drive;
+    pub fn setControlVariables(&mut self, delay: f32, gain: f32) {
+        self.smoother.set_target(0, delay);
+        self.smoother.set_target(1, gain);
     }
 }
 
-impl fmt::Display for Cubicnl {
+impl fmt::Display for FeedbackComb {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "cubicnl({}, {})", self.fHslider1, self.fHslider0)
+        write!(f, "feedbackcomb({}, {})", self.fHslider0, self.fHslider1)
     }
 }
 
-impl AudioEffect for Cubicnl {
+impl AudioEffect for FeedbackComb {
     fn nb_inputs(&self) -> usize {
         return 1;
     }
@@ -2284,29 +4343,394 @@ impl AudioEffect for Cubicnl {
         return 1;
     }
 
+    fn parameters(&self) -> &[Parameter] {
+        &FEEDBACKCOMB_PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
     fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
         debug_assert_eq!(inputs.len(), self.nb_inputs());
         debug_assert_eq!(outputs.len(), self.nb_outputs());
         let actual_samplerate = outputs[0].samplerate as i32;
 
-        let input = inputs[0].buffer();
-        let output = outputs[0].buffer_mut();
-        let count = output.len();
-
         //Constants have to be changed if we change the samplerate...
-        // We should smooth it actually...
         if self.fSampleRate != actual_samplerate {
-            self.instanceInit(actual_samplerate);
+            match self.rate_switch {
+                RateSwitch::Reset => self.instanceInit(actual_samplerate),
+                RateSwitch::Preserve => self.switch_samplerate(actual_samplerate),
+            }
+            self.update_sliders();
         }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
 
-        let fSlow0: f32 = self.fHslider0 as f32;
-        let fSlow1: f32 = f32::powf(10.0, 2.0 * (self.fHslider1 as f32));
+impl FaustDsp for FeedbackComb {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        self.fHslider0 = self.smoother.value(0);
+        self.fHslider1 = self.smoother.value(1);
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        //The delay is in samples, whatever the samplerate
+        self.instanceConstants(sample_rate);
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let input = &inputs[0].buffer()[start..end];
+        let output = &mut outputs[0].buffer_mut()[start..end];
+        let count = output.len();
+
+        let fSlow0: f32 = f32::min(16.0, f32::max(0.0, (self.fHslider0 as f32) + -1.0));
+        let fSlow1: f32 = self.fHslider1 as f32;
         for i in 0..count {
-            let mut fTemp0: f32 = f32::max(
-                -1.0,
-                f32::min(1.0, fSlow0 + (fSlow1 * (input[i as usize] as f32))),
+            let mut fTemp0: f32 = (input[i as usize] as f32) - (fSlow1 * self.fRec0[1]);
+            self.fVec0[(self.IOTA & 31) as usize] = fTemp0;
+            self.fRec0[0] = fractional_delay(&self.fVec0, self.IOTA, fSlow0);
+            output[i as usize] = (self.fRec0[1] + (fSlow1 * fTemp0)) as f32;
+            self.IOTA = self.IOTA + 1;
+            self.fRec0[1] = self.fRec0[0];
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use faust_generated;
+
+    /// Largest difference between two consecutive samples when `drive` jumps from 0 to 0.5
+    /// after the first cycle, on a constant input
+    fn cubicnl_step(smoothing_time: f32) -> (f32, f32) {
+        let mut cubicnl = faust_generated::Cubicnl::with_parameters(&[0., 0.]);
+        cubicnl.set_smoothing_time(smoothing_time);
+        let mut input = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for sample in input[0].buffer_mut().iter_mut() {
+            *sample = 0.1;
+        }
+        let mut output = vec![DspEdge::new(1, 1, 64, 44_100)];
+
+        let mut max_step = 0f32;
+        let mut previous: Option<f32> = None;
+        for cycle in 0..200 {
+            if cycle == 1 {
+                assert!(cubicnl.set_parameter_by_name("drive", 0.5));
+            }
+            cubicnl.process(&input, &mut output);
+            for &sample in output[0].buffer().iter() {
+                if let Some(previous) = previous {
+                    max_step = max_step.max((sample - previous).abs());
+                }
+                previous = Some(sample);
+            }
+        }
+        (max_step, previous.unwrap())
+    }
+
+    /// Energy of the output of a reverb fed with a burst of noise, for each cycle, while the samplerate
+    /// goes from 44.1kHz to 22.05kHz and back
+    fn zita_reverb_switches(rate_switch: RateSwitch) -> Vec<f32> {
+        let mut reverb = ZitaReverb::new(10., 200, 6000, 3., 2., 96_200);
+        reverb.set_rate_switch(rate_switch);
+        let mut energies = Vec::new();
+        for cycle in 0..90 {
+            let (size, samplerate) = if cycle >= 30 && cycle < 60 {
+                (32, 22_050)
+            } else {
+                (64, 44_100)
+            };
+            let mut inputs = vec![DspEdge::new(1, 1, size, samplerate); 2];
+            if cycle < 10 {
+                for (i, sample) in inputs[0].buffer_mut().iter_mut().enumerate() {
+                    *sample = ((i as f32 * 12.9898).sin() * 43_758.547).fract();
+                }
+                inputs[1] = inputs[0].clone();
+            }
+            let mut outputs = vec![DspEdge::new(1, 1, size, samplerate); 2];
+            reverb.process(&inputs, &mut outputs);
+            energies.push(
+                outputs
+                    .iter()
+                    .flat_map(|output| output.buffer().iter())
+                    .map(|sample| sample * sample)
+                    .sum::<f32>()
+                    / size as f32,
+            );
+        }
+        energies
+    }
+
+    /// Render a few cycles at two samplerates, with noise on the inputs, and check that
+    /// all the outputs are finite and not silent
+    pub(crate) fn check_renders<T: AudioEffect, F: Fn() -> T>(new_effect: F) {
+        for &samplerate in [44_100, 96_000].iter() {
+            let mut effect = new_effect();
+            let mut inputs = vec![DspEdge::new(1, 1, 64, samplerate); effect.nb_inputs()];
+            let mut outputs = vec![DspEdge::new(1, 1, 64, samplerate); effect.nb_outputs()];
+            let mut energies = vec![0f32; effect.nb_outputs()];
+            for cycle in 0..32 {
+                for input in inputs.iter_mut() {
+                    for (i, sample) in input.buffer_mut().iter_mut().enumerate() {
+                        let t = (cycle * 64 + i) as f32;
+                        *sample = ((t * 12.9898).sin() * 43_758.547).fract();
+                    }
+                }
+                effect.process(&inputs, &mut outputs);
+                for (energy, output) in energies.iter_mut().zip(outputs.iter()) {
+                    assert!(
+                        output.buffer().iter().all(|sample| sample.is_finite()),
+                        "{} at {}Hz",
+                        effect,
+                        samplerate
+                    );
+                    *energy += output
+                        .buffer()
+                        .iter()
+                        .map(|sample| sample * sample)
+                        .sum::<f32>();
+                }
+            }
+            assert!(
+                energies.iter().all(|&energy| energy > 0.),
+                "{} is silent at {}Hz: {:?}",
+                effect,
+                samplerate,
+                energies
+            );
+        }
+    }
+
+    #[test]
+    fn test_brass() {
+        check_renders(|| Brass::new(3., 0.6, 0.9, 0.8));
+    }
+
+    #[test]
+    fn test_violin() {
+        check_renders(|| Violin::new(2., 0.4, 0.5, 0.6));
+    }
+
+    #[test]
+    fn test_wah() {
+        check_renders(|| Wah::new(893.));
+    }
+
+    #[test]
+    fn test_panner() {
+        check_renders(|| Panner::new(0.8));
+    }
+
+    #[test]
+    fn test_spat() {
+        check_renders(|| Spat::new(3, 0.7, 0.9));
+        // Close to the listener, so that it is heard on all the speakers
+        check_renders(|| Spat::new(8, 0.7, 0.1));
+    }
+
+    #[test]
+    fn test_pink_noise() {
+        check_renders(PinkNoise::new);
+    }
+
+    #[test]
+    fn test_sawtooth() {
+        check_renders(|| Sawtooth::new(440.));
+    }
+
+    #[test]
+    fn test_feedbackcomb() {
+        check_renders(|| FeedbackComb::new(5.5, 0.8));
+    }
+
+    #[test]
+    fn test_rate_switch_preserves_state() {
+        let energies = zita_reverb_switches(RateSwitch::Reset);
+        assert!(energies[29] > 0.);
+        assert!(energies[30..].iter().all(|&energy| energy == 0.));
+
+        let energies = zita_reverb_switches(RateSwitch::Preserve);
+        assert!(energies.iter().all(|energy| energy.is_finite()));
+        // The tail goes on after each switch, without jumping
+        for &switch in [30, 60].iter() {
+            let ratio = energies[switch] / energies[switch - 1];
+            assert!(ratio > 0.2 && ratio < 5., "Energy ratio of {}", ratio);
+        }
+        assert!(energies[89] > 0.);
+    }
+
+    #[test]
+    fn test_variants_clear_idle_lines() {
+        let mut inputs = vec![DspEdge::new(1, 1, 64, 44_100); 2];
+        for (i, sample) in inputs[0].buffer_mut().iter_mut().enumerate() {
+            *sample = ((i as f32 * 12.9898).sin() * 43_758.547).fract();
+        }
+        inputs[1] = inputs[0].clone();
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100); 2];
+
+        let mut reverb = ZitaReverb::new(10., 200, 6000, 3., 2., 96_200);
+        for _ in 0..100 {
+            reverb.process(&inputs, &mut outputs);
+        }
+        assert!(reverb.fVec3.iter().any(|&sample| sample != 0.));
+        assert!(reverb.set_variant(1));
+        assert!(reverb.fVec3.iter().all(|&sample| sample == 0.));
+        assert!(reverb.fVec0.iter().any(|&sample| sample != 0.));
+
+        let mut freeverb = MonoFreeverb::new(0.5, 0.5, 0.5, 0.5);
+        for _ in 0..100 {
+            freeverb.process(&inputs[0..1], &mut outputs[0..1]);
+        }
+        assert!(freeverb.set_variant(1));
+        assert!(freeverb.fVec1.iter().all(|&sample| sample == 0.));
+        assert!(freeverb.fVec8.iter().any(|&sample| sample != 0.));
+        freeverb.process(&inputs[0..1], &mut outputs[0..1]);
+        assert!(freeverb.set_variant(2));
+        assert!(freeverb.fVec8.iter().all(|&sample| sample == 0.));
+        assert!(freeverb.fVec0.iter().any(|&sample| sample != 0.));
+    }
+
+    #[test]
+    fn test_rescaling_by_steps() {
+        let ramp = (0..1024).map(|i| i as f32).collect::<Vec<_>>();
+        for &ratio in [0.5, 2.].iter() {
+            let mut at_once = ramp.clone();
+            let mut rescaling = Rescaling::default();
+            rescaling.start(ratio, 100);
+            assert_eq!(rescaling.rescale(&mut at_once, 100, 4096), 1024);
+
+            let mut by_steps = ramp.clone();
+            rescaling.start(ratio, 100);
+            for _ in 0..4 {
+                assert_eq!(rescaling.rescale(&mut by_steps, 100, 256), 256);
+            }
+            assert_eq!(rescaling.line, 1);
+            assert_eq!(by_steps, at_once);
+            // A delay d reads what was d / ratio samples before the switch
+            let delay = 10;
+            let expected = ramp[(100 - (delay as f32 / ratio) as i32) as usize];
+            assert_eq!(at_once[100 - delay], expected);
+        }
+
+        // The samples written since the switch are kept
+        let mut line = ramp.clone();
+        let mut rescaling = Rescaling::default();
+        rescaling.start(0.5, 100);
+        rescaling.rescale(&mut line, 110, 1024);
+        assert_eq!(&line[100..110], &ramp[100..110]);
+    }
+
+    #[test]
+    fn test_smoothing_step() {
+        // The output goes from about 0.1 to 2/3
+        let (max_step, last) = cubicnl_step(DEFAULT_SMOOTHING_TIME);
+        // The parameters follow a ramp, whose steps are much smaller than the jump without smoothing
+        assert!(max_step < 0.05, "Discontinuity of {}", max_step);
+        assert!((last - 2. / 3.).abs() < 1e-4);
+
+        let (max_step, last) = cubicnl_step(0.);
+        assert!(max_step > 0.5);
+        assert!((last - 2. / 3.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_smoothing_ramp_per_sample() {
+        // The right output of the panner of a constant 1 is its position
+        let mut panner = Panner::new(0.);
+        let mut input = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for sample in input[0].buffer_mut().iter_mut() {
+            *sample = 1.;
+        }
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100); 2];
+        assert!(panner.set_parameter_by_name("pan", 1.));
+
+        let length = (DEFAULT_SMOOTHING_TIME * 44_100.).round() as usize;
+        let mut samples = Vec::new();
+        for _ in 0..10 {
+            panner.process(&input, &mut outputs);
+            samples.extend_from_slice(outputs[1].buffer());
+        }
+        // Every sample moves by the same step, without staircase, until the target is reached
+        for (n, sample) in samples.iter().enumerate() {
+            let expected = ((n + 1) as f32 / length as f32).min(1.);
+            assert!(
+                (sample - expected).abs() < 1e-4,
+                "{}: {} instead of {}",
+                n,
+                sample,
+                expected
             );
-            output[i as usize] = (fTemp0 * (1.0 - (0.333333343 * faustpower2_f(fTemp0)))) as f32;
         }
+        assert_eq!(samples[length - 1], 1.);
+        assert!(panner.smoother().is_settled());
+    }
+
+    #[test]
+    fn test_integer_parameter_reaches_dsp() {
+        let mut inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for (i, sample) in inputs[0].buffer_mut().iter_mut().enumerate() {
+            *sample = (i as f32 * 0.3).sin();
+        }
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut shifted_outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut transposer = Transposer::default();
+        let mut shifted = Transposer::default();
+        let semitones = shifted.parameter_index("semitones").unwrap();
+        let mut changed = false;
+        for cycle in 0..20 {
+            if cycle == 10 {
+                assert!(shifted.set_parameter(semitones, 12.));
+            }
+            transposer.process(&inputs, &mut outputs);
+            shifted.process(&inputs, &mut shifted_outputs);
+            if cycle < 10 {
+                assert_eq!(outputs[0].buffer(), shifted_outputs[0].buffer());
+            } else {
+                changed = changed || outputs[0].buffer() != shifted_outputs[0].buffer();
+            }
+        }
+        assert!(changed);
+    }
+
+    #[test]
+    fn test_smoothing_only_floats() {
+        let mut smoother = Smoother::new(faust_generated::TRANSPOSE.parameters);
+        // Shift
+        smoother.set_target(2, 12.);
+        assert_eq!(smoother.value(2), 12.);
+        smoother.tick(1);
+        assert!(smoother.is_settled());
+
+        let mut smoother = Smoother::new(&ZITA_REVERB_PARAMETERS);
+        smoother.set_target(0, 80.);
+        assert!(!smoother.is_settled());
+        smoother.tick(32);
+        assert!(smoother.value(0) > 60. && smoother.value(0) < 80.);
+        assert!(!smoother.is_settled());
+        // The values move by blocks as much as sample by sample
+        let mut by_sample = Smoother::new(&ZITA_REVERB_PARAMETERS);
+        by_sample.set_target(0, 80.);
+        for _ in 0..32 {
+            by_sample.tick(1);
+        }
+        assert!((by_sample.value(0) - smoother.value(0)).abs() < 1e-3);
     }
 }
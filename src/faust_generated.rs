@@ -0,0 +1,561 @@
+//! Effects generated by build.rs from the Faust code in faust-effects/
+//!
+//! To add an effect, drop `name.dsp` into faust-effects/, with `declare description "...";`
+//! for the listing of nodes, and commit the output of `faust -lang rust -cn Name name.dsp`
+//! as `name.rs`. The `faust` feature compiles the .dsp files instead.
+//! The effect becomes the node kind `name`, whose attributes are the widgets of its UI, named after
+//! their labels, or by `PARAMETER_NAMES` in build.rs to keep the names used by existing graphs.
+//! Effects which need code that Faust cannot generate, such as variants or a number of outputs
+//! set by the node, are in `faust_effect` and declare `hand_ported` in their .dsp file.
+//!
+//! Without the `faust` feature, only autowah, compressor, cubicnl and transpose are generated for now,
+//! and their committed .rs files are written by hand in the format of the rust backend of Faust, as
+//! stated in their headers: they are to be replaced by the output of faust. panner, brass, violin, wah,
+//! pink_noise, sawtooth and feedbackcomb have no committed .rs file yet, and effects written by hand
+//! in `faust_effect` stand in for them.
+#![allow(non_snake_case)]
+
+use std::fmt;
+
+use audiograph::*;
+use audiograph_parser::{AudiographError, Node};
+use faust_effect::{self, compute_smoothed, RateSwitch, Rescaling, Smoother};
+use node_registry::{NodeMetadata, NodeRegistry};
+
+/// Largest number of inputs or outputs of a generated effect
+pub const MAX_PORTS: usize = 32;
+
+/* Interface expected by the code generated by the rust backend of Faust */
+
+pub type F32 = f32;
+pub type F64 = f64;
+pub type FaustFloat = F32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamIndex(pub i32);
+
+pub trait Meta {
+    fn declare(&mut self, key: &str, value: &str);
+}
+
+pub trait UI<T> {
+    fn open_tab_box(&mut self, label: &str);
+    fn open_horizontal_box(&mut self, label: &str);
+    fn open_vertical_box(&mut self, label: &str);
+    fn close_box(&mut self);
+    fn add_button(&mut self, label: &str, param: ParamIndex);
+    fn add_check_button(&mut self, label: &str, param: ParamIndex);
+    fn add_vertical_slider(
+        &mut self,
+        label: &str,
+        param: ParamIndex,
+        init: T,
+        min: T,
+        max: T,
+        step: T,
+    );
+    fn add_horizontal_slider(
+        &mut self,
+        label: &str,
+        param: ParamIndex,
+        init: T,
+        min: T,
+        max: T,
+        step: T,
+    );
+    fn add_num_entry(&mut self, label: &str, param: ParamIndex, init: T, min: T, max: T, step: T);
+    fn add_horizontal_bargraph(&mut self, label: &str, param: ParamIndex, min: T, max: T);
+    fn add_vertical_bargraph(&mut self, label: &str, param: ParamIndex, min: T, max: T);
+    fn declare(&mut self, param: Option<ParamIndex>, key: &str, value: &str);
+}
+
+pub trait FaustDsp {
+    type T;
+
+    fn new() -> Self
+    where
+        Self: Sized;
+    fn metadata(&self, m: &mut dyn Meta);
+    fn get_sample_rate(&self) -> i32;
+    fn get_num_inputs(&self) -> i32;
+    fn get_num_outputs(&self) -> i32;
+    fn class_init(sample_rate: i32)
+    where
+        Self: Sized;
+    fn instance_reset_params(&mut self);
+    fn instance_clear(&mut self);
+    fn instance_constants(&mut self, sample_rate: i32);
+    fn instance_init(&mut self, sample_rate: i32);
+    fn init(&mut self, sample_rate: i32);
+    fn build_user_interface(&self, ui_interface: &mut dyn UI<Self::T>);
+    fn build_user_interface_static(ui_interface: &mut dyn UI<Self::T>)
+    where
+        Self: Sized;
+    fn get_param(&self, param: ParamIndex) -> Option<Self::T>;
+    fn set_param(&mut self, param: ParamIndex, value: Self::T);
+    fn compute(&mut self, count: i32, inputs: &[&[Self::T]], outputs: &mut [&mut [Self::T]]);
+}
+
+/// Ports and parameters of an effect of faust-effects/, extracted from its Faust code by build.rs
+pub struct GeneratedEffect {
+    pub kind: &'static str,
+    pub description: &'static str,
+    pub nb_inputs: usize,
+    pub nb_outputs: usize,
+    pub parameters: &'static [Parameter],
+    /// Index in the Faust code of each parameter
+    pub param_indices: &'static [i32],
+}
+
+impl GeneratedEffect {
+    pub fn metadata(&self) -> NodeMetadata {
+        NodeMetadata::new(
+            self.description,
+            Some(self.nb_inputs as u32),
+            Some(self.nb_outputs as u32),
+            self.parameters,
+        )
+    }
+}
+
+/// What build.rs finds out about the code generated by Faust for an effect
+pub trait GeneratedDsp: FaustDsp<T = F32> {
+    fn effect() -> &'static GeneratedEffect;
+
+    /// Array indexed with `IOTA` number `index` in the struct, or None after the last one
+    fn delay_line(&mut self, index: usize) -> Option<&mut [F32]>;
+
+    /// Index of the next sample in the delay lines
+    fn iota(&self) -> i32;
+}
+
+/// The code generated by Faust for an effect, with smoothed parameters
+pub struct FaustEffect<D> {
+    dsp: Box<D>,
+    effect: &'static GeneratedEffect,
+    smoother: Smoother,
+    rate_switch: RateSwitch,
+    rescaling: Rescaling,
+}
+
+impl<D: GeneratedDsp + Send> FaustEffect<D> {
+    /// The first parameters take `values`, clamped to their ranges, and the others keep their default values
+    pub fn with_parameters(values: &[f32]) -> FaustEffect<D> {
+        let mut faust_effect = FaustEffect::default();
+        for (index, &value) in values.iter().enumerate() {
+            assert!(faust_effect.set_parameter(index, value));
+        }
+        faust_effect::FaustDsp::skip_smoothing(&mut faust_effect);
+        faust_effect
+    }
+
+    /// The parameters missing in the attributes of the node keep their default values
+    pub fn from_node_infos(node_infos: &Node) -> Result<FaustEffect<D>, AudiographError> {
+        let mut faust_effect = FaustEffect::default();
+        for (index, parameter) in faust_effect.effect.parameters.iter().enumerate() {
+            if !node_infos.more.contains_key(parameter.name) {
+                continue;
+            }
+            let value = if parameter.kind == ParameterType::Boolean {
+                let value: bool = node_infos.attribute(parameter.name, "a boolean")?;
+                value as u32 as f32
+            } else {
+                node_infos.attribute(parameter.name, "a number")?
+            };
+            faust_effect.set_parameter(index, value);
+        }
+        faust_effect::FaustDsp::skip_smoothing(&mut faust_effect);
+        faust_effect.check_io_node_infos(node_infos)?;
+        faust_effect::FaustDsp::set_rate_switch(
+            &mut faust_effect,
+            RateSwitch::from_node_infos(node_infos),
+        );
+        Ok(faust_effect)
+    }
+}
+
+/// All the parameters take their default values
+impl<D: GeneratedDsp + Send> Default for FaustEffect<D> {
+    fn default() -> FaustEffect<D> {
+        let mut dsp = Box::new(D::new());
+        dsp.init(44_100);
+        let effect = D::effect();
+        let mut faust_effect = FaustEffect {
+            dsp,
+            effect,
+            smoother: Smoother::new(effect.parameters),
+            rate_switch: RateSwitch::Reset,
+            rescaling: Rescaling::default(),
+        };
+        faust_effect::FaustDsp::skip_smoothing(&mut faust_effect);
+        faust_effect
+    }
+}
+
+impl<D> fmt::Display for FaustEffect<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values = (0..self.effect.parameters.len())
+            .map(|index| self.smoother.value(index).to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}({})", self.effect.kind, values.join(", "))
+    }
+}
+
+impl<D: GeneratedDsp + Send> AudioEffect for FaustEffect<D> {
+    fn nb_inputs(&self) -> usize {
+        self.effect.nb_inputs
+    }
+    fn nb_outputs(&self) -> usize {
+        self.effect.nb_outputs
+    }
+
+    fn parameters(&self) -> &[Parameter] {
+        self.effect.parameters
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        if index < self.parameters().len() {
+            Some(self.smoother.target(index))
+        } else {
+            None
+        }
+    }
+
+    fn store_parameter(&mut self, index: usize, value: f32) {
+        self.smoother.set_target(index, value);
+    }
+
+    fn process(&mut self, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        debug_assert_eq!(inputs.len(), self.nb_inputs());
+        debug_assert_eq!(outputs.len(), self.nb_outputs());
+        let actual_samplerate = outputs[0].samplerate as i32;
+
+        //Constants have to be changed if we change the samplerate...
+        if self.dsp.get_sample_rate() != actual_samplerate {
+            match self.rate_switch {
+                RateSwitch::Reset => self.dsp.instance_init(actual_samplerate),
+                RateSwitch::Preserve => {
+                    faust_effect::FaustDsp::switch_samplerate(self, actual_samplerate)
+                }
+            }
+            faust_effect::FaustDsp::update_sliders(self);
+        }
+        compute_smoothed(self, inputs, outputs);
+    }
+}
+
+impl<D: GeneratedDsp> faust_effect::FaustDsp for FaustEffect<D> {
+    fn smoother(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    fn update_sliders(&mut self) {
+        for (index, &param) in self.effect.param_indices.iter().enumerate() {
+            self.dsp
+                .set_param(ParamIndex(param), self.smoother.value(index));
+        }
+    }
+
+    fn set_rate_switch(&mut self, rate_switch: RateSwitch) {
+        self.rate_switch = rate_switch;
+    }
+
+    fn switch_samplerate(&mut self, sample_rate: i32) {
+        let ratio = sample_rate as f32 / self.dsp.get_sample_rate() as f32;
+        self.rescaling.start(ratio, self.dsp.iota());
+        self.dsp.instance_constants(sample_rate);
+    }
+
+    fn delay_line(&mut self, index: usize) -> Option<&mut [f32]> {
+        self.dsp.delay_line(index)
+    }
+
+    fn iota(&self) -> i32 {
+        self.dsp.iota()
+    }
+
+    fn rescaling(&mut self) -> Option<&mut Rescaling> {
+        Some(&mut self.rescaling)
+    }
+
+    fn compute(&mut self, start: usize, end: usize, inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+        let mut input_buffers: [&[f32]; MAX_PORTS] = Default::default();
+        for (buffer, input) in input_buffers.iter_mut().zip(inputs.iter()) {
+            *buffer = &input.buffer()[start..end];
+        }
+        let nb_outputs = outputs.len();
+        let mut output_buffers: [&mut [f32]; MAX_PORTS] = Default::default();
+        for (buffer, output) in output_buffers.iter_mut().zip(outputs.iter_mut()) {
+            *buffer = &mut output.buffer_mut()[start..end];
+        }
+        self.dsp.compute(
+            (end - start) as i32,
+            &input_buffers[..inputs.len()],
+            &mut output_buffers[..nb_outputs],
+        );
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/faust_generated.rs"));
+
+/* Constructors of the effects which were ported by hand before being generated */
+
+impl Transpose {
+    pub fn new(semitones: i32) -> Transpose {
+        let mut transpose = Transpose::default();
+        assert!(transpose.set_parameter_by_name("semitones", semitones as f32));
+        faust_effect::FaustDsp::skip_smoothing(&mut transpose);
+        transpose
+    }
+}
+
+impl Compressor {
+    pub fn new(ratio: f32, thresh: f32, att: f32, rel: f32) -> Compressor {
+        Compressor::with_parameters(&[ratio, thresh, att, rel])
+    }
+}
+
+impl Autowah {
+    pub fn new(level: f32) -> Autowah {
+        Autowah::with_parameters(&[level])
+    }
+}
+
+impl Cubicnl {
+    pub fn new(drive: f32, offset: f32) -> Cubicnl {
+        Cubicnl::with_parameters(&[drive, offset])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiograph_parser::{self, Value};
+    use faust_effect::tests::check_renders;
+    use faust_effect::FaustDsp;
+
+    /// Energy of the output of a transposition one octave down of a burst of noise, for each cycle,
+    /// while the samplerate goes from 44.1kHz to 22.05kHz
+    fn transpose_switches(rate_switch: RateSwitch) -> Vec<f32> {
+        let mut transpose = Transpose::with_parameters(&[10_000., 32., -12.]);
+        transpose.set_rate_switch(rate_switch);
+        let mut energies = Vec::new();
+        for cycle in 0..20 {
+            let (size, samplerate) = if cycle >= 10 {
+                (32, 22_050)
+            } else {
+                (64, 44_100)
+            };
+            let mut inputs = vec![DspEdge::new(1, 1, size, samplerate)];
+            if cycle < 10 {
+                for (i, sample) in inputs[0].buffer_mut().iter_mut().enumerate() {
+                    *sample = ((i as f32 * 12.9898).sin() * 43_758.547).fract();
+                }
+            }
+            let mut outputs = vec![DspEdge::new(1, 1, size, samplerate)];
+            transpose.process(&inputs, &mut outputs);
+            energies.push(
+                outputs[0]
+                    .buffer()
+                    .iter()
+                    .map(|sample| sample * sample)
+                    .sum::<f32>()
+                    / size as f32,
+            );
+        }
+        energies
+    }
+
+    /// Render the node of kind `D::effect().kind` built by the default registry with the parameters
+    /// `values`, and the code compiled by faust from its .dsp file with the same parameters, at two
+    /// samplerates, and check that their outputs are the same and not silent
+    #[cfg(feature = "faust")]
+    fn check_matches_dsp<D: GeneratedDsp + Send + 'static>(values: &[f32]) {
+        let effect = D::effect();
+        for &samplerate in [44_100, 96_000].iter() {
+            let mut node_infos = Node::new();
+            node_infos.class_name = effect.kind.to_string();
+            node_infos.nb_inlets = effect.nb_inputs as u32;
+            node_infos.nb_outlets = effect.nb_outputs as u32;
+            for (parameter, &value) in effect.parameters.iter().zip(values.iter()) {
+                node_infos
+                    .more
+                    .insert(parameter.name.to_string(), Value::Float(value as f64));
+            }
+            let (_, mut node) = NodeRegistry::default()
+                .create_node(node_infos, 1)
+                .unwrap()
+                .into_parts();
+            let mut dsp = FaustEffect::<D>::with_parameters(values);
+
+            let mut inputs = vec![DspEdge::new(1, 1, 64, samplerate); effect.nb_inputs];
+            let mut node_outputs = vec![DspEdge::new(1, 1, 64, samplerate); effect.nb_outputs];
+            let mut dsp_outputs = node_outputs.clone();
+            let mut energy = 0.;
+            for cycle in 0..32 {
+                for input in inputs.iter_mut() {
+                    for (i, sample) in input.buffer_mut().iter_mut().enumerate() {
+                        let t = (cycle * 64 + i) as f32;
+                        *sample = ((t * 12.9898).sin() * 43_758.547).fract();
+                    }
+                }
+                node.process(&inputs, &mut node_outputs);
+                dsp.process(&inputs, &mut dsp_outputs);
+                for (node_output, dsp_output) in node_outputs.iter().zip(dsp_outputs.iter()) {
+                    assert_eq!(
+                        node_output.buffer(),
+                        dsp_output.buffer(),
+                        "{} at {}Hz",
+                        effect.kind,
+                        samplerate
+                    );
+                    energy += dsp_output
+                        .buffer()
+                        .iter()
+                        .map(|sample| sample * sample)
+                        .sum::<f32>();
+                }
+            }
+            assert!(energy > 0., "{} is silent at {}Hz", effect.kind, samplerate);
+        }
+    }
+
+    #[test]
+    fn test_generated_node() {
+        let registry = NodeRegistry::default();
+        let metadata = registry.metadata("cubicnl").unwrap();
+        assert_eq!(
+            (metadata.nb_inlets, metadata.nb_outlets),
+            (Some(1), Some(1))
+        );
+        assert_eq!(metadata.parameters[0].name, "drive");
+        assert_eq!(metadata.parameters[0].default, 0.9);
+
+        // Attributes set the parameters, and missing ones keep their default value
+        let mut node_infos = Node::new();
+        node_infos.class_name = "cubicnl".to_string();
+        node_infos.nb_inlets = 1;
+        node_infos.nb_outlets = 1;
+        let node = registry.create_node(node_infos.clone(), 1).unwrap();
+        assert_eq!(node.to_string(), "cubicnl(0.9, 0.9)");
+        node_infos.more.insert("drive".to_string(), Value::Integer(1));
+        let node = registry.create_node(node_infos, 1).unwrap();
+        assert_eq!(node.to_string(), "cubicnl(1, 0.9)");
+    }
+
+    #[test]
+    fn test_generated_effects_render() {
+        check_renders(Autowah::default);
+        check_renders(Compressor::default);
+        check_renders(Cubicnl::default);
+        check_renders(Transpose::default);
+    }
+
+    #[test]
+    fn test_generated_kinds() {
+        let registry = NodeRegistry::default();
+        for effect in GENERATED.iter() {
+            let metadata = registry.metadata(effect.kind).unwrap();
+            assert_eq!(metadata.description, effect.description);
+            assert_eq!(metadata.nb_inlets, Some(effect.nb_inputs as u32));
+            assert_eq!(metadata.nb_outlets, Some(effect.nb_outputs as u32));
+            assert_eq!(metadata.parameters, effect.parameters);
+
+            // Every parameter can be set in an audiograph
+            let mut attributes = format!("kind : \"{}\", ", effect.kind);
+            if effect.nb_inputs > 0 {
+                attributes.push_str(&format!("in : {}, ", effect.nb_inputs));
+            }
+            attributes.push_str(&format!("out : {}, ", effect.nb_outputs));
+            for parameter in effect.parameters.iter() {
+                let value = if parameter.kind == ParameterType::Boolean {
+                    "true".to_string()
+                } else {
+                    parameter.max.to_string()
+                };
+                attributes.push_str(&format!("{} : {}, ", parameter.name, value));
+            }
+            let audio_graph = audiograph_parser::parse_audiograph(
+                &format!("effect = {{{}}};", attributes),
+                64,
+                1,
+                44_100,
+            )
+            .unwrap_or_else(|e| panic!("{}: {}", effect.kind, e));
+            let node = audio_graph
+                .graph
+                .node_indices()
+                .map(|node| &audio_graph.graph[node])
+                .find(|node| node.node_infos().id == "effect")
+                .unwrap();
+            let values = effect
+                .parameters
+                .iter()
+                .map(|parameter| parameter.max.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                node.to_string(),
+                format!("{}({})", effect.kind, values.join(", "))
+            );
+        }
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_panner_matches_dsp() {
+        check_matches_dsp::<panner::Panner>(&[0.3]);
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_brass_matches_dsp() {
+        check_matches_dsp::<brass::Brass>(&[2., 0.6, 0.9, 0.8]);
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_violin_matches_dsp() {
+        check_matches_dsp::<violin::Violin>(&[1.5, 0.4, 0.5, 0.6]);
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_wah_matches_dsp() {
+        check_matches_dsp::<wah::Wah>(&[1200.]);
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_pink_noise_matches_dsp() {
+        check_matches_dsp::<pink_noise::PinkNoise>(&[]);
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_sawtooth_matches_dsp() {
+        check_matches_dsp::<sawtooth::Sawtooth>(&[220.]);
+    }
+
+    #[cfg(feature = "faust")]
+    #[test]
+    fn test_feedbackcomb_matches_dsp() {
+        check_matches_dsp::<feedbackcomb::Feedbackcomb>(&[8., 0.5]);
+    }
+
+    #[test]
+    fn test_generated_rate_switch() {
+        let mut transpose = Transpose::default();
+        assert!(transpose.delay_line(0).is_some());
+        assert!(transpose.delay_line(1).is_none());
+        assert!(Cubicnl::default().delay_line(0).is_none());
+
+        let energies = transpose_switches(RateSwitch::Reset);
+        assert!(energies[9] > 0.);
+        assert!(energies[10..].iter().all(|&energy| energy == 0.));
+
+        // The delay line is rescaled instead of being cleared
+        let energies = transpose_switches(RateSwitch::Preserve);
+        assert!(energies.iter().all(|energy| energy.is_finite()));
+        assert!(energies[10] > 0.);
+    }
+}
@@ -21,10 +21,15 @@ pub mod sndfile;
 //pub mod reverb;
 pub mod amath;
 pub mod audiograph;
+pub mod audiograph_optim;
+pub mod audiograph_parallel;
 pub mod audiograph_parser;
+pub mod cost_model;
 pub mod effect;
 pub mod experiments;
 pub mod faust_effect;
+pub mod faust_generated;
+pub mod node_registry;
 pub mod stats;
 
 extern crate pest;
@@ -34,3 +39,6 @@ extern crate pest_derive;
 extern crate itertools;
 
 extern crate crossbeam_channel;
+
+#[macro_use]
+extern crate lazy_static;
@@ -0,0 +1,429 @@
+//! Kinds of nodes which can be used in audiographs, and how to build them
+use std::collections::BTreeMap;
+use std::fmt;
+
+use audiograph::*;
+use audiograph_parser::{AudiographError, Node};
+use faust_effect;
+use faust_generated;
+
+/// Builds the processor of a node from its attributes, in an audiograph with `nb_channels` channels
+pub type NodeFactory =
+    Box<dyn Fn(&Node, usize) -> Result<Box<dyn AudioEffect>, AudiographError> + Send + Sync>;
+
+/// Description of a kind of node
+#[derive(Debug, Clone)]
+pub struct NodeMetadata {
+    pub description: String,
+    /// Number of inlets, or `None` if it is given by the `in` attribute of the node
+    pub nb_inlets: Option<u32>,
+    /// Number of outlets, or `None` if it is given by the `out` attribute of the node
+    pub nb_outlets: Option<u32>,
+    pub parameters: Vec<Parameter>,
+}
+
+impl NodeMetadata {
+    pub fn new(
+        description: &str,
+        nb_inlets: Option<u32>,
+        nb_outlets: Option<u32>,
+        parameters: &[Parameter],
+    ) -> NodeMetadata {
+        NodeMetadata {
+            description: description.to_string(),
+            nb_inlets,
+            nb_outlets,
+            parameters: parameters.to_vec(),
+        }
+    }
+}
+
+struct NodeKind {
+    metadata: NodeMetadata,
+    factory: NodeFactory,
+}
+
+/// Maps the `kind` of nodes in .ag files to the processors of the nodes.
+///
+/// Other crates can add their own `AudioEffect`:
+///
+/// ```ignore
+/// let mut registry = NodeRegistry::default();
+/// registry.register(
+///     "gain",
+///     NodeMetadata::new("Multiplies its input", Some(1), Some(1), &[]),
+///     |node, _| Ok(Box::new(Gain::new(node.attribute("gain", "a float")?))),
+/// );
+/// let audiograph = parse_audiograph_with_registry(ag, &registry, 64, 1, 44_100)?;
+/// ```
+pub struct NodeRegistry {
+    kinds: BTreeMap<String, NodeKind>,
+    strict: bool,
+}
+
+impl NodeRegistry {
+    /// A registry without any kind of nodes
+    pub fn empty() -> NodeRegistry {
+        NodeRegistry {
+            kinds: BTreeMap::new(),
+            strict: false,
+        }
+    }
+
+    /// Add a kind of node, or replace the kind with the same name
+    pub fn register<F>(&mut self, kind: &str, metadata: NodeMetadata, factory: F)
+    where
+        F: Fn(&Node, usize) -> Result<Box<dyn AudioEffect>, AudiographError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.kinds.insert(
+            kind.to_string(),
+            NodeKind {
+                metadata,
+                factory: Box::new(factory),
+            },
+        );
+    }
+
+    /// In strict mode, unknown kinds of nodes are errors. Otherwise, they are replaced
+    /// by an oscillator, a modulator or a mixer depending on their number of ports.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn metadata(&self, kind: &str) -> Option<&NodeMetadata> {
+        self.kinds.get(kind).map(|kind| &kind.metadata)
+    }
+
+    /// Registered kinds, in alphabetical order
+    pub fn kinds(&self) -> impl Iterator<Item = (&str, &NodeMetadata)> {
+        self.kinds
+            .iter()
+            .map(|(name, kind)| (name.as_str(), &kind.metadata))
+    }
+
+    pub fn create_node(
+        &self,
+        node_infos: Node,
+        nb_channels: usize,
+    ) -> Result<DspNode, AudiographError> {
+        let node_processor: Box<dyn AudioEffect> = match self.kinds.get(&node_infos.class_name) {
+            Some(kind) => {
+                let node_processor = (kind.factory)(&node_infos, nb_channels)?;
+                //The ports of source and sink on the side of the real input and output are not declared
+                node_infos.check_ports(
+                    kind.metadata
+                        .nb_inlets
+                        .unwrap_or(node_processor.nb_inputs() as u32),
+                    kind.metadata
+                        .nb_outlets
+                        .unwrap_or(node_processor.nb_outputs() as u32),
+                )?;
+                node_processor
+            }
+            None if self.strict => {
+                return Err(AudiographError::UnsupportedNode {
+                    node: node_infos.id.clone(),
+                    kind: node_infos.class_name.clone(),
+                    position: node_infos.position.clone(),
+                })
+            }
+            None => {
+                //We replace it by a default effect
+                println!(
+                    "Unkwown node {:?}. Replacing it by a known one.",
+                    node_infos
+                );
+                if node_infos.nb_inlets == 0 && node_infos.nb_outlets == 1 {
+                    Box::new(Oscillator::new(0., 440, 1.))
+                } else if node_infos.nb_inlets == 1 && node_infos.nb_outlets == 1 {
+                    Box::new(Modulator::new(0., 445, 1.))
+                } else {
+                    Box::new(InputsOutputsAdaptor::from_node_infos(&node_infos)?)
+                }
+            }
+        };
+        Ok(DspNode::from_parts(node_infos, node_processor))
+    }
+}
+
+lazy_static! {
+    /// The built-in kinds of nodes, built once and shared by the functions which do not take a registry
+    pub(crate) static ref DEFAULT_REGISTRY: NodeRegistry = NodeRegistry::default();
+}
+
+/// The built-in kinds of nodes, not strict. The effects of faust-effects/ are generated,
+/// except the ones ported by hand in `faust_effect`. The generated effects replace the stand-ins
+/// of `faust_effect` of the same kind.
+impl Default for NodeRegistry {
+    fn default() -> NodeRegistry {
+        let mut registry = NodeRegistry::empty();
+        let metadata = NodeMetadata::new;
+
+        registry.register(
+            "osc",
+            metadata("Sine oscillator", Some(0), Some(1), &SINE_PARAMETERS),
+            |node, _| Ok(Box::new(Oscillator::from_node_infos(node)?)),
+        );
+        registry.register(
+            "mod",
+            metadata(
+                "Multiplies its input with a sine",
+                Some(1),
+                Some(1),
+                &SINE_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(Modulator::from_node_infos(node)?)),
+        );
+        registry.register(
+            "mix",
+            metadata(
+                "Mixes or dispatches its inputs to its outputs",
+                None,
+                None,
+                &[],
+            ),
+            |node, _| Ok(Box::new(InputsOutputsAdaptor::from_node_infos(node)?)),
+        );
+        registry.register(
+            "resampler",
+            metadata(
+                "Resamples its input with attributes ratio and conv",
+                Some(1),
+                Some(1),
+                &[],
+            ),
+            |node, _| Ok(Box::new(Resampler::from_node_infos(node)?)),
+        );
+        registry.register(
+            "source",
+            metadata("Audio input of the audiograph", Some(0), None, &[]),
+            |node, nb_channels| {
+                Ok(Box::new(InputsOutputsAdaptor::new(
+                    nb_channels,
+                    node.nb_outlets as usize,
+                )))
+            },
+        );
+        registry.register(
+            "sink",
+            metadata("Audio output of the audiograph", None, Some(0), &[]),
+            |node, nb_channels| {
+                Ok(Box::new(InputsOutputsAdaptor::new(
+                    node.nb_inlets as usize,
+                    nb_channels,
+                )))
+            },
+        );
+
+        registry.register(
+            "guitar",
+            metadata(
+                "Plucked string",
+                Some(0),
+                Some(1),
+                &faust_effect::GUITAR_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Guitar::from_node_infos(node)?)),
+        );
+        registry.register(
+            "zita_reverb",
+            metadata(
+                "Stereo reverb",
+                Some(2),
+                Some(2),
+                &faust_effect::ZITA_REVERB_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::ZitaReverb::from_node_infos(node)?)),
+        );
+        registry.register(
+            "freeverb",
+            metadata(
+                "Mono reverb",
+                Some(1),
+                Some(1),
+                &faust_effect::FREEVERB_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::MonoFreeverb::from_node_infos(node)?)),
+        );
+        registry.register(
+            "brass",
+            metadata(
+                "Brass physical model (hand-written approximation)",
+                Some(0),
+                Some(1),
+                &faust_effect::BRASS_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Brass::from_node_infos(node)?)),
+        );
+        registry.register(
+            "violin",
+            metadata(
+                "Bowed string physical model (hand-written approximation)",
+                Some(0),
+                Some(1),
+                &faust_effect::VIOLIN_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Violin::from_node_infos(node)?)),
+        );
+        registry.register(
+            "wah",
+            metadata(
+                "Resonant lowpass filter (hand-written approximation)",
+                Some(1),
+                Some(1),
+                &faust_effect::WAH_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Wah::from_node_infos(node)?)),
+        );
+        registry.register(
+            "panner",
+            metadata(
+                "Mono to stereo panner",
+                Some(1),
+                Some(2),
+                &faust_effect::PANNER_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Panner::from_node_infos(node)?)),
+        );
+        registry.register(
+            "spat",
+            metadata(
+                "Spatializer on as many speakers as outlets",
+                Some(1),
+                None,
+                &faust_effect::SPAT_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Spat::from_node_infos(node)?)),
+        );
+        registry.register(
+            "pink_noise",
+            metadata(
+                "Pink noise generator (hand-written approximation)",
+                Some(0),
+                Some(1),
+                &faust_effect::PINK_NOISE_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::PinkNoise::from_node_infos(node)?)),
+        );
+        registry.register(
+            "sawtooth",
+            metadata(
+                "Band-limited sawtooth oscillator (hand-written approximation)",
+                Some(0),
+                Some(1),
+                &faust_effect::SAWTOOTH_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::Sawtooth::from_node_infos(node)?)),
+        );
+        registry.register(
+            "feedbackcomb",
+            metadata(
+                "Allpass feedback comb filter (hand-written approximation)",
+                Some(1),
+                Some(1),
+                &faust_effect::FEEDBACKCOMB_PARAMETERS,
+            ),
+            |node, _| Ok(Box::new(faust_effect::FeedbackComb::from_node_infos(node)?)),
+        );
+        faust_generated::register_generated(&mut registry);
+        registry
+    }
+}
+
+fn ports(nb_ports: Option<u32>) -> String {
+    nb_ports.map_or("n".to_string(), |nb_ports| nb_ports.to_string())
+}
+
+impl fmt::Display for NodeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, metadata) in self.kinds() {
+            writeln!(
+                f,
+                "{}\t{} -> {}\t{}",
+                name,
+                ports(metadata.nb_inlets),
+                ports(metadata.nb_outlets),
+                metadata.description
+            )?;
+            for parameter in metadata.parameters.iter() {
+                writeln!(
+                    f,
+                    "\t{}: {:?} in [{}, {}], default {}",
+                    parameter.name, parameter.kind, parameter.min, parameter.max, parameter.default
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiograph_parser::parse_audiograph_with_registry;
+    use std::fmt;
+
+    struct Silence;
+
+    impl fmt::Display for Silence {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "silence")
+        }
+    }
+
+    impl AudioEffect for Silence {
+        fn process(&mut self, _inputs: &[DspEdge], outputs: &mut [DspEdge]) {
+            for sample in outputs[0].buffer_mut().iter_mut() {
+                *sample = 0.;
+            }
+        }
+
+        fn nb_inputs(&self) -> usize {
+            0
+        }
+
+        fn nb_outputs(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let ag = "a = {kind : \"silence\", out : 1,};";
+        let mut registry = NodeRegistry::default();
+        assert!(registry.metadata("zita_reverb").is_some());
+        assert!(registry.to_string().contains("freq: Integer"));
+
+        //Unknown kinds are replaced when not strict
+        let audiograph = parse_audiograph_with_registry(ag, &registry, 64, 1, 44_100).unwrap();
+        assert_eq!(audiograph.nb_nodes(), 3);
+
+        registry.set_strict(true);
+        assert!(matches!(
+            parse_audiograph_with_registry(ag, &registry, 64, 1, 44_100),
+            Err(AudiographError::UnsupportedNode { .. })
+        ));
+
+        registry.register(
+            "silence",
+            NodeMetadata::new("Silence", Some(0), Some(1), &[]),
+            |_, _| Ok(Box::new(Silence)),
+        );
+        let mut audiograph = parse_audiograph_with_registry(ag, &registry, 64, 1, 44_100).unwrap();
+        audiograph.update_schedule().unwrap();
+        let inputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        let mut outputs = vec![DspEdge::new(1, 1, 64, 44_100)];
+        for sample in outputs[0].buffer_mut().iter_mut() {
+            *sample = 1.;
+        }
+        audiograph.process(&inputs, &mut outputs);
+        assert!(outputs[0].buffer().iter().all(|&s| s == 0.));
+    }
+}
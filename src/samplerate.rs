@@ -198,6 +198,9 @@ impl Drop for Resampler {
     }
 }
 
+// The state of the converter is owned by the resampler and is only used through `&mut self`
+unsafe impl Send for Resampler {}
+
 /// For several resampling algorithms, libresample yields less samples than requested because of a delay.
 /// SmartResampler uses a ring buffer to output the same number of samples as requested.
 /// It makes it easier to change the resampling ratio in real time. We also aim at making easier at changing